@@ -41,10 +41,37 @@ impl Writer for ConfigWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use server::secret::REDACTED;
 
     #[test]
     fn test_config_validate() {
         let (config, _) = load_from_file("../../config.example.toml").unwrap();
         config.validate().unwrap();
     }
+
+    #[test]
+    fn test_config_redacted() {
+        let (config, _) = load_from_file("../../config.example.toml").unwrap();
+        let redacted = config.redacted();
+        assert_eq!(redacted.downloader.qbittorrent.password.raw(), REDACTED);
+        assert!(redacted.downloader.aria2.secret.is_empty());
+
+        // 未修改的占位符合并后与原配置一致
+        let mut merged = redacted.clone();
+        merged.merge_redacted(&config);
+        assert_eq!(
+            toml::to_string(&merged).unwrap(),
+            toml::to_string(&config).unwrap()
+        );
+
+        // 修改过的值不会被覆盖
+        let mut merged = redacted;
+        merged.downloader.qbittorrent.password = "new_password".into();
+        merged.merge_redacted(&config);
+        assert_eq!(merged.downloader.qbittorrent.password.raw(), "new_password");
+        assert_eq!(
+            merged.downloader.deluge.password,
+            config.downloader.deluge.password
+        );
+    }
 }
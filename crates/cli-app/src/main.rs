@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use ::server::secret::{SECRET_KEY_ENV, SecretKey};
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use config::load_from_file;
@@ -12,6 +13,12 @@ mod config;
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     Start,
+    /// 生成 auth.password_hash 使用的密码哈希, 未指定密码时从标准输入读取
+    HashPassword {
+        password: Option<String>,
+    },
+    /// 使用环境变量 BANGUMI_SECRET_KEY 中的密钥加密配置文件中明文保存的敏感字段
+    EncryptSecrets,
 }
 
 #[derive(Parser)]
@@ -31,18 +38,40 @@ static GLOBAL: Jemalloc = Jemalloc;
 #[tokio::main]
 pub async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let (config, writer) = load_from_file(cli.config)?;
-
-    setup_panic_hook();
 
     match cli.commands {
-        Commands::Start => match server::Server::new(config, writer).await?.serve().await {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::error!("服务启动失败，错误原因: {}", e);
-                std::process::exit(1);
+        Commands::Start => {
+            let (config, writer) = load_from_file(cli.config)?;
+
+            setup_panic_hook();
+
+            match server::Server::new(config, writer).await?.serve().await {
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!("服务启动失败，错误原因: {}", e);
+                    std::process::exit(1);
+                }
             }
-        },
+        }
+        Commands::HashPassword { password } => {
+            let password = match password {
+                Some(password) => password,
+                None => {
+                    let mut line = String::new();
+                    std::io::stdin().read_line(&mut line)?;
+                    line.trim_end_matches(['\r', '\n']).to_owned()
+                }
+            };
+            println!("{}", ::server::auth::hash_password(&password)?);
+        }
+        Commands::EncryptSecrets => {
+            let key = SecretKey::from_env()
+                .ok_or_else(|| anyhow::anyhow!("未设置环境变量 {}", SECRET_KEY_ENV))?;
+            let (mut config, writer) = load_from_file(cli.config)?;
+            config.encrypt_secrets(&key)?;
+            writer.write(&config)?;
+            println!("配置文件中的敏感字段已加密");
+        }
     }
     Ok(())
 }
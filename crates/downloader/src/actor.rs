@@ -1,17 +1,22 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
 
 use crate::{
-    DownloadInfo, Downloader, DownloaderInfo, FileInfo, RemoteTaskStatus, Store,
-    ThirdPartyDownloader, Tid,
+    DownloadInfo, Downloader, DownloaderInfo, FileInfo, FileMove, RemoteTaskStatus, Store,
+    TaskProgress, ThirdPartyDownloader, Tid,
     config::Config,
-    dlrs::{Dlrs, assigned_dlr},
+    dlrs::{Dlrs, SelectState, SelectStrategy, Selection, assigned_dlr},
     errors::{Error, Result},
     metrics,
     resource::Resource,
     stm::{Context, Event, TaskDL},
 };
 use async_trait::async_trait;
-use chrono::Local;
+use chrono::{Local, NaiveDateTime};
 use model::{
     sea_orm_active_enums::{DownloadStatus, ResourceType},
     torrent_download_tasks::Model,
@@ -22,6 +27,11 @@ use tracing::{debug, error, info, warn};
 
 pub type Tx = (String, Event);
 
+/// 等待并发槽位的任务, 按创建时间排序, 先订阅的任务先启动
+///
+/// 排队的任务在数据库中保持 Pending 状态, 重启后按创建时间重新入队
+type TaskQueue = BTreeSet<(NaiveDateTime, String)>;
+
 #[derive(Clone)]
 pub struct Actor {
     tx: mpsc::UnboundedSender<Tx>,
@@ -29,6 +39,16 @@ pub struct Actor {
     dlrs: Vec<Arc<Box<dyn ThirdPartyDownloader>>>,
     notify_tx: broadcast::Sender<crate::Event>,
     config: Config,
+    /// 最近一次同步得到的任务进度, key 为 info_hash
+    progress: Arc<RwLock<HashMap<String, TaskProgress>>>,
+    /// 各下载器中排队等待启动的任务, key 为下载器名称
+    queue: Arc<Mutex<HashMap<String, TaskQueue>>>,
+    /// 各下载器中占用并发槽位的任务, 包括正在启动的任务, key 为下载器名称
+    slots: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+    /// 下载器选择策略在多次选择之间保留的状态
+    select_state: Arc<Mutex<SelectState>>,
+    /// 健康检查失败的下载器名称
+    unhealthy: Arc<RwLock<HashSet<String>>>,
 }
 
 impl Actor {
@@ -44,6 +64,11 @@ impl Actor {
             config,
             notify_tx,
             dlrs,
+            progress: Arc::new(RwLock::new(HashMap::new())),
+            queue: Arc::new(Mutex::new(HashMap::new())),
+            slots: Arc::new(Mutex::new(HashMap::new())),
+            select_state: Arc::new(Mutex::new(SelectState::default())),
+            unhealthy: Arc::new(RwLock::new(HashSet::new())),
         })
     }
 
@@ -89,6 +114,27 @@ impl Actor {
             });
         }
 
+        {
+            let actor = self.clone();
+            tokio::spawn(async move {
+                let mut ticker = actor.config.health_check_tick();
+                loop {
+                    ticker.tick().await;
+                    actor.health_check().await;
+                }
+            });
+        }
+
+        for notify in self.dlrs.iter().filter_map(|dlr| dlr.status_notifier()) {
+            let actor = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    notify.notified().await;
+                    actor.sync_all().await;
+                }
+            });
+        }
+
         Ok(())
     }
 
@@ -109,6 +155,13 @@ impl Actor {
                     error!("处理事件失败: {}", e);
                 })
                 .ok();
+
+            self.dispatch_queued(&mut stm, &dlrs)
+                .await
+                .inspect_err(|e| {
+                    error!("启动排队任务失败: {}", e);
+                })
+                .ok();
         }
     }
 
@@ -148,6 +201,12 @@ impl Actor {
                 init = false;
             }
 
+            // 下载器没有空闲的并发槽位时, 任务保持 Pending 并进入队列
+            if matches!(event, Event::Start(_)) && !self.acquire_slot(tdl, &task.info_hash) {
+                self.enqueue(tdl.name(), &task);
+                break;
+            }
+
             stm.handle_with_context(&event, &mut ctx).await;
 
             if let Some(next) = ctx.next_event {
@@ -157,14 +216,202 @@ impl Actor {
             }
         }
 
+        self.release_slot_if_inactive(&info_hash).await
+    }
+
+    /// 检查各下载器是否可用, 失败的下载器在恢复前不参与选择
+    async fn health_check(&self) {
+        for dlr in self.dlrs.iter() {
+            let result = dlr.health_check().await;
+            let mut unhealthy = self.unhealthy.write().unwrap();
+            match result {
+                Ok(_) => {
+                    if unhealthy.remove(dlr.name()) {
+                        info!("下载器已恢复: {}", dlr.name());
+                    }
+                }
+                Err(e) => {
+                    if unhealthy.insert(dlr.name().to_owned()) {
+                        warn!("下载器健康检查失败: {} - {}", dlr.name(), e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 按策略为新任务选择下载器
+    async fn select_dlr(
+        &self,
+        resource: &Resource,
+        strategy: SelectStrategy,
+    ) -> Result<&dyn ThirdPartyDownloader> {
+        let mut active = HashMap::new();
+        if strategy == SelectStrategy::LeastActive {
+            let tasks = self
+                .store
+                .list_by_status(&[DownloadStatus::Pending, DownloadStatus::Downloading])
+                .await?;
+            for task in tasks.iter() {
+                *active
+                    .entry(assigned_dlr(&task.downloader).to_owned())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let unhealthy = self.unhealthy.read().unwrap().clone();
+        let selection = Selection {
+            strategy,
+            resource_type: resource.get_type(),
+            active: &active,
+            unhealthy: &unhealthy,
+        };
+        let dlr = self
+            .dlrs()
+            .select(&selection, &mut self.select_state.lock().unwrap());
+        debug!(
+            "选择下载器: info_hash={}, strategy={}, downloader={}",
+            resource.info_hash(),
+            strategy,
+            dlr.name()
+        );
+        Ok(dlr)
+    }
+
+    /// 下载器是否还有空闲的并发槽位
+    fn has_free_slot(&self, dlr: &dyn ThirdPartyDownloader) -> bool {
+        let max = dlr.config().max_concurrent_tasks;
+        max == 0
+            || self
+                .slots
+                .lock()
+                .unwrap()
+                .get(dlr.name())
+                .is_none_or(|s| s.len() < max)
+    }
+
+    /// 为即将启动的任务占用并发槽位, 没有空闲槽位时返回 false
+    fn acquire_slot(&self, dlr: &dyn ThirdPartyDownloader, info_hash: &str) -> bool {
+        let max = dlr.config().max_concurrent_tasks;
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots.entry(dlr.name().to_owned()).or_default();
+        if slot.contains(info_hash) || max == 0 || slot.len() < max {
+            slot.insert(info_hash.to_owned());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 事件处理完成后按任务状态更新槽位, 只有下载中的任务继续占用槽位
+    async fn release_slot_if_inactive(&self, info_hash: &str) -> Result<()> {
+        let task = self.store.get_by_hash(info_hash).await?;
+        let mut slots = self.slots.lock().unwrap();
+        for slot in slots.values_mut() {
+            slot.remove(info_hash);
+        }
+        if let Some(task) = task.filter(|t| t.download_status == DownloadStatus::Downloading) {
+            slots
+                .entry(assigned_dlr(&task.downloader).to_owned())
+                .or_default()
+                .insert(task.info_hash);
+        }
+        Ok(())
+    }
+
+    fn enqueue(&self, dlr_name: &str, task: &Model) {
+        info!(
+            "下载器并发任务已满, 任务进入队列: info_hash={}, downloader={}",
+            task.info_hash, dlr_name
+        );
+        self.queue
+            .lock()
+            .unwrap()
+            .entry(dlr_name.to_owned())
+            .or_default()
+            .insert((task.created_at, task.info_hash.clone()));
+    }
+
+    fn dequeue(&self, dlr_name: &str) -> Option<String> {
+        let mut queue = self.queue.lock().unwrap();
+        queue.get_mut(dlr_name)?.pop_first().map(|(_, ih)| ih)
+    }
+
+    fn is_queued(&self, info_hash: &str) -> bool {
+        self.queue
+            .lock()
+            .unwrap()
+            .values()
+            .any(|q| q.iter().any(|(_, ih)| ih == info_hash))
+    }
+
+    /// 为有空闲槽位的下载器启动排队中的任务
+    async fn dispatch_queued(
+        &self,
+        stm: &mut InitializedStateMachine<TaskDL<'_>>,
+        dlrs: &Dlrs<'_>,
+    ) -> Result<()> {
+        let dlr_names: Vec<String> = self
+            .queue
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, q)| !q.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in dlr_names {
+            let dlr = dlrs.must_take(&name)?;
+            while self.has_free_slot(dlr) {
+                let Some(info_hash) = self.dequeue(&name) else {
+                    break;
+                };
+
+                // 排队期间任务可能已被移除或切换了下载器
+                let task = self.store.get_by_hash(&info_hash).await?;
+                let queued = task.is_some_and(|t| {
+                    t.download_status == DownloadStatus::Pending
+                        && assigned_dlr(&t.downloader) == name
+                });
+                if !queued {
+                    continue;
+                }
+                let Some(resource) = self.store.load_resource(&info_hash).await? else {
+                    continue;
+                };
+
+                info!(
+                    "下载器有空闲槽位, 启动排队任务: info_hash={}, downloader={}",
+                    info_hash, name
+                );
+                self.execute(stm, (info_hash, Event::Start(resource)), dlrs)
+                    .await?;
+            }
+        }
         Ok(())
     }
 
     async fn recover(&self) -> Result<()> {
-        let pending = self
+        // 重启前下载中的任务继续占用槽位
+        let downloading = self
+            .store
+            .list_by_status(&[DownloadStatus::Downloading])
+            .await?;
+        {
+            let mut slots = self.slots.lock().unwrap();
+            for task in downloading {
+                slots
+                    .entry(assigned_dlr(&task.downloader).to_owned())
+                    .or_default()
+                    .insert(task.info_hash);
+            }
+        }
+
+        // 按创建时间依次启动, 没有空闲槽位的任务按原来的顺序重新排队
+        let mut pending = self
             .store
             .list_by_status(&[DownloadStatus::Pending])
             .await?;
+        pending.sort_by_key(|t| t.created_at);
 
         for task in pending {
             let resource = self
@@ -235,6 +482,7 @@ impl Actor {
         };
 
         if tasks.is_empty() {
+            self.update_progress(HashMap::new());
             return;
         }
 
@@ -251,34 +499,61 @@ impl Actor {
         }
 
         // 同步每个下载器
+        let mut progress = HashMap::new();
         for (dlr, tasks) in dlr_with_tasks.iter() {
-            self.sync_single(dlr, tasks)
-                .await
-                .inspect_err(|e| {
+            match self.sync_single(dlr, tasks).await {
+                Ok(p) => progress.extend(p),
+                Err(e) => {
                     error!("同步下载器:({}) 任务状态失败: {}", dlr, e);
-                })
-                .ok();
+                    // 同步失败时保留上一次的进度
+                    let last = self.progress.read().unwrap();
+                    progress.extend(tasks.iter().filter_map(|t| {
+                        last.get(&t.info_hash)
+                            .map(|p| (t.info_hash.clone(), p.clone()))
+                    }));
+                }
+            }
         }
+        self.update_progress(progress);
     }
 
-    async fn sync_single(&self, dlr_name: &str, ltasks: &[&Model]) -> Result<()> {
+    /// 同步单个下载器的任务状态, 返回任务的最新进度
+    async fn sync_single(
+        &self,
+        dlr_name: &str,
+        ltasks: &[&Model],
+    ) -> Result<HashMap<String, TaskProgress>> {
         let dlr = self.dlrs().must_take(dlr_name)?;
         if ltasks.is_empty() {
-            return Ok(());
+            return Ok(HashMap::new());
         }
 
         let tids: Vec<Tid> = ltasks.iter().map(|t| Tid::from(t.tid())).collect();
         let rtasks = dlr.list_tasks(&tids).await?;
 
+        let mut progress = HashMap::new();
         for ltask in ltasks {
             let ih = ltask.info_hash.clone();
             let tid = Tid::from(ltask.tid());
 
+            if let Some(p) = rtasks.get(&tid).and_then(|r| r.progress.clone()) {
+                progress.insert(ih.clone(), p);
+            }
             self.sync_task(dlr, ltask, &rtasks, &tid, &ih)?;
         }
 
         debug!("同步远程任务状态完成");
-        Ok(())
+        Ok(progress)
+    }
+
+    /// 替换进度快照并通知订阅者
+    fn update_progress(&self, progress: HashMap<String, TaskProgress>) {
+        let mut last = self.progress.write().unwrap();
+        if last.is_empty() && progress.is_empty() {
+            return;
+        }
+        *last = progress.clone();
+        let _ = self.notify_tx.send(crate::Event::ProgressUpdated(progress));
     }
 
     fn sync_task(
@@ -295,6 +570,10 @@ impl Actor {
             self.handle_status_change(ih, &ltask.download_status, &st, err_msg, res)?;
         }
 
+        // 排队中的任务还没有开始下载, 不计算超时
+        if self.is_queued(ih) {
+            return Ok(());
+        }
         self.chk_task_timeout(dlr, ltask, ih)
     }
 
@@ -397,6 +676,7 @@ impl Downloader for Actor {
         resource: Resource,
         dir: PathBuf,
         dlr_name: Option<String>,
+        strategy: Option<SelectStrategy>,
         allow_fallback: bool,
     ) -> Result<()> {
         let info_hash = resource.info_hash();
@@ -404,7 +684,8 @@ impl Downloader for Actor {
         let dlr = if let Some(name) = dlr_name {
             self.dlrs().must_take(&name)?
         } else {
-            self.dlrs().best()
+            let strategy = strategy.unwrap_or(self.config.select_strategy);
+            self.select_dlr(&resource, strategy).await?
         };
 
         self.store
@@ -449,6 +730,10 @@ impl Downloader for Actor {
         self.notify_tx.subscribe()
     }
 
+    fn task_progress(&self) -> HashMap<String, TaskProgress> {
+        self.progress.read().unwrap().clone()
+    }
+
     async fn retry(&self, info_hash: &str) -> Result<()> {
         self.tx.send((info_hash.to_string(), Event::Retry))?;
         Ok(())
@@ -511,6 +796,42 @@ impl Downloader for Actor {
         Ok(())
     }
 
+    async fn organize_files(&self, info_hash: &str, moves: &[FileMove]) -> Result<bool> {
+        let task = self
+            .store
+            .get_by_hash(info_hash)
+            .await?
+            .ok_or_else(|| Error::TaskNotFound(info_hash.to_string()))?;
+
+        let dlr = self.dlrs().must_take(&task.downloader)?;
+        // list_files 返回的文件 ID 带有下载器名称前缀
+        let prefix = format!("{}-", dlr.name());
+        let moves: Vec<_> = moves
+            .iter()
+            .map(|mv| FileMove {
+                file_id: mv
+                    .file_id
+                    .strip_prefix(&prefix)
+                    .unwrap_or(&mv.file_id)
+                    .to_owned(),
+                ..mv.clone()
+            })
+            .collect();
+
+        let tid = Tid::from(task.tid());
+        let Some(result) = dlr
+            .organize_files(&tid, task.context.clone(), &moves)
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        self.store
+            .update_status(info_hash, task.download_status, task.err_msg, Some(result))
+            .await?;
+        Ok(true)
+    }
+
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
         for dlr in self.dlrs.iter() {
             if dlr.supports_resource_type(resource_type.clone()) {
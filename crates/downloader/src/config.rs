@@ -2,6 +2,8 @@ use std::{path::PathBuf, time::Duration};
 
 use chrono::{Local, NaiveDateTime};
 
+use crate::dlrs::SelectStrategy;
+
 /// 下载器配置
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -11,6 +13,10 @@ pub struct Config {
     pub event_queue_size: usize,
     /// 重试任务间隔
     pub retry_processor_interval: Duration,
+    /// 下载器健康检查间隔
+    pub health_check_interval: Duration,
+    /// 订阅未指定时使用的下载器选择策略
+    pub select_strategy: SelectStrategy,
 }
 
 impl Default for Config {
@@ -19,6 +25,8 @@ impl Default for Config {
             sync_interval: Duration::from_secs(10),
             retry_processor_interval: Duration::from_secs(30),
             event_queue_size: 128,
+            health_check_interval: Duration::from_secs(60),
+            select_strategy: SelectStrategy::default(),
         }
     }
 }
@@ -31,6 +39,10 @@ impl Config {
     pub fn retry_tick(&self) -> tokio::time::Interval {
         tokio::time::interval(self.retry_processor_interval)
     }
+
+    pub fn health_check_tick(&self) -> tokio::time::Interval {
+        tokio::time::interval(self.health_check_interval)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +61,10 @@ pub struct GenericConfig {
     pub delete_task_on_completion: bool,
     // 优先级
     pub priority: u8,
+    /// 同时下载的最大任务数, 超出的任务排队等待, 0 表示不限制
+    pub max_concurrent_tasks: usize,
+    /// 按权重选择下载器时的权重, 0 表示不参与分配
+    pub weight: u32,
 }
 
 impl Default for GenericConfig {
@@ -60,6 +76,8 @@ impl Default for GenericConfig {
             download_timeout: chrono::Duration::minutes(30),
             delete_task_on_completion: true,
             priority: 0,
+            max_concurrent_tasks: 0,
+            weight: 1,
             download_dir: PathBuf::from("/"),
         }
     }
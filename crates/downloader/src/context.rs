@@ -6,6 +6,16 @@ pub struct Pan115Context {
     pub file_id: String,
     pub dir_id: String,
     pub file_name: String,
+    /// 整理后的文件, 不为空时直接使用这些文件, 原来的下载目录可能已被删除
+    #[serde(default)]
+    pub files: Vec<Pan115File>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pan115File {
+    pub file_id: String,
+    pub file_name: String,
+    pub file_size: usize,
 }
 
 impl From<&OfflineTask> for Pan115Context {
@@ -14,6 +24,7 @@ impl From<&OfflineTask> for Pan115Context {
             file_id: task.file_id.clone(),
             dir_id: task.dir_id.clone(),
             file_name: task.name.clone(),
+            files: vec![],
         }
     }
 }
@@ -90,7 +90,10 @@ impl Store for Db {
         let now = Local::now().naive_utc();
 
         torrent_download_tasks::Entity::update_many()
-            .col_expr(Column::DownloadStatus, SimpleExpr::from(status))
+            .col_expr(
+                Column::DownloadStatus,
+                Column::DownloadStatus.save_as(Expr::val(status)),
+            )
             .col_expr(Column::UpdatedAt, SimpleExpr::from(now))
             .col_expr(Column::ErrMsg, SimpleExpr::from(err_msg))
             .apply_if(context, |q, c| {
@@ -154,7 +157,7 @@ impl Store for Db {
         torrent_download_tasks::Entity::update_many()
             .col_expr(
                 Column::DownloadStatus,
-                SimpleExpr::from(DownloadStatus::Retrying),
+                Column::DownloadStatus.save_as(Expr::val(DownloadStatus::Retrying)),
             )
             .col_expr(Column::RetryCount, Expr::col(Column::RetryCount).add(1))
             .col_expr(Column::NextRetryAt, SimpleExpr::from(next_retry_at))
@@ -162,7 +165,7 @@ impl Store for Db {
             .col_expr(Column::UpdatedAt, SimpleExpr::from(now))
             .col_expr(
                 Column::DownloadStatus,
-                SimpleExpr::from(DownloadStatus::Retrying),
+                Column::DownloadStatus.save_as(Expr::val(DownloadStatus::Retrying)),
             )
             .filter(Column::InfoHash.eq(info_hash))
             .exec(&*self.conn)
@@ -1,4 +1,12 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+};
+
+use model::sea_orm_active_enums::ResourceType;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     DownloaderInfo, ThirdPartyDownloader,
@@ -13,6 +21,75 @@ pub fn assign_dlr(dlr: &str, new_dlr: &str) -> String {
     format!("{},{}", dlr, new_dlr)
 }
 
+/// 未指定下载器时选择下载器的策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectStrategy {
+    /// 优先级最高的下载器
+    #[default]
+    Priority,
+    /// 轮流使用各下载器
+    RoundRobin,
+    /// 进行中任务最少的下载器
+    LeastActive,
+    /// 按权重分配任务
+    Weighted,
+    /// 优先使用推荐资源类型与资源一致的下载器
+    ResourceType,
+}
+
+impl SelectStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SelectStrategy::Priority => "priority",
+            SelectStrategy::RoundRobin => "round_robin",
+            SelectStrategy::LeastActive => "least_active",
+            SelectStrategy::Weighted => "weighted",
+            SelectStrategy::ResourceType => "resource_type",
+        }
+    }
+}
+
+impl fmt::Display for SelectStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SelectStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "priority" => Ok(SelectStrategy::Priority),
+            "round_robin" => Ok(SelectStrategy::RoundRobin),
+            "least_active" => Ok(SelectStrategy::LeastActive),
+            "weighted" => Ok(SelectStrategy::Weighted),
+            "resource_type" => Ok(SelectStrategy::ResourceType),
+            _ => Err(Error::InvalidStrategy(s.to_owned())),
+        }
+    }
+}
+
+/// 选择下载器时需要在多次选择之间保留的状态
+#[derive(Debug, Default)]
+pub struct SelectState {
+    /// 轮询计数
+    round_robin: usize,
+    /// 平滑加权轮询中各下载器的当前权重
+    weights: HashMap<String, i64>,
+}
+
+/// 选择下载器的依据
+pub struct Selection<'s> {
+    pub strategy: SelectStrategy,
+    pub resource_type: ResourceType,
+    /// 各下载器进行中的任务数, 仅 [`SelectStrategy::LeastActive`] 使用
+    pub active: &'s HashMap<String, usize>,
+    /// 健康检查失败的下载器
+    pub unhealthy: &'s HashSet<String>,
+}
+
 pub struct Dlrs<'a> {
     inner: Vec<&'a dyn ThirdPartyDownloader>,
 }
@@ -35,6 +112,88 @@ impl<'a> Dlrs<'a> {
             .copied()
     }
 
+    /// 按策略选择下载器
+    ///
+    /// 只在支持该资源类型且健康的下载器中选择, 都不可用时依次放宽条件,
+    /// 由后续的添加任务流程报告错误
+    pub fn select(
+        &self,
+        selection: &Selection<'_>,
+        state: &mut SelectState,
+    ) -> &'a dyn ThirdPartyDownloader {
+        let supported: Vec<_> = self
+            .inner
+            .iter()
+            .filter(|d| d.supports_resource_type(selection.resource_type.clone()))
+            .copied()
+            .collect();
+        let healthy: Vec<_> = supported
+            .iter()
+            .filter(|d| !selection.unhealthy.contains(d.name()))
+            .copied()
+            .collect();
+
+        let candidates = if !healthy.is_empty() {
+            healthy
+        } else if !supported.is_empty() {
+            supported
+        } else {
+            return self.best();
+        };
+
+        match selection.strategy {
+            SelectStrategy::Priority => by_priority(&candidates),
+            SelectStrategy::RoundRobin => {
+                let dlr = candidates[state.round_robin % candidates.len()];
+                state.round_robin = state.round_robin.wrapping_add(1);
+                dlr
+            }
+            SelectStrategy::LeastActive => candidates
+                .iter()
+                .min_by_key(|d| {
+                    let active = selection.active.get(d.name()).copied().unwrap_or_default();
+                    (active, std::cmp::Reverse(d.config().priority))
+                })
+                .copied()
+                .unwrap(),
+            SelectStrategy::Weighted => {
+                // 平滑加权轮询, 权重为 0 的下载器不参与分配
+                let weighted: Vec<_> = candidates
+                    .iter()
+                    .filter(|d| d.config().weight > 0)
+                    .copied()
+                    .collect();
+                if weighted.is_empty() {
+                    return by_priority(&candidates);
+                }
+                let total: i64 = weighted.iter().map(|d| d.config().weight as i64).sum();
+                for d in &weighted {
+                    *state.weights.entry(d.name().to_owned()).or_default() +=
+                        d.config().weight as i64;
+                }
+                let dlr = weighted
+                    .iter()
+                    .max_by_key(|d| state.weights[d.name()])
+                    .copied()
+                    .unwrap();
+                *state.weights.get_mut(dlr.name()).unwrap() -= total;
+                dlr
+            }
+            SelectStrategy::ResourceType => {
+                let matched: Vec<_> = candidates
+                    .iter()
+                    .filter(|d| d.recommended_resource_type() == selection.resource_type)
+                    .copied()
+                    .collect();
+                if matched.is_empty() {
+                    by_priority(&candidates)
+                } else {
+                    by_priority(&matched)
+                }
+            }
+        }
+    }
+
     pub fn take(&self, name: &str) -> Option<&'a dyn ThirdPartyDownloader> {
         let latest = assigned_dlr(name);
         self.inner.iter().find(|d| d.name() == latest).copied()
@@ -56,6 +215,13 @@ impl<'a> Dlrs<'a> {
     }
 }
 
+fn by_priority<'a>(dlrs: &[&'a dyn ThirdPartyDownloader]) -> &'a dyn ThirdPartyDownloader {
+    dlrs.iter()
+        .max_by_key(|d| d.config().priority)
+        .copied()
+        .unwrap()
+}
+
 impl<'a> From<&'a [Arc<Box<dyn ThirdPartyDownloader>>]> for Dlrs<'a> {
     fn from(dlrs: &'a [Arc<Box<dyn ThirdPartyDownloader>>]) -> Self {
         let dlrs = dlrs.iter().map(|d| &***d).collect::<Vec<_>>();
@@ -68,3 +234,167 @@ impl<'a> From<&'a Vec<Arc<Box<dyn ThirdPartyDownloader>>>> for Dlrs<'a> {
         Self::from(dlrs.as_slice())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockThirdPartyDownloader, config::GenericConfig};
+
+    fn mock_dlr(
+        name: &'static str,
+        priority: u8,
+        weight: u32,
+        recommended: ResourceType,
+    ) -> Arc<Box<dyn ThirdPartyDownloader>> {
+        let mut dlr = MockThirdPartyDownloader::new();
+        dlr.expect_name().return_const(name);
+        dlr.expect_config().return_const(GenericConfig {
+            priority,
+            weight,
+            ..Default::default()
+        });
+        dlr.expect_supports_resource_type()
+            .returning(move |t| name != "pan115" || t != ResourceType::Torrent);
+        dlr.expect_recommended_resource_type()
+            .returning(move || recommended.clone());
+        Arc::new(Box::new(dlr))
+    }
+
+    fn select_n(
+        dlrs: &Dlrs<'_>,
+        strategy: SelectStrategy,
+        active: &HashMap<String, usize>,
+        unhealthy: &HashSet<String>,
+        n: usize,
+    ) -> Vec<&'static str> {
+        let mut state = SelectState::default();
+        let selection = Selection {
+            strategy,
+            resource_type: ResourceType::InfoHash,
+            active,
+            unhealthy,
+        };
+        (0..n)
+            .map(|_| dlrs.select(&selection, &mut state).name())
+            .collect()
+    }
+
+    fn dlrs() -> Vec<Arc<Box<dyn ThirdPartyDownloader>>> {
+        vec![
+            mock_dlr("qbittorrent", 2, 2, ResourceType::Torrent),
+            mock_dlr("transmission", 1, 1, ResourceType::Torrent),
+            mock_dlr("pan115", 0, 0, ResourceType::InfoHash),
+        ]
+    }
+
+    #[test]
+    fn test_select_strategy_from_str() {
+        for s in [
+            SelectStrategy::Priority,
+            SelectStrategy::RoundRobin,
+            SelectStrategy::LeastActive,
+            SelectStrategy::Weighted,
+            SelectStrategy::ResourceType,
+        ] {
+            assert_eq!(s.to_string().parse::<SelectStrategy>().unwrap(), s);
+        }
+        assert!("random".parse::<SelectStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_select_priority_and_round_robin() {
+        let dlrs = dlrs();
+        let dlrs = Dlrs::from(&dlrs);
+        let none = HashMap::new();
+        let healthy = HashSet::new();
+
+        assert_eq!(
+            select_n(&dlrs, SelectStrategy::Priority, &none, &healthy, 2),
+            vec!["qbittorrent", "qbittorrent"]
+        );
+        assert_eq!(
+            select_n(&dlrs, SelectStrategy::RoundRobin, &none, &healthy, 4),
+            vec!["qbittorrent", "transmission", "pan115", "qbittorrent"]
+        );
+    }
+
+    #[test]
+    fn test_select_weighted() {
+        let dlrs = dlrs();
+        let dlrs = Dlrs::from(&dlrs);
+        let picks = select_n(
+            &dlrs,
+            SelectStrategy::Weighted,
+            &HashMap::new(),
+            &HashSet::new(),
+            6,
+        );
+        assert_eq!(picks.iter().filter(|n| **n == "qbittorrent").count(), 4);
+        assert_eq!(picks.iter().filter(|n| **n == "transmission").count(), 2);
+        // 平滑加权轮询不会连续把任务都分给同一个下载器
+        assert_eq!(&picks[..3], &["qbittorrent", "transmission", "qbittorrent"]);
+    }
+
+    #[test]
+    fn test_select_least_active() {
+        let dlrs = dlrs();
+        let dlrs = Dlrs::from(&dlrs);
+        let active = HashMap::from([
+            ("qbittorrent".to_owned(), 3),
+            ("transmission".to_owned(), 1),
+        ]);
+        assert_eq!(
+            select_n(
+                &dlrs,
+                SelectStrategy::LeastActive,
+                &active,
+                &HashSet::new(),
+                1
+            ),
+            vec!["pan115"]
+        );
+    }
+
+    #[test]
+    fn test_select_resource_type_and_unhealthy() {
+        let dlrs = dlrs();
+        let dlrs = Dlrs::from(&dlrs);
+        let none = HashMap::new();
+
+        assert_eq!(
+            select_n(
+                &dlrs,
+                SelectStrategy::ResourceType,
+                &none,
+                &HashSet::new(),
+                1
+            ),
+            vec!["pan115"]
+        );
+
+        // 不支持种子文件的下载器不参与选择
+        let selection = Selection {
+            strategy: SelectStrategy::ResourceType,
+            resource_type: ResourceType::Torrent,
+            active: &none,
+            unhealthy: &HashSet::new(),
+        };
+        let mut state = SelectState::default();
+        assert_eq!(dlrs.select(&selection, &mut state).name(), "qbittorrent");
+
+        // 健康检查失败的下载器被跳过, 全部失败时仍然选择
+        let unhealthy = HashSet::from(["qbittorrent".to_owned()]);
+        assert_eq!(
+            select_n(&dlrs, SelectStrategy::Priority, &none, &unhealthy, 1),
+            vec!["transmission"]
+        );
+        let unhealthy: HashSet<String> = ["qbittorrent", "transmission", "pan115"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            select_n(&dlrs, SelectStrategy::Priority, &none, &unhealthy, 1),
+            vec!["qbittorrent"]
+        );
+    }
+}
@@ -34,6 +34,9 @@ pub enum Error {
     #[error("指定的下载器不存在: {0}")]
     DownloaderNotFound(String),
 
+    #[error("无效的下载器选择策略: {0}")]
+    InvalidStrategy(String),
+
     #[error("数据库错误: {0}")]
     DB(#[from] sea_orm::DbErr),
 
@@ -67,6 +70,12 @@ pub enum Error {
     #[error("Pan115错误: {0}")]
     Pan115(#[from] pan_115::errors::Pan115Error),
 
+    #[error("aria2错误: {0}")]
+    Aria2(#[from] aria2::Error),
+
+    #[error("Deluge错误: {0}")]
+    Deluge(#[from] deluge::Error),
+
     #[error("Alist错误: {0}")]
     Alist(#[from] alist::Error),
 
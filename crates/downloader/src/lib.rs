@@ -10,16 +10,18 @@ pub mod resource;
 pub mod stm;
 pub mod thirdparty;
 
-use crate::errors::Result;
+use crate::{dlrs::SelectStrategy, errors::Result};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use resource::Resource;
+use serde::Serialize;
 use std::{
     collections::HashMap,
     fmt::{self, Display},
     path::PathBuf,
+    sync::Arc,
 };
-use tokio::sync::broadcast;
+use tokio::sync::{Notify, broadcast};
 
 use model::{
     sea_orm_active_enums::{DownloadStatus, ResourceType},
@@ -52,6 +54,7 @@ pub trait Downloader: Send + Sync {
         resource: Resource,
         dir: PathBuf,
         downloader: Option<String>,
+        strategy: Option<SelectStrategy>,
         allow_fallback: bool,
     ) -> Result<()>;
     async fn list_tasks(&self, info_hashes: &[String]) -> Result<Vec<Model>>;
@@ -64,6 +67,10 @@ pub trait Downloader: Send + Sync {
     async fn retry(&self, info_hash: &str) -> Result<()>;
     async fn pause_task(&self, info_hash: &str) -> Result<()>;
     async fn resume_task(&self, info_hash: &str) -> Result<()>;
+    /// 在下载器内部整理已完成任务的文件, 下载器不支持时返回 false
+    async fn organize_files(&self, info_hash: &str, moves: &[FileMove]) -> Result<bool>;
+    /// 最近一次同步得到的活跃任务进度, key 为 info_hash
+    fn task_progress(&self) -> HashMap<String, TaskProgress>;
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool;
     fn recommended_resource_type(&self) -> ResourceType;
     fn take_dlr(&self, downloader: &str) -> Option<&dyn ThirdPartyDownloader>;
@@ -74,6 +81,8 @@ pub trait Downloader: Send + Sync {
 pub enum Event {
     /// 任务更新
     TaskUpdated((String, DownloadStatus, Option<String>)),
+    /// 每次同步后活跃任务的最新进度, key 为 info_hash
+    ProgressUpdated(HashMap<String, TaskProgress>),
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +90,24 @@ pub struct RemoteTaskStatus {
     pub status: DownloadStatus,
     pub err_msg: Option<String>,
     pub result: Option<String>,
+    /// 下载进度, 下载器无法提供时为 None
+    pub progress: Option<TaskProgress>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TaskProgress {
+    /// 下载进度, 0.0 - 1.0
+    pub progress: f64,
+    /// 已下载的字节数
+    pub downloaded: u64,
+    /// 需要下载的总字节数, 未知时为 0
+    pub total_size: u64,
+    /// 下载速度, 字节/秒
+    pub download_speed: u64,
+    /// 连接的节点数, 下载器不提供时为 None
+    pub peers: Option<u32>,
+    /// 预计剩余时间, 单位秒, 未知时为 None
+    pub eta: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -91,6 +118,14 @@ pub struct FileInfo {
     pub is_dir: bool,
 }
 
+/// 将下载器中的文件移动到目标路径, 目标路径包含新的文件名
+#[derive(Debug, Clone)]
+pub struct FileMove {
+    pub file_id: String,
+    pub file_size: usize,
+    pub target: PathBuf,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Tid(String);
 
@@ -141,6 +176,23 @@ pub trait ThirdPartyDownloader: Send + Sync {
     async fn remove_task(&self, tid: &Tid, remove_files: bool) -> Result<()>;
     async fn pause_task(&self, tid: &Tid) -> Result<()>;
     async fn resume_task(&self, tid: &Tid) -> Result<()>;
+    // 整理已完成任务的文件, 返回更新后的任务结果, 默认不支持, 返回 None
+    async fn organize_files(
+        &self,
+        _tid: &Tid,
+        _result: Option<String>,
+        _moves: &[FileMove],
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+    // 任务状态发生变化时通知 Actor 立即同步, 默认不支持, 只依赖定时同步
+    fn status_notifier(&self) -> Option<Arc<Notify>> {
+        None
+    }
+    // 检查下载器是否可用, 例如登录是否有效, 失败的下载器不参与选择
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
     // 支持的资源类型
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool;
     // 推荐的资源类型
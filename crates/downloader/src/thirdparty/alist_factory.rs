@@ -1,7 +1,7 @@
 use crate::errors::{Error, Result};
 use crate::{
-    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, ThirdPartyDownloader, Tid, config,
-    context::TorrentContext, resource::Resource,
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, TaskProgress, ThirdPartyDownloader, Tid,
+    config, context::TorrentContext, resource::Resource,
 };
 use anyhow::Context;
 use async_trait::async_trait;
@@ -35,6 +35,8 @@ impl Default for Config {
                 download_timeout: chrono::Duration::hours(1),
                 delete_task_on_completion: false,
                 priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
                 download_dir: PathBuf::from("/downloads"),
             },
             file_list_cache_size: 16,
@@ -173,6 +175,7 @@ impl ThirdPartyDownloader for AlistDownloaderImpl {
                 .get_task_info(alist::TaskType::OfflineDownload, tid.as_str())
                 .await?
                 .with_context(|| "获取任务信息失败")?;
+            let progress = task_progress(&task);
             let (status, err_msg) = map_task_status(task);
             tasks.insert(
                 tid.clone(),
@@ -180,6 +183,7 @@ impl ThirdPartyDownloader for AlistDownloaderImpl {
                     status,
                     err_msg,
                     result: None,
+                    progress: Some(progress),
                 },
             );
         }
@@ -268,6 +272,11 @@ impl ThirdPartyDownloader for AlistDownloaderImpl {
         }
     }
 
+    async fn health_check(&self) -> Result<()> {
+        self.client.get_me().await?;
+        Ok(())
+    }
+
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
         self.supports_resource_type.contains(&resource_type)
     }
@@ -281,6 +290,18 @@ impl ThirdPartyDownloader for AlistDownloaderImpl {
     }
 }
 
+fn task_progress(task: &alist::TaskInfo) -> TaskProgress {
+    // Alist 的进度范围是 0 - 100, 且不提供速度和剩余时间
+    let progress = (task.progress as f64 / 100.0).clamp(0.0, 1.0);
+    let total_size = task.total_bytes.max(0) as u64;
+    TaskProgress {
+        progress,
+        downloaded: (total_size as f64 * progress) as u64,
+        total_size,
+        ..Default::default()
+    }
+}
+
 fn map_task_status(task: alist::TaskInfo) -> (DownloadStatus, Option<String>) {
     match task.state {
         alist::TaskState::Errored | alist::TaskState::Failed | alist::TaskState::Failing => (
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    num::NonZero,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::errors::{Error, Result};
+use crate::{
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, TaskProgress, ThirdPartyDownloader, Tid,
+    config,
+    context::{TorrentContext, TorrentFileInfo},
+    resource::Resource,
+};
+use anyhow::Context;
+use aria2::model::{AddOptions, NotificationKind, Status, TaskState};
+use async_trait::async_trait;
+use lru::LruCache;
+use model::sea_orm_active_enums::{DownloadStatus, ResourceType};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+const STATUS_KEYS: &[&str] = &[
+    "gid",
+    "status",
+    "totalLength",
+    "completedLength",
+    "downloadSpeed",
+    "connections",
+    "errorCode",
+    "errorMessage",
+    "dir",
+    "files",
+    "followedBy",
+];
+
+/// 磁力链接及种子 URL 会先下载元数据, 再由新的任务下载文件, 最多跟随的次数
+const MAX_FOLLOW_DEPTH: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub generic: config::GenericConfig,
+    pub file_list_cache_size: usize,
+    /// 通过 WebSocket 订阅任务事件, 任务完成时立即同步状态
+    pub subscribe_notifications: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            generic: config::GenericConfig {
+                max_retry_count: 1,
+                retry_min_interval: chrono::Duration::seconds(30),
+                retry_max_interval: chrono::Duration::minutes(60),
+                download_timeout: chrono::Duration::hours(1),
+                delete_task_on_completion: false,
+                priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
+                download_dir: PathBuf::from("/downloads"),
+            },
+            file_list_cache_size: 16,
+            subscribe_notifications: true,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Aria2DownloaderImpl {
+    cli: Arc<aria2::Client>,
+    config: Config,
+    file_cache: Arc<Mutex<LruCache<String, String>>>,
+    notify: Option<Arc<Notify>>,
+}
+
+impl Aria2DownloaderImpl {
+    pub fn new(cli: aria2::Client, config: Config) -> Self {
+        let cli = Arc::new(cli);
+        let notify = config.subscribe_notifications.then(|| {
+            let notify = Arc::new(Notify::new());
+            tokio::spawn(listen_notifications(cli.clone(), notify.clone()));
+            notify
+        });
+        Self {
+            cli,
+            file_cache: Arc::new(Mutex::new(LruCache::new(
+                NonZero::new(config.file_list_cache_size).unwrap(),
+            ))),
+            config,
+            notify,
+        }
+    }
+
+    /// 获取任务状态, 如果任务已被新的任务接替, 返回接替任务的状态
+    async fn resolve_status(&self, gid: &str) -> aria2::Result<Status> {
+        let mut status = self.cli.tell_status(gid, STATUS_KEYS).await?;
+        for _ in 0..MAX_FOLLOW_DEPTH {
+            let Some(next) = status.followed_by.first() else {
+                break;
+            };
+            status = self.cli.tell_status(next, STATUS_KEYS).await?;
+        }
+        Ok(status)
+    }
+
+    /// 任务及其接替任务的 GID
+    async fn task_chain(&self, gid: &str) -> Vec<String> {
+        let mut gids = vec![gid.to_owned()];
+        let mut current = gid.to_owned();
+        for _ in 0..MAX_FOLLOW_DEPTH {
+            let Ok(status) = self.cli.tell_status(&current, &["followedBy"]).await else {
+                break;
+            };
+            let Some(next) = status.followed_by.into_iter().next() else {
+                break;
+            };
+            gids.push(next.clone());
+            current = next;
+        }
+        gids
+    }
+
+    fn download_context(&self, status: &Status) -> TorrentContext {
+        let download_dir = self
+            .config
+            .generic
+            .download_dir
+            .to_string_lossy()
+            .to_string();
+        let dir = Path::new(&status.dir);
+        TorrentContext {
+            dir: status.dir.replace(&download_dir, ""),
+            files: status
+                .files
+                .iter()
+                .filter(|f| f.selected)
+                .map(|f| TorrentFileInfo {
+                    name: Path::new(&f.path)
+                        .strip_prefix(dir)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or(f.path.clone()),
+                    size: f.length as usize,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 订阅 aria2 的任务事件, 连接断开后每隔一段时间重连
+async fn listen_notifications(cli: Arc<aria2::Client>, notify: Arc<Notify>) {
+    loop {
+        match cli.subscribe().await {
+            Ok(mut rx) => {
+                info!("已订阅 aria2 任务事件");
+                while let Some(notification) = rx.recv().await {
+                    if matches!(
+                        notification.kind,
+                        NotificationKind::DownloadComplete
+                            | NotificationKind::BtDownloadComplete
+                            | NotificationKind::DownloadError
+                            | NotificationKind::DownloadStop
+                    ) {
+                        notify.notify_one();
+                    }
+                }
+                warn!("aria2 WebSocket 连接已断开");
+            }
+            Err(e) => {
+                warn!("订阅 aria2 任务事件失败: {}", e);
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    }
+}
+
+#[async_trait]
+impl ThirdPartyDownloader for Aria2DownloaderImpl {
+    fn name(&self) -> &'static str {
+        "aria2"
+    }
+
+    async fn add_task(
+        &self,
+        resource: Resource,
+        dir: PathBuf,
+    ) -> Result<(Option<Tid>, Option<String>)> {
+        if dir.is_absolute() {
+            return Err(Error::DownloadDir(dir.to_string_lossy().to_string()));
+        }
+        let options = AddOptions {
+            dir: Some(
+                self.config
+                    .generic
+                    .download_dir
+                    .join(dir)
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let gid = match resource {
+            Resource::MagnetInfoHash(_) | Resource::MagnetLink(_, _) => {
+                let magnet = resource.magnet().unwrap_or_default();
+                self.cli.add_uri(&[magnet], &options).await?
+            }
+            Resource::TorrentFileBytes(data, _) => self.cli.add_torrent(&data, &options).await?,
+            Resource::TorrentURL(url, _) => self.cli.add_uri(&[url], &options).await?,
+        };
+        Ok((Some(Tid::from(gid)), None))
+    }
+
+    async fn list_tasks(&self, tids: &[Tid]) -> Result<HashMap<Tid, RemoteTaskStatus>> {
+        let mut result = HashMap::new();
+        for tid in tids {
+            let status = match self.resolve_status(tid.as_str()).await {
+                Ok(status) => status,
+                Err(e) if e.is_not_found() => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let (download_status, err_msg) = map_task_status(&status);
+            let ctx = if download_status == DownloadStatus::Completed {
+                self.download_context(&status)
+            } else {
+                TorrentContext::default()
+            };
+            let remote_task_status = RemoteTaskStatus {
+                status: download_status,
+                err_msg,
+                result: Some(ctx.try_into()?),
+                progress: Some(task_progress(&status)),
+            };
+            result.insert(tid.clone(), remote_task_status);
+        }
+        Ok(result)
+    }
+
+    async fn cancel_task(&self, tid: &Tid) -> Result<()> {
+        for gid in self.task_chain(tid.as_str()).await {
+            self.cli
+                .remove(&gid)
+                .await
+                .inspect_err(|e| warn!("取消 aria2 任务失败: gid={}, 错误: {}", gid, e))
+                .ok();
+        }
+        Ok(())
+    }
+
+    async fn remove_task(&self, tid: &Tid, remove_files: bool) -> Result<()> {
+        if remove_files {
+            // aria2 不会删除已下载的文件, 文件也不一定在本机上
+            warn!("aria2 不支持删除已下载的文件: tid={}", tid);
+        }
+        for gid in self.task_chain(tid.as_str()).await {
+            // 已停止的任务无法 remove, 只需清除下载结果
+            let _ = self.cli.remove(&gid).await;
+            let _ = self.cli.remove_download_result(&gid).await;
+        }
+        Ok(())
+    }
+
+    async fn pause_task(&self, tid: &Tid) -> Result<()> {
+        let status = self.resolve_status(tid.as_str()).await?;
+        self.cli.pause(&status.gid).await?;
+        Ok(())
+    }
+
+    async fn resume_task(&self, tid: &Tid) -> Result<()> {
+        let status = self.resolve_status(tid.as_str()).await?;
+        self.cli.unpause(&status.gid).await?;
+        Ok(())
+    }
+
+    async fn list_files(&self, tid: &Tid, result: Option<String>) -> Result<Vec<FileInfo>> {
+        let ctx = result.context(Error::NoDownloadResult(tid.to_string()))?;
+        let ctx = TorrentContext::try_from(ctx)?;
+        let files = ctx
+            .files
+            .into_iter()
+            .map(|f| {
+                let path = Path::new(&ctx.dir).join(&f.name);
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or(f.name.clone());
+                let mut hasher = DefaultHasher::new();
+                path.hash(&mut hasher);
+                let file_id = hasher.finish().to_string();
+                let fi = FileInfo {
+                    file_id: file_id.clone(),
+                    file_name,
+                    file_size: f.size,
+                    is_dir: false,
+                };
+                self.file_cache
+                    .lock()
+                    .unwrap()
+                    .put(file_id, path.to_string_lossy().to_string());
+                fi
+            })
+            .collect();
+        Ok(files)
+    }
+
+    async fn dl_file(&self, file_id: &str, _ua: &str) -> Result<DownloadInfo> {
+        let mut file_cache = self.file_cache.lock().unwrap();
+        let file_info = file_cache.get(file_id);
+        if let Some(file_path) = file_info {
+            Ok(DownloadInfo {
+                url: format!("{}/{}", self.name(), file_path),
+                access_type: AccessType::Forward,
+            })
+        } else {
+            Err(Error::FileNotFound(file_id.to_string()))
+        }
+    }
+
+    fn status_notifier(&self) -> Option<Arc<Notify>> {
+        self.notify.clone()
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.cli.tell_active(&["gid"]).await?;
+        Ok(())
+    }
+
+    fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
+        matches!(
+            resource_type,
+            ResourceType::Magnet
+                | ResourceType::InfoHash
+                | ResourceType::Torrent
+                | ResourceType::TorrentURL
+        )
+    }
+
+    fn recommended_resource_type(&self) -> ResourceType {
+        ResourceType::Torrent
+    }
+
+    fn config(&self) -> &config::GenericConfig {
+        &self.config.generic
+    }
+}
+
+fn task_progress(status: &Status) -> TaskProgress {
+    let progress = if status.total_length > 0 {
+        status.completed_length as f64 / status.total_length as f64
+    } else {
+        0.0
+    };
+    let left = status.total_length.saturating_sub(status.completed_length);
+    TaskProgress {
+        progress,
+        downloaded: status.completed_length,
+        total_size: status.total_length,
+        download_speed: status.download_speed,
+        peers: Some(status.connections as u32),
+        eta: (status.download_speed > 0).then(|| left / status.download_speed),
+    }
+}
+
+fn map_task_status(status: &Status) -> (DownloadStatus, Option<String>) {
+    // 下载完成后做种的任务也视为已完成
+    if status.is_seeding() {
+        return (DownloadStatus::Completed, None);
+    }
+    match status.status {
+        TaskState::Active => (DownloadStatus::Downloading, None),
+        TaskState::Waiting => (DownloadStatus::Pending, None),
+        TaskState::Paused => (DownloadStatus::Paused, None),
+        TaskState::Complete => (DownloadStatus::Completed, None),
+        TaskState::Error => (
+            DownloadStatus::Failed,
+            Some(
+                status
+                    .error_message
+                    .clone()
+                    .unwrap_or_else(|| "下载失败".to_string()),
+            ),
+        ),
+        TaskState::Removed => (
+            DownloadStatus::Failed,
+            Some("任务已在 aria2 中被删除".to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_task_status() {
+        let mut status = Status {
+            status: TaskState::Active,
+            total_length: 100,
+            completed_length: 50,
+            ..Default::default()
+        };
+        assert_eq!(map_task_status(&status).0, DownloadStatus::Downloading);
+
+        status.completed_length = 100;
+        assert_eq!(map_task_status(&status).0, DownloadStatus::Completed);
+
+        status.status = TaskState::Error;
+        status.completed_length = 0;
+        status.error_message = Some("No peer".to_owned());
+        assert_eq!(
+            map_task_status(&status),
+            (DownloadStatus::Failed, Some("No peer".to_owned()))
+        );
+    }
+}
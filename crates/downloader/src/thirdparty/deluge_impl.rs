@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    num::NonZero,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::errors::{Error, Result};
+use crate::{
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, TaskProgress, ThirdPartyDownloader, Tid,
+    config,
+    context::{TorrentContext, TorrentFileInfo},
+    resource::Resource,
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use deluge::model::{AddOptions, State, TorrentStatus};
+use lru::LruCache;
+use model::sea_orm_active_enums::{DownloadStatus, ResourceType};
+
+const STATUS_KEYS: &[&str] = &[
+    "name",
+    "state",
+    "progress",
+    "save_path",
+    "download_location",
+    "message",
+    "total_done",
+    "total_wanted",
+    "download_payload_rate",
+    "num_peers",
+    "eta",
+];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub generic: config::GenericConfig,
+    pub file_list_cache_size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            generic: config::GenericConfig {
+                max_retry_count: 1,
+                retry_min_interval: chrono::Duration::seconds(30),
+                retry_max_interval: chrono::Duration::minutes(60),
+                download_timeout: chrono::Duration::hours(1),
+                delete_task_on_completion: false,
+                priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
+                download_dir: PathBuf::from("/downloads"),
+            },
+            file_list_cache_size: 16,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DelugeDownloaderImpl {
+    cli: Arc<deluge::Client>,
+    config: Config,
+    file_cache: Arc<Mutex<LruCache<String, String>>>,
+}
+
+impl DelugeDownloaderImpl {
+    pub fn new(cli: deluge::Client, config: Config) -> Self {
+        Self {
+            cli: Arc::new(cli),
+            file_cache: Arc::new(Mutex::new(LruCache::new(
+                NonZero::new(config.file_list_cache_size).unwrap(),
+            ))),
+            config,
+        }
+    }
+}
+
+#[async_trait]
+impl ThirdPartyDownloader for DelugeDownloaderImpl {
+    fn name(&self) -> &'static str {
+        "deluge"
+    }
+
+    async fn add_task(
+        &self,
+        resource: Resource,
+        dir: PathBuf,
+    ) -> Result<(Option<Tid>, Option<String>)> {
+        if dir.is_absolute() {
+            return Err(Error::DownloadDir(dir.to_string_lossy().to_string()));
+        }
+        let options = AddOptions {
+            download_location: Some(
+                self.config
+                    .generic
+                    .download_dir
+                    .join(dir)
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            add_paused: Some(false),
+        };
+
+        let torrent_id = match resource {
+            Resource::MagnetInfoHash(_) | Resource::MagnetLink(_, _) => {
+                let magnet = resource.magnet().unwrap_or_default();
+                self.cli.add_torrent_magnet(&magnet, &options).await?
+            }
+            Resource::TorrentFileBytes(data, info_hash) => {
+                self.cli
+                    .add_torrent_file(&format!("{}.torrent", info_hash), &data, &options)
+                    .await?
+            }
+            Resource::TorrentURL(url, _) => self.cli.add_torrent_url(&url, &options).await?,
+        };
+        // 种子已存在时 Deluge 返回 null
+        torrent_id.context("添加种子任务失败, 种子可能已存在")?;
+
+        Ok((None, None))
+    }
+
+    async fn list_tasks(&self, tids: &[Tid]) -> Result<HashMap<Tid, RemoteTaskStatus>> {
+        if tids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let ids: Vec<String> = tids.iter().map(|tid| tid.to_string()).collect();
+        let ui = self.cli.update_ui(STATUS_KEYS, &ids).await?;
+
+        let mut result = HashMap::new();
+        for (id, torrent) in ui.torrents {
+            let (status, err_msg) = map_task_status(&torrent);
+            let mut ctx = TorrentContext::default();
+
+            if status == DownloadStatus::Completed {
+                let download_dir = self
+                    .config
+                    .generic
+                    .download_dir
+                    .to_string_lossy()
+                    .to_string();
+                ctx.dir = torrent.location().replace(&download_dir, "");
+                ctx.files = self
+                    .cli
+                    .get_torrent_files(&id)
+                    .await?
+                    .into_iter()
+                    .filter(|f| f.priority != 0)
+                    .map(|f| TorrentFileInfo {
+                        name: f.path,
+                        size: f.size as usize,
+                    })
+                    .collect();
+            }
+
+            let rts = RemoteTaskStatus {
+                status,
+                err_msg,
+                result: Some(ctx.try_into()?),
+                progress: Some(task_progress(&torrent)),
+            };
+            result.insert(Tid::from(id), rts);
+        }
+        Ok(result)
+    }
+
+    async fn cancel_task(&self, tid: &Tid) -> Result<()> {
+        self.cli.pause_torrent(tid.as_str()).await?;
+        Ok(())
+    }
+
+    async fn remove_task(&self, tid: &Tid, remove_files: bool) -> Result<()> {
+        self.cli.remove_torrent(tid.as_str(), remove_files).await?;
+        Ok(())
+    }
+
+    async fn pause_task(&self, tid: &Tid) -> Result<()> {
+        self.cli.pause_torrent(tid.as_str()).await?;
+        Ok(())
+    }
+
+    async fn resume_task(&self, tid: &Tid) -> Result<()> {
+        self.cli.resume_torrent(tid.as_str()).await?;
+        Ok(())
+    }
+
+    async fn list_files(&self, tid: &Tid, result: Option<String>) -> Result<Vec<FileInfo>> {
+        let ctx = result.context(Error::NoDownloadResult(tid.to_string()))?;
+        let ctx = TorrentContext::try_from(ctx)?;
+
+        let files = ctx
+            .files
+            .into_iter()
+            .map(|f| {
+                let path = Path::new(&ctx.dir).join(&f.name);
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or(f.name.clone());
+
+                let mut hasher = DefaultHasher::new();
+                path.hash(&mut hasher);
+                let file_id = hasher.finish().to_string();
+
+                // 将文件路径缓存起来，以便后续下载使用
+                self.file_cache
+                    .lock()
+                    .unwrap()
+                    .put(file_id.clone(), path.to_string_lossy().to_string());
+
+                FileInfo {
+                    file_id,
+                    file_name,
+                    file_size: f.size,
+                    is_dir: false,
+                }
+            })
+            .collect();
+
+        Ok(files)
+    }
+
+    async fn dl_file(&self, file_id: &str, _ua: &str) -> Result<DownloadInfo> {
+        let mut file_cache = self.file_cache.lock().unwrap();
+        let file_info = file_cache.get(file_id);
+        if let Some(file_path) = file_info {
+            Ok(DownloadInfo {
+                url: format!("{}/{}", self.name(), file_path),
+                access_type: AccessType::Forward,
+            })
+        } else {
+            Err(Error::FileNotFound(file_id.to_string()))
+        }
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        if !self.cli.connected().await? {
+            return Err(anyhow::anyhow!("Deluge Web 未连接到守护进程").into());
+        }
+        Ok(())
+    }
+
+    fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
+        matches!(
+            resource_type,
+            ResourceType::Magnet
+                | ResourceType::InfoHash
+                | ResourceType::Torrent
+                | ResourceType::TorrentURL
+        )
+    }
+
+    fn recommended_resource_type(&self) -> ResourceType {
+        ResourceType::Torrent
+    }
+
+    fn config(&self) -> &config::GenericConfig {
+        &self.config.generic
+    }
+}
+
+fn task_progress(torrent: &TorrentStatus) -> TaskProgress {
+    TaskProgress {
+        // Deluge 的进度范围是 0 - 100
+        progress: (torrent.progress / 100.0).clamp(0.0, 1.0),
+        downloaded: torrent.total_done,
+        total_size: torrent.total_wanted,
+        download_speed: torrent.download_payload_rate,
+        peers: Some(torrent.num_peers),
+        // 未知时为 0 或负数
+        eta: (torrent.eta > 0).then_some(torrent.eta as u64),
+    }
+}
+
+fn map_task_status(torrent: &TorrentStatus) -> (DownloadStatus, Option<String>) {
+    if torrent.progress >= 100.0 {
+        return (DownloadStatus::Completed, None);
+    }
+    match torrent.state {
+        State::Seeding => (DownloadStatus::Completed, None),
+        State::Error => {
+            let msg = if torrent.message.is_empty() {
+                "下载失败".to_string()
+            } else {
+                torrent.message.clone()
+            };
+            (DownloadStatus::Failed, Some(msg))
+        }
+        State::Paused => (DownloadStatus::Paused, None),
+        State::Queued => (DownloadStatus::Pending, None),
+        _ => (DownloadStatus::Downloading, None),
+    }
+}
@@ -1,4 +1,7 @@
 pub mod alist_factory;
+pub mod aria2_impl;
+pub mod deluge_impl;
 pub mod pan_115_impl;
 pub mod qbittorrent_impl;
 pub mod transmission_impl;
+pub mod watch_folder_impl;
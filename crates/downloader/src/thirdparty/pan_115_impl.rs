@@ -9,14 +9,18 @@ use std::{
 use anyhow::Context;
 use lru::LruCache;
 use model::sea_orm_active_enums::DownloadStatus;
-use pan_115::{errors::Pan115Error, model::OfflineTaskStatus};
+use pan_115::{
+    errors::Pan115Error,
+    model::{OfflineTask, OfflineTaskStatus},
+};
 use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
 use crate::errors::{Error, Result};
 use crate::{
-    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, Resource, ResourceType,
-    ThirdPartyDownloader, Tid, config, context::Pan115Context,
+    AccessType, DownloadInfo, FileInfo, FileMove, RemoteTaskStatus, Resource, ResourceType,
+    TaskProgress, ThirdPartyDownloader, Tid, config,
+    context::{Pan115Context, Pan115File},
 };
 use async_trait::async_trait;
 
@@ -41,6 +45,8 @@ impl Default for Config {
                 download_timeout: chrono::Duration::minutes(30),
                 delete_task_on_completion: true,
                 priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
                 download_dir: PathBuf::from("/downloads"),
             },
         }
@@ -149,12 +155,14 @@ impl ThirdPartyDownloader for Pan115DownloaderImpl {
                 };
                 let context: Pan115Context = (&task).into();
                 let status = task.status();
+                let progress = Some(task_progress(&task));
                 (
                     Tid::from(task.info_hash),
                     RemoteTaskStatus {
                         status: map_task_status(status),
                         err_msg,
                         result: Some(context.try_into().unwrap_or_default()),
+                        progress,
                     },
                 )
             }));
@@ -172,8 +180,21 @@ impl ThirdPartyDownloader for Pan115DownloaderImpl {
     async fn list_files(&self, tid: &Tid, result: Option<String>) -> Result<Vec<FileInfo>> {
         match result {
             Some(result) => {
-                let mut cache = self.file_list_cache.lock().await;
                 let context: Pan115Context = serde_json::from_str(&result)?;
+                if !context.files.is_empty() {
+                    return Ok(context
+                        .files
+                        .into_iter()
+                        .map(|file| FileInfo {
+                            file_id: file.file_id,
+                            file_name: file.file_name,
+                            file_size: file.file_size,
+                            is_dir: false,
+                        })
+                        .collect());
+                }
+
+                let mut cache = self.file_list_cache.lock().await;
                 let now = std::time::Instant::now();
 
                 if let Some((files, last_update)) = cache.get(&context.file_id) {
@@ -253,6 +274,83 @@ impl ThirdPartyDownloader for Pan115DownloaderImpl {
         Ok(())
     }
 
+    async fn organize_files(
+        &self,
+        tid: &Tid,
+        result: Option<String>,
+        moves: &[FileMove],
+    ) -> Result<Option<String>> {
+        let result = result.ok_or_else(|| Error::NoDownloadResult(tid.to_string()))?;
+        let mut context: Pan115Context = serde_json::from_str(&result)?;
+
+        let mut files = Vec::with_capacity(moves.len());
+        for mv in moves {
+            let (Some(dir), Some(file_name)) = (
+                mv.target.parent(),
+                mv.target.file_name().and_then(|name| name.to_str()),
+            ) else {
+                return Err(anyhow::anyhow!("非法的目标路径: {}", mv.target.display()).into());
+            };
+            let dir_cid = self.get_or_create_dir_cid(&dir.to_path_buf()).await?;
+
+            // 覆盖目标目录中的同名文件, 如洗版前的旧文件
+            let name = file_name.to_owned();
+            let existing: Vec<String> = self
+                .pan115
+                .list_files_with_fn(&dir_cid, move |file| !file.is_dir() && file.name == name)
+                .await?
+                .iter()
+                .map(|file| file.file_id())
+                .filter(|file_id| file_id != &mv.file_id)
+                .collect();
+            self.pan115.delete_files(&existing).await?;
+
+            self.pan115
+                .move_files(&[mv.file_id.as_str()], dir_cid.as_str())
+                .await?;
+            self.pan115
+                .rename_file(mv.file_id.as_str(), file_name)
+                .await?;
+            debug!(
+                "已整理文件: file_id={}, target={}",
+                mv.file_id,
+                mv.target.display()
+            );
+
+            context.dir_id = dir_cid;
+            files.push(Pan115File {
+                file_id: mv.file_id.clone(),
+                file_name: file_name.to_owned(),
+                file_size: mv.file_size,
+            });
+        }
+
+        // 单文件的任务, file_id 即为视频文件本身, 不需要清理
+        if !moves.iter().any(|mv| mv.file_id == context.file_id) {
+            self.remove_leftovers(&context.file_id)
+                .await
+                .inspect_err(|e| {
+                    warn!("清理下载目录失败: file_id={}, 错误: {}", context.file_id, e);
+                })
+                .ok();
+        }
+        self.file_list_cache.lock().await.pop(&context.file_id);
+
+        if let Some(video) = files.first() {
+            context.file_id = video.file_id.clone();
+            context.file_name = video.file_name.clone();
+        }
+        context.files = files;
+        info!("115网盘任务文件整理完成: tid={}", tid);
+        Ok(Some(serde_json::to_string(&context)?))
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        // login_check 会更新客户端的用户 ID, 使用副本检查登录状态
+        self.pan115.clone().login_check().await?;
+        Ok(())
+    }
+
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
         matches!(resource_type, ResourceType::Magnet | ResourceType::InfoHash)
     }
@@ -284,6 +382,35 @@ impl Pan115DownloaderImpl {
         cache.insert(path, cid);
     }
 
+    /// 清理整理后剩余的文件, 只剩广告等无用文件时删除整个下载目录
+    async fn remove_leftovers(&self, dir_cid: &str) -> Result<()> {
+        let leftovers: Vec<_> = self
+            .pan115
+            .list_files_recursive(dir_cid)
+            .await?
+            .into_iter()
+            .filter(|file| !file.is_dir())
+            .collect();
+
+        if leftovers.iter().all(|file| is_junk_file(&file.name)) {
+            info!("删除整理后的下载目录: cid={}", dir_cid);
+            self.pan115.delete_files(&[dir_cid]).await?;
+        } else {
+            let junk: Vec<String> = leftovers
+                .iter()
+                .filter(|file| is_junk_file(&file.name))
+                .map(|file| file.file_id())
+                .collect();
+            info!(
+                "删除下载目录中的无用文件: cid={}, 数量={}",
+                dir_cid,
+                junk.len()
+            );
+            self.pan115.delete_files(&junk).await?;
+        }
+        Ok(())
+    }
+
     /// 获取或创建目录 CID
     async fn get_or_create_dir_cid(&self, path: &PathBuf) -> Result<String> {
         if let Some(cid) = self.get_cached_cid(path).await {
@@ -297,6 +424,32 @@ impl Pan115DownloaderImpl {
     }
 }
 
+/// 发布组附带的广告及说明文件
+fn is_junk_file(name: &str) -> bool {
+    const JUNK_EXTS: &[&str] = &[
+        "txt", "url", "jpg", "jpeg", "png", "gif", "bmp", "webp", "html", "htm", "mht", "lnk",
+        "exe", "apk",
+    ];
+    std::path::Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| JUNK_EXTS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn task_progress(task: &OfflineTask) -> TaskProgress {
+    // percentDone 的范围是 0 - 100
+    let progress = (task.percent / 100.0).clamp(0.0, 1.0);
+    let total_size = task.size.max(0) as u64;
+    TaskProgress {
+        progress,
+        downloaded: (total_size as f64 * progress) as u64,
+        total_size,
+        download_speed: task.rate_download.max(0.0) as u64,
+        peers: Some(task.peers.max(0) as u32),
+        eta: (task.left_time > 0).then_some(task.left_time as u64),
+    }
+}
+
 fn map_task_status(status: OfflineTaskStatus) -> DownloadStatus {
     match status {
         OfflineTaskStatus::Pending => DownloadStatus::Pending,
@@ -310,6 +463,16 @@ fn map_task_status(status: OfflineTaskStatus) -> DownloadStatus {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_junk_file() {
+        assert!(is_junk_file("招募翻译.TXT"));
+        assert!(is_junk_file("更多动漫请访问.url"));
+        assert!(is_junk_file("poster.jpg"));
+        assert!(!is_junk_file("[LoliHouse] Frieren - 05.mkv"));
+        assert!(!is_junk_file("[LoliHouse] Frieren - 05.sc.ass"));
+        assert!(!is_junk_file("README"));
+    }
+
     #[test]
     fn test_get_or_create_dir_cid() {
         let path = PathBuf::from("/downloads/test/1");
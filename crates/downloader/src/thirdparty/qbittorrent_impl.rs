@@ -3,12 +3,14 @@ use std::{
     hash::{DefaultHasher, Hash, Hasher},
     num::NonZero,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
 };
 
 use crate::errors::{Error, Result};
 use crate::{
-    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, ThirdPartyDownloader, Tid, config,
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, TaskProgress, ThirdPartyDownloader, Tid,
+    config,
     context::{TorrentContext, TorrentFileInfo},
     resource::Resource,
 };
@@ -18,11 +20,15 @@ use lru::LruCache;
 use model::sea_orm_active_enums::{DownloadStatus, ResourceType};
 use qbittorrent::model::{
     Sep,
-    torrent::{
-        AddTorrentArg, GetTorrentListArg, Hashes, State, Torrent, TorrentFile, TorrentSource,
-    },
+    sync::SyncState,
+    torrent::{AddTorrentArg, Hashes, State, Torrent, TorrentFile, TorrentSource},
 };
 use reqwest::Url;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
+use tracing::warn;
+
+/// 轮询 sync/maindata 的间隔, 种子状态变化时立即通知 Actor 同步
+const MAINDATA_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -40,6 +46,8 @@ impl Default for Config {
                 download_timeout: chrono::Duration::hours(1),
                 delete_task_on_completion: false,
                 priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
                 download_dir: PathBuf::from("/downloads"),
             },
             file_list_cache_size: 16,
@@ -52,18 +60,72 @@ pub struct QbittorrentDownloaderImpl {
     cli: Arc<qbittorrent::client::Client>,
     config: Config,
     file_cache: Arc<Mutex<LruCache<String, String>>>,
+    /// 通过 sync/maindata 增量同步的种子列表
+    sync_state: Arc<AsyncMutex<SyncState>>,
+    notify: Arc<Notify>,
 }
 
 impl QbittorrentDownloaderImpl {
     pub fn new(cli: qbittorrent::client::Client, config: Config) -> Self {
+        let cli = Arc::new(cli);
+        let sync_state = Arc::new(AsyncMutex::new(SyncState::default()));
+        let notify = Arc::new(Notify::new());
+        tokio::spawn(poll_maindata(
+            cli.clone(),
+            Arc::downgrade(&sync_state),
+            notify.clone(),
+        ));
         Self {
-            cli: Arc::new(cli),
+            cli,
             file_cache: Arc::new(Mutex::new(LruCache::new(
                 NonZero::new(config.file_list_cache_size).unwrap(),
             ))),
+            sync_state,
+            notify,
             config,
         }
     }
+
+    /// 拉取自上次同步以来的变化并返回指定种子的最新状态
+    async fn sync_torrents(&self, tids: &[Tid]) -> Result<Vec<(Tid, Torrent)>> {
+        let mut state = self.sync_state.lock().await;
+        let data = self.cli.sync_maindata(state.rid()).await?;
+        state.apply(data);
+        let mut torrents = Vec::new();
+        for tid in tids {
+            if let Some(torrent) = state.get(&tid.as_str().to_lowercase())? {
+                torrents.push((tid.clone(), torrent));
+            }
+        }
+        Ok(torrents)
+    }
+}
+
+/// 定时拉取 sync/maindata 的增量, 种子状态变化时通知 Actor 同步,
+/// 由 Actor 产生 Synced/Completed 等事件, 下载器被释放后退出
+async fn poll_maindata(
+    cli: Arc<qbittorrent::client::Client>,
+    sync_state: Weak<AsyncMutex<SyncState>>,
+    notify: Arc<Notify>,
+) {
+    let mut ticker = tokio::time::interval(MAINDATA_POLL_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let Some(sync_state) = sync_state.upgrade() else {
+            break;
+        };
+        let mut state = sync_state.lock().await;
+        match cli.sync_maindata(state.rid()).await {
+            Ok(data) => {
+                let changed = data.has_state_changes();
+                state.apply(data);
+                if changed {
+                    notify.notify_one();
+                }
+            }
+            Err(e) => warn!("拉取 qBittorrent 任务状态变化失败: {}", e),
+        }
+    }
 }
 
 #[async_trait]
@@ -114,18 +176,19 @@ impl ThirdPartyDownloader for QbittorrentDownloaderImpl {
         Ok((None, None))
     }
 
-    async fn list_tasks(&self, tid: &[Tid]) -> Result<HashMap<Tid, RemoteTaskStatus>> {
-        let arg = GetTorrentListArg {
-            hashes: Some(Sep::<Tid, '|'>::from(tid).to_string()),
-            ..Default::default()
-        };
-        let torrents = self.cli.get_torrent_list(arg).await?;
+    async fn list_tasks(&self, tids: &[Tid]) -> Result<HashMap<Tid, RemoteTaskStatus>> {
+        if tids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let torrents = self.sync_torrents(tids).await?;
         let mut result = HashMap::new();
-        for torrent in torrents {
+        for (tid, torrent) in torrents {
             let hash = torrent.hash.clone().unwrap();
             let (status, err_msg) = map_task_status(&torrent);
+            let progress = task_progress(&torrent);
             let mut ctx = TorrentContext::default();
 
+            // 文件列表只在任务完成时获取一次
             if status == DownloadStatus::Completed {
                 let contents = self.cli.get_torrent_contents(&hash, None).await?;
                 if let Some(save_path) = torrent.save_path {
@@ -149,12 +212,17 @@ impl ThirdPartyDownloader for QbittorrentDownloaderImpl {
                 status,
                 err_msg,
                 result: Some(ctx.try_into()?),
+                progress,
             };
-            result.insert(Tid::from(hash), remote_task_status);
+            result.insert(tid, remote_task_status);
         }
         Ok(result)
     }
 
+    fn status_notifier(&self) -> Option<Arc<Notify>> {
+        Some(self.notify.clone())
+    }
+
     async fn cancel_task(&self, tid: &Tid) -> Result<()> {
         self.cli
             .stop_torrents(Hashes::Hashes(Sep::from(vec![tid.to_string()])))
@@ -230,6 +298,11 @@ impl ThirdPartyDownloader for QbittorrentDownloaderImpl {
         }
     }
 
+    async fn health_check(&self) -> Result<()> {
+        self.cli.app_version().await?;
+        Ok(())
+    }
+
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
         matches!(
             resource_type,
@@ -249,6 +322,26 @@ impl ThirdPartyDownloader for QbittorrentDownloaderImpl {
     }
 }
 
+fn task_progress(torrent: &Torrent) -> Option<TaskProgress> {
+    let progress = torrent.progress?;
+    let bytes = |v: Option<i64>| v.unwrap_or_default().max(0) as u64;
+    Some(TaskProgress {
+        progress,
+        downloaded: bytes(torrent.completed),
+        total_size: bytes(torrent.size),
+        download_speed: bytes(torrent.dlspeed),
+        peers: torrent
+            .num_seeds
+            .zip(torrent.num_leechs)
+            .map(|(seeds, leechs)| (seeds + leechs).max(0) as u32),
+        // qBittorrent 用 8640000 表示无穷大
+        eta: torrent
+            .eta
+            .filter(|eta| (0..8_640_000).contains(eta))
+            .map(|eta| eta as u64),
+    })
+}
+
 fn map_task_status(torrent: &Torrent) -> (DownloadStatus, Option<String>) {
     if torrent.state.is_none() {
         return (DownloadStatus::Pending, None);
@@ -17,7 +17,8 @@ use transmission_rpc::{
 };
 
 use crate::{
-    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, ThirdPartyDownloader, Tid, config,
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, TaskProgress, ThirdPartyDownloader, Tid,
+    config,
     context::{TorrentContext, TorrentFileInfo},
     resource::Resource,
 };
@@ -43,6 +44,8 @@ impl Default for Config {
                 download_timeout: chrono::Duration::hours(1),
                 delete_task_on_completion: false,
                 priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
                 download_dir: PathBuf::from("/downloads/complete"),
             },
             file_list_cache_size: 16,
@@ -156,6 +159,11 @@ impl ThirdPartyDownloader for TransmissionDownloaderImpl {
             TorrentGetField::Files,
             TorrentGetField::DownloadDir,
             TorrentGetField::PercentDone,
+            TorrentGetField::SizeWhenDone,
+            TorrentGetField::LeftUntilDone,
+            TorrentGetField::RateDownload,
+            TorrentGetField::PeersConnected,
+            TorrentGetField::Eta,
         ];
 
         // 直接使用Arc里的引用
@@ -206,6 +214,7 @@ impl ThirdPartyDownloader for TransmissionDownloaderImpl {
                 status,
                 err_msg,
                 result: Some(ctx.try_into()?),
+                progress: task_progress(&torrent),
             };
 
             result.insert(Tid::from(hash), rts);
@@ -299,6 +308,14 @@ impl ThirdPartyDownloader for TransmissionDownloaderImpl {
         }
     }
 
+    async fn health_check(&self) -> Result<()> {
+        let resp = self.cli.session_get().await?;
+        if !resp.is_ok() {
+            return Err(anyhow::anyhow!("获取 Transmission 会话失败: {}", resp.result).into());
+        }
+        Ok(())
+    }
+
     fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
         matches!(
             resource_type,
@@ -319,6 +336,21 @@ impl ThirdPartyDownloader for TransmissionDownloaderImpl {
 }
 
 // 辅助函数：将 Transmission 的状态映射到我们的状态
+fn task_progress(torrent: &transmission_rpc::types::Torrent) -> Option<TaskProgress> {
+    let progress = torrent.percent_done?;
+    let bytes = |v: Option<i64>| v.unwrap_or_default().max(0) as u64;
+    let total_size = bytes(torrent.size_when_done);
+    Some(TaskProgress {
+        progress: progress as f64,
+        downloaded: total_size.saturating_sub(bytes(torrent.left_until_done)),
+        total_size,
+        download_speed: bytes(torrent.rate_download),
+        peers: torrent.peers_connected.map(|n| n.max(0) as u32),
+        // Transmission 用负数表示未知
+        eta: torrent.eta.filter(|eta| *eta >= 0).map(|eta| eta as u64),
+    })
+}
+
 fn map_task_status(torrent: &transmission_rpc::types::Torrent) -> (DownloadStatus, Option<String>) {
     if torrent.percent_done.is_none() && torrent.status.is_none() {
         return (DownloadStatus::Pending, None);
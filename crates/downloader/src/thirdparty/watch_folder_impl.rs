@@ -0,0 +1,632 @@
+use crate::errors::{Error, Result};
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    num::NonZero,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use lru::LruCache;
+use model::sea_orm_active_enums::{DownloadStatus, ResourceType};
+use tracing::{info, warn};
+
+use crate::{
+    AccessType, DownloadInfo, FileInfo, RemoteTaskStatus, ThirdPartyDownloader, Tid, config,
+    context::{TorrentContext, TorrentFileInfo},
+    resource::Resource,
+};
+
+/// 监视目录下载器配置
+///
+/// 任意支持监视目录的下载器 (rtorrent, Deluge, aria2 等) 都可以使用,
+/// 种子写入 `watch_dir` 后由下载器自动添加, 下载完成的文件需要出现在 `generic.download_dir` 中
+#[derive(Clone)]
+pub struct Config {
+    pub generic: config::GenericConfig,
+    pub file_list_cache_size: usize,
+    /// 下载器监视的目录, 写入 .torrent 或 .magnet 文件
+    pub watch_dir: PathBuf,
+    /// 记录已投递任务的文件列表, 用于检测下载是否完成
+    pub state_dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            generic: config::GenericConfig {
+                max_retry_count: 1,
+                retry_min_interval: chrono::Duration::seconds(30),
+                retry_max_interval: chrono::Duration::minutes(60),
+                download_timeout: chrono::Duration::hours(6),
+                delete_task_on_completion: false,
+                priority: 0,
+                max_concurrent_tasks: 0,
+                weight: 1,
+                download_dir: PathBuf::from("/downloads/complete"),
+            },
+            file_list_cache_size: 16,
+            watch_dir: PathBuf::from("/watch"),
+            state_dir: PathBuf::from("/watch/.state"),
+        }
+    }
+}
+
+/// 下载器写入未完成文件时常用的后缀, 存在这些文件时任务视为未完成
+const INCOMPLETE_SUFFIXES: &[&str] = &[".part", ".!qB", ".!ut", ".aria2", ".crdownload", ".tmp"];
+
+/// 文件的大小及修改时间, 两次同步之间没有变化才视为下载完成
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    name: String,
+    size: u64,
+    /// 修改时间, 自 UNIX 纪元的毫秒数
+    modified: u128,
+}
+
+/// 已投递到监视目录的任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WatchEntry {
+    /// 种子名称, 即下载完成后的文件或目录名
+    name: String,
+    /// 期望的保存目录, 相对于 download_dir
+    dir: PathBuf,
+    /// 种子中的文件, 路径相对于保存目录, 磁力链接无法得知文件列表, 为空
+    files: Vec<TorrentFileInfo>,
+    /// 上一次同步时找到的文件状态
+    #[serde(default)]
+    stamps: Vec<FileStamp>,
+}
+
+#[derive(Clone)]
+pub struct WatchFolderDownloaderImpl {
+    client: reqwest::Client,
+    config: Config,
+    file_cache: Arc<Mutex<LruCache<String, String>>>,
+}
+
+impl WatchFolderDownloaderImpl {
+    pub fn new(client: reqwest::Client, config: Config) -> Self {
+        Self {
+            client,
+            file_cache: Arc::new(Mutex::new(LruCache::new(
+                NonZero::new(config.file_list_cache_size).unwrap(),
+            ))),
+            config,
+        }
+    }
+
+    fn state_path(&self, tid: &Tid) -> PathBuf {
+        self.config.state_dir.join(format!("{}.json", tid))
+    }
+
+    async fn load_entry(&self, tid: &Tid) -> anyhow::Result<Option<WatchEntry>> {
+        match tokio::fs::read(self.state_path(tid)).await {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save_entry(&self, tid: &Tid, entry: &WatchEntry) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.config.state_dir).await?;
+        tokio::fs::write(self.state_path(tid), serde_json::to_vec(entry)?).await?;
+        Ok(())
+    }
+
+    /// 将种子或磁力链接写入监视目录, 先写临时文件再重命名, 避免下载器读到不完整的文件
+    async fn write_watch_file(&self, file_name: &str, content: &[u8]) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.config.watch_dir).await?;
+        let tmp_path = self.config.watch_dir.join(format!(".{}.tmp", file_name));
+        tokio::fs::write(&tmp_path, content).await?;
+        tokio::fs::rename(&tmp_path, self.config.watch_dir.join(file_name)).await?;
+        Ok(())
+    }
+
+    async fn fetch_torrent(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// 在下载目录中查找已完成的文件, 优先查找任务指定的保存目录, 其次是下载目录本身
+    ///
+    /// 返回文件所在目录 (相对于 download_dir) 及文件列表
+    async fn locate(&self, entry: &WatchEntry) -> Option<(PathBuf, Vec<TorrentFileInfo>)> {
+        let mut candidates = vec![entry.dir.clone()];
+        if !entry.dir.as_os_str().is_empty() {
+            candidates.push(PathBuf::new());
+        }
+        for rel in candidates {
+            let root = self.config.generic.download_dir.join(&rel);
+            if entry.files.is_empty() {
+                if let Ok(files) = scan_files(&root, &entry.name).await {
+                    return Some((rel, files));
+                }
+            } else if files_complete(&root, &entry.files).await {
+                return Some((rel, entry.files.clone()));
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl ThirdPartyDownloader for WatchFolderDownloaderImpl {
+    fn name(&self) -> &'static str {
+        "watch_folder"
+    }
+
+    async fn add_task(
+        &self,
+        resource: Resource,
+        dir: PathBuf,
+    ) -> Result<(Option<Tid>, Option<String>)> {
+        if dir.is_absolute() {
+            return Err(Error::DownloadDir(dir.to_string_lossy().to_string()));
+        }
+
+        let (info_hash, ext, content, entry) = match resource {
+            Resource::TorrentFileBytes(data, info_hash) => {
+                let torrent = torrent::Torrent::from_bytes(&data)?;
+                let entry = entry_from_torrent(&torrent, dir)?;
+                (info_hash, "torrent", data.to_vec(), entry)
+            }
+            Resource::TorrentURL(url, info_hash) => {
+                let data = self.fetch_torrent(&url).await?;
+                let torrent = torrent::Torrent::from_bytes(&data)?;
+                let entry = entry_from_torrent(&torrent, dir)?;
+                (info_hash, "torrent", data, entry)
+            }
+            Resource::MagnetLink(magnet, info_hash) => {
+                // 磁力链接只能通过 dn 参数得知下载完成后的名称
+                let name = magnet_display_name(&magnet).with_context(|| {
+                    format!("磁力链接缺少 dn 参数, 无法检测下载完成: {}", magnet)
+                })?;
+                let entry = WatchEntry {
+                    name,
+                    dir,
+                    files: vec![],
+                    stamps: vec![],
+                };
+                (info_hash, "magnet", magnet.into_bytes(), entry)
+            }
+            Resource::MagnetInfoHash(_) => {
+                return Err(Error::UnsupportedResourceType(ResourceType::InfoHash));
+            }
+        };
+
+        let tid = Tid::from(info_hash.clone());
+        self.save_entry(&tid, &entry).await?;
+        self.write_watch_file(&format!("{}.{}", info_hash, ext), &content)
+            .await?;
+        info!(
+            "已写入监视目录: info_hash={}, name={}",
+            info_hash, entry.name
+        );
+
+        Ok((None, None))
+    }
+
+    async fn list_tasks(&self, tids: &[Tid]) -> Result<HashMap<Tid, RemoteTaskStatus>> {
+        let mut result = HashMap::new();
+        for tid in tids {
+            let Some(entry) = self.load_entry(tid).await? else {
+                warn!("监视目录任务记录不存在: tid={}", tid);
+                continue;
+            };
+
+            let Some((rel, files)) = self.locate(&entry).await else {
+                result.insert(tid.clone(), downloading());
+                continue;
+            };
+
+            // 预分配或仍在写入的文件大小可能已经一致, 只有文件在两次同步之间没有变化才视为完成
+            let root = self.config.generic.download_dir.join(&rel);
+            let stamps = file_stamps(&root, &files).await?;
+            if stamps != entry.stamps {
+                let entry = WatchEntry { stamps, ..entry };
+                self.save_entry(tid, &entry).await?;
+                result.insert(tid.clone(), downloading());
+                continue;
+            }
+
+            let rel = rel.to_string_lossy();
+            let ctx = TorrentContext {
+                dir: if rel.is_empty() {
+                    String::new()
+                } else {
+                    format!("/{}", rel)
+                },
+                files,
+            };
+            let rts = RemoteTaskStatus {
+                status: DownloadStatus::Completed,
+                err_msg: None,
+                result: Some(ctx.try_into()?),
+                progress: None,
+            };
+            result.insert(tid.clone(), rts);
+        }
+        Ok(result)
+    }
+
+    async fn cancel_task(&self, tid: &Tid) -> Result<()> {
+        // 只能撤回尚未被下载器读取的种子, 已添加的任务需要在下载器中手动处理
+        for ext in ["torrent", "magnet"] {
+            let path = self.config.watch_dir.join(format!("{}.{}", tid, ext));
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        Ok(())
+    }
+
+    async fn remove_task(&self, tid: &Tid, remove_files: bool) -> Result<()> {
+        let Some(entry) = self.load_entry(tid).await? else {
+            return Ok(());
+        };
+        let located = if remove_files {
+            self.locate(&entry).await
+        } else {
+            None
+        };
+        if let Some((rel, _)) = located {
+            let path = self.config.generic.download_dir.join(rel).join(&entry.name);
+            let removed = if path.is_dir() {
+                tokio::fs::remove_dir_all(&path).await
+            } else {
+                tokio::fs::remove_file(&path).await
+            };
+            removed.with_context(|| format!("删除文件失败: {}", path.display()))?;
+        }
+        self.cancel_task(tid).await?;
+        let _ = tokio::fs::remove_file(self.state_path(tid)).await;
+        Ok(())
+    }
+
+    async fn pause_task(&self, tid: &Tid) -> Result<()> {
+        warn!("监视目录下载器不支持暂停任务: tid={}", tid);
+        Ok(())
+    }
+
+    async fn resume_task(&self, tid: &Tid) -> Result<()> {
+        warn!("监视目录下载器不支持恢复任务: tid={}", tid);
+        Ok(())
+    }
+
+    async fn list_files(&self, tid: &Tid, result: Option<String>) -> Result<Vec<FileInfo>> {
+        let ctx = result.context(Error::NoDownloadResult(tid.to_string()))?;
+        let ctx = TorrentContext::try_from(ctx)?;
+
+        let files = ctx
+            .files
+            .into_iter()
+            .map(|f| {
+                let path = Path::new(&ctx.dir).join(&f.name);
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or(f.name.clone());
+
+                let mut hasher = DefaultHasher::new();
+                path.hash(&mut hasher);
+                let file_id = hasher.finish().to_string();
+
+                // 将文件路径缓存起来，以便后续下载使用
+                self.file_cache
+                    .lock()
+                    .unwrap()
+                    .put(file_id.clone(), path.to_string_lossy().to_string());
+
+                FileInfo {
+                    file_id,
+                    file_name,
+                    file_size: f.size,
+                    is_dir: false,
+                }
+            })
+            .collect();
+
+        Ok(files)
+    }
+
+    async fn dl_file(&self, file_id: &str, _ua: &str) -> Result<DownloadInfo> {
+        let mut file_cache = self.file_cache.lock().unwrap();
+        let file_info = file_cache.get(file_id);
+        if let Some(file_path) = file_info {
+            Ok(DownloadInfo {
+                url: format!("{}/{}", self.name(), file_path),
+                access_type: AccessType::Forward,
+            })
+        } else {
+            Err(Error::FileNotFound(file_id.to_string()))
+        }
+    }
+
+    fn supports_resource_type(&self, resource_type: ResourceType) -> bool {
+        matches!(
+            resource_type,
+            ResourceType::Magnet | ResourceType::Torrent | ResourceType::TorrentURL
+        )
+    }
+
+    fn recommended_resource_type(&self) -> ResourceType {
+        ResourceType::Torrent
+    }
+
+    fn config(&self) -> &config::GenericConfig {
+        &self.config.generic
+    }
+}
+
+/// 从种子中读取文件列表, 路径以种子名称开头, 与下载器保存的结构一致
+fn entry_from_torrent(torrent: &torrent::Torrent, dir: PathBuf) -> anyhow::Result<WatchEntry> {
+    let name = torrent
+        .info
+        .name
+        .clone()
+        .filter(|name| is_safe_path(name))
+        .context("种子名称为空或不合法")?;
+
+    let files = match &torrent.info.files {
+        Some(files) => files
+            .iter()
+            .filter(|f| !is_padding_file(&f.path))
+            .map(|f| TorrentFileInfo {
+                name: format!("{}/{}", name, f.path.join("/")),
+                size: f.length as usize,
+            })
+            .filter(|f| is_safe_path(&f.name))
+            .collect(),
+        None => vec![TorrentFileInfo {
+            name: name.clone(),
+            size: torrent.info.length.unwrap_or_default() as usize,
+        }],
+    };
+    if files.is_empty() {
+        anyhow::bail!("种子中没有可下载的文件");
+    }
+
+    Ok(WatchEntry {
+        name,
+        dir,
+        files,
+        stamps: vec![],
+    })
+}
+
+fn magnet_display_name(magnet: &str) -> Option<String> {
+    let url = url::Url::parse(magnet).ok()?;
+    url.query_pairs()
+        .find(|(k, _)| k == "dn")
+        .map(|(_, v)| v.into_owned())
+        .filter(|name| is_safe_path(name))
+}
+
+/// BEP 47 填充文件, 下载器不会写入磁盘
+fn is_padding_file(path: &[String]) -> bool {
+    path.last()
+        .is_some_and(|name| name.starts_with("_____padding_file"))
+        || path.first().is_some_and(|dir| dir == ".pad")
+}
+
+/// 只允许普通的相对路径, 避免 `..` 等路径逃出下载目录
+fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn downloading() -> RemoteTaskStatus {
+    RemoteTaskStatus {
+        status: DownloadStatus::Downloading,
+        err_msg: None,
+        result: None,
+        progress: None,
+    }
+}
+
+fn is_incomplete_file(name: &str) -> bool {
+    INCOMPLETE_SUFFIXES
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+/// 种子中的文件都已存在且大小一致, 并且没有下载器的未完成标记
+async fn files_complete(root: &Path, files: &[TorrentFileInfo]) -> bool {
+    for file in files {
+        match tokio::fs::metadata(root.join(&file.name)).await {
+            Ok(meta) if meta.is_file() && meta.len() as usize == file.size => {}
+            _ => return false,
+        }
+        for suffix in INCOMPLETE_SUFFIXES {
+            let marker = root.join(format!("{}{}", file.name, suffix));
+            if tokio::fs::try_exists(marker).await.unwrap_or(false) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// 记录文件当前的大小及修改时间
+async fn file_stamps(root: &Path, files: &[TorrentFileInfo]) -> anyhow::Result<Vec<FileStamp>> {
+    let mut stamps = Vec::with_capacity(files.len());
+    for file in files {
+        let meta = tokio::fs::metadata(root.join(&file.name)).await?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        stamps.push(FileStamp {
+            name: file.name.clone(),
+            size: meta.len(),
+            modified,
+        });
+    }
+    Ok(stamps)
+}
+
+/// 列出 `root/name` 下的所有文件, name 为文件时只返回该文件,
+/// 存在未完成标记的文件时返回错误
+async fn scan_files(root: &Path, name: &str) -> anyhow::Result<Vec<TorrentFileInfo>> {
+    for suffix in INCOMPLETE_SUFFIXES {
+        if tokio::fs::try_exists(root.join(format!("{}{}", name, suffix))).await? {
+            anyhow::bail!("{} 仍在下载中", name);
+        }
+    }
+    let meta = tokio::fs::metadata(root.join(name)).await?;
+    if meta.is_file() {
+        return Ok(vec![TorrentFileInfo {
+            name: name.to_owned(),
+            size: meta.len() as usize,
+        }]);
+    }
+
+    let mut files = vec![];
+    let mut pending = vec![name.to_owned()];
+    while let Some(rel) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(root.join(&rel)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let child = format!("{}/{}", rel, entry.file_name().to_string_lossy());
+            let meta = entry.metadata().await?;
+            if meta.is_dir() {
+                pending.push(child);
+            } else if is_incomplete_file(&child) {
+                anyhow::bail!("{} 仍在下载中", child);
+            } else {
+                files.push(TorrentFileInfo {
+                    name: child,
+                    size: meta.len() as usize,
+                });
+            }
+        }
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTI_FILE_TORRENT: &[u8] = b"d4:infod5:filesld6:lengthi5e4:pathl6:01.mkveed6:lengthi3e4:pathl4:subs6:01.asseed6:lengthi2e4:pathl4:.pad1:0eee4:name4:Show12:piece lengthi16384e6:pieces0:ee";
+
+    fn downloader(name: &str) -> (WatchFolderDownloaderImpl, PathBuf) {
+        let root =
+            std::env::temp_dir().join(format!("watch-folder-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let mut config = Config {
+            watch_dir: root.join("watch"),
+            state_dir: root.join("state"),
+            ..Default::default()
+        };
+        config.generic.download_dir = root.join("complete");
+        (
+            WatchFolderDownloaderImpl::new(reqwest::Client::new(), config),
+            root,
+        )
+    }
+
+    #[test]
+    fn test_entry_from_torrent() {
+        let torrent = torrent::Torrent::from_bytes(MULTI_FILE_TORRENT).unwrap();
+        let entry = entry_from_torrent(&torrent, PathBuf::from("Show/Season 1")).unwrap();
+        assert_eq!(entry.name, "Show");
+        let files: Vec<_> = entry
+            .files
+            .iter()
+            .map(|f| (f.name.as_str(), f.size))
+            .collect();
+        assert_eq!(files, vec![("Show/01.mkv", 5), ("Show/subs/01.ass", 3)]);
+    }
+
+    #[test]
+    fn test_magnet_display_name() {
+        assert_eq!(
+            magnet_display_name(
+                "magnet:?xt=urn:btih:0123456789abcdef0123456789abcdef01234567&dn=%5BSub%5D%20Show%2001.mkv"
+            ),
+            Some("[Sub] Show 01.mkv".to_owned())
+        );
+        assert_eq!(
+            magnet_display_name("magnet:?xt=urn:btih:0123456789abcdef0123456789abcdef01234567"),
+            None
+        );
+        assert_eq!(
+            magnet_display_name(
+                "magnet:?xt=urn:btih:0123456789abcdef0123456789abcdef01234567&dn=.."
+            ),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_incomplete_files() {
+        let root = std::env::temp_dir().join(format!("watch-folder-scan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("Show")).unwrap();
+        std::fs::write(root.join("Show/01.mkv"), b"video").unwrap();
+        std::fs::write(root.join("Show/02.mkv.part"), b"vid").unwrap();
+        assert!(scan_files(&root, "Show").await.is_err());
+
+        std::fs::rename(root.join("Show/02.mkv.part"), root.join("Show/02.mkv")).unwrap();
+        let files = scan_files(&root, "Show").await.unwrap();
+        assert_eq!(files.len(), 2);
+
+        std::fs::write(root.join("03.mkv.!qB"), b"").unwrap();
+        assert!(scan_files(&root, "03.mkv").await.is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_watch_folder_lifecycle() -> Result<()> {
+        let (dlr, root) = downloader("lifecycle");
+        let resource = Resource::from_torrent_file_bytes(MULTI_FILE_TORRENT)?;
+        let tid = Tid::from(resource.info_hash());
+
+        dlr.add_task(resource, PathBuf::from("Show")).await?;
+        assert!(root.join(format!("watch/{}.torrent", tid)).exists());
+
+        let tasks = dlr.list_tasks(std::slice::from_ref(&tid)).await?;
+        assert_eq!(tasks[&tid].status, DownloadStatus::Downloading);
+
+        // 下载器保存到了默认目录, 而不是指定的目录
+        let complete = root.join("complete/Show/subs");
+        std::fs::create_dir_all(&complete).unwrap();
+        std::fs::write(root.join("complete/Show/01.mkv"), b"video").unwrap();
+        let tasks = dlr.list_tasks(std::slice::from_ref(&tid)).await?;
+        assert_eq!(tasks[&tid].status, DownloadStatus::Downloading);
+
+        // 大小一致但仍有未完成标记
+        std::fs::write(complete.join("01.ass"), b"sub").unwrap();
+        std::fs::write(complete.join("01.ass.aria2"), b"").unwrap();
+        let tasks = dlr.list_tasks(std::slice::from_ref(&tid)).await?;
+        assert_eq!(tasks[&tid].status, DownloadStatus::Downloading);
+
+        // 文件在两次同步之间没有变化才视为完成
+        std::fs::remove_file(complete.join("01.ass.aria2")).unwrap();
+        let tasks = dlr.list_tasks(std::slice::from_ref(&tid)).await?;
+        assert_eq!(tasks[&tid].status, DownloadStatus::Downloading);
+        let tasks = dlr.list_tasks(std::slice::from_ref(&tid)).await?;
+        assert_eq!(tasks[&tid].status, DownloadStatus::Completed);
+
+        let files = dlr.list_files(&tid, tasks[&tid].result.clone()).await?;
+        assert_eq!(files.len(), 2);
+        let info = dlr.dl_file(&files[0].file_id, "").await?;
+        assert_eq!(info.url, "watch_folder/Show/01.mkv");
+
+        dlr.remove_task(&tid, true).await?;
+        assert!(!root.join("complete/Show").exists());
+        assert!(!root.join(format!("state/{}.json", tid)).exists());
+        assert!(dlr.list_tasks(&[tid]).await?.is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 mod mock_store;
@@ -6,7 +7,7 @@ use chrono::{Local, TimeDelta};
 use downloader::actor::Actor;
 use downloader::config::GenericConfig;
 use downloader::{Downloader, Event, Tid};
-use downloader::{MockThirdPartyDownloader, RemoteTaskStatus, config::Config};
+use downloader::{MockThirdPartyDownloader, RemoteTaskStatus, TaskProgress, config::Config};
 use downloader::{Store, resource::Resource};
 use mock_store::MockStore;
 use model::sea_orm_active_enums::DownloadStatus;
@@ -31,6 +32,8 @@ fn create_test_config() -> GenericConfig {
         download_timeout: TimeDelta::try_seconds(10).unwrap(),
         delete_task_on_completion: true,
         priority: 0,
+        max_concurrent_tasks: 0,
+        weight: 1,
     }
 }
 
@@ -65,6 +68,11 @@ fn create_mock_downloader(config: GenericConfig) -> MockThirdPartyDownloader {
 
     mock_downloader.expect_resume_task().returning(|_| Ok(()));
     mock_downloader.expect_config().return_const(config);
+    mock_downloader.expect_status_notifier().returning(|| None);
+    mock_downloader.expect_health_check().returning(|| Ok(()));
+    mock_downloader
+        .expect_supports_resource_type()
+        .returning(|_| true);
     mock_downloader
 }
 
@@ -88,6 +96,7 @@ fn create_test_worker(mock_store: MockStore, mock_downloader: MockThirdPartyDown
             sync_interval: Duration::from_millis(100),
             retry_processor_interval: Duration::from_secs(1),
             event_queue_size: 100,
+            ..Default::default()
         },
         vec![Arc::new(Box::new(mock_downloader))],
     )
@@ -104,6 +113,7 @@ fn create_failed_tasks() -> HashMap<Tid, RemoteTaskStatus> {
             status: DownloadStatus::Failed,
             err_msg: Some("error msg".to_string()),
             result: None,
+            progress: None,
         },
     );
     tasks
@@ -130,7 +140,7 @@ async fn test_retry_exceed_max_count() {
     let resource = create_test_resource();
     // 添加任务
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test"), None, None, true)
         .await
         .unwrap();
 
@@ -171,6 +181,7 @@ async fn test_download_timeout_no_retry() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
     let mut config = create_test_config();
@@ -187,7 +198,7 @@ async fn test_download_timeout_no_retry() {
 
     // 添加任务
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test"), None, None, true)
         .await
         .unwrap();
 
@@ -228,6 +239,7 @@ async fn test_worker_retry_success() {
             status: DownloadStatus::Failed,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -238,6 +250,7 @@ async fn test_worker_retry_success() {
             status: DownloadStatus::Completed,
             err_msg: None,
             result: Some("completed".to_string()),
+            progress: None,
         },
     );
 
@@ -259,7 +272,7 @@ async fn test_worker_retry_success() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -291,6 +304,7 @@ async fn test_worker_add_task_success() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -308,7 +322,7 @@ async fn test_worker_add_task_success() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -339,6 +353,7 @@ async fn test_worker_add_cancel_downloading_task() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -356,7 +371,7 @@ async fn test_worker_add_cancel_downloading_task() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -391,6 +406,7 @@ async fn test_worker_add_retry_failed_task() {
             status: DownloadStatus::Failed,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -401,6 +417,7 @@ async fn test_worker_add_retry_failed_task() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -424,7 +441,7 @@ async fn test_worker_add_retry_failed_task() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -457,6 +474,7 @@ async fn test_worker_recover_pending_tasks() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -519,6 +537,7 @@ async fn test_worker_pause_task() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -536,7 +555,7 @@ async fn test_worker_pause_task() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -568,6 +587,7 @@ async fn test_worker_resume_task() {
             status: DownloadStatus::Paused,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -584,7 +604,7 @@ async fn test_worker_resume_task() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -620,6 +640,7 @@ async fn test_worker_user_manual_pause_task() {
             status: DownloadStatus::Paused,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -637,7 +658,7 @@ async fn test_worker_user_manual_pause_task() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -676,6 +697,7 @@ async fn test_worker_fallback_task() {
                 status: DownloadStatus::Failed,
                 err_msg: Some("模拟下载失败".to_string()),
                 result: None,
+                progress: None,
             },
         );
         Ok(tasks)
@@ -683,6 +705,13 @@ async fn test_worker_fallback_task() {
     failed_downloader
         .expect_config()
         .return_const(failed_config);
+    failed_downloader
+        .expect_status_notifier()
+        .returning(|| None);
+    failed_downloader.expect_health_check().returning(|| Ok(()));
+    failed_downloader
+        .expect_supports_resource_type()
+        .returning(|_| true);
     failed_downloader
         .expect_remove_task()
         .returning(|_, _| Ok(()));
@@ -703,6 +732,7 @@ async fn test_worker_fallback_task() {
                 status: DownloadStatus::Completed,
                 err_msg: None,
                 result: None,
+                progress: None,
             },
         );
         Ok(tasks)
@@ -713,6 +743,15 @@ async fn test_worker_fallback_task() {
     success_downloader
         .expect_config()
         .return_const(success_config);
+    success_downloader
+        .expect_status_notifier()
+        .returning(|| None);
+    success_downloader
+        .expect_health_check()
+        .returning(|| Ok(()));
+    success_downloader
+        .expect_supports_resource_type()
+        .returning(|_| true);
 
     // 创建存储和配置
     let store = MockStore::new();
@@ -720,6 +759,7 @@ async fn test_worker_fallback_task() {
         event_queue_size: 100,
         sync_interval: Duration::from_secs(1),
         retry_processor_interval: Duration::from_secs(1),
+        ..Default::default()
     };
 
     // 创建下载器工作者
@@ -739,7 +779,7 @@ async fn test_worker_fallback_task() {
     // 添加下载任务
     let resource = create_test_resource();
     worker
-        .add_task(resource.clone(), PathBuf::from("/tmp"), None, true)
+        .add_task(resource.clone(), PathBuf::from("/tmp"), None, None, true)
         .await
         .unwrap();
 
@@ -785,6 +825,7 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
                 status: DownloadStatus::Failed,
                 err_msg: Some("模拟下载失败".to_string()),
                 result: None,
+                progress: None,
             },
         );
         Ok(tasks)
@@ -792,6 +833,13 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
     failed_downloader
         .expect_config()
         .return_const(failed_config);
+    failed_downloader
+        .expect_status_notifier()
+        .returning(|| None);
+    failed_downloader.expect_health_check().returning(|| Ok(()));
+    failed_downloader
+        .expect_supports_resource_type()
+        .returning(|_| true);
     failed_downloader
         .expect_remove_task()
         .returning(|_, _| Ok(()));
@@ -812,6 +860,7 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
                 status: DownloadStatus::Downloading,
                 err_msg: None,
                 result: None,
+                progress: None,
             },
         );
         Ok(tasks)
@@ -822,6 +871,15 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
     success_downloader
         .expect_config()
         .return_const(success_config);
+    success_downloader
+        .expect_status_notifier()
+        .returning(|| None);
+    success_downloader
+        .expect_health_check()
+        .returning(|| Ok(()));
+    success_downloader
+        .expect_supports_resource_type()
+        .returning(|_| true);
 
     // 创建存储和配置
     let store = MockStore::new();
@@ -829,6 +887,7 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
         event_queue_size: 100,
         sync_interval: Duration::from_secs(1),
         retry_processor_interval: Duration::from_secs(1),
+        ..Default::default()
     };
 
     // 创建下载器工作者
@@ -848,7 +907,7 @@ async fn test_worker_fallback_task_with_allow_fallback_false() {
     // 添加下载任务
     let resource = create_test_resource();
     worker
-        .add_task(resource.clone(), PathBuf::from("/tmp"), None, false)
+        .add_task(resource.clone(), PathBuf::from("/tmp"), None, None, false)
         .await
         .unwrap();
 
@@ -882,6 +941,7 @@ async fn test_notify_subscriber() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -892,6 +952,7 @@ async fn test_notify_subscriber() {
             status: DownloadStatus::Completed,
             err_msg: None,
             result: Some("completed".to_string()),
+            progress: None,
         },
     );
 
@@ -916,7 +977,7 @@ async fn test_notify_subscriber() {
 
     // 添加任务并同步
     worker_clone
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -959,6 +1020,252 @@ async fn test_notify_subscriber() {
     assert_eq!(tasks[0].context, Some("completed".to_string()));
 }
 
+#[tokio::test]
+async fn test_task_progress_snapshot() {
+    // 初始化测试环境
+    init_test_env();
+
+    let resource = create_test_resource();
+    let progress = TaskProgress {
+        progress: 0.5,
+        downloaded: 50,
+        total_size: 100,
+        download_speed: 10,
+        peers: Some(3),
+        eta: Some(5),
+    };
+    let mut downloading_task = HashMap::new();
+    downloading_task.insert(
+        Tid::from(resource.info_hash()),
+        RemoteTaskStatus {
+            status: DownloadStatus::Downloading,
+            err_msg: None,
+            result: None,
+            progress: Some(progress.clone()),
+        },
+    );
+
+    let mock_store = MockStore::new();
+    let mut mock_downloader = create_mock_downloader(create_test_config());
+    mock_downloader
+        .expect_list_tasks()
+        .returning(move |_| Ok(downloading_task.clone()));
+
+    let mut worker = create_test_worker(mock_store, mock_downloader);
+    worker.spawn().await.unwrap();
+    let mut rx = worker.subscribe().await;
+
+    worker
+        .add_task(resource.clone(), PathBuf::from("/tmp"), None, None, false)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    worker.sync_all().await;
+
+    // 验证进度快照
+    assert_eq!(
+        worker.task_progress().get(resource.info_hash()),
+        Some(&progress)
+    );
+
+    // 验证收到了进度更新通知
+    let mut received_progress = false;
+    for _ in 0..5 {
+        match tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+            Ok(Ok(Event::ProgressUpdated(p))) if p.contains_key(resource.info_hash()) => {
+                received_progress = true;
+                break;
+            }
+            Ok(Ok(_)) => {}
+            _ => break,
+        }
+    }
+    assert!(received_progress, "应该收到任务进度的通知");
+
+    worker.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_max_concurrent_tasks() {
+    // 初始化测试环境
+    init_test_env();
+
+    let resource1 = create_random_resource();
+    let resource2 = create_random_resource();
+
+    // 远程只返回第一个任务, 测试手动同步前保持下载中, 之后已完成
+    let completed = Arc::new(AtomicBool::new(false));
+    let remote_status = {
+        let completed = completed.clone();
+        let info_hash = resource1.info_hash().to_owned();
+        move |_: &[Tid]| {
+            let status = if completed.load(Ordering::SeqCst) {
+                DownloadStatus::Completed
+            } else {
+                DownloadStatus::Downloading
+            };
+            let mut tasks = HashMap::new();
+            tasks.insert(
+                Tid::from(info_hash.as_str()),
+                RemoteTaskStatus {
+                    status,
+                    err_msg: None,
+                    result: None,
+                    progress: None,
+                },
+            );
+            Ok(tasks)
+        }
+    };
+
+    let mut config = create_test_config();
+    config.max_concurrent_tasks = 1;
+    config.delete_task_on_completion = false;
+    let mock_store = MockStore::new();
+    let mut mock_downloader = create_mock_downloader(config);
+    mock_downloader.expect_list_tasks().returning(remote_status);
+
+    // 关闭定时同步, 由测试手动触发
+    let mut worker = Actor::new(
+        Box::new(mock_store.clone()),
+        Config {
+            sync_interval: Duration::from_secs(3600),
+            retry_processor_interval: Duration::from_secs(3600),
+            event_queue_size: 100,
+            ..Default::default()
+        },
+        vec![Arc::new(Box::new(mock_downloader))],
+    )
+    .unwrap();
+    worker.spawn().await.unwrap();
+
+    worker
+        .add_task(resource1.clone(), PathBuf::from("/tmp"), None, None, false)
+        .await
+        .unwrap();
+    worker
+        .add_task(resource2.clone(), PathBuf::from("/tmp"), None, None, false)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // 并发已满, 第二个任务排队等待
+    let tasks = mock_store
+        .list_by_hashes(&[resource2.info_hash().to_string()])
+        .await
+        .unwrap();
+    assert_eq!(tasks[0].download_status, DownloadStatus::Pending);
+
+    // 定时同步不会让排队的任务越过并发限制
+    worker.sync_all().await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let tasks = mock_store
+        .list_by_hashes(&[resource2.info_hash().to_string()])
+        .await
+        .unwrap();
+    assert_eq!(tasks[0].download_status, DownloadStatus::Pending);
+
+    // 第一个任务完成后启动排队的任务
+    completed.store(true, Ordering::SeqCst);
+    worker.sync_all().await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let tasks = mock_store
+        .list_by_hashes(&[resource1.info_hash().to_string()])
+        .await
+        .unwrap();
+    assert_eq!(tasks[0].download_status, DownloadStatus::Completed);
+    let tasks = mock_store
+        .list_by_hashes(&[resource2.info_hash().to_string()])
+        .await
+        .unwrap();
+    assert_eq!(tasks[0].download_status, DownloadStatus::Downloading);
+
+    worker.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recover_queued_tasks() {
+    // 初始化测试环境
+    init_test_env();
+
+    // 模拟重启前的状态: 一个任务下载中, 两个任务排队
+    let mock_store = MockStore::new();
+    let now = Local::now().naive_utc();
+    let resources: Vec<_> = (0..3).map(|_| create_random_resource()).collect();
+    for (i, resource) in resources.iter().enumerate() {
+        mock_store
+            .insert_task(torrent_download_tasks::Model {
+                info_hash: resource.info_hash().to_string(),
+                download_status: if i == 0 {
+                    DownloadStatus::Downloading
+                } else {
+                    DownloadStatus::Pending
+                },
+                downloader: "mock_downloader".to_string(),
+                dir: "test".to_string(),
+                context: None,
+                err_msg: None,
+                retry_count: 0,
+                next_retry_at: now,
+                // 后创建的任务排在后面
+                created_at: now + TimeDelta::seconds(i as i64),
+                updated_at: now,
+                magnet: None,
+                resource_type: resource.get_type(),
+                allow_fallback: false,
+                torrent_url: None,
+                tid: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    let mut config = create_test_config();
+    config.max_concurrent_tasks = 2;
+    let mut mock_downloader = create_mock_downloader(config);
+    let downloading = resources[0].info_hash().to_owned();
+    mock_downloader.expect_list_tasks().returning(move |_| {
+        let mut tasks = HashMap::new();
+        tasks.insert(
+            Tid::from(downloading.as_str()),
+            RemoteTaskStatus {
+                status: DownloadStatus::Downloading,
+                err_msg: None,
+                result: None,
+                progress: None,
+            },
+        );
+        Ok(tasks)
+    });
+
+    let mut worker = Actor::new(
+        Box::new(mock_store.clone()),
+        Config {
+            sync_interval: Duration::from_secs(3600),
+            retry_processor_interval: Duration::from_secs(3600),
+            event_queue_size: 100,
+            ..Default::default()
+        },
+        vec![Arc::new(Box::new(mock_downloader))],
+    )
+    .unwrap();
+    worker.spawn().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // 重启前下载中的任务继续占用槽位, 只有最早创建的排队任务启动
+    let hashes: Vec<_> = resources
+        .iter()
+        .map(|r| r.info_hash().to_string())
+        .collect();
+    let tasks = mock_store.list_by_hashes(&hashes).await.unwrap();
+    assert_eq!(tasks[0].download_status, DownloadStatus::Downloading);
+    assert_eq!(tasks[1].download_status, DownloadStatus::Downloading);
+    assert_eq!(tasks[2].download_status, DownloadStatus::Pending);
+
+    worker.shutdown().await.unwrap();
+}
+
 #[tokio::test]
 async fn test_reset_stm_state() {
     // 初始化测试环境
@@ -973,6 +1280,7 @@ async fn test_reset_stm_state() {
             status: DownloadStatus::Completed,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -984,6 +1292,7 @@ async fn test_reset_stm_state() {
             status: DownloadStatus::Downloading,
             err_msg: None,
             result: None,
+            progress: None,
         },
     );
 
@@ -1000,7 +1309,7 @@ async fn test_reset_stm_state() {
 
     // 添加任务并同步
     worker
-        .add_task(resource.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
 
@@ -1016,7 +1325,7 @@ async fn test_reset_stm_state() {
     assert_eq!(tasks.len(), 1);
     assert_eq!(tasks[0].info_hash, resource.info_hash());
     worker
-        .add_task(resource2.clone(), PathBuf::from("test2"), None, true)
+        .add_task(resource2.clone(), PathBuf::from("test2"), None, None, true)
         .await
         .unwrap();
     tokio::time::sleep(Duration::from_secs(1)).await;
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use model::{sea_orm_active_enums::Source, torrents};
+
+/// 聚合后的种子信息, 记录了所有发现过该种子的来源
+#[derive(Debug, Clone)]
+pub struct AggregatedTorrent {
+    pub torrent: torrents::Model,
+    pub sources: Vec<Source>,
+}
+
+/// 多个种子来源的结果聚合器
+///
+/// 以 info hash 去重, 同一个种子被多个来源发现时保留信息最完整的记录:
+/// 种子文件优先于磁力链接, 发布时间取最早的, 文件大小取已知的, 做种数取较大值
+#[derive(Debug, Default)]
+pub struct TorrentAggregator {
+    torrents: Vec<AggregatedTorrent>,
+    index: HashMap<String, usize>,
+}
+
+impl TorrentAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加某个来源的种子, 按照添加顺序, 先添加的来源作为主来源
+    pub fn add(&mut self, mut torrent: torrents::Model) {
+        torrent.info_hash = torrent.info_hash.to_lowercase();
+        match self.index.get(&torrent.info_hash) {
+            Some(&i) => {
+                let aggregated = &mut self.torrents[i];
+                if !aggregated.sources.contains(&torrent.source) {
+                    aggregated.sources.push(torrent.source.clone());
+                }
+                merge(&mut aggregated.torrent, torrent);
+            }
+            None => {
+                self.index
+                    .insert(torrent.info_hash.clone(), self.torrents.len());
+                self.torrents.push(AggregatedTorrent {
+                    sources: vec![torrent.source.clone()],
+                    torrent,
+                });
+            }
+        }
+    }
+
+    pub fn extend<I: IntoIterator<Item = torrents::Model>>(&mut self, torrents: I) {
+        for torrent in torrents {
+            self.add(torrent);
+        }
+    }
+
+    /// 与数据库中已存在的记录合并, 已存在的记录作为基础, 保留其标题与来源
+    pub fn merge_existing(&mut self, existing: Vec<torrents::Model>) {
+        for mut exist in existing {
+            exist.info_hash = exist.info_hash.to_lowercase();
+            if let Some(&i) = self.index.get(&exist.info_hash) {
+                let aggregated = &mut self.torrents[i];
+                let incoming = std::mem::replace(&mut aggregated.torrent, exist);
+                merge(&mut aggregated.torrent, incoming);
+            }
+        }
+    }
+
+    pub fn info_hashes(&self) -> Vec<String> {
+        self.torrents
+            .iter()
+            .map(|t| t.torrent.info_hash.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.torrents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.torrents.is_empty()
+    }
+
+    pub fn into_torrents(self) -> Vec<AggregatedTorrent> {
+        self.torrents
+    }
+}
+
+/// 将 other 中更完整的信息合并到 target
+fn merge(target: &mut torrents::Model, other: torrents::Model) {
+    if target.data.is_none() && other.data.is_some() {
+        target.data = other.data;
+    }
+    if target.magnet.is_none() && other.magnet.is_some() {
+        target.magnet = other.magnet;
+    }
+    if target.download_url.is_none() && other.download_url.is_some() {
+        target.download_url = other.download_url;
+    }
+    if target.size <= 0 && other.size > 0 {
+        target.size = other.size;
+    }
+    if other.pub_date < target.pub_date {
+        target.pub_date = other.pub_date;
+    }
+    // 不同来源的做种数统计口径不同, 取较大值
+    if other.seeders > target.seeders {
+        target.seeders = other.seeders;
+    }
+    if target.title.is_empty() {
+        target.title = other.title;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn torrent(info_hash: &str, source: Source, pub_date: &str) -> torrents::Model {
+        torrents::Model {
+            info_hash: info_hash.to_owned(),
+            bangumi_id: 1,
+            title: format!("{:?}", source),
+            size: 0,
+            magnet: None,
+            data: None,
+            download_url: None,
+            pub_date: NaiveDateTime::parse_from_str(pub_date, "%Y-%m-%d %H:%M:%S").unwrap(),
+            source,
+            seeders: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut aggregator = TorrentAggregator::new();
+
+        let mut mikan = torrent("AAAA", Source::Mikan, "2025-01-02 00:00:00");
+        mikan.magnet = Some("magnet:?xt=urn:btih:aaaa".to_owned());
+        let mut nyaa = torrent("aaaa", Source::NyaaLand, "2025-01-01 00:00:00");
+        nyaa.size = 1024;
+        nyaa.seeders = Some(12);
+        let mut acgrip = torrent("aaaa", Source::AcgripOrg, "2025-01-03 00:00:00");
+        acgrip.data = Some(vec![1, 2, 3]);
+        acgrip.size = 2048;
+        let other = torrent("bbbb", Source::DmhyOrg, "2025-01-01 00:00:00");
+
+        aggregator.extend(vec![mikan, nyaa, acgrip, other]);
+        assert_eq!(aggregator.len(), 2);
+
+        let torrents = aggregator.into_torrents();
+        let merged = &torrents[0];
+        assert_eq!(
+            merged.sources,
+            vec![Source::Mikan, Source::NyaaLand, Source::AcgripOrg]
+        );
+        assert_eq!(merged.torrent.info_hash, "aaaa");
+        assert_eq!(merged.torrent.title, "Mikan");
+        assert_eq!(merged.torrent.source, Source::Mikan);
+        assert_eq!(merged.torrent.size, 1024);
+        assert_eq!(merged.torrent.seeders, Some(12));
+        assert_eq!(merged.torrent.data, Some(vec![1, 2, 3]));
+        assert_eq!(
+            merged.torrent.magnet.as_deref(),
+            Some("magnet:?xt=urn:btih:aaaa")
+        );
+        assert_eq!(merged.torrent.pub_date.to_string(), "2025-01-01 00:00:00");
+        assert_eq!(torrents[1].sources, vec![Source::DmhyOrg]);
+    }
+
+    #[test]
+    fn test_merge_existing() {
+        let mut aggregator = TorrentAggregator::new();
+        let mut acgrip = torrent("aaaa", Source::AcgripOrg, "2025-01-03 00:00:00");
+        acgrip.data = Some(vec![1, 2, 3]);
+        aggregator.add(acgrip);
+
+        let mut exist = torrent("aaaa", Source::Mikan, "2025-01-02 00:00:00");
+        exist.size = 4096;
+        aggregator.merge_existing(vec![
+            exist,
+            torrent("cccc", Source::Mikan, "2025-01-01 00:00:00"),
+        ]);
+
+        let torrents = aggregator.into_torrents();
+        assert_eq!(torrents.len(), 1);
+        let merged = &torrents[0].torrent;
+        assert_eq!(merged.source, Source::Mikan);
+        assert_eq!(merged.size, 4096);
+        assert_eq!(merged.data, Some(vec![1, 2, 3]));
+        assert_eq!(merged.pub_date.to_string(), "2025-01-02 00:00:00");
+        assert_eq!(torrents[0].sources, vec![Source::AcgripOrg]);
+    }
+}
@@ -5,12 +5,14 @@ use anyhow::Result;
 use chrono::NaiveDateTime;
 use sea_orm::{
     ColumnTrait, ConnectOptions, Database, DatabaseConnection, EntityTrait, IntoActiveModel,
-    QueryFilter, Set, sea_query::OnConflict,
+    NotSet, QueryFilter, Set, TransactionTrait, sea_query::OnConflict,
 };
 
 use bangumi_tv::model::EpisodeList;
 use mikan::client::Calendar;
-use model::{bangumi, episodes, torrents};
+use model::{
+    bangumi, episodes, mikan_collect_state, sea_orm_active_enums::Source, torrent_sources, torrents,
+};
 
 #[derive(Clone)]
 pub struct Db(Arc<DatabaseConnection>);
@@ -38,6 +40,10 @@ impl Db {
     pub fn conn(&self) -> &DatabaseConnection {
         &self.0
     }
+
+    pub fn conn_pool(&self) -> Arc<DatabaseConnection> {
+        self.0.clone()
+    }
 }
 
 /// Bangumi 相关
@@ -98,14 +104,18 @@ impl Db {
     }
 
     pub async fn batch_insert_bangumi(&self, bangumis: Vec<bangumi::Model>) -> Result<Vec<i32>> {
-        let count = bangumis.len() as i32;
-        let db = self.conn();
-        let insert_result = bangumi::Entity::insert_many(
-            bangumis.into_iter().map(|model| model.into_active_model()),
-        )
-        .exec(db)
-        .await?;
-        Ok((insert_result.last_insert_id..(insert_result.last_insert_id + count)).collect())
+        // 批量插入时各数据库返回的 last_insert_id 语义不同 (MySQL 为第一行, SQLite 为最后一行),
+        // 因此在事务中逐条插入以获取准确的 ID
+        let txn = self.conn().begin().await?;
+        let mut ids = Vec::with_capacity(bangumis.len());
+        for model in bangumis {
+            let mut active = model.into_active_model();
+            active.id = NotSet;
+            let result = bangumi::Entity::insert(active).exec(&txn).await?;
+            ids.push(result.last_insert_id);
+        }
+        txn.commit().await?;
+        Ok(ids)
     }
 
     pub async fn list_bangumi_by_mikan_ids(
@@ -127,7 +137,7 @@ impl Db {
         title: String,
         mikan_id: i32,
         bangumi_tv_id: Option<i32>,
-        tmdb_id: Option<u64>,
+        tmdb_id: Option<i64>,
     ) -> Result<()> {
         let now = chrono::Local::now().naive_utc();
         let _ = self
@@ -208,23 +218,28 @@ impl Db {
             return Ok(());
         }
         let db = self.conn();
-        episodes::Entity::insert_many(episodes.into_iter().map(|model| model.into_active_model()))
-            .on_conflict(
-                OnConflict::columns([episodes::Column::BangumiId, episodes::Column::Number])
-                    .update_columns([
-                        episodes::Column::UpdatedAt,
-                        episodes::Column::DurationSeconds,
-                        episodes::Column::SortNumber,
-                        episodes::Column::Name,
-                        episodes::Column::AirDate,
-                        episodes::Column::Description,
-                        episodes::Column::ImageUrl,
-                        episodes::Column::Kind,
-                    ])
-                    .to_owned(),
-            )
-            .exec(db)
-            .await?;
+        episodes::Entity::insert_many(episodes.into_iter().map(|model| {
+            let mut active = model.into_active_model();
+            // 由数据库生成自增 ID, SQLite 不会把显式写入的 0 当作自增
+            active.id = NotSet;
+            active
+        }))
+        .on_conflict(
+            OnConflict::columns([episodes::Column::BangumiId, episodes::Column::Number])
+                .update_columns([
+                    episodes::Column::UpdatedAt,
+                    episodes::Column::DurationSeconds,
+                    episodes::Column::SortNumber,
+                    episodes::Column::Name,
+                    episodes::Column::AirDate,
+                    episodes::Column::Description,
+                    episodes::Column::ImageUrl,
+                    episodes::Column::Kind,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
         Ok(())
     }
 
@@ -252,7 +267,7 @@ impl Db {
                     kind: ep.ep_type.to_string().into(),
                     created_at: now,
                     updated_at: now,
-                    duration_seconds: Some(ep.duration_seconds),
+                    duration_seconds: Some(ep.duration_seconds as i64),
                     sort_number: ep_number,
                 }
             })
@@ -265,21 +280,113 @@ impl Db {
 
 /// Torrents 相关
 impl Db {
+    /// 批量写入种子, 做种数在来源提供时更新, 来源不提供时保留原来的值
     pub async fn batch_upsert_torrent(&self, torrents: Vec<torrents::Model>) -> Result<()> {
-        if torrents.is_empty() {
+        let db = self.conn();
+        let (with_seeders, without_seeders): (Vec<_>, Vec<_>) =
+            torrents.into_iter().partition(|t| t.seeders.is_some());
+        for (batch, update_seeders) in [(with_seeders, true), (without_seeders, false)] {
+            if batch.is_empty() {
+                continue;
+            }
+            let mut columns = vec![
+                torrents::Column::PubDate,
+                torrents::Column::Size,
+                torrents::Column::Magnet,
+                torrents::Column::Data,
+                torrents::Column::DownloadUrl,
+            ];
+            if update_seeders {
+                columns.push(torrents::Column::Seeders);
+            }
+            let _ = torrents::Entity::insert_many(
+                batch.into_iter().map(|model| model.into_active_model()),
+            )
+            .on_conflict(
+                OnConflict::column(torrents::Column::InfoHash)
+                    .update_columns(columns)
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_torrents_by_info_hashes(
+        &self,
+        info_hashes: Vec<String>,
+    ) -> Result<Vec<torrents::Model>> {
+        if info_hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        let db = self.conn();
+        let torrents = torrents::Entity::find()
+            .filter(torrents::Column::InfoHash.is_in(info_hashes))
+            .all(db)
+            .await?;
+        Ok(torrents)
+    }
+
+    /// 记录种子的来源, 已存在的记录只更新最近发现时间
+    pub async fn batch_upsert_torrent_sources(&self, sources: Vec<(String, Source)>) -> Result<()> {
+        if sources.is_empty() {
             return Ok(());
         }
         let db = self.conn();
-        let _ = torrents::Entity::insert_many(
-            torrents.into_iter().map(|model| model.into_active_model()),
-        )
-        .on_conflict(
-            OnConflict::column(torrents::Column::InfoHash)
-                .update_column(torrents::Column::PubDate)
+        let now = chrono::Local::now().naive_utc();
+        let _ =
+            torrent_sources::Entity::insert_many(sources.into_iter().map(|(info_hash, source)| {
+                torrent_sources::ActiveModel {
+                    info_hash: Set(info_hash),
+                    source: Set(source),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                }
+            }))
+            .on_conflict(
+                OnConflict::columns([
+                    torrent_sources::Column::InfoHash,
+                    torrent_sources::Column::Source,
+                ])
+                .update_column(torrent_sources::Column::UpdatedAt)
                 .to_owned(),
-        )
-        .exec(db)
-        .await?;
+            )
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_mikan_collect_state(
+        &self,
+        mikan_id: i32,
+    ) -> Result<Option<mikan_collect_state::Model>> {
+        let db = self.conn();
+        let state = mikan_collect_state::Entity::find_by_id(mikan_id)
+            .one(db)
+            .await?;
+        Ok(state)
+    }
+
+    pub async fn upsert_mikan_collect_state(
+        &self,
+        state: mikan_collect_state::Model,
+    ) -> Result<()> {
+        let db = self.conn();
+        let _ = mikan_collect_state::Entity::insert(state.into_active_model())
+            .on_conflict(
+                OnConflict::column(mikan_collect_state::Column::MikanId)
+                    .update_columns([
+                        mikan_collect_state::Column::Etag,
+                        mikan_collect_state::Column::LastModified,
+                        mikan_collect_state::Column::LastPubDate,
+                        mikan_collect_state::Column::LastInfoHashes,
+                        mikan_collect_state::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
         Ok(())
     }
 }
@@ -4,6 +4,7 @@ use async_trait::async_trait;
 
 use model::bangumi;
 
+pub mod aggregator;
 mod db;
 pub mod fetcher;
 mod matcher;
@@ -77,6 +78,17 @@ pub trait MetadataDb {
 
 #[async_trait]
 pub trait TorrentProvider: Send + Sync {
-    async fn search_torrents(&self, bgm: &bangumi::Model) -> Result<Vec<model::torrents::Model>>;
+    /// 搜索番剧的种子, `force` 为 true 时忽略增量收集的状态, 重新获取所有种子
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        force: bool,
+    ) -> Result<Vec<model::torrents::Model>>;
+
+    /// 搜索到的种子保存后调用, 增量收集的来源在这里保存收集状态
+    async fn commit(&self, _bgm: &bangumi::Model) -> Result<()> {
+        Ok(())
+    }
+
     fn name(&self) -> &str;
 }
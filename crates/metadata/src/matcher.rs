@@ -33,7 +33,7 @@ impl Matcher {
         let result = match (bgm.tmdb_id, bgm.season_number) {
             (Some(tmdb_id), Some(season_number)) => {
                 self.tmdb
-                    .get_bangumi_and_season(tmdb_id, season_number)
+                    .get_bangumi_and_season(tmdb_id as u64, season_number as u64)
                     .await?
             }
             _ => {
@@ -48,8 +48,8 @@ impl Matcher {
                 "匹配到TMDB番剧: {} {:?}",
                 tv.inner.name, season.inner.season_number
             );
-            bgm.tmdb_id = Some(tv.inner.id);
-            bgm.season_number = Some(season.inner.season_number);
+            bgm.tmdb_id = Some(tv.inner.id as i64);
+            bgm.season_number = Some(season.inner.season_number as i64);
             bgm.bgm_kind = Some(BgmKind::Anime);
             Ok(Some((tv, season)))
         } else {
@@ -70,7 +70,7 @@ impl Matcher {
         }
         let movie = movie.unwrap();
         info!("匹配到TMDB电影: {} {:?}", movie.inner.title, air_date);
-        bgm.tmdb_id = Some(movie.inner.id);
+        bgm.tmdb_id = Some(movie.inner.id as i64);
         bgm.bgm_kind = Some(BgmKind::Movie);
         Ok(Some(movie.clone()))
     }
@@ -46,7 +46,7 @@ impl MetadataDb for MdbTmdb {
 
         debug!("开始更新TMDB元数据");
 
-        let tmdb_id = bgm.tmdb_id.unwrap();
+        let tmdb_id = bgm.tmdb_id.unwrap() as u64;
 
         if bgm.bgm_kind == Some(BgmKind::Anime) && bgm.season_number.is_none() {
             warn!("[TMDB] 没有 season_number, 也不是电影，跳过更新");
@@ -60,13 +60,13 @@ impl MetadataDb for MdbTmdb {
             Some(BgmKind::Anime) => {
                 let (tv, season) = self
                     .tmdb
-                    .get_bangumi_and_season(tmdb_id, bgm.season_number.unwrap())
+                    .get_bangumi_and_season(tmdb_id, bgm.season_number.unwrap() as u64)
                     .await?
                     .context("[TMDB] 更新元数据失败，未找到元数据信息")?;
                 if attrs.is_required(MetadataAttr::SeasonNumber)
                     && (bgm.season_number.is_none() || force)
                 {
-                    bgm.season_number = Some(season.inner.season_number);
+                    bgm.season_number = Some(season.inner.season_number as i64);
                 }
                 poster_path = tv.inner.poster_path;
                 backdrop_path = tv.inner.backdrop_path;
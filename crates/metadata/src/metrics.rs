@@ -12,8 +12,47 @@ pub struct Service {
     pub status: ServiceStatus,
 }
 
+/// 种子来源的收集统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderMetrics {
+    pub name: String,
+    pub success_count: u64,
+    pub failure_count: u64,
+    /// 最近一次收集到的种子数量
+    pub last_torrent_count: usize,
+    pub last_error: Option<String>,
+}
+
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct Metrics {
     pub services: Vec<Service>,
+    pub providers: Vec<ProviderMetrics>,
     pub last_refresh_time: i64,
 }
+
+impl Metrics {
+    pub fn record_provider_result(&mut self, name: &str, result: Result<usize, String>) {
+        let index = match self.providers.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                self.providers.push(ProviderMetrics {
+                    name: name.to_owned(),
+                    ..Default::default()
+                });
+                self.providers.len() - 1
+            }
+        };
+        let provider = &mut self.providers[index];
+        match result {
+            Ok(count) => {
+                provider.success_count += 1;
+                provider.last_torrent_count = count;
+                provider.last_error = None;
+            }
+            Err(e) => {
+                provider.failure_count += 1;
+                provider.last_error = Some(e);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+use crate::TorrentProvider;
+use crate::providers::search_keywords;
+use acgrip::client::FeedItem;
+use anyhow::Result;
+use async_trait::async_trait;
+use lru::LruCache;
+use model::{bangumi, sea_orm_active_enums::Source, torrents};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+const TORRENT_CACHE_SIZE: usize = 512;
+
+/// 已下载的种子文件, 以种子链接为键缓存, 避免每次收集都重复下载
+#[derive(Debug, Clone)]
+struct TorrentFile {
+    info_hash: String,
+    magnet: String,
+    size: i64,
+    data: Vec<u8>,
+}
+
+pub struct AcgripProvider {
+    pub acgrip: acgrip::client::Client,
+    /// 每次搜索最多处理的条目数量, 每个条目都需要下载种子文件
+    pub max_items: usize,
+    cache: Mutex<LruCache<String, TorrentFile>>,
+}
+
+#[async_trait]
+impl TorrentProvider for AcgripProvider {
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        _force: bool,
+    ) -> Result<Vec<torrents::Model>> {
+        info!("[Acgrip] 搜索番剧 {} 的种子", bgm.name);
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for keyword in search_keywords(bgm) {
+            let items = match self.acgrip.search(&keyword).await {
+                Ok(items) => items,
+                Err(e) => {
+                    error!("[Acgrip] 搜索关键词 {} 失败: {}", keyword, e);
+                    continue;
+                }
+            };
+            for item in items.into_iter().take(self.max_items) {
+                if item.pub_date.is_none() {
+                    continue;
+                }
+                let file = match self.get_torrent_file(&item).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        error!(
+                            "[Acgrip] 下载种子文件 {} 失败: {}",
+                            item.torrent_download_url, e
+                        );
+                        continue;
+                    }
+                };
+                if !seen.insert(file.info_hash.clone()) {
+                    continue;
+                }
+                if let Some(torrent) = Self::to_torrent(bgm, item, file) {
+                    result.push(torrent);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "Acgrip"
+    }
+}
+
+impl AcgripProvider {
+    pub fn new(acgrip: acgrip::client::Client, max_items: usize) -> Self {
+        Self {
+            acgrip,
+            max_items,
+            cache: Mutex::new(LruCache::new(
+                NonZeroUsize::new(TORRENT_CACHE_SIZE).unwrap(),
+            )),
+        }
+    }
+
+    pub fn new_from_env() -> Result<Self> {
+        let acgrip = acgrip::client::Client::from_env()?;
+        Ok(Self::new(acgrip, 20))
+    }
+
+    async fn get_torrent_file(&self, item: &FeedItem) -> Result<TorrentFile> {
+        let url = item.torrent_download_url.to_string();
+        if let Some(file) = self.cache.lock().await.get(&url) {
+            return Ok(file.clone());
+        }
+
+        let data = self
+            .acgrip
+            .download_torrent(&item.torrent_download_url)
+            .await?;
+        let file = Self::parse_torrent_file(data)?;
+        self.cache.lock().await.put(url, file.clone());
+        Ok(file)
+    }
+
+    fn to_torrent(
+        bgm: &bangumi::Model,
+        item: FeedItem,
+        file: TorrentFile,
+    ) -> Option<torrents::Model> {
+        item.pub_date.map(|pub_date| torrents::Model {
+            bangumi_id: bgm.id,
+            title: item.title,
+            size: file.size,
+            info_hash: file.info_hash,
+            magnet: Some(file.magnet),
+            data: Some(file.data),
+            download_url: Some(item.torrent_download_url.to_string()),
+            pub_date,
+            source: Source::AcgripOrg,
+            seeders: None,
+        })
+    }
+
+    fn parse_torrent_file(data: Vec<u8>) -> Result<TorrentFile> {
+        let torrent = torrent::Torrent::from_bytes(&data)?;
+        Ok(TorrentFile {
+            info_hash: torrent.info_hash_str()?,
+            magnet: torrent.magnet_link()?,
+            size: torrent.total_size(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 单文件种子: name=test.mkv, length=1048576
+    const TORRENT: &[u8] = b"d8:announce30:http://tracker.example.com/ann4:infod6:lengthi1048576e4:name8:test.mkv12:piece lengthi262144e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+
+    #[test]
+    fn test_parse_torrent_file() -> Result<()> {
+        let data = TORRENT.to_vec();
+
+        let file = AcgripProvider::parse_torrent_file(data.clone())?;
+        assert_eq!(file.info_hash, "8e1fdb6bb1bb746ef0d442625c95a8184154683e");
+        assert_eq!(file.size, 1048576);
+        assert!(file.magnet.starts_with("magnet:?xt=urn:btih:"));
+        assert_eq!(file.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_torrent() -> Result<()> {
+        let bgm = model::testing::bangumi("葬送的芙莉莲 / Sousou no Frieren");
+        let item = FeedItem {
+            title: "[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [1080p]".to_owned(),
+            view_url: None,
+            pub_date: Some(chrono::NaiveDate::from_ymd_opt(2024, 3, 22).unwrap().into()),
+            torrent_download_url: "https://acg.rip/t/300000.torrent".parse()?,
+        };
+        let file = AcgripProvider::parse_torrent_file(TORRENT.to_vec())?;
+
+        let torrent = AcgripProvider::to_torrent(&bgm, item, file).unwrap();
+        assert_eq!(torrent.bangumi_id, 1);
+        assert_eq!(
+            torrent.info_hash,
+            "8e1fdb6bb1bb746ef0d442625c95a8184154683e"
+        );
+        assert_eq!(torrent.source, Source::AcgripOrg);
+        assert_eq!(
+            torrent.download_url.as_deref(),
+            Some("https://acg.rip/t/300000.torrent")
+        );
+        assert_eq!(torrent.data.as_deref(), Some(TORRENT));
+        Ok(())
+    }
+}
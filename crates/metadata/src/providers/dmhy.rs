@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use crate::TorrentProvider;
+use crate::providers::search_keywords;
+use anyhow::Result;
+use async_trait::async_trait;
+use dmhy::client::TorrentItem;
+use model::{bangumi, sea_orm_active_enums::Source, torrents};
+use tracing::{error, info};
+
+pub struct DmhyProvider {
+    pub dmhy: dmhy::client::Client,
+    pub sort_id: i32,
+    /// 字幕组ID, 为空时搜索所有字幕组
+    pub team_ids: Vec<i32>,
+    /// 字幕组名称白名单, 为空时不过滤
+    pub release_groups: Vec<String>,
+}
+
+#[async_trait]
+impl TorrentProvider for DmhyProvider {
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        _force: bool,
+    ) -> Result<Vec<torrents::Model>> {
+        info!("[Dmhy] 搜索番剧 {} 的种子", bgm.name);
+        let team_ids = if self.team_ids.is_empty() {
+            vec![None]
+        } else {
+            self.team_ids.iter().copied().map(Some).collect()
+        };
+
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for keyword in search_keywords(bgm) {
+            for team_id in &team_ids {
+                let items = match self.dmhy.search(&keyword, self.sort_id, *team_id).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        error!(
+                            "[Dmhy] 搜索关键词 {} 字幕组 {:?} 失败: {}",
+                            keyword, team_id, e
+                        );
+                        continue;
+                    }
+                };
+                for item in items {
+                    if !self.is_allowed_release_group(&item) || !seen.insert(item.info_hash.clone())
+                    {
+                        continue;
+                    }
+                    if let Some(torrent) = Self::to_torrent(bgm, item) {
+                        result.push(torrent);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "Dmhy"
+    }
+}
+
+impl DmhyProvider {
+    pub fn new(
+        dmhy: dmhy::client::Client,
+        sort_id: i32,
+        team_ids: Vec<i32>,
+        release_groups: Vec<String>,
+    ) -> Self {
+        Self {
+            dmhy,
+            sort_id,
+            team_ids,
+            release_groups,
+        }
+    }
+
+    pub fn new_from_env() -> Result<Self> {
+        let dmhy = dmhy::client::Client::from_env()?;
+        Ok(Self::new(dmhy, dmhy::client::SORT_ID_ANIME, vec![], vec![]))
+    }
+
+    /// 字幕组名称可能出现在发布者或者标题中
+    fn is_allowed_release_group(&self, item: &TorrentItem) -> bool {
+        if self.release_groups.is_empty() {
+            return true;
+        }
+        let title = item.title.to_lowercase();
+        let sub_group = item.sub_group.as_deref().unwrap_or_default().to_lowercase();
+        self.release_groups.iter().any(|group| {
+            let group = group.to_lowercase();
+            sub_group.contains(&group) || title.contains(&group)
+        })
+    }
+
+    fn to_torrent(bgm: &bangumi::Model, item: TorrentItem) -> Option<torrents::Model> {
+        item.pub_date.map(|pub_date| torrents::Model {
+            bangumi_id: bgm.id,
+            title: item.title,
+            size: item.file_size as i64,
+            info_hash: item.info_hash,
+            magnet: Some(item.magnet_link),
+            data: None,
+            download_url: None,
+            pub_date,
+            source: Source::DmhyOrg,
+            seeders: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_release_group_filter() -> Result<()> {
+        let xml = r#"<rss version="2.0">
+        <channel>
+        <title>動漫花園資源網</title>
+        <item>
+        <title><![CDATA[[LoliHouse] 葬送的芙莉莲 - 28 [WebRip 1080p][436.5MB]]]></title>
+        <pubDate>Sat, 23 Mar 2024 21:30:00 +0800</pubDate>
+        <enclosure url="magnet:?xt=urn:btih:QQQARCICM57I4FBLGXRLYMS6QLDMV6YE" length="1" type="application/x-bittorrent"></enclosure>
+        <author><![CDATA[LoliHouse]]></author>
+        </item>
+        <item>
+        <title><![CDATA[[喵萌奶茶屋] 葬送的芙莉莲 - 28 [1080p]]]></title>
+        <pubDate>Sat, 23 Mar 2024 20:00:00 +0800</pubDate>
+        <enclosure url="magnet:?xt=urn:btih:3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b" length="1" type="application/x-bittorrent"></enclosure>
+        <author><![CDATA[nekomoe]]></author>
+        </item>
+        </channel>
+        </rss>"#;
+        let provider = DmhyProvider::new(
+            dmhy::client::Client::new_with_client(
+                reqwest::Client::new(),
+                "https://share.dmhy.org",
+                None,
+            )?,
+            dmhy::client::SORT_ID_ANIME,
+            vec![],
+            vec!["lolihouse".to_owned()],
+        );
+
+        let items: Vec<_> = dmhy::client::Client::parse_rss(xml)?
+            .into_iter()
+            .filter(|item| provider.is_allowed_release_group(item))
+            .collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].info_hash,
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert_eq!(items[0].file_size, 457_703_424);
+        Ok(())
+    }
+}
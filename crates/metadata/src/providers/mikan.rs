@@ -1,42 +1,105 @@
-use crate::TorrentProvider;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::{TorrentProvider, db::Db};
 use anyhow::Result;
 use async_trait::async_trait;
-use model::{bangumi, sea_orm_active_enums::Source, torrents};
+use chrono::NaiveDateTime;
+use mikan::client::{EpisodeItem, FeedResult, FeedValidator};
+use model::{bangumi, mikan_collect_state, sea_orm_active_enums::Source, torrents};
+use sea_orm::DatabaseConnection;
 use tracing::{info, warn};
 
 pub struct MikanProvider {
     pub mikan: mikan::client::Client,
+    db: Db,
+    /// 已搜索但种子还未保存的收集状态, key 为 mikan_id, 种子保存后才写入数据库
+    pending: Mutex<HashMap<i32, mikan_collect_state::Model>>,
 }
 
 #[async_trait]
 impl TorrentProvider for MikanProvider {
-    async fn search_torrents(&self, bgm: &bangumi::Model) -> Result<Vec<torrents::Model>> {
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        force: bool,
+    ) -> Result<Vec<torrents::Model>> {
         info!("[Mikan] 搜索番剧 {} 的种子", bgm.name);
-        match bgm.mikan_id {
-            Some(id) => Ok(self
-                .mikan
-                .collect_by_bangumi_id(id)
-                .await?
-                .iter()
-                .filter_map(|t| {
-                    t.pub_date.map(|pub_date| torrents::Model {
-                        bangumi_id: bgm.id,
-                        title: t.file_name.clone().unwrap_or_default(),
-                        size: t.file_size as i64,
-                        info_hash: t.info_hash.clone(),
-                        magnet: Some(t.magnet_link.clone()),
-                        data: None,
-                        download_url: t.torrent_download_url.as_ref().map(|url| url.to_string()),
-                        pub_date,
-                        source: Source::Mikan,
-                    })
-                })
-                .collect()),
-            None => {
-                warn!("番剧 {} 缺少 mikan_id", bgm.name);
-                Ok(vec![])
+        let Some(mikan_id) = bgm.mikan_id else {
+            warn!("番剧 {} 缺少 mikan_id", bgm.name);
+            return Ok(vec![]);
+        };
+
+        // 强制刷新时不使用上一次的收集状态, 重新获取所有条目
+        let state = if force {
+            None
+        } else {
+            self.db.get_mikan_collect_state(mikan_id).await?
+        };
+        let validator = state
+            .as_ref()
+            .map(|state| FeedValidator {
+                etag: state.etag.clone(),
+                last_modified: state.last_modified.clone(),
+            })
+            .unwrap_or_default();
+
+        let (items, validator) = match self
+            .mikan
+            .collect_by_bangumi_id_if_modified(mikan_id, &validator)
+            .await?
+        {
+            FeedResult::NotModified => {
+                info!("[Mikan] 番剧 {} 的 RSS 没有变化", bgm.name);
+                return Ok(vec![]);
             }
+            FeedResult::Modified { items, validator } => (items, validator),
+        };
+
+        let mut mark = state
+            .as_ref()
+            .map(HighWaterMark::from_state)
+            .unwrap_or_default();
+        let new_items: Vec<_> = items.iter().filter(|item| mark.is_new(item)).collect();
+        info!(
+            "[Mikan] 番剧 {} 共 {} 个条目, 其中 {} 个为新条目",
+            bgm.name,
+            items.len(),
+            new_items.len()
+        );
+
+        let torrents = new_items
+            .into_iter()
+            .filter_map(|item| Self::to_torrent(bgm, item))
+            .collect();
+
+        mark.advance(&items);
+        self.pending.lock().unwrap().insert(
+            mikan_id,
+            mikan_collect_state::Model {
+                mikan_id,
+                etag: validator.etag,
+                last_modified: validator.last_modified,
+                last_pub_date: mark.pub_date,
+                last_info_hashes: mark.info_hashes_str(),
+                updated_at: chrono::Local::now().naive_utc(),
+            },
+        );
+
+        Ok(torrents)
+    }
+
+    async fn commit(&self, bgm: &bangumi::Model) -> Result<()> {
+        let Some(mikan_id) = bgm.mikan_id else {
+            return Ok(());
+        };
+        let state = self.pending.lock().unwrap().remove(&mikan_id);
+        if let Some(state) = state {
+            self.db.upsert_mikan_collect_state(state).await?;
         }
+        Ok(())
     }
 
     fn name(&self) -> &str {
@@ -45,12 +108,148 @@ impl TorrentProvider for MikanProvider {
 }
 
 impl MikanProvider {
-    pub fn new(mikan: mikan::client::Client) -> Self {
-        Self { mikan }
+    pub fn new(mikan: mikan::client::Client, conn: Arc<DatabaseConnection>) -> Self {
+        Self {
+            mikan,
+            db: Db::new(conn),
+            pending: Mutex::new(HashMap::new()),
+        }
     }
 
-    pub fn new_from_env() -> Result<Self> {
+    pub async fn new_from_env() -> Result<Self> {
         let mikan = mikan::client::Client::from_env()?;
-        Ok(Self { mikan })
+        let db = Db::new_from_env().await?;
+        Ok(Self {
+            mikan,
+            db,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn to_torrent(bgm: &bangumi::Model, item: &EpisodeItem) -> Option<torrents::Model> {
+        item.pub_date.map(|pub_date| torrents::Model {
+            bangumi_id: bgm.id,
+            title: item.file_name.clone().unwrap_or_default(),
+            size: item.file_size as i64,
+            info_hash: item.info_hash.clone(),
+            magnet: Some(item.magnet_link.clone()),
+            data: None,
+            download_url: item
+                .torrent_download_url
+                .as_ref()
+                .map(|url| url.to_string()),
+            pub_date,
+            source: Source::Mikan,
+            seeders: None,
+        })
+    }
+}
+
+/// 已收集条目的高水位: 最新的发布时间, 以及该发布时间下已收集的种子
+///
+/// 发布时间早于高水位的条目视为已收集, 发布时间相同的条目通过 info hash 区分
+#[derive(Debug, Clone, Default)]
+struct HighWaterMark {
+    pub_date: Option<NaiveDateTime>,
+    info_hashes: HashSet<String>,
+}
+
+impl HighWaterMark {
+    fn from_state(state: &mikan_collect_state::Model) -> Self {
+        Self {
+            pub_date: state.last_pub_date,
+            info_hashes: state
+                .last_info_hashes
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|hash| !hash.is_empty())
+                .map(|hash| hash.to_owned())
+                .collect(),
+        }
+    }
+
+    fn is_new(&self, item: &EpisodeItem) -> bool {
+        match (self.pub_date, item.pub_date) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(mark), Some(pub_date)) => {
+                pub_date > mark || (pub_date == mark && !self.info_hashes.contains(&item.info_hash))
+            }
+        }
+    }
+
+    /// 将高水位推进到条目中最新的发布时间
+    fn advance(&mut self, items: &[EpisodeItem]) {
+        for item in items {
+            let Some(pub_date) = item.pub_date else {
+                continue;
+            };
+            if self.pub_date.is_none_or(|mark| pub_date > mark) {
+                self.pub_date = Some(pub_date);
+                self.info_hashes.clear();
+            }
+            if self.pub_date == Some(pub_date) {
+                self.info_hashes.insert(item.info_hash.clone());
+            }
+        }
+    }
+
+    fn info_hashes_str(&self) -> Option<String> {
+        if self.info_hashes.is_empty() {
+            return None;
+        }
+        let mut hashes: Vec<_> = self.info_hashes.iter().map(|h| h.as_str()).collect();
+        hashes.sort();
+        Some(hashes.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(info_hash: &str, pub_date: &str) -> EpisodeItem {
+        EpisodeItem {
+            info_hash: info_hash.to_owned(),
+            pub_date: NaiveDateTime::parse_from_str(pub_date, "%Y-%m-%d %H:%M:%S").ok(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_high_water_mark() {
+        let mut mark = HighWaterMark::default();
+        let items = vec![
+            item("aaaa", "2025-01-01 00:00:00"),
+            item("bbbb", "2025-01-02 00:00:00"),
+            item("cccc", "2025-01-02 00:00:00"),
+        ];
+        assert!(items.iter().all(|i| mark.is_new(i)));
+
+        mark.advance(&items);
+        assert_eq!(mark.info_hashes_str().as_deref(), Some("bbbb,cccc"));
+        assert!(items.iter().all(|i| !mark.is_new(i)));
+
+        // 相同发布时间的新种子, 以及更新的种子
+        assert!(mark.is_new(&item("dddd", "2025-01-02 00:00:00")));
+        assert!(mark.is_new(&item("eeee", "2025-01-03 00:00:00")));
+        assert!(!mark.is_new(&item("ffff", "2024-12-31 00:00:00")));
+
+        // 从持久化的状态恢复
+        let state = mikan_collect_state::Model {
+            mikan_id: 1,
+            etag: None,
+            last_modified: None,
+            last_pub_date: mark.pub_date,
+            last_info_hashes: mark.info_hashes_str(),
+            updated_at: Default::default(),
+        };
+        let restored = HighWaterMark::from_state(&state);
+        assert_eq!(restored.pub_date, mark.pub_date);
+        assert_eq!(restored.info_hashes, mark.info_hashes);
+
+        mark.advance(&[item("eeee", "2025-01-03 00:00:00")]);
+        assert_eq!(mark.info_hashes_str().as_deref(), Some("eeee"));
     }
 }
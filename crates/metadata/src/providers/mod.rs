@@ -1 +1,35 @@
+pub mod acgrip;
+pub mod dmhy;
 pub mod mikan;
+pub mod nyaa;
+pub mod torznab;
+
+use model::bangumi;
+
+/// 搜索关键词: 番剧名称以 " / " 分隔的各个名称, 去除空白及重复
+pub(crate) fn search_keywords(bgm: &bangumi::Model) -> Vec<String> {
+    let mut keywords: Vec<String> = vec![];
+    for name in bgm.name.split(" / ") {
+        let name = name.trim();
+        if !name.is_empty() && !keywords.iter().any(|keyword| keyword == name) {
+            keywords.push(name.to_owned());
+        }
+    }
+    keywords
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_keywords() {
+        let bgm = model::testing::bangumi("葬送的芙莉莲 / Sousou no Frieren / 葬送的芙莉莲 ");
+        assert_eq!(
+            search_keywords(&bgm),
+            vec!["葬送的芙莉莲", "Sousou no Frieren"]
+        );
+        let bgm = model::testing::bangumi(" 葬送的芙莉莲 ");
+        assert_eq!(search_keywords(&bgm), vec!["葬送的芙莉莲"]);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+
+use crate::TorrentProvider;
+use crate::providers::search_keywords;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::{bangumi, sea_orm_active_enums::Source, torrents};
+use nyaa::client::{Filter, TorrentItem};
+use tracing::{error, info};
+
+pub struct NyaaProvider {
+    pub nyaa: nyaa::client::Client,
+    pub category: String,
+    pub filter: Filter,
+}
+
+#[async_trait]
+impl TorrentProvider for NyaaProvider {
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        _force: bool,
+    ) -> Result<Vec<torrents::Model>> {
+        info!("[Nyaa] 搜索番剧 {} 的种子", bgm.name);
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for keyword in search_keywords(bgm) {
+            let items = match self
+                .nyaa
+                .search(&keyword, &self.category, self.filter)
+                .await
+            {
+                Ok(items) => items,
+                Err(e) => {
+                    error!("[Nyaa] 搜索关键词 {} 失败: {}", keyword, e);
+                    continue;
+                }
+            };
+            for item in items {
+                if !seen.insert(item.info_hash.clone()) {
+                    continue;
+                }
+                if let Some(torrent) = Self::to_torrent(bgm, item) {
+                    result.push(torrent);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "Nyaa"
+    }
+}
+
+impl NyaaProvider {
+    pub fn new(nyaa: nyaa::client::Client, category: String, filter: Filter) -> Self {
+        Self {
+            nyaa,
+            category,
+            filter,
+        }
+    }
+
+    pub fn new_from_env() -> Result<Self> {
+        let nyaa = nyaa::client::Client::from_env()?;
+        Ok(Self::new(nyaa, "1_0".to_owned(), Filter::NoFilter))
+    }
+
+    fn to_torrent(bgm: &bangumi::Model, item: TorrentItem) -> Option<torrents::Model> {
+        item.pub_date.map(|pub_date| torrents::Model {
+            bangumi_id: bgm.id,
+            title: item.title,
+            size: item.file_size as i64,
+            info_hash: item.info_hash,
+            magnet: Some(item.magnet_link),
+            data: None,
+            download_url: item.torrent_download_url.map(|url| url.to_string()),
+            pub_date,
+            source: Source::NyaaLand,
+            seeders: Some(item.seeders as i32),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_torrent() -> Result<()> {
+        let xml = r#"<rss xmlns:nyaa="https://nyaa.si/xmlns/nyaa" version="2.0">
+        <channel>
+        <title>Nyaa - Torrent File RSS</title>
+        <item>
+        <title>[SubsPlease] Sousou no Frieren - 28 (1080p) [8D1B5F8B].mkv</title>
+        <link>https://nyaa.si/download/1784245.torrent</link>
+        <pubDate>Fri, 22 Mar 2024 16:02:16 -0000</pubDate>
+        <nyaa:infoHash>3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b</nyaa:infoHash>
+        <nyaa:size>1 GiB</nyaa:size>
+        </item>
+        </channel>
+        </rss>"#;
+        let bgm = model::testing::bangumi("葬送的芙莉莲 / Sousou no Frieren");
+
+        let item = nyaa::client::Client::parse_rss(xml)?.remove(0);
+        let torrent = NyaaProvider::to_torrent(&bgm, item).unwrap();
+        assert_eq!(torrent.bangumi_id, 1);
+        assert_eq!(torrent.size, 1 << 30);
+        assert_eq!(torrent.source, Source::NyaaLand);
+        assert_eq!(
+            torrent.download_url.as_deref(),
+            Some("https://nyaa.si/download/1784245.torrent")
+        );
+        Ok(())
+    }
+}
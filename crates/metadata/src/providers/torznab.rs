@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+
+use chrono::Datelike;
+
+use crate::TorrentProvider;
+use crate::providers::search_keywords;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::{bangumi, sea_orm_active_enums::Source, torrents};
+use torznab::client::TorrentItem;
+use tracing::{error, info};
+
+/// 默认搜索模板
+pub const DEFAULT_QUERY_TEMPLATE: &str = "{name}";
+
+/// 通用的 Torznab (Jackett/Prowlarr) 或 RSS 种子来源, 可以配置多个
+pub struct TorznabProvider {
+    pub name: String,
+    pub torznab: torznab::client::Client,
+    /// 搜索模板, 支持 {name}, {season}, {year} 占位符
+    pub query_template: String,
+}
+
+#[async_trait]
+impl TorrentProvider for TorznabProvider {
+    async fn search_torrents(
+        &self,
+        bgm: &bangumi::Model,
+        _force: bool,
+    ) -> Result<Vec<torrents::Model>> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for query in Self::render_queries(&self.query_template, bgm) {
+            info!("[{}] 搜索番剧 {} 的种子: {}", self.name, bgm.name, query);
+            let items = match self.torznab.search(&query).await {
+                Ok(items) => items,
+                Err(e) => {
+                    error!("[{}] 搜索 {} 失败: {}", self.name, query, e);
+                    continue;
+                }
+            };
+            for item in items {
+                if !seen.insert(item.info_hash.clone()) {
+                    continue;
+                }
+                if let Some(torrent) = Self::to_torrent(bgm, item) {
+                    result.push(torrent);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl TorznabProvider {
+    pub fn new(name: String, torznab: torznab::client::Client, query_template: String) -> Self {
+        Self {
+            name,
+            torznab,
+            query_template,
+        }
+    }
+
+    /// 使用每个搜索关键词渲染搜索模板, 去除重复的查询
+    fn render_queries(template: &str, bgm: &bangumi::Model) -> Vec<String> {
+        let mut queries = Vec::new();
+        for keyword in search_keywords(bgm) {
+            let query = Self::render_query(template, bgm, &keyword);
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+        queries
+    }
+
+    /// 渲染搜索模板, 缺失的字段替换为空, 并合并多余的空白
+    fn render_query(template: &str, bgm: &bangumi::Model, name: &str) -> String {
+        let season = bgm
+            .season_number
+            .map(|season| season.to_string())
+            .unwrap_or_default();
+        let year = bgm
+            .air_date
+            .map(|date| date.year().to_string())
+            .unwrap_or_default();
+        let query = template
+            .replace("{name}", name)
+            .replace("{season}", &season)
+            .replace("{year}", &year);
+        query.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn to_torrent(bgm: &bangumi::Model, item: TorrentItem) -> Option<torrents::Model> {
+        item.pub_date.map(|pub_date| torrents::Model {
+            bangumi_id: bgm.id,
+            title: item.title,
+            size: item.file_size as i64,
+            magnet: item
+                .magnet_link
+                .or_else(|| Some(format!("magnet:?xt=urn:btih:{}", item.info_hash))),
+            info_hash: item.info_hash,
+            data: item.data,
+            download_url: item.torrent_download_url,
+            pub_date,
+            source: Source::Rss,
+            seeders: item.seeders.map(|seeders| seeders as i32),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bangumi(name: &str, season_number: Option<i64>) -> bangumi::Model {
+        let mut bgm = model::testing::bangumi(name);
+        bgm.season_number = season_number;
+        bgm
+    }
+
+    #[test]
+    fn test_render_query() {
+        let bgm = bangumi(" 葬送的芙莉莲 ", Some(1));
+        assert_eq!(
+            TorznabProvider::render_queries("{name} {season}", &bgm),
+            vec!["葬送的芙莉莲 1"]
+        );
+        assert_eq!(
+            TorznabProvider::render_queries("{name} {year}", &bgm),
+            vec!["葬送的芙莉莲 2023"]
+        );
+
+        let bgm = bangumi("葬送的芙莉莲", None);
+        assert_eq!(
+            TorznabProvider::render_queries("{name} {season}", &bgm),
+            vec!["葬送的芙莉莲"]
+        );
+
+        // 每个名称分别搜索, 模板不包含名称时只搜索一次
+        let bgm = bangumi("葬送的芙莉莲 / Sousou no Frieren", Some(1));
+        assert_eq!(
+            TorznabProvider::render_queries("{name} {season}", &bgm),
+            vec!["葬送的芙莉莲 1", "Sousou no Frieren 1"]
+        );
+        assert_eq!(
+            TorznabProvider::render_queries("Frieren {year}", &bgm),
+            vec!["Frieren 2023"]
+        );
+    }
+
+    #[test]
+    fn test_to_torrent() {
+        let bgm = bangumi("葬送的芙莉莲", Some(1));
+        let item = TorrentItem {
+            title: "[SubsPlease] Sousou no Frieren - 28 (1080p)".to_owned(),
+            info_hash: "3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b".to_owned(),
+            torrent_download_url: Some("http://localhost:9696/1/download?link=abc".to_owned()),
+            pub_date: Some(Default::default()),
+            file_size: 1024,
+            data: Some(b"d4:infode".to_vec()),
+            ..Default::default()
+        };
+
+        let torrent = TorznabProvider::to_torrent(&bgm, item).unwrap();
+        assert_eq!(torrent.source, Source::Rss);
+        assert_eq!(torrent.size, 1024);
+        assert_eq!(torrent.data.as_deref(), Some(&b"d4:infode"[..]));
+        assert_eq!(
+            torrent.magnet.as_deref(),
+            Some("magnet:?xt=urn:btih:3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b")
+        );
+
+        let item = TorrentItem {
+            pub_date: None,
+            ..Default::default()
+        };
+        assert!(TorznabProvider::to_torrent(&bgm, item).is_none());
+    }
+}
@@ -10,9 +10,9 @@ use dict::DictCode;
 use model::sea_orm_active_enums::BgmKind;
 
 use crate::{
-    MetadataAttr, MetadataAttrSet, MetadataDb, TorrentProvider, db::Db, fetcher::Fetcher,
-    matcher::Matcher, mdb_bgmtv::MdbBgmTV, mdb_mikan::MdbMikan, mdb_tmdb::MdbTmdb, metrics,
-    providers::mikan::MikanProvider,
+    MetadataAttr, MetadataAttrSet, MetadataDb, TorrentProvider, aggregator::TorrentAggregator,
+    db::Db, fetcher::Fetcher, matcher::Matcher, mdb_bgmtv::MdbBgmTV, mdb_mikan::MdbMikan,
+    mdb_tmdb::MdbTmdb, metrics, providers::mikan::MikanProvider,
 };
 
 const REFRESH_COOLDOWN: i64 = 1; // minutes
@@ -20,7 +20,7 @@ const REFRESH_COOLDOWN: i64 = 1; // minutes
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub enum Inner {
     Metadata(i32, bool),
-    Torrents(i32),
+    Torrents(i32, bool),
     /// 刷新指定季节的放送列表, 如果为None, 则刷新当前季节
     Calendar(Option<String>, bool),
 }
@@ -28,7 +28,7 @@ pub enum Inner {
 #[derive(Debug)]
 enum Cmd {
     Refresh(Inner),
-    AddBangumi(String, i32, Option<i32>, Option<u64>),
+    AddBangumi(String, i32, Option<i32>, Option<i64>),
     Shutdown(),
 }
 
@@ -102,7 +102,7 @@ impl Worker {
         let db = Db::new_from_env().await?;
         let assets_path = std::env::var("ASSETS_PATH")?;
         let mikan = mikan::client::Client::from_env()?;
-        let mikan_provider = MikanProvider::new(mikan.clone());
+        let mikan_provider = MikanProvider::new(mikan.clone(), db.conn_pool());
         Ok(Self::new(
             db,
             reqwest::Client::new(),
@@ -208,13 +208,20 @@ impl Worker {
         self.send_cmd(Cmd::Refresh(Inner::Metadata(bangumi_id, force)), None)
     }
 
-    pub fn request_refresh_torrents(&self, bangumi_id: i32) -> Result<()> {
-        self.send_cmd(Cmd::Refresh(Inner::Torrents(bangumi_id)), None)
+    pub fn request_refresh_torrents(&self, bangumi_id: i32, force: bool) -> Result<()> {
+        self.send_cmd(Cmd::Refresh(Inner::Torrents(bangumi_id, force)), None)
     }
 
-    pub async fn request_refresh_torrents_and_wait(&self, bangumi_id: i32) -> Result<()> {
+    pub async fn request_refresh_torrents_and_wait(
+        &self,
+        bangumi_id: i32,
+        force: bool,
+    ) -> Result<()> {
         let (done_tx, done_rx) = oneshot::channel();
-        self.send_cmd(Cmd::Refresh(Inner::Torrents(bangumi_id)), Some(done_tx))?;
+        self.send_cmd(
+            Cmd::Refresh(Inner::Torrents(bangumi_id, force)),
+            Some(done_tx),
+        )?;
         tokio::time::timeout(Duration::from_secs(60), done_rx)
             .await
             .context("等待种子刷新超时")??;
@@ -230,7 +237,7 @@ impl Worker {
         title: String,
         mikan_id: i32,
         bgm_tv_id: Option<i32>,
-        tmdb_id: Option<u64>,
+        tmdb_id: Option<i64>,
     ) -> Result<()> {
         let (done_tx, done_rx) = oneshot::channel();
         self.send_cmd(
@@ -265,8 +272,8 @@ impl Worker {
 
     async fn process(&self, request: Inner, mdbs: &Arc<Metadatabases>) -> Result<()> {
         match request {
-            Inner::Torrents(id) => {
-                self.handle_collect_torrents(id).await?;
+            Inner::Torrents(id, force) => {
+                self.handle_collect_torrents(id, force).await?;
             }
             Inner::Metadata(id, force) => {
                 self.handle_refresh_metadata(id, force, mdbs).await?;
@@ -433,7 +440,7 @@ impl Worker {
     }
 
     /// 处理番剧种子信息收集请求
-    async fn handle_collect_torrents(&self, bangumi_id: i32) -> Result<()> {
+    async fn handle_collect_torrents(&self, bangumi_id: i32, force: bool) -> Result<()> {
         let bgm = self
             .db
             .get_bangumi_by_id(bangumi_id)
@@ -442,25 +449,45 @@ impl Worker {
 
         info!("正在收集番剧 {} 的种子信息", bgm.name);
 
-        let mut torrents = Vec::new();
+        let mut aggregator = TorrentAggregator::new();
 
         for provider in self.providers.iter() {
-            let result = provider
-                .search_torrents(&bgm)
-                .await
-                .inspect_err(|e| error!("[{}] 收集种子信息失败: {}", provider.name(), e))
-                .unwrap_or_default();
-            torrents.extend(result);
+            let result = provider.search_torrents(&bgm, force).await;
+            self.metrics.write().await.record_provider_result(
+                provider.name(),
+                result
+                    .as_ref()
+                    .map(|torrents| torrents.len())
+                    .map_err(|e| e.to_string()),
+            );
+            match result {
+                Ok(torrents) => aggregator.extend(torrents),
+                Err(e) => error!("[{}] 收集种子信息失败: {}", provider.name(), e),
+            }
         }
 
-        torrents.dedup_by_key(|t| t.info_hash.clone());
-
-        if torrents.is_empty() {
+        if aggregator.is_empty() {
             info!("未找到番剧 {} 的种子信息", bgm.name);
-            return Ok(());
+            return self.commit_providers(&bgm).await;
         }
 
-        info!("已收集 {} 个番剧 {} 的种子信息", torrents.len(), bgm.name);
+        info!("已收集 {} 个番剧 {} 的种子信息", aggregator.len(), bgm.name);
+
+        // 与已存在的种子合并, 避免覆盖已有的种子文件等信息
+        let existing = self
+            .db
+            .list_torrents_by_info_hashes(aggregator.info_hashes())
+            .await?;
+        aggregator.merge_existing(existing);
+
+        let mut torrents = Vec::new();
+        let mut sources = Vec::new();
+        for aggregated in aggregator.into_torrents() {
+            for source in aggregated.sources {
+                sources.push((aggregated.torrent.info_hash.clone(), source));
+            }
+            torrents.push(aggregated.torrent);
+        }
 
         // 获取torrents中最新的种子对应的发布时间
         let latest_torrent = torrents.iter().max_by_key(|t| t.pub_date);
@@ -471,7 +498,18 @@ impl Worker {
         }
 
         self.db.batch_upsert_torrent(torrents).await?;
+        self.db.batch_upsert_torrent_sources(sources).await?;
+
+        self.commit_providers(&bgm).await
+    }
 
+    /// 种子保存后再保存各来源的收集状态, 保存失败时下次仍会重新收集这些种子
+    async fn commit_providers(&self, bgm: &model::bangumi::Model) -> Result<()> {
+        for provider in self.providers.iter() {
+            if let Err(e) = provider.commit(bgm).await {
+                error!("[{}] 保存收集状态失败: {}", provider.name(), e);
+            }
+        }
         Ok(())
     }
 
@@ -480,7 +518,7 @@ impl Worker {
         title: String,
         mikan_id: i32,
         bgm_tv_id: Option<i32>,
-        tmdb_id: Option<u64>,
+        tmdb_id: Option<i64>,
     ) -> Result<()> {
         if self.db.get_bangumi_by_mikan_id(mikan_id).await?.is_some() {
             return Ok(());
@@ -498,10 +536,10 @@ impl Worker {
     pub async fn update_bangumi_mdb(
         &self,
         bgm_id: i32,
-        tmdb_id: Option<u64>,
+        tmdb_id: Option<i64>,
         mikan_id: Option<i32>,
         banugmi_tv_id: Option<i32>,
-        season_number: Option<u64>,
+        season_number: Option<i64>,
         kind: BgmKind,
     ) -> Result<()> {
         let mut bgm = self
@@ -631,7 +669,7 @@ mod test {
 
         let mut worker = Worker::new_from_env().await?;
         worker.spawn()?;
-        worker.request_refresh_torrents_and_wait(20).await?;
+        worker.request_refresh_torrents_and_wait(20, false).await?;
         // tokio::time::sleep(Duration::from_secs(120)).await;
         worker.shutdown().await?;
         Ok(())
@@ -14,7 +14,7 @@ pub struct Model {
     pub description: Option<String>,
     #[sea_orm(unique)]
     pub bangumi_tv_id: Option<i32>,
-    pub tmdb_id: Option<u64>,
+    pub tmdb_id: Option<i64>,
     #[sea_orm(unique)]
     pub mikan_id: Option<i32>,
     pub air_date: Option<DateTime>,
@@ -25,7 +25,7 @@ pub struct Model {
     pub updated_at: DateTime,
     pub poster_image_url: Option<String>,
     pub backdrop_image_url: Option<String>,
-    pub season_number: Option<u64>,
+    pub season_number: Option<i64>,
     pub ep_count: i32,
     pub ep_start_number: i32,
     pub calendar_season: Option<String>,
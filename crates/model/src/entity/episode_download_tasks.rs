@@ -15,6 +15,7 @@ pub struct Model {
     pub ref_torrent_info_hash: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub upgrade_info_hash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -17,7 +17,7 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     pub description: Option<String>,
     pub air_date: Option<Date>,
-    pub duration_seconds: Option<u64>,
+    pub duration_seconds: Option<i64>,
     pub kind: Kind,
     pub created_at: DateTime,
     pub updated_at: DateTime,
@@ -2,12 +2,20 @@
 
 pub mod prelude;
 
+pub mod api_tokens;
+pub mod auth_sessions;
 pub mod bangumi;
 pub mod dictionary;
 pub mod episode_download_tasks;
+pub mod episode_watch_states;
 pub mod episodes;
 pub mod file_name_parse_record;
+pub mod mikan_collect_state;
 pub mod sea_orm_active_enums;
+pub mod subscription_feeds;
 pub mod subscriptions;
 pub mod torrent_download_tasks;
+pub mod torrent_sources;
 pub mod torrents;
+pub mod user_subscriptions;
+pub mod users;
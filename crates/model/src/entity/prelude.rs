@@ -1,10 +1,18 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::api_tokens::Entity as ApiTokens;
+pub use super::auth_sessions::Entity as AuthSessions;
 pub use super::bangumi::Entity as Bangumi;
 pub use super::dictionary::Entity as Dictionary;
 pub use super::episode_download_tasks::Entity as EpisodeDownloadTasks;
+pub use super::episode_watch_states::Entity as EpisodeWatchStates;
 pub use super::episodes::Entity as Episodes;
 pub use super::file_name_parse_record::Entity as FileNameParseRecord;
+pub use super::mikan_collect_state::Entity as MikanCollectState;
+pub use super::subscription_feeds::Entity as SubscriptionFeeds;
 pub use super::subscriptions::Entity as Subscriptions;
 pub use super::torrent_download_tasks::Entity as TorrentDownloadTasks;
+pub use super::torrent_sources::Entity as TorrentSources;
 pub use super::torrents::Entity as Torrents;
+pub use super::user_subscriptions::Entity as UserSubscriptions;
+pub use super::users::Entity as Users;
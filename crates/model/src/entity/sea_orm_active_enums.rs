@@ -80,6 +80,8 @@ pub enum Source {
     DmhyOrg,
     #[sea_orm(string_value = "User")]
     User,
+    #[sea_orm(string_value = "Rss")]
+    Rss,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "state")]
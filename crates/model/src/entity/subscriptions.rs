@@ -18,9 +18,15 @@ pub struct Model {
     pub updated_at: DateTime,
     pub collector_interval: Option<i32>,
     pub metadata_interval: Option<i32>,
-    pub enforce_torrent_release_after_broadcast: i8,
+    pub enforce_torrent_release_after_broadcast: bool,
     pub preferred_downloader: Option<String>,
     pub allow_fallback: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub filter_rules: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub score_profile: Option<String>,
+    pub auto_upgrade: bool,
+    pub download_strategy: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
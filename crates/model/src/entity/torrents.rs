@@ -19,6 +19,7 @@ pub struct Model {
     pub download_url: Option<String>,
     pub pub_date: DateTime,
     pub source: Source,
+    pub seeders: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
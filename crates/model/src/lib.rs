@@ -1,5 +1,7 @@
 mod entity;
 pub mod migrator;
+#[cfg(feature = "testing")]
+pub mod testing;
 use std::fmt::{self, Display};
 
 use entity::sea_orm_active_enums::{BgmKind, Kind, ResourceType, SubscribeStatus};
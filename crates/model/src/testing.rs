@@ -0,0 +1,27 @@
+//! 测试使用的数据, 需要启用 `testing` feature
+
+use crate::bangumi;
+
+/// 葬送的芙莉莲的番剧信息, 名称由测试指定
+pub fn bangumi(name: &str) -> bangumi::Model {
+    bangumi::Model {
+        id: 1,
+        name: name.to_owned(),
+        description: Some("勇者一行人打倒魔王之后".to_owned()),
+        bangumi_tv_id: Some(400602),
+        tmdb_id: Some(209867),
+        mikan_id: None,
+        air_date: chrono::NaiveDate::from_ymd_opt(2023, 9, 29).and_then(|d| d.and_hms_opt(0, 0, 0)),
+        air_week: None,
+        rating: Some(8.96),
+        created_at: Default::default(),
+        updated_at: Default::default(),
+        poster_image_url: None,
+        backdrop_image_url: None,
+        season_number: Some(1),
+        ep_count: 28,
+        ep_start_number: 1,
+        calendar_season: None,
+        bgm_kind: None,
+    }
+}
@@ -197,7 +197,7 @@ pub enum VideoResolution {
 
 impl VideoResolution {
     // 获取优先级值的辅助方法
-    fn priority(&self) -> i32 {
+    pub fn priority(&self) -> i32 {
         match self {
             VideoResolution::P2160 => 4,
             VideoResolution::P1440 => 3,
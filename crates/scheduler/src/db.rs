@@ -1,13 +1,14 @@
 use anyhow::Result;
 use model::{
-    episode_download_tasks, file_name_parse_record, sea_orm_active_enums::State, subscriptions,
-    torrents,
+    episode_download_tasks, file_name_parse_record,
+    sea_orm_active_enums::{Source, State},
+    subscription_feeds, subscriptions, torrent_sources, torrents,
 };
-use sea_orm::Set;
 use sea_orm::{
     ColumnTrait, Condition, ConnectOptions, Database, DatabaseConnection, EntityTrait, QueryFilter,
     QueryOrder, QuerySelect,
 };
+use sea_orm::{IntoActiveModel, Set, prelude::Expr};
 use std::collections::HashSet;
 use std::{sync::Arc, time::Duration};
 
@@ -38,6 +39,10 @@ impl Db {
         &self.0
     }
 
+    pub fn conn_pool(&self) -> Arc<DatabaseConnection> {
+        self.0.clone()
+    }
+
     /// 获取指定番剧的订阅设置
     pub async fn get_subscription(&self, bangumi_id: i32) -> Result<Option<subscriptions::Model>> {
         use model::subscriptions::Column as SubscriptionColumn;
@@ -67,6 +72,45 @@ impl Db {
         Ok(tasks)
     }
 
+    /// 获取所有洗版中的下载任务
+    pub async fn get_all_upgrading_tasks(&self) -> Result<Vec<episode_download_tasks::Model>> {
+        use model::episode_download_tasks::Column as TaskColumn;
+        use model::episode_download_tasks::Entity as Tasks;
+
+        let tasks = Tasks::find()
+            .filter(
+                Condition::all()
+                    .add(TaskColumn::State.eq(State::Downloaded))
+                    .add(TaskColumn::UpgradeInfoHash.is_not_null()),
+            )
+            .order_by_asc(TaskColumn::CreatedAt)
+            .all(self.conn())
+            .await?;
+
+        Ok(tasks)
+    }
+
+    /// 获取指定番剧已下载的任务
+    pub async fn get_downloaded_tasks_by_bangumi(
+        &self,
+        bangumi_id: i32,
+    ) -> Result<Vec<episode_download_tasks::Model>> {
+        use model::episode_download_tasks::Column as TaskColumn;
+        use model::episode_download_tasks::Entity as Tasks;
+
+        let tasks = Tasks::find()
+            .filter(
+                Condition::all()
+                    .add(TaskColumn::BangumiId.eq(bangumi_id))
+                    .add(TaskColumn::State.eq(State::Downloaded)),
+            )
+            .order_by_asc(TaskColumn::EpisodeNumber)
+            .all(self.conn())
+            .await?;
+
+        Ok(tasks)
+    }
+
     /// 获取指定番剧的未完成下载任务
     pub async fn get_unfinished_tasks_by_bangumi(
         &self,
@@ -121,7 +165,10 @@ impl Db {
         use model::episode_download_tasks::Entity as Tasks;
 
         Tasks::update_many()
-            .col_expr(TaskColumn::State, state.into())
+            .col_expr(
+                TaskColumn::State,
+                TaskColumn::State.save_as(Expr::val(state)),
+            )
             .filter(
                 Condition::all()
                     .add(TaskColumn::BangumiId.eq(bangumi_id))
@@ -144,8 +191,60 @@ impl Db {
         use model::episode_download_tasks::Entity as Tasks;
 
         Tasks::update_many()
-            .col_expr(TaskColumn::State, State::Ready.into())
+            .col_expr(
+                TaskColumn::State,
+                TaskColumn::State.save_as(Expr::val(State::Ready)),
+            )
+            .col_expr(TaskColumn::RefTorrentInfoHash, info_hash.into())
+            // 重新选择种子时放弃洗版
+            .col_expr(TaskColumn::UpgradeInfoHash, Option::<String>::None.into())
+            .filter(
+                Condition::all()
+                    .add(TaskColumn::BangumiId.eq(bangumi_id))
+                    .add(TaskColumn::EpisodeNumber.eq(episode_number)),
+            )
+            .exec(self.conn())
+            .await?;
+
+        Ok(())
+    }
+
+    /// 设置或清除洗版中的种子
+    pub async fn update_task_upgrade(
+        &self,
+        bangumi_id: i32,
+        episode_number: i32,
+        info_hash: Option<&str>,
+    ) -> Result<()> {
+        use model::episode_download_tasks::Column as TaskColumn;
+        use model::episode_download_tasks::Entity as Tasks;
+
+        Tasks::update_many()
+            .col_expr(TaskColumn::UpgradeInfoHash, info_hash.into())
+            .filter(
+                Condition::all()
+                    .add(TaskColumn::BangumiId.eq(bangumi_id))
+                    .add(TaskColumn::EpisodeNumber.eq(episode_number)),
+            )
+            .exec(self.conn())
+            .await?;
+
+        Ok(())
+    }
+
+    /// 洗版完成, 使用新种子替换原来的种子
+    pub async fn complete_task_upgrade(
+        &self,
+        bangumi_id: i32,
+        episode_number: i32,
+        info_hash: &str,
+    ) -> Result<()> {
+        use model::episode_download_tasks::Column as TaskColumn;
+        use model::episode_download_tasks::Entity as Tasks;
+
+        Tasks::update_many()
             .col_expr(TaskColumn::RefTorrentInfoHash, info_hash.into())
+            .col_expr(TaskColumn::UpgradeInfoHash, Option::<String>::None.into())
             .filter(
                 Condition::all()
                     .add(TaskColumn::BangumiId.eq(bangumi_id))
@@ -174,6 +273,26 @@ impl Db {
         Ok(torrents)
     }
 
+    /// 获取番剧所有种子的 info_hash 及做种数, 用于判断种子列表是否有变化
+    pub async fn get_bangumi_torrent_seeders(
+        &self,
+        bangumi_id: i32,
+    ) -> Result<Vec<(String, Option<i32>)>> {
+        use model::torrents::Column as TorrentColumn;
+        use model::torrents::Entity as Torrents;
+
+        let torrents = Torrents::find()
+            .select_only()
+            .columns([TorrentColumn::InfoHash, TorrentColumn::Seeders])
+            .filter(TorrentColumn::BangumiId.eq(bangumi_id))
+            .order_by_asc(TorrentColumn::InfoHash)
+            .into_tuple::<(String, Option<i32>)>()
+            .all(self.conn())
+            .await?;
+
+        Ok(torrents)
+    }
+
     /// 通过 info_hash 获取种子信息, 不包含种子数据
     pub async fn get_torrent_without_data_by_info_hash(
         &self,
@@ -227,6 +346,143 @@ impl Db {
         Ok(())
     }
 
+    /// 批量写入种子, `update_pub_date` 为 true 时已存在的种子更新发布时间, 否则保持不变,
+    /// 做种数在来源提供时更新, 来源不提供时保留原来的值
+    pub async fn batch_upsert_torrents(
+        &self,
+        torrents: Vec<torrents::Model>,
+        update_pub_date: bool,
+    ) -> Result<()> {
+        use model::torrents::Column as TorrentColumn;
+        use model::torrents::Entity as Torrents;
+
+        let (with_seeders, without_seeders): (Vec<_>, Vec<_>) =
+            torrents.into_iter().partition(|t| t.seeders.is_some());
+        for (batch, update_seeders) in [(with_seeders, true), (without_seeders, false)] {
+            if batch.is_empty() {
+                continue;
+            }
+            let mut columns = vec![];
+            if update_pub_date {
+                columns.push(TorrentColumn::PubDate);
+            }
+            if update_seeders {
+                columns.push(TorrentColumn::Seeders);
+            }
+            let mut on_conflict = sea_orm::sea_query::OnConflict::column(TorrentColumn::InfoHash);
+            if columns.is_empty() {
+                on_conflict.do_nothing();
+            } else {
+                on_conflict.update_columns(columns);
+            }
+            Torrents::insert_many(batch.into_iter().map(|t| t.into_active_model()))
+                .on_conflict(on_conflict)
+                .exec_without_returning(self.conn())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 记录种子的来源, 已存在的记录只更新最近发现时间
+    pub async fn batch_upsert_torrent_sources(
+        &self,
+        info_hashes: Vec<String>,
+        source: Source,
+    ) -> Result<()> {
+        if info_hashes.is_empty() {
+            return Ok(());
+        }
+        let now = chrono::Local::now().naive_utc();
+        torrent_sources::Entity::insert_many(info_hashes.into_iter().map(|info_hash| {
+            torrent_sources::ActiveModel {
+                info_hash: Set(info_hash),
+                source: Set(source.clone()),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+        }))
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::columns([
+                torrent_sources::Column::InfoHash,
+                torrent_sources::Column::Source,
+            ])
+            .update_column(torrent_sources::Column::UpdatedAt)
+            .to_owned(),
+        )
+        .exec_without_returning(self.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// 获取订阅的自定义 RSS 源
+    pub async fn list_subscription_feeds(
+        &self,
+        bangumi_id: i32,
+    ) -> Result<Vec<subscription_feeds::Model>> {
+        use model::subscription_feeds::Column as FeedColumn;
+        use model::subscription_feeds::Entity as Feeds;
+
+        let feeds = Feeds::find()
+            .filter(FeedColumn::BangumiId.eq(bangumi_id))
+            .order_by_asc(FeedColumn::Id)
+            .all(self.conn())
+            .await?;
+        Ok(feeds)
+    }
+
+    /// 替换订阅的自定义 RSS 源, 保留已存在源的收集记录
+    pub async fn replace_subscription_feeds(
+        &self,
+        bangumi_id: i32,
+        urls: Vec<String>,
+    ) -> Result<()> {
+        use model::subscription_feeds::Column as FeedColumn;
+        use model::subscription_feeds::Entity as Feeds;
+
+        let mut delete = Feeds::delete_many().filter(FeedColumn::BangumiId.eq(bangumi_id));
+        if !urls.is_empty() {
+            delete = delete.filter(FeedColumn::Url.is_not_in(urls.clone()));
+        }
+        delete.exec(self.conn()).await?;
+
+        if urls.is_empty() {
+            return Ok(());
+        }
+        let now = chrono::Local::now().naive_utc();
+        Feeds::insert_many(urls.into_iter().map(|url| subscription_feeds::ActiveModel {
+            bangumi_id: Set(bangumi_id),
+            url: Set(url),
+            enabled: Set(true),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }))
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::columns([FeedColumn::BangumiId, FeedColumn::Url])
+                .update_column(FeedColumn::Enabled)
+                .to_owned(),
+        )
+        .exec(self.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// 更新 RSS 源的最近收集时间
+    pub async fn update_subscription_feed_collected(&self, id: i32) -> Result<()> {
+        use model::subscription_feeds::Column as FeedColumn;
+        use model::subscription_feeds::Entity as Feeds;
+
+        Feeds::update_many()
+            .col_expr(
+                FeedColumn::LastCollectedAt,
+                chrono::Local::now().naive_utc().into(),
+            )
+            .filter(FeedColumn::Id.eq(id))
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+
     /// 获取番剧的所有种子及其解析结果
     pub async fn get_bangumi_torrents_with_parse_results(
         &self,
@@ -249,6 +505,7 @@ impl Db {
                 TorrentColumn::Magnet,
                 TorrentColumn::PubDate,
                 TorrentColumn::Source,
+                TorrentColumn::Seeders,
             ])
             .filter(TorrentColumn::BangumiId.eq(bangumi_id))
             .join(
@@ -370,14 +627,21 @@ impl Db {
     ) -> Result<Option<episode_download_tasks::Model>> {
         use model::episode_download_tasks::Column as TaskColumn;
         use model::episode_download_tasks::Entity as Tasks;
+        // 洗版中的种子同样关联到剧集任务
         let task = Tasks::find()
-            .filter(TaskColumn::RefTorrentInfoHash.eq(info_hash))
+            .filter(
+                Condition::any()
+                    .add(TaskColumn::RefTorrentInfoHash.eq(info_hash))
+                    .add(TaskColumn::UpgradeInfoHash.eq(info_hash)),
+            )
             .one(self.conn())
             .await?;
         Ok(task)
     }
 
     /// 更新或创建订阅记录
+    ///
+    /// `filter_rules` 和 `score_profile` 为 `None` 时保留已有的值, 为 `Some(None)` 时清除
     #[allow(clippy::too_many_arguments)]
     pub async fn upsert_subscription(
         &self,
@@ -391,11 +655,15 @@ impl Db {
         enforce_torrent_release_after_broadcast: bool,
         preferred_downloader: Option<String>,
         allow_fallback: bool,
+        filter_rules: Option<Option<String>>,
+        score_profile: Option<Option<String>>,
+        auto_upgrade: bool,
+        download_strategy: Option<String>,
     ) -> Result<()> {
         use model::subscriptions::Column as SubscriptionColumn;
         use model::subscriptions::Entity as Subscriptions;
 
-        let subscription = model::subscriptions::ActiveModel {
+        let mut subscription = model::subscriptions::ActiveModel {
             bangumi_id: Set(bangumi_id),
             subscribe_status: Set(model::sea_orm_active_enums::SubscribeStatus::Subscribed),
             resolution_filter: Set(resolution_filter),
@@ -404,28 +672,41 @@ impl Db {
             collector_interval: Set(collector_interval),
             metadata_interval: Set(metadata_interval),
             start_episode_number: Set(start_episode_number),
-            enforce_torrent_release_after_broadcast: Set(
-                enforce_torrent_release_after_broadcast as i8
-            ),
+            enforce_torrent_release_after_broadcast: Set(enforce_torrent_release_after_broadcast),
             preferred_downloader: Set(preferred_downloader),
             allow_fallback: Set(allow_fallback),
+            auto_upgrade: Set(auto_upgrade),
+            download_strategy: Set(download_strategy),
             ..Default::default()
         };
+        let mut columns = vec![
+            SubscriptionColumn::SubscribeStatus,
+            SubscriptionColumn::ResolutionFilter,
+            SubscriptionColumn::LanguageFilter,
+            SubscriptionColumn::ReleaseGroupFilter,
+            SubscriptionColumn::CollectorInterval,
+            SubscriptionColumn::MetadataInterval,
+            SubscriptionColumn::StartEpisodeNumber,
+            SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast,
+            SubscriptionColumn::PreferredDownloader,
+            SubscriptionColumn::AllowFallback,
+            SubscriptionColumn::AutoUpgrade,
+            SubscriptionColumn::DownloadStrategy,
+        ];
+        if let Some(filter_rules) = filter_rules {
+            subscription.filter_rules = Set(filter_rules);
+            columns.push(SubscriptionColumn::FilterRules);
+        }
+        if let Some(score_profile) = score_profile {
+            subscription.score_profile = Set(score_profile);
+            columns.push(SubscriptionColumn::ScoreProfile);
+        }
 
         // 尝试插入新记录，如果已存在则更新状态
         Subscriptions::insert(subscription)
             .on_conflict(
                 sea_orm::sea_query::OnConflict::column(SubscriptionColumn::BangumiId)
-                    .update_column(SubscriptionColumn::SubscribeStatus)
-                    .update_column(SubscriptionColumn::ResolutionFilter)
-                    .update_column(SubscriptionColumn::LanguageFilter)
-                    .update_column(SubscriptionColumn::ReleaseGroupFilter)
-                    .update_column(SubscriptionColumn::CollectorInterval)
-                    .update_column(SubscriptionColumn::MetadataInterval)
-                    .update_column(SubscriptionColumn::StartEpisodeNumber)
-                    .update_column(SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast)
-                    .update_column(SubscriptionColumn::PreferredDownloader)
-                    .update_column(SubscriptionColumn::AllowFallback)
+                    .update_columns(columns)
                     .to_owned(),
             )
             .exec(self.conn())
@@ -443,7 +724,7 @@ impl Db {
         Subscriptions::update_many()
             .col_expr(
                 SubscriptionColumn::SubscribeStatus,
-                SubscribeStatus::None.into(),
+                SubscriptionColumn::SubscribeStatus.save_as(Expr::val(SubscribeStatus::None)),
             )
             .filter(SubscriptionColumn::BangumiId.eq(bangumi_id))
             .exec(self.conn())
@@ -460,7 +741,7 @@ impl Db {
         Subscriptions::update_many()
             .col_expr(
                 SubscriptionColumn::SubscribeStatus,
-                SubscribeStatus::Downloaded.into(),
+                SubscriptionColumn::SubscribeStatus.save_as(Expr::val(SubscribeStatus::Downloaded)),
             )
             .filter(SubscriptionColumn::BangumiId.eq(bangumi_id))
             .exec(self.conn())
@@ -495,4 +776,79 @@ mod tests {
         println!("info_hashes: {:?}", info_hashes);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_batch_upsert_torrents() -> Result<()> {
+        let db = crate::testing::test_db(false).await?;
+        let (mut torrent, _) = crate::testing::pair("aaaa", "1080P", 1);
+        torrent.seeders = Some(10);
+        db.batch_upsert_torrents(vec![torrent.clone()], false)
+            .await?;
+
+        // 来源提供做种数时更新, 未带日期的种子不更新发布时间
+        let mut updated = torrent.clone();
+        updated.seeders = Some(200);
+        updated.pub_date = chrono::Local::now().naive_utc();
+        db.batch_upsert_torrents(vec![updated.clone()], false)
+            .await?;
+        let saved = db.get_torrent_by_info_hash("aaaa").await?.unwrap();
+        assert_eq!(saved.seeders, Some(200));
+        assert_eq!(saved.pub_date, torrent.pub_date);
+
+        // 来源不提供做种数时保留原来的值
+        let mut unknown = updated.clone();
+        unknown.seeders = None;
+        db.batch_upsert_torrents(vec![unknown], true).await?;
+        let saved = db.get_torrent_by_info_hash("aaaa").await?.unwrap();
+        assert_eq!(saved.seeders, Some(200));
+        assert_eq!(saved.pub_date, updated.pub_date);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upsert_subscription_keeps_rules() -> Result<()> {
+        let db = crate::testing::test_db(false).await?;
+        let upsert = |filter_rules, score_profile| {
+            db.upsert_subscription(
+                1,
+                Some(1),
+                None,
+                None,
+                None,
+                None,
+                None,
+                true,
+                None,
+                true,
+                filter_rules,
+                score_profile,
+                false,
+                None,
+            )
+        };
+        upsert(
+            Some(Some(r#"{"type":"title","pattern":"CHS"}"#.to_owned())),
+            Some(Some(r#"{"seeders_weight":1}"#.to_owned())),
+        )
+        .await?;
+
+        // 为 None 时保留已有的值
+        upsert(None, None).await?;
+        let saved = db.get_subscription(1).await?.unwrap();
+        assert_eq!(
+            saved.filter_rules.as_deref(),
+            Some(r#"{"type":"title","pattern":"CHS"}"#)
+        );
+        assert_eq!(
+            saved.score_profile.as_deref(),
+            Some(r#"{"seeders_weight":1}"#)
+        );
+
+        // 为 Some(None) 时清除
+        upsert(Some(None), Some(None)).await?;
+        let saved = db.get_subscription(1).await?.unwrap();
+        assert_eq!(saved.filter_rules, None);
+        assert_eq!(saved.score_profile, None);
+        Ok(())
+    }
 }
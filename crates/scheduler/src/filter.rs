@@ -0,0 +1,349 @@
+use anyhow::{Context, Result};
+use model::{file_name_parse_record, torrents};
+use parser::{Language, VideoResolution};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use utils::size::parse_human_size;
+
+lazy_static::lazy_static! {
+    static ref HEVC_RE: Regex = RegexBuilder::new(r"hevc|[xh]\.?265").case_insensitive(true).build().unwrap();
+    static ref AVC_RE: Regex = RegexBuilder::new(r"avc|[xh]\.?264").case_insensitive(true).build().unwrap();
+    static ref AV1_RE: Regex = RegexBuilder::new(r"\bav1\b").case_insensitive(true).build().unwrap();
+    static ref WEBRIP_RE: Regex = RegexBuilder::new(r"web-?rip|web-?dl|\bweb\b|baha|\bcr\b|b-global").case_insensitive(true).build().unwrap();
+    static ref BDRIP_RE: Regex = RegexBuilder::new(r"bd-?rip|blu-?ray|\bbd\b|bdmv|\bbdremux\b").case_insensitive(true).build().unwrap();
+    static ref TVRIP_RE: Regex = RegexBuilder::new(r"tv-?rip|hdtv").case_insensitive(true).build().unwrap();
+}
+
+/// 视频编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VideoCodec {
+    #[serde(rename = "HEVC")]
+    Hevc,
+    #[serde(rename = "AVC")]
+    Avc,
+    #[serde(rename = "AV1")]
+    Av1,
+}
+
+impl VideoCodec {
+    /// 从文件名中识别视频编码
+    pub fn detect(title: &str) -> Option<Self> {
+        if HEVC_RE.is_match(title) {
+            Some(Self::Hevc)
+        } else if AV1_RE.is_match(title) {
+            Some(Self::Av1)
+        } else if AVC_RE.is_match(title) {
+            Some(Self::Avc)
+        } else {
+            None
+        }
+    }
+}
+
+/// 片源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaSource {
+    WebRip,
+    BDRip,
+    TVRip,
+}
+
+impl MediaSource {
+    /// 从文件名中识别片源
+    pub fn detect(title: &str) -> Option<Self> {
+        if BDRIP_RE.is_match(title) {
+            Some(Self::BDRip)
+        } else if WEBRIP_RE.is_match(title) {
+            Some(Self::WebRip)
+        } else if TVRIP_RE.is_match(title) {
+            Some(Self::TVRip)
+        } else {
+            None
+        }
+    }
+}
+
+/// 种子过滤规则, 以 JSON 形式保存在订阅中, 例如:
+///
+/// ```json
+/// {"type": "all", "rules": [
+///     {"type": "not", "rule": {"type": "title", "pattern": "繁體|CHT"}},
+///     {"type": "not", "rule": {"type": "codec", "codecs": ["HEVC"]}},
+///     {"type": "size", "min": "300MB"}
+/// ]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterRule {
+    /// 所有规则都满足
+    All { rules: Vec<FilterRule> },
+    /// 任一规则满足
+    Any { rules: Vec<FilterRule> },
+    /// 规则不满足
+    Not { rule: Box<FilterRule> },
+    /// 标题匹配正则表达式, 忽略大小写
+    Title { pattern: String },
+    /// 文件大小范围, 支持 "300MB", "1.5GiB" 等写法
+    Size {
+        #[serde(default)]
+        min: Option<String>,
+        #[serde(default)]
+        max: Option<String>,
+    },
+    /// 视频编码为其中之一
+    Codec { codecs: Vec<VideoCodec> },
+    /// 片源为其中之一
+    Source { sources: Vec<MediaSource> },
+    /// 分辨率为其中之一
+    Resolution { resolutions: Vec<VideoResolution> },
+    /// 包含其中任一语言
+    Language { languages: Vec<Language> },
+    /// 发布组为其中之一
+    ReleaseGroup { groups: Vec<String> },
+}
+
+impl FilterRule {
+    /// 从 JSON 解析并校验规则
+    pub fn from_json(json: &str) -> Result<Self> {
+        let rule: FilterRule = serde_json::from_str(json).context("过滤规则格式错误")?;
+        rule.compile()?;
+        Ok(rule)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// 是否为不包含任何规则的 `all`, 订阅时用于清除已保存的过滤规则
+    pub fn is_empty(&self) -> bool {
+        matches!(self, FilterRule::All { rules } if rules.is_empty())
+    }
+
+    /// 编译规则, 校验正则表达式与文件大小
+    pub fn compile(&self) -> Result<CompiledRule> {
+        let compiled = match self {
+            FilterRule::All { rules } => {
+                CompiledRule::All(rules.iter().map(|r| r.compile()).collect::<Result<_>>()?)
+            }
+            FilterRule::Any { rules } => {
+                CompiledRule::Any(rules.iter().map(|r| r.compile()).collect::<Result<_>>()?)
+            }
+            FilterRule::Not { rule } => CompiledRule::Not(Box::new(rule.compile()?)),
+            FilterRule::Title { pattern } => CompiledRule::Title(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("无效的正则表达式: {}", pattern))?,
+            ),
+            FilterRule::Size { min, max } => {
+                let parse = |size: &Option<String>| -> Result<Option<i64>> {
+                    size.as_deref()
+                        .map(|s| {
+                            parse_human_size(s)
+                                .map(|size| size as i64)
+                                .with_context(|| format!("无效的文件大小: {}", s))
+                        })
+                        .transpose()
+                };
+                let (min, max) = (parse(min)?, parse(max)?);
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(anyhow::anyhow!("文件大小下限不能大于上限"));
+                    }
+                }
+                CompiledRule::Size { min, max }
+            }
+            FilterRule::Codec { codecs } => CompiledRule::Codec(codecs.clone()),
+            FilterRule::Source { sources } => CompiledRule::Source(sources.clone()),
+            FilterRule::Resolution { resolutions } => CompiledRule::Resolution(resolutions.clone()),
+            FilterRule::Language { languages } => CompiledRule::Language(languages.clone()),
+            FilterRule::ReleaseGroup { groups } => CompiledRule::ReleaseGroup(groups.clone()),
+        };
+        Ok(compiled)
+    }
+}
+
+/// 编译后的过滤规则
+#[derive(Debug, Clone)]
+pub enum CompiledRule {
+    All(Vec<CompiledRule>),
+    Any(Vec<CompiledRule>),
+    Not(Box<CompiledRule>),
+    Title(Regex),
+    Size { min: Option<i64>, max: Option<i64> },
+    Codec(Vec<VideoCodec>),
+    Source(Vec<MediaSource>),
+    Resolution(Vec<VideoResolution>),
+    Language(Vec<Language>),
+    ReleaseGroup(Vec<String>),
+}
+
+impl CompiledRule {
+    pub fn matches(
+        &self,
+        torrent: &torrents::Model,
+        parse_result: &file_name_parse_record::Model,
+    ) -> bool {
+        match self {
+            CompiledRule::All(rules) => rules.iter().all(|r| r.matches(torrent, parse_result)),
+            CompiledRule::Any(rules) => rules.iter().any(|r| r.matches(torrent, parse_result)),
+            CompiledRule::Not(rule) => !rule.matches(torrent, parse_result),
+            CompiledRule::Title(re) => re.is_match(&torrent.title),
+            CompiledRule::Size { min, max } => {
+                min.is_none_or(|min| torrent.size >= min)
+                    && max.is_none_or(|max| torrent.size <= max)
+            }
+            CompiledRule::Codec(codecs) => {
+                VideoCodec::detect(&torrent.title).is_some_and(|codec| codecs.contains(&codec))
+            }
+            CompiledRule::Source(sources) => {
+                MediaSource::detect(&torrent.title).is_some_and(|source| sources.contains(&source))
+            }
+            CompiledRule::Resolution(resolutions) => {
+                let resolution = parse_result
+                    .video_resolution
+                    .as_deref()
+                    .map(VideoResolution::from)
+                    .unwrap_or(VideoResolution::Unknown);
+                resolutions.contains(&resolution)
+            }
+            CompiledRule::Language(languages) => parse_result
+                .language
+                .as_ref()
+                .map(|langs| {
+                    langs
+                        .split(',')
+                        .map(Language::from)
+                        .any(|lang| languages.contains(&lang))
+                })
+                .unwrap_or(false),
+            CompiledRule::ReleaseGroup(groups) => parse_result
+                .release_group
+                .as_ref()
+                .map(|group| groups.iter().any(|g| g.eq_ignore_ascii_case(group)))
+                .unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::sea_orm_active_enums::{ParserStatus, Source};
+
+    fn pair(
+        title: &str,
+        size: i64,
+        language: &str,
+    ) -> (torrents::Model, file_name_parse_record::Model) {
+        (
+            torrents::Model {
+                info_hash: "aaaa".to_owned(),
+                bangumi_id: 1,
+                title: title.to_owned(),
+                size,
+                magnet: None,
+                data: None,
+                download_url: None,
+                pub_date: Default::default(),
+                source: Source::Mikan,
+                seeders: None,
+            },
+            file_name_parse_record::Model {
+                file_name: title.to_owned(),
+                release_group: Some("LoliHouse".to_owned()),
+                bangumi_name: None,
+                season_number: None,
+                episode_number: Some(1),
+                language: Some(language.to_owned()),
+                video_resolution: Some("1080P".to_owned()),
+                year: None,
+                parser_name: "test".to_owned(),
+                parser_status: ParserStatus::Completed,
+                err_msg: None,
+                created_at: Default::default(),
+                updated_at: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            VideoCodec::detect("[LoliHouse] Frieren - 28 [WebRip 1080p HEVC-10bit AAC]"),
+            Some(VideoCodec::Hevc)
+        );
+        assert_eq!(
+            VideoCodec::detect("[SubsPlease] Frieren - 28 (1080p) x264"),
+            Some(VideoCodec::Avc)
+        );
+        assert_eq!(
+            MediaSource::detect("[LoliHouse] Frieren - 28 [WebRip 1080p HEVC-10bit AAC]"),
+            Some(MediaSource::WebRip)
+        );
+        assert_eq!(
+            MediaSource::detect("[VCB-Studio] Frieren [Ma10p_1080p][BDRip]"),
+            Some(MediaSource::BDRip)
+        );
+    }
+
+    #[test]
+    fn test_rules() -> Result<()> {
+        let rule = FilterRule::from_json(
+            r#"{"type": "all", "rules": [
+                {"type": "not", "rule": {"type": "title", "pattern": "繁體|CHT"}},
+                {"type": "not", "rule": {"type": "codec", "codecs": ["HEVC"]}},
+                {"type": "size", "min": "300MB"}
+            ]}"#,
+        )?
+        .compile()?;
+
+        let (t, p) = pair(
+            "[ANi] Frieren - 28 [1080P][Baha][WEB-DL][AAC AVC][CHT]",
+            500 << 20,
+            "CHT",
+        );
+        assert!(!rule.matches(&t, &p));
+        let (t, p) = pair(
+            "[LoliHouse] Frieren - 28 [WebRip 1080p HEVC-10bit AAC]",
+            500 << 20,
+            "CHS",
+        );
+        assert!(!rule.matches(&t, &p));
+        let (t, p) = pair(
+            "[SweetSub] Frieren - 28 [WebRip][1080P][AVC 8bit][简日]",
+            200 << 20,
+            "CHS",
+        );
+        assert!(!rule.matches(&t, &p));
+        let (t, p) = pair(
+            "[SweetSub] Frieren - 28 [WebRip][1080P][AVC 8bit][简日]",
+            400 << 20,
+            "CHS",
+        );
+        assert!(rule.matches(&t, &p));
+
+        let rule = FilterRule::Any {
+            rules: vec![
+                FilterRule::Language {
+                    languages: vec![Language::CHS],
+                },
+                FilterRule::ReleaseGroup {
+                    groups: vec!["lolihouse".to_owned()],
+                },
+            ],
+        }
+        .compile()?;
+        let (t, p) = pair("[LoliHouse] Frieren - 28", 0, "JPN");
+        assert!(rule.matches(&t, &p));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(FilterRule::from_json(r#"{"type": "title", "pattern": "("}"#).is_err());
+        assert!(FilterRule::from_json(r#"{"type": "size", "min": "1GB", "max": "1MB"}"#).is_err());
+        assert!(FilterRule::from_json(r#"{"type": "size", "min": "abc"}"#).is_err());
+        assert!(FilterRule::from_json(r#"{"type": "unknown"}"#).is_err());
+    }
+}
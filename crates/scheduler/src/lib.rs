@@ -1,15 +1,24 @@
 #![deny(clippy::unused_async)]
 mod db;
+pub mod filter;
 pub mod metrics;
+pub mod organizer;
 mod scheduler;
+pub mod score;
 mod selector;
+pub mod sidecar;
 mod subscribe;
 mod tasks;
+#[cfg(test)]
+mod testing;
 mod worker;
 
 pub use db::Db;
+pub use filter::FilterRule;
+pub use organizer::{Organizer, OrganizerConfig};
 pub use scheduler::Scheduler;
-pub use selector::TorrentSelector;
+pub use score::{ScoreBreakdown, ScoreProfile};
+pub use selector::{TorrentScore, TorrentSelector};
 pub use tasks::TaskManager;
 use torrent::Torrent;
 pub use worker::BangumiWorker;
@@ -0,0 +1,647 @@
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use downloader::{Downloader, FileMove, context::TorrentContext};
+use model::{bangumi, episodes, torrent_download_tasks};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::sidecar::{SidecarPaths, SidecarWriter};
+
+/// Plex 命名模板
+pub const PLEX_TEMPLATE: &str =
+    "{name} ({year})/Season {season:02}/{name} - s{season:02}e{episode:02}.{ext}";
+/// Jellyfin 命名模板
+pub const JELLYFIN_TEMPLATE: &str =
+    "{name} ({year})/Season {season:02}/{name} S{season:02}E{episode:02}.{ext}";
+
+const VIDEO_EXTS: &[&str] = &[
+    "mkv", "mp4", "avi", "mov", "ts", "m2ts", "wmv", "flv", "webm", "rmvb",
+];
+const SUBTITLE_EXTS: &[&str] = &["ass", "ssa", "srt", "vtt", "sup", "idx", "sub"];
+
+/// 整理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeMode {
+    /// 硬链接, 不影响下载器做种, 跨文件系统时退化为复制
+    #[default]
+    Hardlink,
+    /// 移动, 下载器将无法继续做种
+    Move,
+    /// 复制
+    Copy,
+}
+
+/// 媒体库整理配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrganizerConfig {
+    pub enabled: bool,
+    pub mode: OrganizeMode,
+    /// 媒体库根目录
+    pub library_dir: String,
+    /// 网盘中的媒体库根目录, 用于无法挂载到本地的网盘下载器, 如 115 网盘
+    pub remote_library_dir: String,
+    /// 命名模板, 可以使用预设 "plex", "jellyfin",
+    /// 支持 {name}, {year}, {season}, {episode}, {tmdb_id}, {ext} 占位符,
+    /// 数字可以指定补零宽度, 如 {episode:02}
+    pub template: String,
+    /// 是否生成 nfo 及海报等元数据文件
+    pub write_sidecars: bool,
+}
+
+impl Default for OrganizerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: OrganizeMode::Hardlink,
+            library_dir: "/media/anime".to_owned(),
+            remote_library_dir: "/anime".to_owned(),
+            template: "jellyfin".to_owned(),
+            write_sidecars: true,
+        }
+    }
+}
+
+impl OrganizerConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !Path::new(&self.library_dir).is_absolute() {
+            return Err(anyhow::anyhow!("organizer.library_dir 必须是绝对路径"));
+        }
+        if !Path::new(&self.remote_library_dir).is_absolute() {
+            return Err(anyhow::anyhow!(
+                "organizer.remote_library_dir 必须是绝对路径"
+            ));
+        }
+        NamingTemplate::parse(&self.template)?;
+        Ok(())
+    }
+}
+
+/// 命名模板中的片段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Var { key: String, width: usize },
+}
+
+/// 文件命名模板
+#[derive(Debug, Clone)]
+pub struct NamingTemplate {
+    segments: Vec<Segment>,
+}
+
+/// 渲染模板所需的剧集信息
+#[derive(Debug, Clone, Default)]
+pub struct EpisodeInfo {
+    pub name: String,
+    pub year: Option<i32>,
+    pub season: i32,
+    pub episode: i32,
+    pub tmdb_id: Option<i64>,
+}
+
+impl EpisodeInfo {
+    pub fn new(bangumi: &bangumi::Model, episode_number: i32) -> Self {
+        Self {
+            name: bangumi.name.clone(),
+            year: bangumi.air_date.map(|date| date.year()),
+            season: bangumi.season_number.unwrap_or(1) as i32,
+            episode: episode_number,
+            tmdb_id: bangumi.tmdb_id,
+        }
+    }
+}
+
+impl NamingTemplate {
+    const KEYS: &[&str] = &["name", "year", "season", "episode", "tmdb_id", "ext"];
+
+    pub fn parse(template: &str) -> Result<Self> {
+        let template = match template.trim().to_ascii_lowercase().as_str() {
+            "plex" => PLEX_TEMPLATE,
+            "jellyfin" => JELLYFIN_TEMPLATE,
+            _ => template.trim(),
+        };
+        if template.is_empty() {
+            return Err(anyhow::anyhow!("命名模板不能为空"));
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .with_context(|| format!("命名模板缺少 '}}': {}", template))?;
+            let (key, width) = match rest[start + 1..end].split_once(':') {
+                Some((key, spec)) => {
+                    let width = spec
+                        .strip_prefix('0')
+                        .and_then(|width| width.parse().ok())
+                        .with_context(|| format!("无效的格式 {}, 只支持补零, 如 :02", spec))?;
+                    (key, width)
+                }
+                None => (&rest[start + 1..end], 0),
+            };
+            if !Self::KEYS.contains(&key) {
+                return Err(anyhow::anyhow!("命名模板中存在未知的占位符: {}", key));
+            }
+            segments.push(Segment::Var {
+                key: key.to_owned(),
+                width,
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_owned()));
+        }
+        if !segments
+            .iter()
+            .any(|s| matches!(s, Segment::Var { key, .. } if key == "ext"))
+        {
+            return Err(anyhow::anyhow!("命名模板必须包含 {{ext}}"));
+        }
+        Ok(Self { segments })
+    }
+
+    /// 渲染为相对路径, 缺失的字段渲染为空, 并去除因此产生的空括号
+    pub fn render(&self, info: &EpisodeInfo, ext: &str) -> PathBuf {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Var { key, width } => {
+                    let value = match key.as_str() {
+                        "name" => sanitize(&info.name),
+                        "year" => info.year.map(|y| y.to_string()).unwrap_or_default(),
+                        "season" => format!("{:0width$}", info.season, width = *width),
+                        "episode" => format!("{:0width$}", info.episode, width = *width),
+                        "tmdb_id" => info.tmdb_id.map(|id| id.to_string()).unwrap_or_default(),
+                        "ext" => ext.to_owned(),
+                        _ => String::new(),
+                    };
+                    rendered.push_str(&value);
+                }
+            }
+        }
+
+        rendered
+            .split('/')
+            .map(|component| {
+                component
+                    .replace("()", "")
+                    .replace("[]", "")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|component| !component.is_empty() && component != "." && component != "..")
+            .collect()
+    }
+}
+
+/// 替换文件名中不允许的字符
+fn sanitize(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+fn has_ext(path: &Path, exts: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| exts.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// 从种子的文件列表中选出视频文件, 以及与之匹配的字幕文件
+///
+/// 多个视频文件时选择最大的, 字幕文件需要与视频在同一目录且以视频文件名开头,
+/// 返回 (文件下标, 扩展名), 字幕的扩展名保留语言后缀, 如 "sc.ass"
+fn pick_files(files: &[(PathBuf, usize)]) -> Vec<(usize, String)> {
+    let Some((video_index, (video, _))) = files
+        .iter()
+        .enumerate()
+        .filter(|(_, (path, _))| has_ext(path, VIDEO_EXTS))
+        .max_by_key(|(_, (_, size))| *size)
+    else {
+        return vec![];
+    };
+    let (Some(stem), Some(ext)) = (
+        video.file_stem().and_then(|s| s.to_str()),
+        video.extension().and_then(|s| s.to_str()),
+    ) else {
+        return vec![];
+    };
+
+    let mut result = vec![(video_index, ext.to_owned())];
+    for (index, (path, _)) in files.iter().enumerate() {
+        if !has_ext(path, SUBTITLE_EXTS) || path.parent() != video.parent() {
+            continue;
+        }
+        let suffix = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(stem))
+            .and_then(|suffix| suffix.strip_prefix('.'));
+        if let Some(suffix) = suffix {
+            result.push((index, suffix.to_owned()));
+        }
+    }
+    result
+}
+
+/// 下载完成后将剧集整理到媒体库
+pub struct Organizer {
+    config: OrganizerConfig,
+    template: NamingTemplate,
+    /// 下载器名称 -> 下载目录在本地的挂载路径
+    mounts: HashMap<String, PathBuf>,
+    sidecar: SidecarWriter,
+}
+
+impl Organizer {
+    pub fn new(
+        config: OrganizerConfig,
+        mounts: HashMap<String, PathBuf>,
+        assets_path: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        config.validate()?;
+        let template = NamingTemplate::parse(&config.template)?;
+        Ok(Self {
+            config,
+            template,
+            mounts,
+            sidecar: SidecarWriter::new(assets_path),
+        })
+    }
+
+    pub fn mode(&self) -> OrganizeMode {
+        self.config.mode
+    }
+
+    /// 剧集文件在媒体库中的路径
+    pub fn target_path(&self, info: &EpisodeInfo, ext: &str) -> PathBuf {
+        PathBuf::from(&self.config.library_dir).join(self.template.render(info, ext))
+    }
+
+    /// 元数据文件的路径, 模板至少有两级目录时才会生成番剧及季的元数据
+    pub fn sidecar_paths(&self, info: &EpisodeInfo) -> SidecarPaths {
+        let library_dir = PathBuf::from(&self.config.library_dir);
+        let relative = self.template.render(info, "nfo");
+        let depth = relative.components().count();
+        SidecarPaths {
+            show_dir: relative
+                .components()
+                .next()
+                .filter(|_| depth >= 2)
+                .map(|show| library_dir.join(show)),
+            season_dir: relative
+                .parent()
+                .filter(|_| depth >= 3)
+                .map(|season| library_dir.join(season)),
+            episode_nfo: library_dir.join(relative),
+        }
+    }
+
+    /// 重新生成已整理剧集的元数据文件, 剧集目录不存在时跳过并返回 false
+    pub async fn write_sidecars(
+        &self,
+        bangumi: &bangumi::Model,
+        episode: Option<&episodes::Model>,
+        episode_number: i32,
+    ) -> Result<bool> {
+        let paths = self.sidecar_paths(&EpisodeInfo::new(bangumi, episode_number));
+        let organized = match paths.episode_nfo.parent() {
+            Some(dir) => tokio::fs::try_exists(dir).await?,
+            None => false,
+        };
+        if !organized {
+            return Ok(false);
+        }
+        self.sidecar
+            .write(bangumi, episode, episode_number, &paths)
+            .await?;
+        Ok(true)
+    }
+
+    /// 网盘中剧集文件的路径
+    pub fn remote_target_path(&self, info: &EpisodeInfo, ext: &str) -> PathBuf {
+        PathBuf::from(&self.config.remote_library_dir).join(self.template.render(info, ext))
+    }
+
+    /// 整理下载完成的剧集, 返回整理后的文件路径
+    ///
+    /// 挂载到本地的下载器直接操作本地文件, 其他下载器交给下载器在内部移动及重命名,
+    /// 下载器不支持时跳过
+    pub async fn organize(
+        &self,
+        bangumi: &bangumi::Model,
+        episode: Option<&episodes::Model>,
+        episode_number: i32,
+        task: &torrent_download_tasks::Model,
+        downloader: &dyn Downloader,
+    ) -> Result<Vec<PathBuf>> {
+        let info = EpisodeInfo::new(bangumi, episode_number);
+        let organized = match self.mounts.get(&task.downloader) {
+            Some(mount) => {
+                let organized = self.organize_local(mount, &info, task).await?;
+                if !organized.is_empty() && self.config.write_sidecars {
+                    self.sidecar
+                        .write(bangumi, episode, episode_number, &self.sidecar_paths(&info))
+                        .await
+                        .context("生成元数据文件失败")?;
+                }
+                organized
+            }
+            None => self.organize_remote(&info, task, downloader).await?,
+        };
+
+        if let Some(first) = organized.first() {
+            info!(
+                "番剧 {} 第 {} 集已整理到 {}",
+                bangumi.name,
+                episode_number,
+                first.display()
+            );
+        }
+        Ok(organized)
+    }
+
+    async fn organize_local(
+        &self,
+        mount: &Path,
+        info: &EpisodeInfo,
+        task: &torrent_download_tasks::Model,
+    ) -> Result<Vec<PathBuf>> {
+        let ctx = task
+            .context
+            .clone()
+            .context("下载任务没有文件信息")
+            .and_then(TorrentContext::try_from)?;
+        let source_dir = mount.join(ctx.dir.trim_start_matches('/'));
+        let files: Vec<_> = ctx
+            .files
+            .iter()
+            .map(|file| (source_dir.join(&file.name), file.size))
+            .collect();
+
+        let picked = pick_files(&files);
+        if picked.is_empty() {
+            warn!(
+                "番剧 {} 第 {} 集没有找到视频文件, 跳过整理",
+                info.name, info.episode
+            );
+            return Ok(vec![]);
+        }
+
+        // 洗版时新文件的扩展名可能不同, 先移除原来整理的视频及字幕
+        self.remove_organized(info).await?;
+
+        let mut organized = Vec::new();
+        for (index, ext) in picked {
+            let source = &files[index].0;
+            let target = self.target_path(info, &ext);
+            self.place(source, &target)
+                .await
+                .with_context(|| format!("整理文件 {} 失败", source.display()))?;
+            organized.push(target);
+        }
+        Ok(organized)
+    }
+
+    /// 在网盘内部移动及重命名文件, 元数据文件无法写入网盘, 不会生成
+    async fn organize_remote(
+        &self,
+        info: &EpisodeInfo,
+        task: &torrent_download_tasks::Model,
+        downloader: &dyn Downloader,
+    ) -> Result<Vec<PathBuf>> {
+        let files: Vec<_> = downloader
+            .list_files(&task.info_hash)
+            .await?
+            .into_iter()
+            .filter(|file| !file.is_dir)
+            .collect();
+        let picked = pick_files(
+            &files
+                .iter()
+                .map(|file| (PathBuf::from(&file.file_name), file.file_size))
+                .collect::<Vec<_>>(),
+        );
+        if picked.is_empty() {
+            warn!(
+                "番剧 {} 第 {} 集没有找到视频文件, 跳过整理",
+                info.name, info.episode
+            );
+            return Ok(vec![]);
+        }
+
+        let moves: Vec<_> = picked
+            .into_iter()
+            .map(|(index, ext)| FileMove {
+                file_id: files[index].file_id.clone(),
+                file_size: files[index].file_size,
+                target: self.remote_target_path(info, &ext),
+            })
+            .collect();
+        if !downloader.organize_files(&task.info_hash, &moves).await? {
+            info!("下载器 {} 不支持整理文件, 跳过整理", task.downloader);
+            return Ok(vec![]);
+        }
+        Ok(moves.into_iter().map(|mv| mv.target).collect())
+    }
+
+    /// 移除剧集已整理的视频及字幕文件, 元数据文件会被重新生成, 不需要移除
+    async fn remove_organized(&self, info: &EpisodeInfo) -> Result<()> {
+        let target = self.target_path(info, "mkv");
+        let (Some(dir), Some(stem)) = (
+            target.parent(),
+            target.file_stem().and_then(|stem| stem.to_str()),
+        ) else {
+            return Ok(());
+        };
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_episode_file = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(stem))
+                .is_some_and(|suffix| suffix.starts_with('.'));
+            if is_episode_file
+                && (has_ext(&path, VIDEO_EXTS) || has_ext(&path, SUBTITLE_EXTS))
+                && entry.file_type().await?.is_file()
+            {
+                info!("移除原来整理的文件 {}", path.display());
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn place(&self, source: &Path, target: &Path) -> Result<()> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // 洗版时覆盖原来的文件
+        if tokio::fs::try_exists(target).await? {
+            tokio::fs::remove_file(target).await?;
+        }
+        match self.config.mode {
+            OrganizeMode::Hardlink => {
+                if let Err(e) = tokio::fs::hard_link(source, target).await {
+                    warn!("创建硬链接失败, 改为复制: {}", e);
+                    tokio::fs::copy(source, target).await?;
+                }
+            }
+            OrganizeMode::Move => {
+                if let Err(e) = tokio::fs::rename(source, target).await {
+                    warn!("移动文件失败, 改为复制后删除: {}", e);
+                    tokio::fs::copy(source, target).await?;
+                    tokio::fs::remove_file(source).await?;
+                }
+            }
+            OrganizeMode::Copy => {
+                tokio::fs::copy(source, target).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> EpisodeInfo {
+        EpisodeInfo {
+            name: "葬送的芙莉莲".to_owned(),
+            year: Some(2023),
+            season: 1,
+            episode: 5,
+            tmdb_id: Some(209867),
+        }
+    }
+
+    #[test]
+    fn test_render_template() -> Result<()> {
+        let template = NamingTemplate::parse("jellyfin")?;
+        assert_eq!(
+            template.render(&info(), "mkv"),
+            PathBuf::from("葬送的芙莉莲 (2023)/Season 01/葬送的芙莉莲 S01E05.mkv")
+        );
+
+        let template = NamingTemplate::parse("plex")?;
+        let mut no_year = info();
+        no_year.year = None;
+        no_year.name = "Re:Zero".to_owned();
+        assert_eq!(
+            template.render(&no_year, "sc.ass"),
+            PathBuf::from("Re Zero/Season 01/Re Zero - s01e05.sc.ass")
+        );
+
+        let template = NamingTemplate::parse("{name} [tmdbid-{tmdb_id}]/{episode:03}.{ext}")?;
+        assert_eq!(
+            template.render(&info(), "mp4"),
+            PathBuf::from("葬送的芙莉莲 [tmdbid-209867]/005.mp4")
+        );
+
+        assert!(NamingTemplate::parse("{name}/{episode}").is_err());
+        assert!(NamingTemplate::parse("{title}.{ext}").is_err());
+        assert!(NamingTemplate::parse("{episode:2}.{ext}").is_err());
+        assert!(NamingTemplate::parse("{episode.{ext}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pick_files() {
+        let files = vec![
+            (PathBuf::from("/dl/a/[LoliHouse] Frieren - 05.mkv"), 700),
+            (PathBuf::from("/dl/a/[LoliHouse] Frieren - 05.sc.ass"), 1),
+            (PathBuf::from("/dl/a/[LoliHouse] Frieren - 05.tc.ass"), 1),
+            (PathBuf::from("/dl/a/other.ass"), 1),
+            (PathBuf::from("/dl/a/SP/[LoliHouse] Frieren - 05.ass"), 1),
+            (PathBuf::from("/dl/a/NCOP.mkv"), 100),
+        ];
+        let picked = pick_files(&files);
+        assert_eq!(
+            picked,
+            vec![
+                (0, "mkv".to_owned()),
+                (1, "sc.ass".to_owned()),
+                (2, "tc.ass".to_owned()),
+            ]
+        );
+        assert!(pick_files(&[(PathBuf::from("/dl/readme.txt"), 1)]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_place() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("organizer-test-{}", std::process::id()));
+        let source = root.join("download/episode.mkv");
+        tokio::fs::create_dir_all(source.parent().unwrap()).await?;
+        tokio::fs::write(&source, b"video").await?;
+
+        let config = OrganizerConfig {
+            enabled: true,
+            library_dir: root.join("library").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let organizer = Organizer::new(config, HashMap::new(), root.join("assets"))?;
+        let target = organizer.target_path(&info(), "mkv");
+        organizer.place(&source, &target).await?;
+        // 重复整理时覆盖
+        organizer.place(&source, &target).await?;
+        assert_eq!(tokio::fs::read(&target).await?, b"video");
+        assert!(tokio::fs::try_exists(&source).await?);
+
+        // 洗版时移除原来的视频及字幕, 其他剧集及元数据文件保留
+        let old_video = organizer.target_path(&info(), "mp4");
+        let old_subtitle = organizer.target_path(&info(), "sc.ass");
+        let nfo = organizer.target_path(&info(), "nfo");
+        let mut next = info();
+        next.episode = 6;
+        let next_video = organizer.target_path(&next, "mp4");
+        for path in [&old_video, &old_subtitle, &nfo, &next_video] {
+            tokio::fs::write(path, b"old").await?;
+        }
+        organizer.remove_organized(&info()).await?;
+        for path in [&target, &old_video, &old_subtitle] {
+            assert!(!tokio::fs::try_exists(path).await?);
+        }
+        assert!(tokio::fs::try_exists(&nfo).await?);
+        assert!(tokio::fs::try_exists(&next_video).await?);
+        assert!(tokio::fs::try_exists(&source).await?);
+
+        let paths = organizer.sidecar_paths(&info());
+        let library = root.join("library");
+        assert_eq!(paths.show_dir, Some(library.join("葬送的芙莉莲 (2023)")));
+        assert_eq!(
+            paths.season_dir,
+            Some(library.join("葬送的芙莉莲 (2023)/Season 01"))
+        );
+        assert_eq!(
+            paths.episode_nfo,
+            library.join("葬送的芙莉莲 (2023)/Season 01/葬送的芙莉莲 S01E05.nfo")
+        );
+
+        tokio::fs::remove_dir_all(&root).await?;
+        Ok(())
+    }
+}
@@ -9,6 +9,9 @@ use tracing::{error, info};
 
 use crate::db::Db;
 use crate::metrics::Metrics;
+use crate::organizer::Organizer;
+use crate::score::ScoreProfile;
+use crate::selector::{TorrentScore, TorrentSelector};
 use crate::tasks::TaskManager;
 use crate::worker::BangumiWorker;
 
@@ -23,9 +26,12 @@ pub struct Scheduler {
     pub(crate) workers: Arc<Mutex<HashMap<i32, BangumiWorker>>>, // 存储 worker 实例以便管理生命周期
     pub(crate) notify: notify::worker::Worker,
     pub(crate) client: reqwest::Client,
+    /// 全局评分配置, 订阅可以覆盖
+    pub(crate) score_profile: ScoreProfile,
 }
 
 impl Scheduler {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db: Db,
         parser: parser::worker::Worker,
@@ -33,8 +39,15 @@ impl Scheduler {
         downloader: Arc<Box<dyn Downloader>>,
         notify: notify::worker::Worker,
         client: reqwest::Client,
+        score_profile: ScoreProfile,
+        organizer: Option<Organizer>,
     ) -> Self {
-        let task_manager = TaskManager::new(db.clone(), downloader.clone(), notify.clone());
+        let task_manager = TaskManager::new(
+            db.clone(),
+            downloader.clone(),
+            notify.clone(),
+            organizer.map(Arc::new),
+        );
         Self {
             db,
             parser,
@@ -44,9 +57,11 @@ impl Scheduler {
             workers: Arc::new(Mutex::new(HashMap::new())),
             notify,
             client,
+            score_profile,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_conn(
         conn: Arc<DatabaseConnection>,
         parser: parser::worker::Worker,
@@ -54,9 +69,20 @@ impl Scheduler {
         downloader: Arc<Box<dyn Downloader>>,
         notify: notify::worker::Worker,
         client: reqwest::Client,
+        score_profile: ScoreProfile,
+        organizer: Option<Organizer>,
     ) -> Self {
         let db = Db::new(conn);
-        Self::new(db, parser, metadata, downloader, notify, client)
+        Self::new(
+            db,
+            parser,
+            metadata,
+            downloader,
+            notify,
+            client,
+            score_profile,
+            organizer,
+        )
     }
 
     /// 创建并启动 worker 或重启 worker
@@ -102,6 +128,7 @@ impl Scheduler {
             self.task_manager.clone(),
             recommended_resource_type,
             self.client.clone(),
+            &self.score_profile,
         );
         let worker_clone = worker.clone();
         worker.spawn();
@@ -212,17 +239,22 @@ impl Scheduler {
         Ok(())
     }
 
-    pub async fn trigger_collection(&self, bangumi_id: i32) -> Result<()> {
+    /// 元数据更新后, 重新生成已整理剧集的 nfo 及图片
+    pub async fn regenerate_sidecars(&self, bangumi_id: i32) -> Result<usize> {
+        self.task_manager.regenerate_sidecars(bangumi_id).await
+    }
+
+    pub async fn trigger_collection(&self, bangumi_id: i32, force: bool) -> Result<()> {
         let mut workers = self.workers.lock().await;
         if let Some(worker) = workers.get_mut(&bangumi_id) {
-            worker.trigger_collection();
+            worker.trigger_collection(force);
         }
         Ok(())
     }
 
     pub async fn collect_torrents_and_parse(&self, bangumi_id: i32) -> Result<Vec<ParseResult>> {
         self.metadata
-            .request_refresh_torrents_and_wait(bangumi_id)
+            .request_refresh_torrents_and_wait(bangumi_id, false)
             .await?;
         let torrents_file_names = self.db.get_bangumi_torrents_file_names(bangumi_id).await?;
         if !torrents_file_names.is_empty() {
@@ -231,4 +263,43 @@ impl Scheduler {
             Ok(vec![])
         }
     }
+
+    /// 按集数分组计算番剧所有种子的得分, 用于解释种子的选择结果
+    pub async fn score_bangumi_torrents(
+        &self,
+        bangumi_id: i32,
+    ) -> Result<HashMap<String, TorrentScore>> {
+        let Some(bangumi) = self.db.get_bangumi_by_id(bangumi_id).await? else {
+            return Err(anyhow::anyhow!("未找到番剧记录"));
+        };
+        let selector = match self.db.get_subscription(bangumi_id).await? {
+            Some(sub) => TorrentSelector::new(&sub, &self.score_profile),
+            None => TorrentSelector::from_profile(&self.score_profile),
+        };
+
+        let torrent_pairs = self
+            .db
+            .get_bangumi_torrents_with_parse_results(bangumi_id)
+            .await?;
+        let mut episodes: HashMap<i32, Vec<_>> = HashMap::new();
+        for pair in torrent_pairs {
+            let Some(ep) = pair.1.episode_number else {
+                continue;
+            };
+            // 与选择种子时一致的剧集修复
+            let ep_start_number = bangumi.ep_start_number;
+            let actual_ep = if ep_start_number > 1 && ep < ep_start_number {
+                ep + ep_start_number - 1
+            } else {
+                ep
+            };
+            episodes.entry(actual_ep).or_default().push(pair);
+        }
+
+        Ok(episodes
+            .values()
+            .flat_map(|pairs| selector.score(pairs))
+            .map(|score| (score.info_hash.clone(), score))
+            .collect())
+    }
 }
@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use model::{file_name_parse_record, torrents};
+use parser::{Language, VideoResolution};
+use serde::{Deserialize, Serialize};
+use utils::size::parse_human_size;
+
+use crate::filter::VideoCodec;
+
+/// 做种数达到该值时得满分
+const FULL_SCORE_SEEDERS: f64 = 1000.0;
+
+/// 种子评分配置, 全局配置作为默认值, 订阅可以覆盖其中的部分字段
+///
+/// 每一项得分在 0 到 1 之间, 乘以对应权重后相加得到总分,
+/// 总分最高的种子被选中, 总分相同时选择发布时间较新的种子.
+/// 默认配置等价于按 分辨率 > 语言 > 发布时间 依次比较
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreProfile {
+    /// 分辨率权重, 2160P 得 1 分, 未知分辨率得 0 分
+    pub resolution_weight: f64,
+    /// 语言权重, 按种子包含的最高优先级语言计分 (CHS > CHT > JPN > ENG)
+    pub language_weight: f64,
+    /// 发布组权重
+    pub release_group_weight: f64,
+    /// 发布组偏好顺序, 越靠前得分越高, 不在列表中的发布组得 0 分
+    pub release_groups: Vec<String>,
+    /// 文件大小权重, 按与同一集中最大文件的比例计分
+    pub size_weight: f64,
+    /// 做种数权重, 按对数计分, 做种数未知时得 0 分
+    pub seeders_weight: f64,
+    /// 视频编码权重
+    pub codec_weight: f64,
+    /// 视频编码偏好顺序, 越靠前得分越高
+    pub codecs: Vec<VideoCodec>,
+    /// 文件大小下限, 小于该大小的种子不参与选择
+    pub min_size: String,
+}
+
+impl Default for ScoreProfile {
+    fn default() -> Self {
+        Self {
+            resolution_weight: 100.0,
+            language_weight: 10.0,
+            release_group_weight: 0.0,
+            release_groups: vec![],
+            size_weight: 0.0,
+            seeders_weight: 0.0,
+            codec_weight: 0.0,
+            codecs: vec![],
+            min_size: "100MB".to_owned(),
+        }
+    }
+}
+
+/// 种子的各项得分, 均已乘以权重
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub resolution: f64,
+    pub language: f64,
+    pub release_group: f64,
+    pub size: f64,
+    pub seeders: f64,
+    pub codec: f64,
+    pub total: f64,
+}
+
+impl ScoreProfile {
+    /// 从 JSON 解析并校验评分配置
+    pub fn from_json(json: &str) -> Result<Self> {
+        let profile: ScoreProfile = serde_json::from_str(json).context("评分配置格式错误")?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// 以当前配置为基础, 使用 JSON 中出现的字段覆盖
+    pub fn with_override(&self, json: &str) -> Result<Self> {
+        let overrides: serde_json::Value =
+            serde_json::from_str(json).context("评分配置格式错误")?;
+        let serde_json::Value::Object(overrides) = overrides else {
+            return Err(anyhow::anyhow!("评分配置必须是 JSON 对象"));
+        };
+        let mut merged = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(base) = &mut merged {
+            base.extend(overrides);
+        }
+        let profile: ScoreProfile = serde_json::from_value(merged).context("评分配置格式错误")?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let weights = [
+            ("resolution_weight", self.resolution_weight),
+            ("language_weight", self.language_weight),
+            ("release_group_weight", self.release_group_weight),
+            ("size_weight", self.size_weight),
+            ("seeders_weight", self.seeders_weight),
+            ("codec_weight", self.codec_weight),
+        ];
+        for (name, weight) in weights {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(anyhow::anyhow!("{} 必须是非负数", name));
+            }
+        }
+        self.min_size_bytes()?;
+        Ok(())
+    }
+
+    /// 文件大小下限, 为空时不限制
+    pub fn min_size_bytes(&self) -> Result<i64> {
+        if self.min_size.trim().is_empty() {
+            return Ok(0);
+        }
+        parse_human_size(&self.min_size)
+            .map(|size| size as i64)
+            .with_context(|| format!("无效的文件大小: {}", self.min_size))
+    }
+
+    /// 计算种子得分, max_size 为参与比较的种子中最大的文件大小
+    pub fn score(
+        &self,
+        torrent: &torrents::Model,
+        parse_result: &file_name_parse_record::Model,
+        max_size: i64,
+    ) -> ScoreBreakdown {
+        let resolution = parse_result
+            .video_resolution
+            .as_deref()
+            .map(VideoResolution::from)
+            .unwrap_or(VideoResolution::Unknown);
+        let resolution = resolution.priority() as f64 / VideoResolution::P2160.priority() as f64;
+
+        let language = parse_result
+            .language
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|lang| Language::from(lang.trim()))
+            .max()
+            .unwrap_or(Language::Unknown);
+        let language = language.priority() as f64 / Language::CHS.priority() as f64;
+
+        let release_group = parse_result
+            .release_group
+            .as_ref()
+            .map(|group| preference_score(&self.release_groups, group))
+            .unwrap_or(0.0);
+
+        let size = if max_size > 0 {
+            (torrent.size.max(0) as f64 / max_size as f64).min(1.0)
+        } else {
+            0.0
+        };
+
+        let seeders = torrent
+            .seeders
+            .map(|seeders| {
+                ((seeders.max(0) as f64 + 1.0).ln() / (FULL_SCORE_SEEDERS + 1.0).ln()).min(1.0)
+            })
+            .unwrap_or(0.0);
+
+        let codec = VideoCodec::detect(&torrent.title)
+            .map(|codec| preference_score(&self.codecs, &codec))
+            .unwrap_or(0.0);
+
+        let mut breakdown = ScoreBreakdown {
+            resolution: resolution * self.resolution_weight,
+            language: language * self.language_weight,
+            release_group: release_group * self.release_group_weight,
+            size: size * self.size_weight,
+            seeders: seeders * self.seeders_weight,
+            codec: codec * self.codec_weight,
+            total: 0.0,
+        };
+        breakdown.total = breakdown.resolution
+            + breakdown.language
+            + breakdown.release_group
+            + breakdown.size
+            + breakdown.seeders
+            + breakdown.codec;
+        breakdown
+    }
+}
+
+/// 按偏好顺序计分, 第一个得 1 分, 之后依次递减, 不在列表中得 0 分
+fn preference_score<T: PartialEq>(preferences: &[T], value: &T) -> f64 {
+    preferences
+        .iter()
+        .position(|p| p == value)
+        .map(|i| (preferences.len() - i) as f64 / preferences.len() as f64)
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::sea_orm_active_enums::{ParserStatus, Source};
+
+    fn pair(
+        title: &str,
+        release_group: &str,
+        resolution: &str,
+        language: &str,
+        seeders: Option<i32>,
+    ) -> (torrents::Model, file_name_parse_record::Model) {
+        (
+            torrents::Model {
+                info_hash: "aaaa".to_owned(),
+                bangumi_id: 1,
+                title: title.to_owned(),
+                size: 500 * 1024 * 1024,
+                magnet: None,
+                data: None,
+                download_url: None,
+                pub_date: Default::default(),
+                source: Source::Mikan,
+                seeders,
+            },
+            file_name_parse_record::Model {
+                file_name: title.to_owned(),
+                release_group: Some(release_group.to_owned()),
+                bangumi_name: None,
+                season_number: None,
+                episode_number: Some(1),
+                language: Some(language.to_owned()),
+                video_resolution: Some(resolution.to_owned()),
+                year: None,
+                parser_name: "test".to_owned(),
+                parser_status: ParserStatus::Completed,
+                err_msg: None,
+                created_at: Default::default(),
+                updated_at: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_default_profile() {
+        let profile = ScoreProfile::default();
+        let (t1, p1) = pair("[A] 1080p HEVC", "A", "1080P", "JPN", None);
+        let (t2, p2) = pair("[B] 720p", "B", "720P", "CHS,CHT", None);
+
+        let s1 = profile.score(&t1, &p1, t1.size);
+        let s2 = profile.score(&t2, &p2, t1.size);
+        assert_eq!(s1.resolution, 50.0);
+        assert_eq!(s1.language, 5.0);
+        assert_eq!(s2.language, 10.0);
+        assert_eq!(s1.codec, 0.0);
+        // 分辨率优先于语言
+        assert!(s1.total > s2.total);
+        assert_eq!(profile.min_size_bytes().unwrap(), 100 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_weighted_profile() {
+        let profile = ScoreProfile {
+            release_group_weight: 20.0,
+            release_groups: vec!["A".to_owned(), "B".to_owned()],
+            seeders_weight: 10.0,
+            codec_weight: 5.0,
+            codecs: vec![VideoCodec::Hevc, VideoCodec::Avc],
+            ..Default::default()
+        };
+        let (t1, p1) = pair("[A] 1080p HEVC", "A", "1080P", "CHS", Some(1000));
+        let (t2, p2) = pair("[B] 1080p x264", "B", "1080P", "CHS", Some(0));
+        let (t3, p3) = pair("[C] 1080p AV1", "C", "1080P", "CHS", None);
+
+        let s1 = profile.score(&t1, &p1, t1.size);
+        let s2 = profile.score(&t2, &p2, t1.size);
+        let s3 = profile.score(&t3, &p3, t1.size);
+        assert_eq!(s1.release_group, 20.0);
+        assert_eq!(s2.release_group, 10.0);
+        assert_eq!(s3.release_group, 0.0);
+        assert_eq!(s1.seeders, 10.0);
+        assert_eq!(s2.seeders, 0.0);
+        assert_eq!(s1.codec, 5.0);
+        assert_eq!(s2.codec, 2.5);
+        assert_eq!(s3.codec, 0.0);
+        assert_eq!(s1.size, 0.0);
+        assert!(s1.total > s2.total && s2.total > s3.total);
+    }
+
+    #[test]
+    fn test_override() -> Result<()> {
+        let global = ScoreProfile {
+            seeders_weight: 10.0,
+            ..Default::default()
+        };
+        let profile =
+            global.with_override(r#"{"release_groups": ["LoliHouse"], "min_size": ""}"#)?;
+        assert_eq!(profile.seeders_weight, 10.0);
+        assert_eq!(profile.release_groups, vec!["LoliHouse".to_owned()]);
+        assert_eq!(profile.min_size_bytes()?, 0);
+
+        assert!(global.with_override(r#"{"size_weight": -1}"#).is_err());
+        assert!(global.with_override(r#"{"min_size": "abc"}"#).is_err());
+        assert!(global.with_override("[]").is_err());
+        assert!(ScoreProfile::from_json(r#"{"codecs": ["MPEG2"]}"#).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,21 @@
 use model::{file_name_parse_record, subscriptions, torrents};
 use parser::{Language, VideoResolution};
+use serde::Serialize;
 use std::collections::HashSet;
+use tracing::error;
+
+use crate::filter::{CompiledRule, FilterRule};
+use crate::score::{ScoreBreakdown, ScoreProfile};
+
+/// 种子的评分结果
+#[derive(Debug, Clone, Serialize)]
+pub struct TorrentScore {
+    pub info_hash: String,
+    /// 是否满足订阅的过滤条件, 不满足的种子不会被选择
+    pub eligible: bool,
+    #[serde(flatten)]
+    pub breakdown: ScoreBreakdown,
+}
 
 #[derive(Clone)]
 pub struct TorrentSelector {
@@ -8,10 +23,14 @@ pub struct TorrentSelector {
     language_filters: HashSet<Language>,
     resolution_filters: HashSet<VideoResolution>,
     release_group_filters: HashSet<String>,
+    filter_rule: Option<CompiledRule>,
+    profile: ScoreProfile,
+    min_size: i64,
 }
 
 impl TorrentSelector {
-    pub fn new(subscription: &subscriptions::Model) -> Self {
+    /// profile 为全局评分配置, 订阅中的评分配置会覆盖其中的字段
+    pub fn new(subscription: &subscriptions::Model, profile: &ScoreProfile) -> Self {
         // 解析语言过滤器字符串
         let language_filters = subscription
             .language_filter
@@ -49,10 +68,48 @@ impl TorrentSelector {
             })
             .unwrap_or_default();
 
+        // 解析过滤规则, 规则在保存时已经校验过, 这里解析失败时忽略规则
+        let filter_rule = subscription.filter_rules.as_deref().and_then(|json| {
+            FilterRule::from_json(json)
+                .and_then(|rule| rule.compile())
+                .inspect_err(|e| error!("订阅 {} 的过滤规则无效: {}", subscription.bangumi_id, e))
+                .ok()
+        });
+
+        // 合并评分配置, 解析失败时使用全局配置
+        let profile = subscription
+            .score_profile
+            .as_deref()
+            .and_then(|json| {
+                profile
+                    .with_override(json)
+                    .inspect_err(|e| {
+                        error!("订阅 {} 的评分配置无效: {}", subscription.bangumi_id, e)
+                    })
+                    .ok()
+            })
+            .unwrap_or_else(|| profile.clone());
+        let min_size = profile.min_size_bytes().unwrap_or_default();
+
         Self {
             language_filters,
             resolution_filters,
             release_group_filters,
+            filter_rule,
+            profile,
+            min_size,
+        }
+    }
+
+    /// 不带任何过滤条件, 仅使用评分配置
+    pub fn from_profile(profile: &ScoreProfile) -> Self {
+        Self {
+            language_filters: HashSet::new(),
+            resolution_filters: HashSet::new(),
+            release_group_filters: HashSet::new(),
+            filter_rule: None,
+            profile: profile.clone(),
+            min_size: profile.min_size_bytes().unwrap_or_default(),
         }
     }
 
@@ -61,87 +118,62 @@ impl TorrentSelector {
         &self,
         torrents: &[(torrents::Model, file_name_parse_record::Model)],
     ) -> Option<torrents::Model> {
-        if torrents.is_empty() {
-            return None;
-        }
+        // 按照评分排序, 总分相同时选择发布时间较新的种子
+        self.score(torrents)
+            .into_iter()
+            .zip(torrents)
+            .filter(|(score, _)| score.eligible)
+            .max_by(|(a_score, (a_torrent, _)), (b_score, (b_torrent, _))| {
+                a_score
+                    .breakdown
+                    .total
+                    .total_cmp(&b_score.breakdown.total)
+                    .then_with(|| a_torrent.pub_date.cmp(&b_torrent.pub_date))
+            })
+            .map(|(_, (torrent, _))| torrent.clone())
+    }
 
-        // 根据订阅设置过滤种子
-        let mut candidates: Vec<_> = torrents
+    /// 计算每个种子的得分, 结果与输入一一对应
+    ///
+    /// 文件大小得分以满足过滤条件的种子中最大的文件为基准
+    pub fn score(
+        &self,
+        torrents: &[(torrents::Model, file_name_parse_record::Model)],
+    ) -> Vec<TorrentScore> {
+        let eligible: Vec<_> = torrents
             .iter()
-            .filter(|(_, parse_result)| {
-                self.match_resolution_filter(parse_result)
-                    && self.match_language_filter(parse_result)
-                    && self.match_release_group_filter(parse_result)
-            })
-            .filter(|(torrent, _)| {
-                // 过滤掉小于100MB的种子
-                torrent.size >= 100 * 1024 * 1024
-            })
+            .map(|(torrent, parse_result)| self.is_eligible(torrent, parse_result))
             .collect();
+        let max_size = torrents
+            .iter()
+            .zip(&eligible)
+            .filter(|(_, eligible)| **eligible)
+            .map(|((torrent, _), _)| torrent.size)
+            .max()
+            .unwrap_or(0);
 
-        if candidates.is_empty() {
-            return None;
-        }
-
-        // 按照优先级排序：
-        // 1. 分辨率优先级（4K > 1080p > 720p）
-        // 2. 语言优先级（CHS > JPN > Unknown）
-        // 3. 发布时间（新 > 旧）
-        candidates.sort_by(|(a_torrent, a_parse), (b_torrent, b_parse)| {
-            // 先比较分辨率
-            let a_resolution = a_parse
-                .video_resolution
-                .as_deref()
-                .map(VideoResolution::from)
-                .unwrap_or(VideoResolution::Unknown);
-            let b_resolution = b_parse
-                .video_resolution
-                .as_deref()
-                .map(VideoResolution::from)
-                .unwrap_or(VideoResolution::Unknown);
-
-            let resolution_cmp = b_resolution.cmp(&a_resolution);
-
-            if resolution_cmp != std::cmp::Ordering::Equal {
-                return resolution_cmp;
-            }
-
-            // 分辨率相同时，比较语言优先级
-            let a_languages: Vec<Language> = a_parse
-                .language
-                .as_ref()
-                .map(|langs| langs.split(',').map(Language::from).collect())
-                .unwrap_or_default();
-            let b_languages: Vec<Language> = b_parse
-                .language
-                .as_ref()
-                .map(|langs| langs.split(',').map(Language::from).collect())
-                .unwrap_or_default();
-
-            // 获取每个种子的最高语言优先级
-            let a_max_priority = a_languages
-                .iter()
-                .max()
-                .copied()
-                .unwrap_or(Language::Unknown);
-            let b_max_priority = b_languages
-                .iter()
-                .max()
-                .copied()
-                .unwrap_or(Language::Unknown);
-
-            let lang_cmp = b_max_priority.cmp(&a_max_priority);
-
-            if lang_cmp != std::cmp::Ordering::Equal {
-                return lang_cmp;
-            }
-
-            // 语言优先级相同时，按发布时间排序（新的优先）
-            b_torrent.pub_date.cmp(&a_torrent.pub_date)
-        });
+        torrents
+            .iter()
+            .zip(eligible)
+            .map(|((torrent, parse_result), eligible)| TorrentScore {
+                info_hash: torrent.info_hash.clone(),
+                eligible,
+                breakdown: self.profile.score(torrent, parse_result, max_size),
+            })
+            .collect()
+    }
 
-        // 返回排序后的第一个种子
-        Some(candidates[0].0.clone())
+    /// 检查种子是否满足订阅设置
+    fn is_eligible(
+        &self,
+        torrent: &torrents::Model,
+        parse_result: &file_name_parse_record::Model,
+    ) -> bool {
+        self.match_resolution_filter(parse_result)
+            && self.match_language_filter(parse_result)
+            && self.match_release_group_filter(parse_result)
+            && self.match_filter_rule(torrent, parse_result)
+            && torrent.size >= self.min_size
     }
 
     /// 检查是否匹配分辨率过滤器
@@ -179,6 +211,17 @@ impl TorrentSelector {
         !self.language_filters.is_disjoint(&torrent_languages)
     }
 
+    /// 检查是否匹配过滤规则
+    fn match_filter_rule(
+        &self,
+        torrent: &torrents::Model,
+        parse_result: &file_name_parse_record::Model,
+    ) -> bool {
+        self.filter_rule
+            .as_ref()
+            .is_none_or(|rule| rule.matches(torrent, parse_result))
+    }
+
     /// 检查是否匹配发布组过滤器
     fn match_release_group_filter(&self, parse_result: &file_name_parse_record::Model) -> bool {
         // 如果没有设置过滤器，接受所有发布组
@@ -194,3 +237,117 @@ impl TorrentSelector {
             .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pair;
+    use model::sea_orm_active_enums::SubscribeStatus;
+
+    type Pair = (torrents::Model, file_name_parse_record::Model);
+
+    fn with_language(mut pair: Pair, language: &str) -> Pair {
+        pair.1.language = Some(language.to_owned());
+        pair
+    }
+
+    fn subscription(
+        resolution_filter: Option<&str>,
+        language_filter: Option<&str>,
+    ) -> subscriptions::Model {
+        subscriptions::Model {
+            bangumi_id: 1,
+            subscribe_status: SubscribeStatus::Subscribed,
+            start_episode_number: None,
+            resolution_filter: resolution_filter.map(str::to_owned),
+            language_filter: language_filter.map(str::to_owned),
+            release_group_filter: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            collector_interval: None,
+            metadata_interval: None,
+            enforce_torrent_release_after_broadcast: false,
+            preferred_downloader: None,
+            allow_fallback: true,
+            filter_rules: None,
+            score_profile: None,
+            auto_upgrade: false,
+            download_strategy: None,
+        }
+    }
+
+    fn selected(selector: &TorrentSelector, torrents: &[Pair]) -> Option<String> {
+        selector.select(torrents).map(|torrent| torrent.info_hash)
+    }
+
+    #[test]
+    fn test_select_ranking() {
+        let selector = TorrentSelector::from_profile(&ScoreProfile::default());
+
+        // 分辨率优先于语言
+        let torrents = vec![
+            with_language(pair("720-chs", "720P", 1), "CHS"),
+            with_language(pair("1080-jpn", "1080P", 1), "JPN"),
+            with_language(pair("1080-chs", "1080P", 1), "CHS"),
+            with_language(pair("1080-cht", "1080P", 1), "CHT"),
+        ];
+        assert_eq!(selected(&selector, &torrents), Some("1080-chs".to_owned()));
+
+        // 总分相同时选择发布时间较新的种子
+        let mut newer = pair("newer", "1080P", 1);
+        newer.0.pub_date = chrono::NaiveDate::from_ymd_opt(2024, 3, 23)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let torrents = vec![pair("older", "1080P", 1), newer, pair("oldest", "720P", 1)];
+        assert_eq!(selected(&selector, &torrents), Some("newer".to_owned()));
+
+        // 做种数及发布组偏好参与排序
+        let selector = TorrentSelector::from_profile(&ScoreProfile {
+            seeders_weight: 10.0,
+            release_group_weight: 20.0,
+            release_groups: vec!["B".to_owned(), "A".to_owned()],
+            ..Default::default()
+        });
+        let mut few = pair("few", "1080P", 1);
+        few.0.seeders = Some(10);
+        let mut many = pair("many", "1080P", 1);
+        many.0.seeders = Some(500);
+        let mut unknown = pair("unknown", "1080P", 1);
+        unknown.0.seeders = None;
+        assert_eq!(
+            selected(&selector, &[few.clone(), many.clone(), unknown]),
+            Some("many".to_owned())
+        );
+        many.1.release_group = Some("A".to_owned());
+        few.1.release_group = Some("B".to_owned());
+        // 发布组 B 与 A 相差的得分高于做种数相差的得分
+        assert_eq!(selected(&selector, &[few, many]), Some("few".to_owned()));
+    }
+
+    #[test]
+    fn test_select_filters() {
+        let profile = ScoreProfile::default();
+        let selector = TorrentSelector::new(&subscription(Some("720P"), Some("CHT")), &profile);
+        let mut small = with_language(pair("small", "720P", 1), "CHT");
+        small.0.size = 50 * 1024 * 1024;
+        let torrents = vec![
+            with_language(pair("1080-cht", "1080P", 1), "CHT"),
+            with_language(pair("720-chs", "720P", 1), "CHS"),
+            with_language(pair("720-cht", "720P", 1), "CHS,CHT"),
+            small,
+        ];
+
+        // 不满足过滤条件的种子即使评分更高也不会被选择
+        let scores = selector.score(&torrents);
+        assert_eq!(
+            scores.iter().map(|s| s.eligible).collect::<Vec<_>>(),
+            vec![false, false, true, false]
+        );
+        assert!(scores[0].breakdown.total > scores[2].breakdown.total);
+        assert_eq!(selected(&selector, &torrents), Some("720-cht".to_owned()));
+
+        // 没有满足条件的种子
+        assert_eq!(selected(&selector, &torrents[..2]), None);
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::Result;
+use chrono::Datelike;
+use model::{bangumi, episodes};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 剧集整理后, 元数据文件的写入位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidecarPaths {
+    /// 番剧目录, 写入 tvshow.nfo, poster.jpg 及 fanart.jpg
+    pub show_dir: Option<PathBuf>,
+    /// 季目录, 写入 season.nfo
+    pub season_dir: Option<PathBuf>,
+    /// 剧集 nfo, 与视频文件同名
+    pub episode_nfo: PathBuf,
+}
+
+/// 生成 Kodi/Jellyfin 可识别的 nfo 及图片文件
+pub struct SidecarWriter {
+    /// 海报等图片的存放目录
+    assets_path: PathBuf,
+}
+
+impl SidecarWriter {
+    pub fn new(assets_path: impl Into<PathBuf>) -> Self {
+        Self {
+            assets_path: assets_path.into(),
+        }
+    }
+
+    /// 写入番剧及剧集的元数据文件, 已存在的文件会被覆盖
+    pub async fn write(
+        &self,
+        bangumi: &bangumi::Model,
+        episode: Option<&episodes::Model>,
+        episode_number: i32,
+        paths: &SidecarPaths,
+    ) -> Result<()> {
+        if let Some(show_dir) = &paths.show_dir {
+            tokio::fs::create_dir_all(show_dir).await?;
+            tokio::fs::write(show_dir.join("tvshow.nfo"), tvshow_nfo(bangumi)).await?;
+            self.copy_image(bangumi.poster_image_url.as_deref(), show_dir, "poster")
+                .await;
+            self.copy_image(bangumi.backdrop_image_url.as_deref(), show_dir, "fanart")
+                .await;
+        }
+        if let Some(season_dir) = &paths.season_dir {
+            tokio::fs::create_dir_all(season_dir).await?;
+            tokio::fs::write(season_dir.join("season.nfo"), season_nfo(bangumi)).await?;
+        }
+        if let Some(parent) = paths.episode_nfo.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(
+            &paths.episode_nfo,
+            episode_nfo(bangumi, episode, episode_number),
+        )
+        .await?;
+        info!(
+            "已生成番剧 {} 第 {} 集的 nfo 文件",
+            bangumi.name, episode_number
+        );
+        Ok(())
+    }
+
+    /// 复制图片为媒体服务器识别的 `<name>.jpg`, 图片缺失时只记录日志
+    async fn copy_image(&self, file_name: Option<&str>, dir: &Path, name: &str) {
+        let Some(file_name) = file_name else {
+            return;
+        };
+        let source = self.assets_path.join(file_name);
+        let target = dir.join(format!("{}.jpg", name));
+        if let Err(e) = tokio::fs::copy(&source, &target).await {
+            warn!("复制图片 {} 失败: {}", source.display(), e);
+        }
+    }
+}
+
+fn season_number(bangumi: &bangumi::Model) -> i64 {
+    bangumi.season_number.unwrap_or(1)
+}
+
+fn tvshow_nfo(bangumi: &bangumi::Model) -> String {
+    let mut nfo = NfoBuilder::new("tvshow");
+    nfo.element("title", &bangumi.name);
+    if let Some(description) = &bangumi.description {
+        nfo.element("plot", description);
+    }
+    if let Some(rating) = bangumi.rating {
+        nfo.element("rating", &format!("{:.1}", rating));
+    }
+    if let Some(air_date) = bangumi.air_date {
+        nfo.element("year", &air_date.year().to_string());
+        nfo.element("premiered", &air_date.format("%Y-%m-%d").to_string());
+    }
+    nfo.unique_ids(bangumi);
+    nfo.finish()
+}
+
+fn season_nfo(bangumi: &bangumi::Model) -> String {
+    let season = season_number(bangumi);
+    let mut nfo = NfoBuilder::new("season");
+    nfo.element("title", &format!("第 {} 季", season));
+    nfo.element("seasonnumber", &season.to_string());
+    if let Some(description) = &bangumi.description {
+        nfo.element("plot", description);
+    }
+    if let Some(air_date) = bangumi.air_date {
+        nfo.element("premiered", &air_date.format("%Y-%m-%d").to_string());
+    }
+    nfo.finish()
+}
+
+fn episode_nfo(
+    bangumi: &bangumi::Model,
+    episode: Option<&episodes::Model>,
+    episode_number: i32,
+) -> String {
+    let mut nfo = NfoBuilder::new("episodedetails");
+    let title = episode
+        .and_then(|ep| ep.name.clone())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("第 {} 集", episode_number));
+    nfo.element("title", &title);
+    nfo.element("showtitle", &bangumi.name);
+    nfo.element("season", &season_number(bangumi).to_string());
+    nfo.element("episode", &episode_number.to_string());
+    if let Some(episode) = episode {
+        if let Some(description) = &episode.description {
+            nfo.element("plot", description);
+        }
+        if let Some(air_date) = episode.air_date {
+            nfo.element("aired", &air_date.format("%Y-%m-%d").to_string());
+        }
+        if let Some(duration) = episode.duration_seconds {
+            nfo.element("runtime", &(duration / 60).to_string());
+        }
+    }
+    nfo.finish()
+}
+
+/// 简单的 nfo 构建器, 只需要一层子元素
+struct NfoBuilder {
+    root: &'static str,
+    body: String,
+}
+
+impl NfoBuilder {
+    fn new(root: &'static str) -> Self {
+        Self {
+            root,
+            body: String::new(),
+        }
+    }
+
+    fn element(&mut self, name: &str, value: &str) {
+        let _ = writeln!(self.body, "  <{name}>{}</{name}>", escape(value));
+    }
+
+    /// TMDB 及 bgm.tv 的 ID, 帮助媒体服务器准确匹配
+    fn unique_ids(&mut self, bangumi: &bangumi::Model) {
+        if let Some(tmdb_id) = bangumi.tmdb_id {
+            let _ = writeln!(
+                self.body,
+                r#"  <uniqueid type="tmdb" default="true">{}</uniqueid>"#,
+                tmdb_id
+            );
+            self.element("tmdbid", &tmdb_id.to_string());
+        }
+        if let Some(bangumi_tv_id) = bangumi.bangumi_tv_id {
+            let _ = writeln!(
+                self.body,
+                r#"  <uniqueid type="bangumi"{}>{}</uniqueid>"#,
+                if bangumi.tmdb_id.is_none() {
+                    r#" default="true""#
+                } else {
+                    ""
+                },
+                bangumi_tv_id
+            );
+        }
+    }
+
+    fn finish(self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<{root}>\n{}</{root}>\n",
+            self.body,
+            root = self.root
+        )
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::sea_orm_active_enums::Kind;
+
+    fn bangumi() -> bangumi::Model {
+        model::testing::bangumi("葬送的芙莉莲 & Friends")
+    }
+
+    #[test]
+    fn test_tvshow_nfo() {
+        let nfo = tvshow_nfo(&bangumi());
+        assert!(nfo.starts_with("<?xml"));
+        assert!(nfo.contains("<title>葬送的芙莉莲 &amp; Friends</title>"));
+        assert!(nfo.contains("<rating>9.0</rating>"));
+        assert!(nfo.contains("<year>2023</year>"));
+        assert!(nfo.contains("<premiered>2023-09-29</premiered>"));
+        assert!(nfo.contains(r#"<uniqueid type="tmdb" default="true">209867</uniqueid>"#));
+        assert!(nfo.contains(r#"<uniqueid type="bangumi">400602</uniqueid>"#));
+        assert!(nfo.trim_end().ends_with("</tvshow>"));
+
+        let mut no_tmdb = bangumi();
+        no_tmdb.tmdb_id = None;
+        let nfo = tvshow_nfo(&no_tmdb);
+        assert!(nfo.contains(r#"<uniqueid type="bangumi" default="true">400602</uniqueid>"#));
+        assert!(!nfo.contains("tmdb"));
+    }
+
+    #[test]
+    fn test_episode_nfo() {
+        let episode = episodes::Model {
+            id: 1,
+            bangumi_id: 1,
+            number: 5,
+            sort_number: Some(5),
+            name: Some("魔法使的<秘密>".to_owned()),
+            image_url: None,
+            description: None,
+            air_date: chrono::NaiveDate::from_ymd_opt(2023, 10, 20),
+            duration_seconds: Some(1440),
+            kind: Kind::Ep,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        };
+        let nfo = episode_nfo(&bangumi(), Some(&episode), 5);
+        assert!(nfo.contains("<title>魔法使的&lt;秘密&gt;</title>"));
+        assert!(nfo.contains("<season>1</season>"));
+        assert!(nfo.contains("<episode>5</episode>"));
+        assert!(nfo.contains("<aired>2023-10-20</aired>"));
+        assert!(nfo.contains("<runtime>24</runtime>"));
+
+        let nfo = episode_nfo(&bangumi(), None, 6);
+        assert!(nfo.contains("<title>第 6 集</title>"));
+        assert!(!nfo.contains("<aired>"));
+    }
+
+    #[tokio::test]
+    async fn test_write_artwork() {
+        let root = std::env::temp_dir().join(format!("sidecar-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let assets = root.join("assets");
+        std::fs::create_dir_all(&assets).unwrap();
+        // 元数据中的图片文件名没有扩展名
+        std::fs::write(assets.join("bangumi_poster_1"), b"poster").unwrap();
+        std::fs::write(assets.join("bangumi_backdrop_1"), b"fanart").unwrap();
+
+        let mut bgm = bangumi();
+        bgm.poster_image_url = Some("bangumi_poster_1".to_owned());
+        bgm.backdrop_image_url = Some("bangumi_backdrop_1".to_owned());
+        let show_dir = root.join("show");
+        let paths = SidecarPaths {
+            show_dir: Some(show_dir.clone()),
+            season_dir: None,
+            episode_nfo: show_dir.join("S01E01.nfo"),
+        };
+        SidecarWriter::new(&assets)
+            .write(&bgm, None, 1, &paths)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(show_dir.join("poster.jpg")).unwrap(),
+            b"poster"
+        );
+        assert_eq!(
+            std::fs::read(show_dir.join("fanart.jpg")).unwrap(),
+            b"fanart"
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
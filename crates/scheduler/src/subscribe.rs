@@ -1,9 +1,14 @@
 use crate::Scheduler;
+use crate::filter::FilterRule;
 use anyhow::{Context, Result};
+use downloader::dlrs::SelectStrategy;
 use tracing::{error, info};
 
 impl Scheduler {
     /// 订阅番剧
+    ///
+    /// `feeds`、`filter_rules` 和 `score_profile` 为 `None` 时保留已有的值,
+    /// 清除时分别传入空列表、不包含任何规则的 `all` 和空的评分配置
     #[allow(clippy::too_many_arguments)]
     pub async fn subscribe(
         &self,
@@ -17,6 +22,11 @@ impl Scheduler {
         enforce_torrent_release_after_broadcast: bool,
         preferred_downloader: Option<String>,
         allow_fallback: bool,
+        feeds: Option<Vec<String>>,
+        filter_rules: Option<FilterRule>,
+        score_profile: Option<serde_json::Map<String, serde_json::Value>>,
+        auto_upgrade: bool,
+        download_strategy: Option<SelectStrategy>,
     ) -> Result<()> {
         // 将分辨率列表转换为逗号分隔的字符串
         let resolution_filter_str = resolution_filter.map(|resolutions| {
@@ -38,6 +48,28 @@ impl Scheduler {
                 .join(",")
         });
 
+        // 过滤规则为 None 时保留已有的规则, 不包含任何规则的 `all` 清除已有的规则
+        let filter_rules = match filter_rules {
+            Some(rule) if rule.is_empty() => Some(None),
+            Some(rule) => {
+                rule.compile()?;
+                Some(Some(rule.to_json()?))
+            }
+            None => None,
+        };
+
+        // 评分配置只保存需要覆盖的字段, 保存前与全局配置合并校验
+        // 为 None 时保留已有的配置, 为空时清除已有的配置
+        let score_profile = match score_profile {
+            Some(overrides) if overrides.is_empty() => Some(None),
+            Some(overrides) => {
+                let json = serde_json::to_string(&overrides)?;
+                self.score_profile.with_override(&json)?;
+                Some(Some(json))
+            }
+            None => None,
+        };
+
         // 刷新元数据
         self.metadata.request_refresh_metadata(bangumi_id, false)?;
 
@@ -84,10 +116,22 @@ impl Scheduler {
                 enforce_torrent_release_after_broadcast,
                 preferred_downloader,
                 allow_fallback,
+                filter_rules,
+                score_profile,
+                auto_upgrade,
+                download_strategy.map(|s| s.to_string()),
             )
             .await
             .context("更新订阅状态失败")?;
 
+        // 为 None 时保留已有的 RSS 源
+        if let Some(feeds) = feeds {
+            self.db
+                .replace_subscription_feeds(bangumi_id, feeds)
+                .await
+                .context("更新订阅 RSS 源失败")?;
+        }
+
         let subscription = self
             .db
             .get_subscription(bangumi_id)
@@ -1,8 +1,8 @@
 use anyhow::{Context, Result};
-use downloader::{Downloader, Event, resource::Resource};
+use downloader::{Downloader, Event, dlrs::SelectStrategy, resource::Resource};
 use model::sea_orm_active_enums::{ResourceType, State};
+use model::{bangumi, subscriptions, torrent_download_tasks, torrents};
 use model::{episode_download_tasks, sea_orm_active_enums::DownloadStatus};
-use model::{subscriptions, torrents};
 use sea_orm::Set;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -10,6 +10,7 @@ use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, info, warn};
 
 use crate::db::Db;
+use crate::organizer::Organizer;
 
 /// 任务缓存管理器
 #[derive(Clone)]
@@ -17,6 +18,7 @@ pub struct TaskManager {
     db: Db,
     downloader: Arc<Box<dyn Downloader>>,
     notify: notify::worker::Worker,
+    organizer: Option<Arc<Organizer>>,
     cmd_tx: Option<mpsc::UnboundedSender<Cmd>>,
 }
 
@@ -30,11 +32,13 @@ impl TaskManager {
         db: Db,
         downloader: Arc<Box<dyn Downloader>>,
         notify: notify::worker::Worker,
+        organizer: Option<Arc<Organizer>>,
     ) -> Self {
         Self {
             db,
             downloader,
             notify,
+            organizer,
             cmd_tx: None,
         }
     }
@@ -119,6 +123,22 @@ impl TaskManager {
         Ok(())
     }
 
+    /// 为已下载的剧集排队洗版, 新种子下载完成后替换原来的种子
+    pub async fn queue_upgrade(
+        &self,
+        bangumi_id: i32,
+        episode_number: i32,
+        info_hash: &str,
+    ) -> Result<()> {
+        self.db
+            .update_task_upgrade(bangumi_id, episode_number, Some(info_hash))
+            .await?;
+
+        // 发送状态变化命令
+        self.send_state_update_cmd(bangumi_id, episode_number)?;
+        Ok(())
+    }
+
     /// 更新任务状态为就绪，并设置选中的种子
     pub async fn retry_task(&self, bangumi_id: i32, episode_number: i32) -> Result<()> {
         // 先更新数据库
@@ -131,6 +151,31 @@ impl TaskManager {
         Ok(())
     }
 
+    /// 重新生成番剧已整理剧集的元数据文件, 返回生成的剧集数量
+    pub async fn regenerate_sidecars(&self, bangumi_id: i32) -> Result<usize> {
+        let organizer = self.organizer.as_ref().context("未启用媒体库整理")?;
+        let bangumi = self
+            .db
+            .get_bangumi_by_id(bangumi_id)
+            .await?
+            .context("番剧不存在")?;
+        let episodes = self.db.get_bangumi_episodes(bangumi_id).await?;
+        let tasks = self.db.get_downloaded_tasks_by_bangumi(bangumi_id).await?;
+
+        let mut count = 0;
+        for task in tasks {
+            let episode = episodes.iter().find(|ep| ep.number == task.episode_number);
+            if organizer
+                .write_sidecars(&bangumi, episode, task.episode_number)
+                .await?
+            {
+                count += 1;
+            }
+        }
+        info!("番剧 {} 重新生成了 {} 集的元数据文件", bangumi.name, count);
+        Ok(count)
+    }
+
     fn send_state_update_cmd(&self, bangumi_id: i32, episode_number: i32) -> Result<()> {
         self.cmd_tx
             .as_ref()
@@ -231,6 +276,7 @@ impl TaskManager {
                     self.process_task(task).await?;
                 }
             }
+            Event::ProgressUpdated(_) => {}
         }
         Ok(())
     }
@@ -239,7 +285,8 @@ impl TaskManager {
     async fn process_tasks(&self) -> Result<()> {
         debug!("开始处理剧集下载任务");
 
-        let tasks = self.db.get_all_unfinished_tasks().await?;
+        let mut tasks = self.db.get_all_unfinished_tasks().await?;
+        tasks.extend(self.db.get_all_upgrading_tasks().await?);
 
         // 处理所有任务
         for task in tasks {
@@ -266,57 +313,7 @@ impl TaskManager {
                         "开始下载番剧 {} 第 {} 集",
                         bangumi.name, task.episode_number
                     );
-                    // 获取种子信息
-                    let torrent = self
-                        .db
-                        .get_torrent_by_info_hash(info_hash)
-                        .await?
-                        .context("种子不存在")?;
-                    let subscribe = self
-                        .db
-                        .get_subscription(task.bangumi_id)
-                        .await?
-                        .context("你需要先订阅番剧")?;
-                    let dir = PathBuf::from(bangumi.name.clone()).join(torrent.title.clone());
-
-                    // 创建下载任务, 如果推荐资源类型为种子，则优先提供种子
-                    if self.use_torrent_to_download(&subscribe, &torrent, false) {
-                        if let Some(data) = torrent.data {
-                            self.downloader
-                                .add_task(
-                                    Resource::from_torrent_file_bytes(data)?,
-                                    dir,
-                                    subscribe.preferred_downloader,
-                                    subscribe.allow_fallback,
-                                )
-                                .await?;
-                        } else if let Some(download_url) = torrent.download_url {
-                            self.downloader
-                                .add_task(
-                                    Resource::from_torrent_url(&download_url, &torrent.info_hash)?,
-                                    dir,
-                                    subscribe.preferred_downloader,
-                                    subscribe.allow_fallback,
-                                )
-                                .await?;
-                        } else {
-                            return Err(anyhow::anyhow!(
-                                "选择的种子，既没有种子数据，也没有下载地址: info_hash: {}, bangumi: {}, episode: {}",
-                                torrent.info_hash,
-                                bangumi.name,
-                                task.episode_number
-                            ));
-                        }
-                    } else {
-                        self.downloader
-                            .add_task(
-                                Resource::from_info_hash(torrent.info_hash)?,
-                                dir,
-                                subscribe.preferred_downloader,
-                                subscribe.allow_fallback,
-                            )
-                            .await?;
-                    }
+                    self.add_download(&bangumi, &task, info_hash).await?;
 
                     // 更新状态为下载中
                     self.db
@@ -342,6 +339,8 @@ impl TaskManager {
                                         State::Downloaded,
                                     )
                                     .await?;
+                                self.organize(&bangumi, task.episode_number, download_task)
+                                    .await;
                                 self.notify
                                     .notify(
                                         notify::worker::Topic::Download,
@@ -391,8 +390,276 @@ impl TaskManager {
                         .await?;
                 }
             }
+            State::Downloaded => {
+                if let Some(ref upgrade_info_hash) = task.upgrade_info_hash {
+                    self.process_upgrade(&bangumi, &task, upgrade_info_hash)
+                        .await?;
+                }
+            }
             _ => {}
         }
         Ok(())
     }
+
+    /// 为剧集任务创建下载任务, 如果推荐资源类型为种子，则优先提供种子
+    async fn add_download(
+        &self,
+        bangumi: &bangumi::Model,
+        task: &episode_download_tasks::Model,
+        info_hash: &str,
+    ) -> Result<()> {
+        // 获取种子信息
+        let torrent = self
+            .db
+            .get_torrent_by_info_hash(info_hash)
+            .await?
+            .context("种子不存在")?;
+        let subscribe = self
+            .db
+            .get_subscription(task.bangumi_id)
+            .await?
+            .context("你需要先订阅番剧")?;
+        let dir = PathBuf::from(bangumi.name.clone()).join(torrent.title.clone());
+        let strategy = subscribe
+            .download_strategy
+            .as_deref()
+            .map(str::parse::<SelectStrategy>)
+            .transpose()?;
+
+        if self.use_torrent_to_download(&subscribe, &torrent, false) {
+            if let Some(data) = torrent.data {
+                self.downloader
+                    .add_task(
+                        Resource::from_torrent_file_bytes(data)?,
+                        dir,
+                        subscribe.preferred_downloader,
+                        strategy,
+                        subscribe.allow_fallback,
+                    )
+                    .await?;
+            } else if let Some(download_url) = torrent.download_url {
+                self.downloader
+                    .add_task(
+                        Resource::from_torrent_url(&download_url, &torrent.info_hash)?,
+                        dir,
+                        subscribe.preferred_downloader,
+                        strategy,
+                        subscribe.allow_fallback,
+                    )
+                    .await?;
+            } else {
+                return Err(anyhow::anyhow!(
+                    "选择的种子，既没有种子数据，也没有下载地址: info_hash: {}, bangumi: {}, episode: {}",
+                    torrent.info_hash,
+                    bangumi.name,
+                    task.episode_number
+                ));
+            }
+        } else {
+            self.downloader
+                .add_task(
+                    Resource::from_info_hash(torrent.info_hash)?,
+                    dir,
+                    subscribe.preferred_downloader,
+                    strategy,
+                    subscribe.allow_fallback,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 处理洗版任务: 下载新种子, 完成后移除原来的下载任务及文件
+    async fn process_upgrade(
+        &self,
+        bangumi: &bangumi::Model,
+        task: &episode_download_tasks::Model,
+        upgrade_info_hash: &str,
+    ) -> Result<()> {
+        let tasks = self
+            .downloader
+            .list_tasks(&[upgrade_info_hash.to_owned()])
+            .await?;
+        let Some(download_task) = tasks.first() else {
+            info!(
+                "开始为番剧 {} 第 {} 集洗版",
+                bangumi.name, task.episode_number
+            );
+            return self.add_download(bangumi, task, upgrade_info_hash).await;
+        };
+
+        match download_task.download_status {
+            DownloadStatus::Completed => {
+                if let Some(ref old_info_hash) = task.ref_torrent_info_hash {
+                    if let Err(e) = self.downloader.remove_task(old_info_hash, true).await {
+                        error!(
+                            "移除番剧 {} 第 {} 集的旧下载任务失败: {}",
+                            bangumi.name, task.episode_number, e
+                        );
+                    }
+                }
+                self.db
+                    .complete_task_upgrade(task.bangumi_id, task.episode_number, upgrade_info_hash)
+                    .await?;
+                self.organize(bangumi, task.episode_number, download_task)
+                    .await;
+                info!(
+                    "番剧 {} 第 {} 集洗版完成",
+                    bangumi.name, task.episode_number
+                );
+                self.notify
+                    .notify(
+                        notify::worker::Topic::Download,
+                        "洗版完成",
+                        format!(
+                            "番剧 [{}] 第 [{}] 集已替换为更好的版本",
+                            bangumi.name, task.episode_number
+                        ),
+                    )
+                    .await?;
+            }
+            DownloadStatus::Failed | DownloadStatus::Cancelled => {
+                // 放弃本次洗版, 保留原来的文件, 失败的种子不会再被选择
+                warn!(
+                    "番剧 {} 第 {} 集洗版失败, 保留原来的版本",
+                    bangumi.name, task.episode_number
+                );
+                self.db
+                    .update_task_upgrade(task.bangumi_id, task.episode_number, None)
+                    .await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 将下载完成的剧集整理到媒体库, 整理失败不影响任务状态
+    async fn organize(
+        &self,
+        bangumi: &bangumi::Model,
+        episode_number: i32,
+        download_task: &torrent_download_tasks::Model,
+    ) {
+        let Some(organizer) = &self.organizer else {
+            return;
+        };
+        let episodes = match self.db.get_bangumi_episodes(bangumi.id).await {
+            Ok(episodes) => episodes,
+            Err(e) => {
+                error!("获取番剧 {} 的剧集信息失败: {}", bangumi.name, e);
+                vec![]
+            }
+        };
+        let episode = episodes.iter().find(|ep| ep.number == episode_number);
+        if let Err(e) = organizer
+            .organize(
+                bangumi,
+                episode,
+                episode_number,
+                download_task,
+                &**self.downloader,
+            )
+            .await
+        {
+            error!(
+                "整理番剧 {} 第 {} 集失败: {}",
+                bangumi.name, episode_number, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeDownloader};
+
+    const OLD: &str = "1111111111111111111111111111111111111111";
+    const NEW: &str = "2222222222222222222222222222222222222222";
+
+    /// 第 1 集已使用 720P 的种子下载完成, 1080P 的种子正在洗版
+    async fn upgrading() -> Result<(Db, TaskManager, FakeDownloader)> {
+        let db = testing::test_db(true).await?;
+        let (old, _) = testing::pair(OLD, "720P", 1);
+        let (new, _) = testing::pair(NEW, "1080P", 1);
+        testing::insert_torrents(&db, &[&old, &new]).await?;
+        testing::insert_task(&db, 1, State::Downloaded, Some(OLD), Some(NEW)).await?;
+        let (task_manager, downloader) = testing::task_manager(&db).await?;
+        downloader.set_status(OLD, DownloadStatus::Completed);
+        Ok((db, task_manager, downloader))
+    }
+
+    #[tokio::test]
+    async fn test_queue_upgrade() -> Result<()> {
+        let db = testing::test_db(true).await?;
+        let (old, _) = testing::pair(OLD, "720P", 1);
+        testing::insert_torrents(&db, &[&old]).await?;
+        testing::insert_task(&db, 1, State::Downloaded, Some(OLD), None).await?;
+        let (mut task_manager, _) = testing::task_manager(&db).await?;
+        let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+        task_manager.cmd_tx = Some(cmd_tx);
+
+        task_manager.queue_upgrade(1, 1, NEW).await?;
+
+        let task = testing::get_task(&db, 1).await?;
+        assert_eq!(task.state, State::Downloaded);
+        assert_eq!(task.ref_torrent_info_hash.as_deref(), Some(OLD));
+        assert_eq!(task.upgrade_info_hash.as_deref(), Some(NEW));
+        assert!(matches!(cmd_rx.try_recv(), Ok(Cmd::StateUpdate((1, 1)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_upgrade_completed() -> Result<()> {
+        let (db, task_manager, downloader) = upgrading().await?;
+
+        // 还没有下载任务时, 开始下载新种子, 保留原来的任务
+        task_manager
+            .process_task(testing::get_task(&db, 1).await?)
+            .await?;
+        assert_eq!(downloader.added(), vec![NEW.to_owned()]);
+        assert!(downloader.removed().is_empty());
+        let task = testing::get_task(&db, 1).await?;
+        assert_eq!(task.ref_torrent_info_hash.as_deref(), Some(OLD));
+        assert_eq!(task.upgrade_info_hash.as_deref(), Some(NEW));
+
+        // 下载中不做处理
+        downloader.set_status(NEW, DownloadStatus::Downloading);
+        task_manager
+            .process_task(testing::get_task(&db, 1).await?)
+            .await?;
+        assert!(downloader.removed().is_empty());
+
+        // 新种子下载完成后, 移除原来的下载任务及文件, 并替换为新种子
+        downloader.set_status(NEW, DownloadStatus::Completed);
+        task_manager
+            .process_task(testing::get_task(&db, 1).await?)
+            .await?;
+        assert_eq!(downloader.removed(), vec![(OLD.to_owned(), true)]);
+        assert_eq!(downloader.added(), vec![NEW.to_owned()]);
+        let task = testing::get_task(&db, 1).await?;
+        assert_eq!(task.state, State::Downloaded);
+        assert_eq!(task.ref_torrent_info_hash.as_deref(), Some(NEW));
+        assert_eq!(task.upgrade_info_hash, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_upgrade_failed() -> Result<()> {
+        let (db, task_manager, downloader) = upgrading().await?;
+        downloader.set_status(NEW, DownloadStatus::Failed);
+
+        task_manager
+            .process_task(testing::get_task(&db, 1).await?)
+            .await?;
+
+        // 放弃洗版, 保留原来的下载任务
+        assert!(downloader.added().is_empty());
+        assert!(downloader.removed().is_empty());
+        let task = testing::get_task(&db, 1).await?;
+        assert_eq!(task.state, State::Downloaded);
+        assert_eq!(task.ref_torrent_info_hash.as_deref(), Some(OLD));
+        assert_eq!(task.upgrade_info_hash, None);
+        Ok(())
+    }
 }
@@ -0,0 +1,284 @@
+//! 测试使用的内存数据库及下载器
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use downloader::{
+    Downloader, DownloaderInfo, Event, FileInfo, FileMove, TaskProgress, ThirdPartyDownloader,
+    dlrs::SelectStrategy, metrics, resource::Resource,
+};
+use model::sea_orm_active_enums::{DownloadStatus, ParserStatus, ResourceType, Source, State};
+use model::{episode_download_tasks, file_name_parse_record, torrent_download_tasks, torrents};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Database, EntityTrait, IntoActiveModel};
+use tokio::sync::broadcast;
+
+use crate::db::Db;
+use crate::tasks::TaskManager;
+
+/// 创建 SQLite 内存数据库, 包含已订阅的番剧 1
+pub(crate) async fn test_db(auto_upgrade: bool) -> Result<Db> {
+    let conn = Database::connect("sqlite::memory:").await?;
+    model::migrator::migrate(&conn).await?;
+    conn.execute_unprepared(&format!(
+        "insert into bangumi (id, name, ep_count) values (1, '葬送的芙莉莲', 28);
+        insert into subscriptions (bangumi_id, subscribe_status, auto_upgrade)
+            values (1, 'subscribed', {});",
+        auto_upgrade
+    ))
+    .await?;
+    Ok(Db::new(Arc::new(conn)))
+}
+
+/// 番剧 1 的种子及其解析结果, 只有分辨率不同
+pub(crate) fn pair(
+    info_hash: &str,
+    resolution: &str,
+    episode_number: i32,
+) -> (torrents::Model, file_name_parse_record::Model) {
+    let title = format!(
+        "[Sub] 葬送的芙莉莲 - {:02} [{}]",
+        episode_number, resolution
+    );
+    (
+        torrents::Model {
+            info_hash: info_hash.to_owned(),
+            bangumi_id: 1,
+            title: title.clone(),
+            size: 500 * 1024 * 1024,
+            magnet: Some(format!("magnet:?xt=urn:btih:{}", info_hash)),
+            data: None,
+            download_url: None,
+            pub_date: Default::default(),
+            source: Source::Mikan,
+            seeders: None,
+        },
+        file_name_parse_record::Model {
+            file_name: title,
+            release_group: Some("Sub".to_owned()),
+            bangumi_name: None,
+            season_number: None,
+            episode_number: Some(episode_number),
+            language: Some("CHS".to_owned()),
+            video_resolution: Some(resolution.to_owned()),
+            year: None,
+            parser_name: "test".to_owned(),
+            parser_status: ParserStatus::Completed,
+            err_msg: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        },
+    )
+}
+
+pub(crate) async fn insert_torrents(db: &Db, torrents: &[&torrents::Model]) -> Result<()> {
+    for torrent in torrents {
+        torrents::Entity::insert((*torrent).clone().into_active_model())
+            .exec(db.conn())
+            .await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn insert_task(
+    db: &Db,
+    episode_number: i32,
+    state: State,
+    ref_torrent_info_hash: Option<&str>,
+    upgrade_info_hash: Option<&str>,
+) -> Result<()> {
+    episode_download_tasks::Model {
+        bangumi_id: 1,
+        episode_number,
+        state,
+        ref_torrent_info_hash: ref_torrent_info_hash.map(str::to_owned),
+        created_at: Default::default(),
+        updated_at: Default::default(),
+        upgrade_info_hash: upgrade_info_hash.map(str::to_owned),
+    }
+    .into_active_model()
+    .insert(db.conn())
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn get_task(
+    db: &Db,
+    episode_number: i32,
+) -> Result<episode_download_tasks::Model> {
+    db.get_episode_task_by_bangumi_id_and_episode_number(1, episode_number)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("任务不存在"))
+}
+
+/// 创建使用 [`FakeDownloader`] 的任务管理器, 通知服务已启动但没有通知器
+pub(crate) async fn task_manager(db: &Db) -> Result<(TaskManager, FakeDownloader)> {
+    let downloader = FakeDownloader::default();
+    let mut notify = notify::worker::Worker::new();
+    notify.spawn().await?;
+    let task_manager = TaskManager::new(
+        db.clone(),
+        Arc::new(Box::new(downloader.clone())),
+        notify,
+        None,
+    );
+    Ok((task_manager, downloader))
+}
+
+/// 记录调用的下载器, 任务状态由测试设置, 克隆后共享状态
+#[derive(Clone, Default)]
+pub(crate) struct FakeDownloader {
+    tasks: Arc<Mutex<HashMap<String, DownloadStatus>>>,
+    added: Arc<Mutex<Vec<String>>>,
+    removed: Arc<Mutex<Vec<(String, bool)>>>,
+    events: Arc<Mutex<Option<broadcast::Sender<Event>>>>,
+}
+
+impl FakeDownloader {
+    pub fn set_status(&self, info_hash: &str, status: DownloadStatus) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .insert(info_hash.to_owned(), status);
+    }
+
+    /// 添加过的下载任务
+    pub fn added(&self) -> Vec<String> {
+        self.added.lock().unwrap().clone()
+    }
+
+    /// 移除过的下载任务及是否删除文件
+    pub fn removed(&self) -> Vec<(String, bool)> {
+        self.removed.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Downloader for FakeDownloader {
+    async fn add_task(
+        &self,
+        resource: Resource,
+        _dir: PathBuf,
+        _downloader: Option<String>,
+        _strategy: Option<SelectStrategy>,
+        _allow_fallback: bool,
+    ) -> downloader::errors::Result<()> {
+        let info_hash = resource.info_hash().to_owned();
+        self.set_status(&info_hash, DownloadStatus::Pending);
+        self.added.lock().unwrap().push(info_hash);
+        Ok(())
+    }
+
+    async fn list_tasks(
+        &self,
+        info_hashes: &[String],
+    ) -> downloader::errors::Result<Vec<torrent_download_tasks::Model>> {
+        let tasks = self.tasks.lock().unwrap();
+        Ok(info_hashes
+            .iter()
+            .filter_map(|info_hash| {
+                let status = tasks.get(info_hash)?;
+                Some(torrent_download_tasks::Model {
+                    info_hash: info_hash.clone(),
+                    download_status: status.clone(),
+                    downloader: "fake".to_owned(),
+                    allow_fallback: false,
+                    dir: String::new(),
+                    context: None,
+                    err_msg: None,
+                    retry_count: 0,
+                    next_retry_at: Default::default(),
+                    created_at: Default::default(),
+                    updated_at: Default::default(),
+                    resource_type: ResourceType::InfoHash,
+                    magnet: None,
+                    torrent_url: None,
+                    tid: None,
+                })
+            })
+            .collect())
+    }
+
+    async fn list_files(&self, _info_hash: &str) -> downloader::errors::Result<Vec<FileInfo>> {
+        Ok(vec![])
+    }
+
+    async fn download_file(
+        &self,
+        _file_id: &str,
+        _ua: &str,
+    ) -> downloader::errors::Result<downloader::DownloadInfo> {
+        unimplemented!()
+    }
+
+    async fn cancel_task(&self, _info_hash: &str) -> downloader::errors::Result<()> {
+        Ok(())
+    }
+
+    async fn remove_task(
+        &self,
+        info_hash: &str,
+        remove_files: bool,
+    ) -> downloader::errors::Result<()> {
+        self.tasks.lock().unwrap().remove(info_hash);
+        self.removed
+            .lock()
+            .unwrap()
+            .push((info_hash.to_owned(), remove_files));
+        Ok(())
+    }
+
+    async fn metrics(&self) -> metrics::Metrics {
+        unimplemented!()
+    }
+
+    async fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| broadcast::channel(16).0)
+            .subscribe()
+    }
+
+    async fn retry(&self, _info_hash: &str) -> downloader::errors::Result<()> {
+        Ok(())
+    }
+
+    async fn pause_task(&self, _info_hash: &str) -> downloader::errors::Result<()> {
+        Ok(())
+    }
+
+    async fn resume_task(&self, _info_hash: &str) -> downloader::errors::Result<()> {
+        Ok(())
+    }
+
+    async fn organize_files(
+        &self,
+        _info_hash: &str,
+        _moves: &[FileMove],
+    ) -> downloader::errors::Result<bool> {
+        Ok(false)
+    }
+
+    fn task_progress(&self) -> HashMap<String, TaskProgress> {
+        HashMap::new()
+    }
+
+    fn supports_resource_type(&self, _resource_type: ResourceType) -> bool {
+        true
+    }
+
+    fn recommended_resource_type(&self) -> ResourceType {
+        ResourceType::InfoHash
+    }
+
+    fn take_dlr(&self, _downloader: &str) -> Option<&dyn ThirdPartyDownloader> {
+        None
+    }
+
+    fn dlrs(&self) -> Vec<DownloaderInfo> {
+        vec![]
+    }
+}
@@ -1,14 +1,17 @@
 use anyhow::Result;
-use model::sea_orm_active_enums::{ResourceType, State};
-use model::{bangumi, episodes, file_name_parse_record, subscriptions, torrents};
+use model::sea_orm_active_enums::{ResourceType, Source, State};
+use model::{
+    bangumi, episode_download_tasks, episodes, file_name_parse_record, subscriptions, torrents,
+};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::{broadcast, mpsc};
 use tracing::{error, info};
 
 use crate::db::Db;
 use crate::download_torrent;
 use crate::metrics::{WorkerMetrics, WorkerState};
+use crate::score::ScoreProfile;
 use crate::selector::TorrentSelector;
 use crate::tasks::TaskManager;
 
@@ -17,8 +20,15 @@ use crate::tasks::TaskManager;
 pub enum WorkerCommand {
     /// 停止 worker
     Shutdown(mpsc::Sender<()>),
-    /// 触发收集种子
-    TriggerCollection,
+    /// 触发收集种子, 为 true 时即使种子没有变化也重新选择
+    TriggerCollection(bool),
+}
+
+/// 上一次处理种子时的种子列表及任务状态, 没有变化时跳过解析和选择
+#[derive(Debug, Clone, PartialEq)]
+struct CollectSnapshot {
+    torrents: Vec<(String, Option<i32>)>,
+    tasks: Vec<(i32, State, Option<String>, Option<String>)>,
 }
 
 /// 负责单个番剧的下载任务处理
@@ -36,6 +46,7 @@ pub struct BangumiWorker {
     pub metrics: Arc<RwLock<WorkerMetrics>>,
     pub recommended_resource_type: ResourceType,
     client: reqwest::Client,
+    last_snapshot: Arc<Mutex<Option<CollectSnapshot>>>,
 }
 
 impl BangumiWorker {
@@ -49,9 +60,10 @@ impl BangumiWorker {
         task_manager: TaskManager,
         recommended_resource_type: ResourceType,
         client: reqwest::Client,
+        score_profile: &ScoreProfile,
     ) -> Self {
         let (cmd_tx, _) = broadcast::channel(16);
-        let selector = TorrentSelector::new(&sub);
+        let selector = TorrentSelector::new(&sub, score_profile);
         let metrics = Arc::new(RwLock::new(WorkerMetrics::new(
             bangumi.name.clone(),
             WorkerState::Idle,
@@ -68,19 +80,20 @@ impl BangumiWorker {
             metrics,
             recommended_resource_type,
             client,
+            last_snapshot: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// 为指定集数选择最合适的种子
-    async fn select_episode_torrent(
+    /// 过滤出指定集数的种子
+    fn episode_torrents(
         &self,
         episode_number: i32,
         ep_start_number: i32,
         torrent_pairs: &[(torrents::Model, file_name_parse_record::Model)],
         episodes: &HashMap<i32, episodes::Model>,
-    ) -> Result<Option<torrents::Model>> {
+    ) -> Vec<(torrents::Model, file_name_parse_record::Model)> {
         // 过滤出当前集数的种子
-        let mut episode_torrents: Vec<_> = torrent_pairs
+        torrent_pairs
             .iter()
             .filter(|(torrent, parse_result)| {
                 if let Some(ep) = parse_result.episode_number {
@@ -94,7 +107,7 @@ impl BangumiWorker {
                     }
 
                     // 确保种子发布时间在番剧集数发布时间之后
-                    if self.sub.enforce_torrent_release_after_broadcast == 1 {
+                    if self.sub.enforce_torrent_release_after_broadcast {
                         if let Some(episode) = episodes.get(&actual_ep) {
                             if let Some(air_date) = episode.air_date {
                                 if torrent.pub_date < air_date.into() {
@@ -110,7 +123,19 @@ impl BangumiWorker {
                 }
             })
             .cloned()
-            .collect();
+            .collect()
+    }
+
+    /// 为指定集数选择最合适的种子
+    async fn select_episode_torrent(
+        &self,
+        episode_number: i32,
+        ep_start_number: i32,
+        torrent_pairs: &[(torrents::Model, file_name_parse_record::Model)],
+        episodes: &HashMap<i32, episodes::Model>,
+    ) -> Result<Option<torrents::Model>> {
+        let mut episode_torrents =
+            self.episode_torrents(episode_number, ep_start_number, torrent_pairs, episodes);
 
         // 退而求其次，即使推荐的是种子文件，那么假设实在没有合适的种子，那么也可以尝试选择磁力链接，或者InfoHash
         let mut first_best_torrent = None;
@@ -178,36 +203,80 @@ impl BangumiWorker {
         }
     }
 
+    /// 收集订阅的自定义 RSS 源中的种子, 单个源失败不影响其他源
+    async fn collect_feed_torrents(&self) -> Result<()> {
+        let feeds = self.db.list_subscription_feeds(self.bangumi.id).await?;
+        for feed in feeds.into_iter().filter(|feed| feed.enabled) {
+            let items = match self.fetch_feed(&feed.url).await {
+                Ok(items) => items,
+                Err(e) => {
+                    error!(
+                        "番剧 {} 收集 RSS 源 {} 失败: {}",
+                        self.bangumi.name, feed.url, e
+                    );
+                    continue;
+                }
+            };
+            info!(
+                "番剧 {} 从 RSS 源 {} 收集到 {} 个种子",
+                self.bangumi.name,
+                feed.url,
+                items.len()
+            );
+            let info_hashes = items.iter().map(|item| item.info_hash.clone()).collect();
+            // 没有发布时间的条目以首次发现的时间作为发布时间, 之后不再更新
+            let (dated, undated): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .map(|item| feed_item_to_torrent(self.bangumi.id, item))
+                .partition(|(_, dated)| *dated);
+            let into_torrents = |items: Vec<(torrents::Model, bool)>| {
+                items.into_iter().map(|(torrent, _)| torrent).collect()
+            };
+            self.db
+                .batch_upsert_torrents(into_torrents(dated), true)
+                .await?;
+            self.db
+                .batch_upsert_torrents(into_torrents(undated), false)
+                .await?;
+            self.db
+                .batch_upsert_torrent_sources(info_hashes, Source::Rss)
+                .await?;
+            self.db.update_subscription_feed_collected(feed.id).await?;
+        }
+        Ok(())
+    }
+
+    async fn fetch_feed(&self, url: &str) -> Result<Vec<torznab::client::TorrentItem>> {
+        let client = torznab::client::Client::new_with_client(
+            self.client.clone(),
+            url,
+            torznab::client::FeedKind::Rss,
+            None,
+            vec![],
+            None,
+        )?;
+        client.search("").await
+    }
+
     /// 获取当前 worker metrics
     pub fn get_metrics(&self) -> WorkerMetrics {
         self.metrics.read().unwrap().clone()
     }
 
     /// 收集并解析种子，然后为任务选择合适的种子
-    async fn collect_and_process_torrents(&self) -> Result<()> {
+    ///
+    /// `force` 为 false 且种子列表和任务状态与上一次相同时, 跳过解析和选择
+    async fn collect_and_process_torrents(&self, force: bool) -> Result<()> {
         // 1. 收集种子
         info!("开始收集番剧 {} 的种子", self.bangumi.name);
         self.metadata
-            .request_refresh_torrents_and_wait(self.bangumi.id)
-            .await?;
-
-        // 2. 获取并解析种子
-        let torrents_file_names = self
-            .db
-            .get_bangumi_torrents_file_names(self.bangumi.id)
+            .request_refresh_torrents_and_wait(self.bangumi.id, force)
             .await?;
-        if !torrents_file_names.is_empty() {
-            info!("开始解析番剧 {} 的种子文件名", self.bangumi.name);
-            self.parser.parse_file_names(torrents_file_names).await?;
+        if let Err(e) = self.collect_feed_torrents().await {
+            error!("番剧 {} 收集自定义 RSS 源失败: {}", self.bangumi.name, e);
         }
 
-        // 3. 获取所有种子及其解析结果
-        let torrent_pairs = self
-            .db
-            .get_bangumi_torrents_with_parse_results(self.bangumi.id)
-            .await?;
-
-        // 5. 获取所有 Missing 状态的任务
+        // 2. 获取所有未完成的任务
         let tasks = self
             .task_manager
             .get_unfinished_tasks(self.bangumi.id)
@@ -225,6 +294,29 @@ impl BangumiWorker {
             return Ok(());
         }
 
+        // 3. 种子和任务都没有变化时不需要重新选择
+        let snapshot = self.collect_snapshot(&tasks).await?;
+        if !force && self.last_snapshot.lock().unwrap().as_ref() == Some(&snapshot) {
+            info!("番剧 {} 的种子及任务没有变化, 跳过选择", self.bangumi.name);
+            return Ok(());
+        }
+
+        // 4. 获取并解析种子
+        let torrents_file_names = self
+            .db
+            .get_bangumi_torrents_file_names(self.bangumi.id)
+            .await?;
+        if !torrents_file_names.is_empty() {
+            info!("开始解析番剧 {} 的种子文件名", self.bangumi.name);
+            self.parser.parse_file_names(torrents_file_names).await?;
+        }
+
+        // 5. 获取所有种子及其解析结果
+        let torrent_pairs = self
+            .db
+            .get_bangumi_torrents_with_parse_results(self.bangumi.id)
+            .await?;
+
         let missing_tasks: Vec<_> = tasks
             .into_iter()
             .filter(|t| t.state == State::Missing)
@@ -249,8 +341,10 @@ impl BangumiWorker {
             .await?;
 
         let mut unused_torrents = Vec::new();
+        let mut used_torrents = Vec::new();
         for (torrent, ps) in torrent_pairs {
             if already_used_info_hashes.contains(&torrent.info_hash) {
+                used_torrents.push((torrent, ps));
                 continue;
             }
             unused_torrents.push((torrent, ps));
@@ -279,10 +373,122 @@ impl BangumiWorker {
             }
         }
 
+        // 7. 为已下载的剧集洗版
+        if self.sub.auto_upgrade {
+            if let Err(e) = self
+                .upgrade_downloaded_episodes(&used_torrents, &unused_torrents, &episodes)
+                .await
+            {
+                error!("番剧 {} 洗版失败: {}", self.bangumi.name, e);
+            }
+        }
+
+        // 选择后任务状态可能变化, 保存处理后的快照, 下一次没有新种子时即可跳过
+        let tasks = self
+            .task_manager
+            .get_unfinished_tasks(self.bangumi.id)
+            .await?;
+        let snapshot = self.collect_snapshot(&tasks).await?;
+        *self.last_snapshot.lock().unwrap() = Some(snapshot);
+
         info!("番剧 {} 种子收集处理完成", self.bangumi.name);
         Ok(())
     }
 
+    /// 记录当前的种子列表及任务状态, 已下载的任务包含洗版相关的种子
+    async fn collect_snapshot(
+        &self,
+        unfinished: &[episode_download_tasks::Model],
+    ) -> Result<CollectSnapshot> {
+        let torrents = self.db.get_bangumi_torrent_seeders(self.bangumi.id).await?;
+        let downloaded = self
+            .db
+            .get_downloaded_tasks_by_bangumi(self.bangumi.id)
+            .await?;
+        let mut tasks: Vec<_> = unfinished
+            .iter()
+            .chain(downloaded.iter())
+            .map(|t| {
+                (
+                    t.episode_number,
+                    t.state.clone(),
+                    t.ref_torrent_info_hash.clone(),
+                    t.upgrade_info_hash.clone(),
+                )
+            })
+            .collect();
+        tasks.sort_by_key(|t| t.0);
+        Ok(CollectSnapshot { torrents, tasks })
+    }
+
+    /// 为已下载的剧集寻找评分更高的种子, 评分严格高于当前种子时排队洗版
+    async fn upgrade_downloaded_episodes(
+        &self,
+        used_torrents: &[(torrents::Model, file_name_parse_record::Model)],
+        unused_torrents: &[(torrents::Model, file_name_parse_record::Model)],
+        episodes: &HashMap<i32, episodes::Model>,
+    ) -> Result<()> {
+        let ep_start_number = self.bangumi.ep_start_number;
+        let tasks = self
+            .db
+            .get_downloaded_tasks_by_bangumi(self.bangumi.id)
+            .await?;
+        for task in tasks {
+            // 已经在洗版中
+            if task.upgrade_info_hash.is_some() {
+                continue;
+            }
+            let Some(current) = task.ref_torrent_info_hash.as_ref().and_then(|info_hash| {
+                used_torrents
+                    .iter()
+                    .find(|(t, _)| &t.info_hash == info_hash)
+            }) else {
+                continue;
+            };
+            let Some(best) = self
+                .select_episode_torrent(
+                    task.episode_number,
+                    ep_start_number,
+                    unused_torrents,
+                    episodes,
+                )
+                .await?
+            else {
+                continue;
+            };
+
+            // 与当前种子放在一起评分, 保证文件大小等相对得分的基准一致
+            let mut candidates = self.episode_torrents(
+                task.episode_number,
+                ep_start_number,
+                unused_torrents,
+                episodes,
+            );
+            candidates.push(current.clone());
+            let scores = self.selector.score(&candidates);
+            let total = |info_hash: &str| {
+                scores
+                    .iter()
+                    .find(|score| score.info_hash == info_hash)
+                    .map(|score| score.breakdown.total)
+                    .unwrap_or_default()
+            };
+            let (best_total, current_total) = (total(&best.info_hash), total(&current.0.info_hash));
+            if best_total <= current_total {
+                continue;
+            }
+
+            info!(
+                "番剧 {} 第 {} 集发现评分更高的种子 ({:.1} > {:.1}), 开始洗版",
+                self.bangumi.name, task.episode_number, best_total, current_total
+            );
+            self.task_manager
+                .queue_upgrade(task.bangumi_id, task.episode_number, &best.info_hash)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// 统一的 worker 运行循环，处理所有定时任务
     async fn run_worker(worker: BangumiWorker, mut cmd_rx: broadcast::Receiver<WorkerCommand>) {
         info!("启动番剧 {} 的后台处理", worker.bangumi.name);
@@ -303,7 +509,7 @@ impl BangumiWorker {
                         let mut metrics = worker.metrics.write().unwrap();
                         metrics.set_state(WorkerState::Collecting);
                     }
-                    if let Err(e) = worker.collect_and_process_torrents().await {
+                    if let Err(e) = worker.collect_and_process_torrents(false).await {
                         error!("番剧 {} 处理种子失败: {}", worker.bangumi.name, e);
                     }
                     {
@@ -326,13 +532,13 @@ impl BangumiWorker {
                             let _ = tx.send(()).await;
                             break;
                         }
-                        WorkerCommand::TriggerCollection => {
+                        WorkerCommand::TriggerCollection(force) => {
                             info!("手动触发番剧 {} 的种子收集", worker.bangumi.name);
                             {
                                 let mut metrics = worker.metrics.write().unwrap();
                                 metrics.set_state(WorkerState::Collecting);
                             }
-                            if let Err(e) = worker.collect_and_process_torrents().await {
+                            if let Err(e) = worker.collect_and_process_torrents(force).await {
                                 error!("番剧 {} 处理种子失败: {}", worker.bangumi.name, e);
                             }
                             {
@@ -377,9 +583,171 @@ impl BangumiWorker {
         let _ = self.cmd_tx.send(WorkerCommand::Shutdown(tx));
     }
 
-    /// 触发种子收集
-    pub fn trigger_collection(&self) {
+    /// 触发种子收集, `force` 为 true 时忽略增量收集的状态并重新选择种子
+    pub fn trigger_collection(&self, force: bool) {
         // 发送触发收集命令
-        let _ = self.cmd_tx.send(WorkerCommand::TriggerCollection);
+        let _ = self.cmd_tx.send(WorkerCommand::TriggerCollection(force));
+    }
+}
+
+/// 将 RSS 源中的条目转换为种子, 没有发布时间的条目以当前时间代替,
+/// 返回的布尔值表示条目是否带有发布时间
+fn feed_item_to_torrent(
+    bangumi_id: i32,
+    item: torznab::client::TorrentItem,
+) -> (torrents::Model, bool) {
+    let dated = item.pub_date.is_some();
+    let torrent = torrents::Model {
+        bangumi_id,
+        title: item.title,
+        size: item.file_size as i64,
+        magnet: item
+            .magnet_link
+            .or_else(|| Some(format!("magnet:?xt=urn:btih:{}", item.info_hash))),
+        info_hash: item.info_hash,
+        data: item.data,
+        download_url: item.torrent_download_url,
+        pub_date: item
+            .pub_date
+            .unwrap_or_else(|| chrono::Local::now().naive_utc()),
+        source: Source::Rss,
+        seeders: item.seeders.map(|seeders| seeders as i32),
+    };
+    (torrent, dated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// 使用内存数据库创建番剧 1 的 worker, 外部服务使用无法访问的地址
+    async fn bangumi_worker(db: &Db, task_manager: TaskManager) -> Result<BangumiWorker> {
+        let client = reqwest::Client::new();
+        let mikan = mikan::client::Client::new_with_client(client.clone(), "http://127.0.0.1:1")?;
+        let fetcher = metadata::fetcher::Fetcher::new(
+            tmdb::client::Client::new(
+                client.clone(),
+                "key",
+                "http://127.0.0.1:1",
+                "http://127.0.0.1:1",
+                "zh-CN",
+            )?,
+            bangumi_tv::client::Client::new_with_client(
+                client.clone(),
+                "http://127.0.0.1:1",
+                "http://127.0.0.1:1",
+            )?,
+            mikan.clone(),
+            client.clone(),
+        );
+        let metadata = metadata::worker::Worker::new_with_conn(
+            db.conn_pool(),
+            client.clone(),
+            mikan,
+            fetcher,
+            dict::Dict::new(db.conn_pool()),
+            String::new(),
+            Arc::new(vec![]),
+        )?;
+        let sub = db.get_subscription(1).await?.unwrap();
+        let bangumi = db.get_bangumi_by_id(1).await?.unwrap();
+        Ok(BangumiWorker::new(
+            sub,
+            bangumi,
+            db.clone(),
+            parser::worker::Worker::new_with_conn(db.conn_pool()),
+            metadata,
+            task_manager,
+            ResourceType::InfoHash,
+            client,
+            &ScoreProfile::default(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_downloaded_episodes() -> Result<()> {
+        let db = testing::test_db(true).await?;
+        // 第 1 集使用 720P 下载, 出现了 1080P 和 480P 的种子
+        let ep1_720 = testing::pair("1000000000000000000000000000000000000720", "720P", 1);
+        let ep1_1080 = testing::pair("1000000000000000000000000000000000001080", "1080P", 1);
+        let ep1_480 = testing::pair("1000000000000000000000000000000000000480", "480P", 1);
+        // 第 2 集已经使用 1080P 下载, 只出现了 720P 的种子
+        let ep2_1080 = testing::pair("2000000000000000000000000000000000001080", "1080P", 2);
+        let ep2_720 = testing::pair("2000000000000000000000000000000000000720", "720P", 2);
+        // 第 3 集正在洗版为 720P, 出现了 1080P 的种子
+        let ep3_480 = testing::pair("3000000000000000000000000000000000000480", "480P", 3);
+        let ep3_720 = testing::pair("3000000000000000000000000000000000000720", "720P", 3);
+        let ep3_1080 = testing::pair("3000000000000000000000000000000000001080", "1080P", 3);
+        let used = vec![
+            ep1_720.clone(),
+            ep2_1080.clone(),
+            ep3_480.clone(),
+            ep3_720.clone(),
+        ];
+        let unused = vec![
+            ep1_1080.clone(),
+            ep1_480.clone(),
+            ep2_720.clone(),
+            ep3_1080.clone(),
+        ];
+        let torrents: Vec<_> = used.iter().chain(unused.iter()).map(|(t, _)| t).collect();
+        testing::insert_torrents(&db, &torrents).await?;
+        let info_hash =
+            |pair: &(torrents::Model, file_name_parse_record::Model)| pair.0.info_hash.clone();
+        testing::insert_task(&db, 1, State::Downloaded, Some(&info_hash(&ep1_720)), None).await?;
+        testing::insert_task(&db, 2, State::Downloaded, Some(&info_hash(&ep2_1080)), None).await?;
+        testing::insert_task(
+            &db,
+            3,
+            State::Downloaded,
+            Some(&info_hash(&ep3_480)),
+            Some(&info_hash(&ep3_720)),
+        )
+        .await?;
+
+        let (mut task_manager, _) = testing::task_manager(&db).await?;
+        task_manager.spawn()?;
+        let worker = bangumi_worker(&db, task_manager.clone()).await?;
+        worker
+            .upgrade_downloaded_episodes(&used, &unused, &HashMap::new())
+            .await?;
+        task_manager.stop().await?;
+
+        // 选择评分最高的种子洗版
+        let task = testing::get_task(&db, 1).await?;
+        assert_eq!(task.ref_torrent_info_hash, Some(info_hash(&ep1_720)));
+        assert_eq!(task.upgrade_info_hash, Some(info_hash(&ep1_1080)));
+        // 当前种子已经是最好的, 不洗版
+        let task = testing::get_task(&db, 2).await?;
+        assert_eq!(task.ref_torrent_info_hash, Some(info_hash(&ep2_1080)));
+        assert_eq!(task.upgrade_info_hash, None);
+        // 洗版中的剧集不会重新选择
+        let task = testing::get_task(&db, 3).await?;
+        assert_eq!(task.upgrade_info_hash, Some(info_hash(&ep3_720)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_item_to_torrent() {
+        let item = torznab::client::TorrentItem {
+            title: "[LoliHouse] 葬送的芙莉莲 - 28 [1080p]".to_owned(),
+            info_hash: "8420088902677e8e142b35e2bc325e82c6cafb04".to_owned(),
+            torrent_download_url: Some(
+                "https://mikanani.me/Download/20240323/8420088902677e8e142b35e2bc325e82c6cafb04.torrent"
+                    .to_owned(),
+            ),
+            file_size: 734318592,
+            ..Default::default()
+        };
+        let (torrent, dated) = feed_item_to_torrent(1, item);
+        assert!(!dated);
+        assert_eq!(torrent.bangumi_id, 1);
+        assert_eq!(torrent.source, Source::Rss);
+        assert_eq!(torrent.size, 734318592);
+        assert_eq!(
+            torrent.magnet.as_deref(),
+            Some("magnet:?xt=urn:btih:8420088902677e8e142b35e2bc325e82c6cafb04")
+        );
     }
 }
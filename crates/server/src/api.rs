@@ -8,10 +8,20 @@ use anyhow::Context;
 use dict::DictCode;
 use downloader::AccessType;
 use model::sea_orm_active_enums::{BgmKind, State, SubscribeStatus};
+use model::subscription_feeds;
 use parser::{Language, VideoResolution};
-use sea_orm::{Condition, prelude::Expr};
+use sea_orm::{Condition, Select, prelude::Expr, sea_query::Func};
 use tracing::{info, instrument};
 
+use crate::{
+    auth::Identity,
+    error::ServerError,
+    model::{Bangumi, Episode, Resp, ScoredTorrent, SubscribeParams, Torrent},
+    router::ASSETS_MOUNT_PATH,
+    secret::SecretKey,
+    server::AppState,
+    users::{SubscriptionFilters, merge_subscriptions, resync_subscription, subscribe_merged},
+};
 use crate::{
     config::Config,
     model::{
@@ -21,12 +31,95 @@ use crate::{
         VersionInfo,
     },
 };
-use crate::{
-    error::ServerError,
-    model::{Bangumi, Episode, Resp, SubscribeParams, Torrent},
-    router::ASSETS_MOUNT_PATH,
-    server::AppState,
-};
+
+/// 番剧及订阅信息的联表查询
+///
+/// 订阅状态和下载配置来自所有用户合并后的订阅, 过滤条件来自当前用户的订阅
+fn bangumi_query(user_id: i32) -> Select<model::bangumi::Entity> {
+    use model::bangumi::Column as BangumiColumn;
+    use model::bangumi::Entity as Bangumis;
+    use model::subscriptions::Column as SubscriptionColumn;
+    use model::subscriptions::Entity as Subscriptions;
+    use model::user_subscriptions::Column as UserSubscriptionColumn;
+    use model::user_subscriptions::Entity as UserSubscriptions;
+    use sea_orm::{EntityTrait, JoinType, QuerySelect};
+
+    Bangumis::find()
+        .join_rev(
+            JoinType::LeftJoin,
+            Subscriptions::belongs_to(Bangumis)
+                .from(SubscriptionColumn::BangumiId)
+                .to(BangumiColumn::Id)
+                .into(),
+        )
+        .join_rev(
+            JoinType::LeftJoin,
+            UserSubscriptions::belongs_to(Bangumis)
+                .from(UserSubscriptionColumn::BangumiId)
+                .to(BangumiColumn::Id)
+                .on_condition(move |left_table, _| {
+                    Condition::all()
+                        .add(Expr::col((left_table, UserSubscriptionColumn::UserId)).eq(user_id))
+                })
+                .into(),
+        )
+}
+
+/// 查询 [`Bangumi`] 需要的字段
+fn select_bangumi(user_id: i32) -> Select<model::bangumi::Entity> {
+    use model::bangumi::Column as BangumiColumn;
+    use model::subscriptions::Column as SubscriptionColumn;
+    use model::user_subscriptions::Column as UserSubscriptionColumn;
+    use sea_orm::QuerySelect;
+
+    bangumi_query(user_id)
+        .select_only()
+        // Bangumi 字段
+        .column(BangumiColumn::Id)
+        .column(BangumiColumn::Name)
+        .column(BangumiColumn::Description)
+        .column(BangumiColumn::BangumiTvId)
+        .column(BangumiColumn::TmdbId)
+        .column(BangumiColumn::MikanId)
+        .column(BangumiColumn::PosterImageUrl)
+        .column(BangumiColumn::AirDate)
+        .column(BangumiColumn::AirWeek)
+        .column(BangumiColumn::Rating)
+        .column(BangumiColumn::EpCount)
+        .column(BangumiColumn::CreatedAt)
+        .column(BangumiColumn::UpdatedAt)
+        .column(BangumiColumn::BackdropImageUrl)
+        .column(BangumiColumn::SeasonNumber)
+        // Subscription 字段
+        .column(SubscriptionColumn::SubscribeStatus)
+        .column(SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast)
+        .column(SubscriptionColumn::PreferredDownloader)
+        .column(SubscriptionColumn::AllowFallback)
+        .column(SubscriptionColumn::FilterRules)
+        .column(SubscriptionColumn::ScoreProfile)
+        .column(SubscriptionColumn::AutoUpgrade)
+        .column(SubscriptionColumn::DownloadStrategy)
+        // 当前用户的订阅字段
+        .column(UserSubscriptionColumn::StartEpisodeNumber)
+        .column(UserSubscriptionColumn::ResolutionFilter)
+        .column(UserSubscriptionColumn::LanguageFilter)
+        .column(UserSubscriptionColumn::ReleaseGroupFilter)
+        .column_as(UserSubscriptionColumn::CreatedAt, "subscribed_at")
+}
+
+/// 处理图片路径, 当前用户没有订阅时不返回订阅状态
+fn normalize_bangumi(mut bangumi: Bangumi) -> Bangumi {
+    if let Some(image) = &mut bangumi.poster_image_url {
+        *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
+    }
+    if let Some(image) = &mut bangumi.backdrop_image_url {
+        *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
+    }
+    if bangumi.subscribed_at.is_none() {
+        bangumi.subscribe_status = None;
+    }
+    bangumi
+}
 
 #[get("/api/calendar/season")]
 pub async fn current_calendar_season(
@@ -45,13 +138,11 @@ pub async fn current_calendar_season(
 #[get("/api/calendar")]
 pub async fn calendar(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     query: web::Query<CalendarQuery>,
 ) -> Result<Json<Resp<Vec<Bangumi>>>, ServerError> {
     use model::bangumi::Column as BangumiColumn;
-    use model::bangumi::Entity as Bangumis;
-    use model::subscriptions::Column as SubscriptionColumn;
-    use model::subscriptions::Entity as Subscriptions;
-    use sea_orm::{ColumnTrait, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect};
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
 
     let calendar_season = match query.season.as_ref() {
         Some(season) if !season.is_empty() => season.clone(),
@@ -62,41 +153,7 @@ pub async fn calendar(
             .unwrap_or_default(),
     };
 
-    let bangumis = Bangumis::find()
-        .select_only()
-        // Bangumi 字段
-        .column(BangumiColumn::Id)
-        .column(BangumiColumn::Name)
-        .column(BangumiColumn::Description)
-        .column(BangumiColumn::BangumiTvId)
-        .column(BangumiColumn::TmdbId)
-        .column(BangumiColumn::MikanId)
-        .column(BangumiColumn::PosterImageUrl)
-        .column(BangumiColumn::AirDate)
-        .column(BangumiColumn::AirWeek)
-        .column(BangumiColumn::Rating)
-        .column(BangumiColumn::EpCount)
-        .column(BangumiColumn::CreatedAt)
-        .column(BangumiColumn::UpdatedAt)
-        .column(BangumiColumn::BackdropImageUrl)
-        .column(BangumiColumn::SeasonNumber)
-        // Subscription 字段
-        .column(SubscriptionColumn::SubscribeStatus)
-        .column(SubscriptionColumn::StartEpisodeNumber)
-        .column(SubscriptionColumn::ResolutionFilter)
-        .column(SubscriptionColumn::LanguageFilter)
-        .column(SubscriptionColumn::ReleaseGroupFilter)
-        .column(SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast)
-        .column(SubscriptionColumn::PreferredDownloader)
-        .column(SubscriptionColumn::AllowFallback)
-        // 联表查询
-        .join_rev(
-            JoinType::LeftJoin,
-            Subscriptions::belongs_to(Bangumis)
-                .from(SubscriptionColumn::BangumiId)
-                .to(BangumiColumn::Id)
-                .into(),
-        )
+    let bangumis = select_bangumi(identity.user_id)
         // 时间范围过滤
         .filter(BangumiColumn::CalendarSeason.eq(calendar_season))
         .order_by_asc(BangumiColumn::AirDate)
@@ -105,18 +162,7 @@ pub async fn calendar(
         .await?;
 
     // 处理图片路径
-    let bangumis = bangumis
-        .into_iter()
-        .map(|mut bangumi| {
-            if let Some(image) = &mut bangumi.poster_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            if let Some(image) = &mut bangumi.backdrop_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            bangumi
-        })
-        .collect();
+    let bangumis = bangumis.into_iter().map(normalize_bangumi).collect();
 
     Ok(Json(Resp::ok(bangumis)))
 }
@@ -125,49 +171,13 @@ pub async fn calendar(
 #[get("/api/bangumi/{id}")]
 pub async fn get_bangumi_by_id(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     id: web::Path<i32>,
 ) -> Result<Json<Resp<Bangumi>>, ServerError> {
     use model::bangumi::Column as BangumiColumn;
-    use model::bangumi::Entity as Bangumis;
-    use model::subscriptions::Column as SubscriptionColumn;
-    use model::subscriptions::Entity as Subscriptions;
-    use sea_orm::{ColumnTrait, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect};
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
 
-    let bangumi = Bangumis::find()
-        .select_only()
-        // Bangumi 字段
-        .column(BangumiColumn::Id)
-        .column(BangumiColumn::Name)
-        .column(BangumiColumn::Description)
-        .column(BangumiColumn::BangumiTvId)
-        .column(BangumiColumn::TmdbId)
-        .column(BangumiColumn::MikanId)
-        .column(BangumiColumn::PosterImageUrl)
-        .column(BangumiColumn::AirDate)
-        .column(BangumiColumn::AirWeek)
-        .column(BangumiColumn::Rating)
-        .column(BangumiColumn::EpCount)
-        .column(BangumiColumn::CreatedAt)
-        .column(BangumiColumn::UpdatedAt)
-        .column(BangumiColumn::BackdropImageUrl)
-        .column(BangumiColumn::SeasonNumber)
-        // Subscription 字段
-        .column(SubscriptionColumn::SubscribeStatus)
-        .column(SubscriptionColumn::StartEpisodeNumber)
-        .column(SubscriptionColumn::ResolutionFilter)
-        .column(SubscriptionColumn::LanguageFilter)
-        .column(SubscriptionColumn::ReleaseGroupFilter)
-        .column(SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast)
-        .column(SubscriptionColumn::PreferredDownloader)
-        .column(SubscriptionColumn::AllowFallback)
-        // 联表查询
-        .join_rev(
-            JoinType::LeftJoin,
-            Subscriptions::belongs_to(Bangumis)
-                .from(SubscriptionColumn::BangumiId)
-                .to(BangumiColumn::Id)
-                .into(),
-        )
+    let bangumi = select_bangumi(identity.user_id)
         .filter(BangumiColumn::Id.eq(id.into_inner()))
         .order_by_asc(BangumiColumn::AirDate)
         .into_model::<Bangumi>()
@@ -175,15 +185,7 @@ pub async fn get_bangumi_by_id(
         .await?;
 
     match bangumi {
-        Some(mut bgm) => {
-            if let Some(image) = &mut bgm.poster_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            if let Some(image) = &mut bgm.backdrop_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            Ok(Json(Resp::ok(bgm)))
-        }
+        Some(bgm) => Ok(Json(Resp::ok(normalize_bangumi(bgm)))),
         None => Err(ServerError::BangumiNotFound),
     }
 }
@@ -192,15 +194,19 @@ pub async fn get_bangumi_by_id(
 #[get("/api/bangumi/{id}/episodes")]
 pub async fn get_bangumi_episodes_by_id(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     id: web::Path<i32>,
 ) -> Result<Json<Resp<Vec<Episode>>>, ServerError> {
     use model::episode_download_tasks::Column as TaskColumn;
     use model::episode_download_tasks::Entity as Tasks;
+    use model::episode_watch_states::Column as WatchColumn;
+    use model::episode_watch_states::Entity as WatchStates;
     use model::episodes::Column as EpisodeColumn;
     use model::episodes::Entity as Episodes;
     use sea_orm::{ColumnTrait, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect};
 
     let bangumi_id = id.into_inner();
+    let user_id = identity.user_id;
     let episodes = Episodes::find()
         .select_only()
         // Episodes 字段
@@ -217,10 +223,17 @@ pub async fn get_bangumi_episodes_by_id(
         .column(EpisodeColumn::CreatedAt)
         .column(EpisodeColumn::UpdatedAt)
         // Tasks 字段
-        .column_as(TaskColumn::State, "download_state")
+        // 枚举字段需要通过 select_as 转换, 否则 PostgreSQL 无法解码
+        .expr_as(
+            TaskColumn::State.select_as(Expr::col((Tasks, TaskColumn::State))),
+            "download_state",
+        )
         .column(TaskColumn::RefTorrentInfoHash)
+        .column(TaskColumn::UpgradeInfoHash)
         .column_as(TaskColumn::CreatedAt, "task_created_at")
         .column_as(TaskColumn::UpdatedAt, "task_updated_at")
+        // 当前用户的观看状态
+        .column(WatchColumn::WatchedAt)
         // 联表查询
         .join_rev(
             JoinType::LeftJoin,
@@ -235,6 +248,21 @@ pub async fn get_bangumi_episodes_by_id(
                 })
                 .into(),
         )
+        .join_rev(
+            JoinType::LeftJoin,
+            WatchStates::belongs_to(Episodes)
+                .from(WatchColumn::BangumiId)
+                .to(EpisodeColumn::BangumiId)
+                .on_condition(move |left_table, right_table| {
+                    Condition::all()
+                        .add(
+                            Expr::col((left_table.clone(), WatchColumn::EpisodeNumber))
+                                .eq(Expr::col((right_table, EpisodeColumn::Number))),
+                        )
+                        .add(Expr::col((left_table, WatchColumn::UserId)).eq(user_id))
+                })
+                .into(),
+        )
         .filter(EpisodeColumn::BangumiId.eq(bangumi_id))
         .order_by_asc(EpisodeColumn::Number)
         .into_model::<Episode>()
@@ -248,9 +276,11 @@ pub async fn get_bangumi_episodes_by_id(
 #[post("/api/bangumi/{id}/subscribe")]
 pub async fn subscribe_bangumi(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     id: web::Path<i32>,
     params: Json<SubscribeParams>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    let bangumi_id = id.into_inner();
     match params.status {
         SubscribeStatus::Subscribed => {
             // 将分辨率字符串转换为VideoResolution枚举列表
@@ -281,24 +311,107 @@ pub async fn subscribe_bangumi(
                     .collect::<Vec<_>>()
             });
 
+            // 当前用户的订阅条件与其他用户的订阅合并后交给调度器
+            let filters = SubscriptionFilters {
+                start_episode_number: params.start_episode_number,
+                resolution_filter: resolution_filter.map(|resolutions| {
+                    resolutions
+                        .into_iter()
+                        .filter(|res| *res != VideoResolution::Unknown)
+                        .map(|res| res.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                }),
+                language_filter: language_filter.map(|langs| {
+                    langs
+                        .into_iter()
+                        .filter(|lang| *lang != Language::Unknown)
+                        .map(|lang| lang.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                }),
+                release_group_filter: params.release_group_filter.clone(),
+            };
+            let mut subscriptions = state
+                .db
+                .list_user_subscriptions(bangumi_id)
+                .await?
+                .into_iter()
+                .filter(|s| s.user_id != identity.user_id)
+                .map(SubscriptionFilters::from)
+                .collect::<Vec<_>>();
+            subscriptions.push(filters.clone());
+
+            if identity.is_admin {
+                // 校验自定义 RSS 源地址, 并去除重复
+                let feeds = match params.feeds.as_ref() {
+                    Some(feeds) => {
+                        let mut urls = Vec::new();
+                        for feed in feeds.iter().map(|feed| feed.trim()) {
+                            if feed.is_empty() || urls.iter().any(|url| url == feed) {
+                                continue;
+                            }
+                            reqwest::Url::parse(feed).map_err(|e| {
+                                ServerError::BadRequest(format!("RSS 源 {} 无效: {}", feed, e))
+                            })?;
+                            urls.push(feed.to_owned());
+                        }
+                        Some(urls)
+                    }
+                    None => None,
+                };
+
+                // 校验过滤规则
+                let filter_rules = params.filter_rules.clone();
+                if let Some(rule) = filter_rules.as_ref() {
+                    rule.compile()
+                        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+                }
+
+                let merged = merge_subscriptions(&subscriptions);
+                state
+                    .scheduler
+                    .subscribe(
+                        bangumi_id,
+                        merged.start_episode_number,
+                        merged.resolutions(),
+                        merged.languages(),
+                        merged.release_group_filter.clone(),
+                        params.collector_interval,
+                        params.metadata_interval,
+                        params.enforce_torrent_release_after_broadcast,
+                        params.preferred_downloader.clone(),
+                        params.allow_fallback,
+                        feeds,
+                        filter_rules,
+                        params.score_profile.clone(),
+                        params.auto_upgrade,
+                        params.download_strategy,
+                    )
+                    .await?;
+            } else {
+                // 普通用户只能修改自己的订阅条件, 下载相关的共享配置沿用已有的订阅
+                subscribe_merged(&state, bangumi_id, &subscriptions).await?;
+            }
+
             state
-                .scheduler
-                .subscribe(
-                    id.into_inner(),
-                    params.start_episode_number,
-                    resolution_filter,
-                    language_filter,
-                    params.release_group_filter.clone(),
-                    params.collector_interval,
-                    params.metadata_interval,
-                    params.enforce_torrent_release_after_broadcast,
-                    params.preferred_downloader.clone(),
-                    params.allow_fallback,
+                .db
+                .upsert_user_subscription(
+                    identity.user_id,
+                    bangumi_id,
+                    filters.start_episode_number,
+                    filters.resolution_filter,
+                    filters.language_filter,
+                    filters.release_group_filter,
                 )
                 .await?;
         }
         SubscribeStatus::None => {
-            state.scheduler.unsubscribe(id.into_inner()).await?;
+            state
+                .db
+                .delete_user_subscription(identity.user_id, bangumi_id)
+                .await?;
+            resync_subscription(&state, bangumi_id).await?;
         }
         _ => {}
     }
@@ -306,11 +419,31 @@ pub async fn subscribe_bangumi(
 }
 
 #[instrument(skip(state), fields(id = %id))]
-#[get("/api/bangumi/{id}/delete_download_tasks")]
+#[get("/api/bangumi/{id}/feeds")]
+pub async fn list_subscription_feeds(
+    state: web::Data<Arc<AppState>>,
+    id: web::Path<i32>,
+) -> Result<Json<Resp<Vec<subscription_feeds::Model>>>, ServerError> {
+    use model::subscription_feeds::Column as FeedColumn;
+    use model::subscription_feeds::Entity as Feeds;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let feeds = Feeds::find()
+        .filter(FeedColumn::BangumiId.eq(id.into_inner()))
+        .order_by_asc(FeedColumn::Id)
+        .all(state.db.conn())
+        .await?;
+    Ok(Json(Resp::ok(feeds)))
+}
+
+#[instrument(skip(state, identity), fields(id = %id))]
+#[post("/api/bangumi/{id}/delete_download_tasks")]
 pub async fn delete_bangumi_download_tasks(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     id: web::Path<i32>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     let bangumi_id = id.into_inner();
     // 先取消订阅
     state.scheduler.unsubscribe(bangumi_id).await?;
@@ -323,7 +456,7 @@ pub async fn delete_bangumi_download_tasks(
 pub async fn get_bangumi_torrents_by_id(
     state: web::Data<Arc<AppState>>,
     id: web::Path<i32>,
-) -> Result<Json<Resp<Vec<Torrent>>>, ServerError> {
+) -> Result<Json<Resp<Vec<ScoredTorrent>>>, ServerError> {
     use model::bangumi::Column as BangumiColumn;
     use model::bangumi::Entity as Bangumis;
     use model::file_name_parse_record::Column as ParseColumn;
@@ -352,6 +485,7 @@ pub async fn get_bangumi_torrents_by_id(
         .column(TorrentColumn::Size)
         .column(TorrentColumn::Magnet)
         .column(TorrentColumn::PubDate)
+        .column(TorrentColumn::Seeders)
         // 文件名解析信息
         .column(ParseColumn::ReleaseGroup)
         .column(ParseColumn::SeasonNumber)
@@ -400,21 +534,44 @@ pub async fn get_bangumi_torrents_by_id(
         true
     });
 
+    // 4. 计算种子评分, 用于解释种子的选择结果
+    let scores = state.scheduler.score_bangumi_torrents(bangumi_id).await?;
+    let torrents = torrents
+        .into_iter()
+        .map(|torrent| ScoredTorrent {
+            score: scores.get(&torrent.info_hash).cloned(),
+            torrent,
+        })
+        .collect();
+
     Ok(Json(Resp::ok(torrents)))
 }
 
-#[instrument(skip(state), fields(id = %params.0))]
-#[get("/api/bangumi/{id}/refresh/{force}")]
+#[instrument(skip(state, identity), fields(id = %params.0))]
+#[post("/api/bangumi/{id}/refresh/{force}")]
 pub async fn refresh_bangumi(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     params: web::Path<(i32, bool)>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     let (id, force) = params.into_inner();
     state.metadata.request_refresh_metadata(id, force)?;
-    state.scheduler.trigger_collection(id).await?;
+    state.scheduler.trigger_collection(id, force).await?;
     Ok(Json(Resp::ok(())))
 }
 
+#[post("/api/bangumi/{id}/regenerate_sidecars")]
+pub async fn regenerate_sidecars(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    id: web::Path<i32>,
+) -> Result<Json<Resp<usize>>, ServerError> {
+    identity.require_admin()?;
+    let count = state.scheduler.regenerate_sidecars(id.into_inner()).await?;
+    Ok(Json(Resp::ok(count)))
+}
+
 #[get("/api/bangumi/{id}/release_groups")]
 pub async fn get_bangumi_release_groups(
     state: web::Data<Arc<AppState>>,
@@ -554,11 +711,13 @@ pub async fn online_watch(
         .body(format!(r#"<a href="{url}">Found</a>"#, url = location)))
 }
 
-#[get("/api/bangumi/{bangumi_id}/{episode_number}/manual_select_torrent/{info_hash}")]
+#[post("/api/bangumi/{bangumi_id}/{episode_number}/manual_select_torrent/{info_hash}")]
 pub async fn manual_select_torrent(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     path: web::Path<(i32, i32, String)>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     let (bangumi_id, episode_number, info_hash) = path.into_inner();
     state
         .scheduler
@@ -567,11 +726,13 @@ pub async fn manual_select_torrent(
     Ok(Json(Resp::ok(())))
 }
 
-#[get("/api/downloads/{bangumi_id}/{episode_number}/retry")]
+#[post("/api/downloads/{bangumi_id}/{episode_number}/retry")]
 pub async fn retry_download_task(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     path: web::Path<(i32, i32)>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     let (bangumi_id, episode_number) = path.into_inner();
     state
         .scheduler
@@ -593,12 +754,14 @@ pub async fn list_download_tasks(
     Ok(Json(Resp::ok(downloads)))
 }
 
-#[get("/api/calendar/refresh/{force}")]
+#[post("/api/calendar/refresh/{force}")]
 pub async fn refresh_calendar(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     query: web::Query<CalendarQuery>,
     force: web::Path<bool>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     let force = force.into_inner();
     let season = query.season.as_ref().filter(|s| !s.is_empty()).cloned();
     state.metadata.request_refresh_calendar(season, force)?;
@@ -613,8 +776,10 @@ pub async fn health() -> Result<Json<Resp<()>>, ServerError> {
 #[post("/api/bangumi/{bangumi_id}/mdb/update")]
 pub async fn update_bangumi_mdb(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     params: Json<UpdateMDBParams>,
 ) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
     state
         .metadata
         .update_bangumi_mdb(
@@ -655,8 +820,10 @@ pub async fn seach_bangumi_at_mikan(
 #[post("/api/bangumi/add")]
 pub async fn add_bangumi(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     params: Json<AddBangumiParams>,
 ) -> Result<Json<Resp<i32>>, ServerError> {
+    identity.require_admin()?;
     let params = params.into_inner();
     state
         .metadata
@@ -787,25 +954,41 @@ pub async fn metrics(state: web::Data<Arc<AppState>>) -> Result<Json<Resp<Metric
 #[get("/api/config")]
 pub async fn get_config(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
 ) -> Result<Json<Resp<Config>>, ServerError> {
+    identity.require_admin()?;
     let config = state.config.read().unwrap();
-    Ok(Json(Resp::ok(config.clone())))
+    Ok(Json(Resp::ok(config.redacted())))
 }
 
 #[post("/api/config")]
 pub async fn update_config(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     body: Json<Config>,
 ) -> Result<Json<Resp<()>>, ServerError> {
-    let mut config = state.config.write().unwrap();
-    let new_config = body.into_inner();
-    match new_config.validate() {
-        Ok(_) => {
-            *config = new_config;
-            state.config_writer.write(&config)?;
+    identity.require_admin()?;
+    let mut new_config = body.into_inner();
+    let (username, password_hash) = {
+        let mut config = state.config.write().unwrap();
+        // 未修改的敏感字段提交的是占位符, 使用当前配置中的值
+        new_config.merge_redacted(&config);
+        if let Err(e) = new_config.validate() {
+            return Err(ServerError::BadRequest(e.to_string()));
         }
-        Err(e) => return Err(ServerError::Internal(e)),
-    }
+        if let Some(key) = SecretKey::from_env() {
+            new_config.encrypt_secrets(&key)?;
+        }
+        let password_hash = new_config.auth.password_hash.expose()?;
+        *config = new_config;
+        state.config_writer.write(&config)?;
+        (config.auth.username.clone(), password_hash)
+    };
+    // 同步配置文件中的管理员账号
+    state
+        .db
+        .ensure_admin_user(&username, &password_hash)
+        .await?;
     Ok(Json(Resp::ok(())))
 }
 
@@ -813,27 +996,38 @@ pub async fn update_config(
 #[post("/api/bangumi/list")]
 pub async fn list_bangumi(
     state: web::Data<Arc<AppState>>,
+    identity: Identity,
     params: Json<QueryBangumiParams>,
 ) -> Result<Json<Resp<BangumiListResp>>, ServerError> {
     use model::bangumi::Column as BangumiColumn;
     use model::bangumi::Entity as Bangumis;
     use model::subscriptions::Column as SubscriptionColumn;
-    use model::subscriptions::Entity as Subscriptions;
+    use model::user_subscriptions::Column as UserSubscriptionColumn;
     use sea_orm::PaginatorTrait;
-    use sea_orm::{
-        ColumnTrait, Condition, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect,
-    };
+    use sea_orm::{ColumnTrait, Condition, QueryFilter, QueryOrder, QuerySelect};
 
     // 构建查询条件
     let mut condition = Condition::all();
 
     // 添加名称过滤条件
     if let Some(name) = &params.name {
-        condition = condition.add(BangumiColumn::Name.like(format!("%{}%", name)));
+        // PostgreSQL 的 LIKE 区分大小写, 统一转为小写后匹配
+        condition = condition.add(
+            Expr::expr(Func::lower(Expr::col((Bangumis, BangumiColumn::Name))))
+                .like(format!("%{}%", name.to_lowercase())),
+        );
     } else {
-        // 添加订阅状态过滤条件
-        if let Some(status) = &params.status {
-            condition = condition.add(SubscriptionColumn::SubscribeStatus.eq(status.clone()));
+        // 添加订阅状态过滤条件, 只匹配当前用户订阅的番剧
+        match &params.status {
+            Some(SubscribeStatus::None) => {
+                condition = condition.add(UserSubscriptionColumn::UserId.is_null());
+            }
+            Some(status) => {
+                condition = condition
+                    .add(UserSubscriptionColumn::UserId.is_not_null())
+                    .add(SubscriptionColumn::SubscribeStatus.eq(status.clone()));
+            }
+            None => {}
         }
 
         // 添加季度过滤条件
@@ -843,54 +1037,13 @@ pub async fn list_bangumi(
     }
 
     // 查询总条数
-    let total = Bangumis::find()
-        .join_rev(
-            JoinType::LeftJoin,
-            Subscriptions::belongs_to(Bangumis)
-                .from(SubscriptionColumn::BangumiId)
-                .to(BangumiColumn::Id)
-                .into(),
-        )
+    let total = bangumi_query(identity.user_id)
         .filter(condition.clone())
         .count(state.db.conn())
         .await?;
 
     // 查询分页数据
-    let bangumis = Bangumis::find()
-        .select_only()
-        // Bangumi 字段
-        .column(BangumiColumn::Id)
-        .column(BangumiColumn::Name)
-        .column(BangumiColumn::Description)
-        .column(BangumiColumn::BangumiTvId)
-        .column(BangumiColumn::TmdbId)
-        .column(BangumiColumn::MikanId)
-        .column(BangumiColumn::PosterImageUrl)
-        .column(BangumiColumn::AirDate)
-        .column(BangumiColumn::AirWeek)
-        .column(BangumiColumn::Rating)
-        .column(BangumiColumn::EpCount)
-        .column(BangumiColumn::CreatedAt)
-        .column(BangumiColumn::UpdatedAt)
-        .column(BangumiColumn::BackdropImageUrl)
-        .column(BangumiColumn::SeasonNumber)
-        // Subscription 字段
-        .column(SubscriptionColumn::SubscribeStatus)
-        .column(SubscriptionColumn::StartEpisodeNumber)
-        .column(SubscriptionColumn::ResolutionFilter)
-        .column(SubscriptionColumn::LanguageFilter)
-        .column(SubscriptionColumn::ReleaseGroupFilter)
-        .column(SubscriptionColumn::EnforceTorrentReleaseAfterBroadcast)
-        .column(SubscriptionColumn::PreferredDownloader)
-        .column(SubscriptionColumn::AllowFallback)
-        // 联表查询
-        .join_rev(
-            JoinType::LeftJoin,
-            Subscriptions::belongs_to(Bangumis)
-                .from(SubscriptionColumn::BangumiId)
-                .to(BangumiColumn::Id)
-                .into(),
-        )
+    let bangumis = select_bangumi(identity.user_id)
         // 应用过滤条件
         .filter(condition)
         // 分页
@@ -903,18 +1056,7 @@ pub async fn list_bangumi(
         .await?;
 
     // 处理图片路径
-    let bangumis = bangumis
-        .into_iter()
-        .map(|mut bangumi| {
-            if let Some(image) = &mut bangumi.poster_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            if let Some(image) = &mut bangumi.backdrop_image_url {
-                *image = format!("{}/{}", ASSETS_MOUNT_PATH, image);
-            }
-            bangumi
-        })
-        .collect();
+    let bangumis = bangumis.into_iter().map(normalize_bangumi).collect();
 
     Ok(Json(Resp::ok(BangumiListResp {
         list: bangumis,
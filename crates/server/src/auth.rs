@@ -0,0 +1,654 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+    body::BoxBody,
+    cookie::{Cookie, SameSite, time::Duration as CookieDuration},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    get,
+    middleware::Next,
+    post,
+    web::{self, Json},
+};
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+};
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::{error::ServerError, model::Resp, server::AppState};
+
+/// Web 界面登录会话的 Cookie 名称
+pub const SESSION_COOKIE: &str = "bangumi_session";
+
+/// API 令牌前缀, 用于和会话令牌区分
+const API_TOKEN_PREFIX: &str = "bgm_";
+
+/// WebSocket 接口, 允许通过 `token` 查询参数传递令牌
+pub const WS_PATH: &str = "/ws";
+
+/// 不需要认证的接口
+const PUBLIC_PATHS: &[&str] = &["/health", "/api/auth/login", "/api/auth/status"];
+
+/// 同一客户端 IP 对同一用户名连续登录失败的次数上限, 超过后锁定 [`LOGIN_LOCKOUT`]
+const MAX_FAILED_LOGINS: u32 = 5;
+const LOGIN_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// 使用 argon2 哈希密码
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|e| anyhow::anyhow!("生成盐值失败: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("哈希密码失败: {}", e))?;
+    Ok(hash.to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+pub fn validate_password_hash(password_hash: &str) -> anyhow::Result<()> {
+    PasswordHash::new(password_hash).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(())
+}
+
+/// 生成随机令牌
+fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// 数据库中只保存令牌的 SHA-256
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// 登录失败限流, 按客户端 IP 和用户名记录连续失败的次数和最后一次失败的时间
+///
+/// 只锁定失败的客户端, 其他客户端仍然可以登录同一用户
+#[derive(Clone, Default)]
+pub struct LoginThrottle(Arc<Mutex<HashMap<LoginKey, (u32, Instant)>>>);
+
+/// 客户端 IP 及用户名
+type LoginKey = (String, String);
+
+impl LoginThrottle {
+    /// 连续失败次数达到上限且未过锁定时间
+    fn is_locked(&self, ip: &str, username: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&(ip.to_owned(), username.to_owned()))
+            .is_some_and(|(count, last)| {
+                *count >= MAX_FAILED_LOGINS && last.elapsed() < LOGIN_LOCKOUT
+            })
+    }
+
+    fn record_failure(&self, ip: &str, username: &str) {
+        let mut failures = self.0.lock().unwrap();
+        // 顺便清理过期记录, 避免随意尝试的用户名一直占用内存
+        failures.retain(|_, (_, last)| last.elapsed() < LOGIN_LOCKOUT);
+        let (count, last) = failures
+            .entry((ip.to_owned(), username.to_owned()))
+            .or_insert((0, Instant::now()));
+        *count += 1;
+        *last = Instant::now();
+    }
+
+    fn reset(&self, ip: &str, username: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(ip.to_owned(), username.to_owned()));
+    }
+}
+
+/// 客户端 IP, 通过反向代理访问时使用 `Forwarded` 或 `X-Forwarded-For` 请求头中的地址
+fn client_ip(req: &HttpRequest) -> String {
+    req.connection_info()
+        .realip_remote_addr()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// 当前请求使用的会话令牌哈希, 通过 API 令牌访问时为空
+pub fn current_session(req: &HttpRequest, identity: &Identity) -> Option<String> {
+    req.cookie(SESSION_COOKIE)
+        .filter(|_| identity.api_token_id.is_none())
+        .map(|cookie| hash_token(cookie.value()))
+}
+
+/// 通过 HTTPS 访问时 (包括反向代理设置的 `X-Forwarded-Proto`) 只允许浏览器通过 HTTPS 发送 Cookie
+fn is_secure(req: &HttpRequest) -> bool {
+    req.connection_info().scheme() == "https"
+}
+
+/// 认证通过的身份
+///
+/// 未启用认证时, 所有请求都视为配置文件中的管理员
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub user_id: i32,
+    pub username: String,
+    pub is_admin: bool,
+    /// 通过 API 令牌访问时的令牌 ID, 通过 Web 界面登录时为空
+    pub api_token_id: Option<i32>,
+}
+
+impl Identity {
+    fn from_user(user: model::users::Model, api_token_id: Option<i32>) -> Self {
+        Self {
+            user_id: user.id,
+            username: user.username,
+            is_admin: user.is_admin,
+            api_token_id,
+        }
+    }
+
+    /// 仅允许管理员访问
+    pub fn require_admin(&self) -> Result<(), ServerError> {
+        if self.is_admin {
+            Ok(())
+        } else {
+            Err(ServerError::Forbidden)
+        }
+    }
+}
+
+impl FromRequest for Identity {
+    type Error = ServerError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // 认证中间件已经写入身份
+        if let Some(identity) = req.extensions().get::<Identity>().cloned() {
+            return Box::pin(async move { Ok(identity) });
+        }
+        let state = req.app_data::<web::Data<Arc<AppState>>>().cloned();
+        Box::pin(async move {
+            let state = state.ok_or_else(|| anyhow::anyhow!("未找到应用状态"))?;
+            default_identity(&state).await
+        })
+    }
+}
+
+/// 未启用认证时使用配置文件中的管理员身份
+async fn default_identity(state: &AppState) -> Result<Identity, ServerError> {
+    let username = state.config.read().unwrap().auth.username.clone();
+    let user = state
+        .db
+        .get_user_by_username(&username)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("未找到用户 {}", username))?;
+    Ok(Identity::from_user(user, None))
+}
+
+/// 除公开接口外的请求都需要认证
+///
+/// 使用路由匹配时的已解码路径判断, 原始路径中的 `/%61pi/config` 同样会被路由到 `/api/config`
+fn requires_auth(req: &ServiceRequest) -> bool {
+    !PUBLIC_PATHS.contains(&req.match_info().as_str())
+}
+
+/// 从请求中提取令牌, 依次检查会话 Cookie, `Authorization: Bearer` 请求头和 `token` 查询参数
+///
+/// 浏览器的 WebSocket 无法设置请求头, 只有 [`WS_PATH`] 接受查询参数传递的令牌,
+/// 避免令牌出现在其他接口的访问日志和 Referer 中
+fn extract_token(req: &HttpRequest) -> Option<String> {
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        return Some(cookie.value().to_owned());
+    }
+    if let Some(token) = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(token.trim().to_owned());
+    }
+    if req.match_info().as_str() != WS_PATH {
+        return None;
+    }
+    web::Query::<TokenQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.into_inner().token)
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+async fn authenticate(state: &AppState, token: &str) -> anyhow::Result<Option<Identity>> {
+    let token_hash = hash_token(token);
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let Some(api_token) = state.db.get_api_token(&token_hash).await? else {
+            return Ok(None);
+        };
+        let Some(user) = state.db.get_user(api_token.user_id).await? else {
+            return Ok(None);
+        };
+        // 避免每个请求都写数据库, 最多每分钟更新一次使用时间
+        let now = Local::now().naive_utc();
+        if api_token
+            .last_used_at
+            .is_none_or(|t| now - t > chrono::Duration::minutes(1))
+        {
+            state.db.update_api_token_used(api_token.id, now).await?;
+        }
+        return Ok(Some(Identity::from_user(user, Some(api_token.id))));
+    }
+
+    let Some(session) = state.db.get_session(&token_hash).await? else {
+        return Ok(None);
+    };
+    let user = state.db.get_user(session.user_id).await?;
+    Ok(user.map(|user| Identity::from_user(user, None)))
+}
+
+/// 认证中间件, 启用认证后除 [`PUBLIC_PATHS`] 外的请求都需要认证
+pub async fn auth_middleware(
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if !requires_auth(&req) {
+        return next.call(req).await;
+    }
+
+    let Some(state) = req.app_data::<web::Data<Arc<AppState>>>().cloned() else {
+        return next.call(req).await;
+    };
+    if !state.config.read().unwrap().auth.enabled {
+        return next.call(req).await;
+    }
+
+    let identity = match extract_token(req.request()) {
+        Some(token) => match authenticate(&state, &token).await {
+            Ok(identity) => identity,
+            Err(e) => {
+                warn!("认证失败: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    match identity {
+        Some(identity) => {
+            req.extensions_mut().insert(identity);
+            next.call(req).await
+        }
+        None => Ok(req.into_response(unauthorized())),
+    }
+}
+
+fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized().json(Resp::<()>::err(401, "未登录或登录已过期".to_owned()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthStatus {
+    /// 是否启用认证
+    pub enabled: bool,
+    /// 当前请求是否已认证
+    pub authenticated: bool,
+    pub username: Option<String>,
+    /// 是否为管理员, 未启用认证时总是管理员
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginParams {
+    pub username: String,
+    pub password: String,
+}
+
+#[post("/api/auth/login")]
+pub async fn login(
+    state: web::Data<Arc<AppState>>,
+    req: HttpRequest,
+    params: Json<LoginParams>,
+) -> Result<HttpResponse, ServerError> {
+    let (enabled, session_ttl) = {
+        let config = state.config.read().unwrap();
+        (config.auth.enabled, config.auth.session_ttl)
+    };
+    if !enabled {
+        return Ok(HttpResponse::Ok().json(Resp::ok(AuthStatus {
+            enabled,
+            authenticated: true,
+            username: None,
+            is_admin: true,
+        })));
+    }
+
+    let params = params.into_inner();
+    let ip = client_ip(&req);
+    if state.login_throttle.is_locked(&ip, &params.username) {
+        warn!("登录失败次数过多, IP: {}, 用户名: {}", ip, params.username);
+        return Err(ServerError::TooManyRequests);
+    }
+    let user = state
+        .db
+        .get_user_by_username(&params.username)
+        .await?
+        .filter(|user| verify_password(&params.password, &user.password_hash));
+    let Some(user) = user else {
+        warn!("登录失败, IP: {}, 用户名: {}", ip, params.username);
+        state.login_throttle.record_failure(&ip, &params.username);
+        return Err(ServerError::Unauthorized);
+    };
+    state.login_throttle.reset(&ip, &params.username);
+
+    let token = generate_token();
+    let expires_at: NaiveDateTime = Local::now().naive_utc()
+        + chrono::Duration::from_std(session_ttl).map_err(anyhow::Error::from)?;
+    state
+        .db
+        .create_session(hash_token(&token), user.id, expires_at)
+        .await?;
+    // 顺便清理过期会话
+    state.db.delete_expired_sessions().await?;
+
+    let cookie = Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .secure(is_secure(&req))
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(session_ttl.as_secs() as i64))
+        .finish();
+    Ok(HttpResponse::Ok().cookie(cookie).json(Resp::ok(AuthStatus {
+        enabled,
+        authenticated: true,
+        username: Some(user.username),
+        is_admin: user.is_admin,
+    })))
+}
+
+#[post("/api/auth/logout")]
+pub async fn logout(
+    state: web::Data<Arc<AppState>>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ServerError> {
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        state.db.delete_session(&hash_token(cookie.value())).await?;
+    }
+    let mut cookie = Cookie::build(SESSION_COOKIE, "")
+        .path("/")
+        .secure(is_secure(&req))
+        .finish();
+    cookie.make_removal();
+    Ok(HttpResponse::Ok().cookie(cookie).json(Resp::ok(())))
+}
+
+#[get("/api/auth/status")]
+pub async fn status(
+    state: web::Data<Arc<AppState>>,
+    req: HttpRequest,
+) -> Result<Json<Resp<AuthStatus>>, ServerError> {
+    let enabled = state.config.read().unwrap().auth.enabled;
+    if !enabled {
+        return Ok(Json(Resp::ok(AuthStatus {
+            enabled,
+            authenticated: true,
+            username: None,
+            is_admin: true,
+        })));
+    }
+
+    let identity = match extract_token(&req) {
+        Some(token) => authenticate(&state, &token).await?,
+        None => None,
+    };
+    Ok(Json(Resp::ok(AuthStatus {
+        enabled,
+        authenticated: identity.is_some(),
+        is_admin: identity.as_ref().is_some_and(|i| i.is_admin),
+        username: identity.map(|i| i.username),
+    })))
+}
+
+/// API 令牌信息, 不包含令牌本身
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<model::api_tokens::Model> for ApiToken {
+    fn from(m: model::api_tokens::Model) -> Self {
+        Self {
+            id: m.id,
+            name: m.name,
+            token_prefix: m.token_prefix,
+            last_used_at: m.last_used_at,
+            created_at: m.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenParams {
+    pub name: String,
+}
+
+/// 新创建的 API 令牌, 令牌只在创建时返回一次
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub info: ApiToken,
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordParams {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+/// 修改当前用户的密码, 并退出其他会话及删除该用户的所有 API 令牌
+///
+/// 配置文件中的管理员密码以 `auth.password_hash` 为准, 重启后会被覆盖
+#[post("/api/auth/password")]
+pub async fn change_password(
+    state: web::Data<Arc<AppState>>,
+    req: HttpRequest,
+    identity: Identity,
+    params: Json<ChangePasswordParams>,
+) -> Result<Json<Resp<()>>, ServerError> {
+    let params = params.into_inner();
+    if params.new_password.is_empty() {
+        return Err(ServerError::BadRequest("新密码不能为空".to_owned()));
+    }
+    let user = state
+        .db
+        .get_user(identity.user_id)
+        .await?
+        .filter(|user| verify_password(&params.old_password, &user.password_hash));
+    if user.is_none() {
+        return Err(ServerError::Unauthorized);
+    }
+    let password_hash = hash_password(&params.new_password)?;
+    state
+        .db
+        .update_user(identity.user_id, Some(password_hash), None)
+        .await?;
+    state
+        .db
+        .revoke_user_credentials(
+            identity.user_id,
+            current_session(&req, &identity).as_deref(),
+        )
+        .await?;
+    Ok(Json(Resp::ok(())))
+}
+
+#[get("/api/auth/tokens")]
+pub async fn list_api_tokens(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+) -> Result<Json<Resp<Vec<ApiToken>>>, ServerError> {
+    let tokens = state.db.list_api_tokens(identity.user_id).await?;
+    Ok(Json(Resp::ok(tokens.into_iter().map(Into::into).collect())))
+}
+
+#[post("/api/auth/tokens")]
+pub async fn create_api_token(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    params: Json<CreateApiTokenParams>,
+) -> Result<Json<Resp<CreatedApiToken>>, ServerError> {
+    let name = params.into_inner().name.trim().to_owned();
+    if name.is_empty() {
+        return Err(ServerError::BadRequest("令牌名称不能为空".to_owned()));
+    }
+    let token = format!("{}{}", API_TOKEN_PREFIX, generate_token());
+    let token_prefix = token[..API_TOKEN_PREFIX.len() + 8].to_owned();
+    let model = state
+        .db
+        .create_api_token(identity.user_id, name, token_prefix, hash_token(&token))
+        .await?;
+    Ok(Json(Resp::ok(CreatedApiToken {
+        info: model.into(),
+        token,
+    })))
+}
+
+#[post("/api/auth/tokens/{id}/delete")]
+pub async fn delete_api_token(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    id: web::Path<i32>,
+) -> Result<Json<Resp<()>>, ServerError> {
+    state
+        .db
+        .delete_api_token(id.into_inner(), identity.user_id)
+        .await?;
+    Ok(Json(Resp::ok(())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_hash() {
+        let hash = hash_password("123456").unwrap();
+        assert!(validate_password_hash(&hash).is_ok());
+        assert!(verify_password("123456", &hash));
+        assert!(!verify_password("654321", &hash));
+        assert!(!verify_password("123456", "invalid"));
+    }
+
+    #[test]
+    fn test_requires_auth() {
+        use actix_web::test::TestRequest;
+
+        let requires_auth = |uri: &str| requires_auth(&TestRequest::with_uri(uri).to_srv_request());
+        assert!(!requires_auth("/health"));
+        assert!(!requires_auth("/api/auth/login"));
+        assert!(!requires_auth("/api/auth/status"));
+        assert!(!requires_auth("/api/auth/%73tatus"));
+        assert!(requires_auth("/api/config"));
+        assert!(requires_auth("/api/auth/tokens"));
+        assert!(requires_auth("/api/assets/poster.jpg"));
+        assert!(requires_auth("/ws"));
+        assert!(requires_auth("/index.html"));
+
+        // 编码后的路径同样需要认证
+        assert!(requires_auth("/%61pi/config"));
+        assert!(requires_auth("/ap%69/config"));
+        assert!(requires_auth("/w%73"));
+        assert!(requires_auth("/%61pi/fs/qbittorrent/a.mkv"));
+        assert!(requires_auth("/health%2F"));
+    }
+
+    #[test]
+    fn test_login_throttle() {
+        let throttle = LoginThrottle::default();
+        for _ in 0..MAX_FAILED_LOGINS - 1 {
+            throttle.record_failure("10.0.0.1", "admin");
+        }
+        assert!(!throttle.is_locked("10.0.0.1", "admin"));
+        throttle.record_failure("10.0.0.1", "admin");
+        assert!(throttle.is_locked("10.0.0.1", "admin"));
+        assert!(!throttle.is_locked("10.0.0.1", "other"));
+        // 其他客户端不受影响
+        assert!(!throttle.is_locked("10.0.0.2", "admin"));
+
+        // 锁定过期后解除
+        throttle
+            .0
+            .lock()
+            .unwrap()
+            .get_mut(&("10.0.0.1".to_owned(), "admin".to_owned()))
+            .unwrap()
+            .1 = Instant::now().checked_sub(LOGIN_LOCKOUT).unwrap();
+        assert!(!throttle.is_locked("10.0.0.1", "admin"));
+
+        throttle.record_failure("10.0.0.1", "other");
+        throttle.reset("10.0.0.1", "other");
+        assert!(throttle.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        use actix_web::test::TestRequest;
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:12345".parse().unwrap())
+            .to_http_request();
+        assert_eq!(client_ip(&req), "10.0.0.1");
+
+        let req = TestRequest::default()
+            .peer_addr("172.17.0.2:12345".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "10.0.0.2"))
+            .to_http_request();
+        assert_eq!(client_ip(&req), "10.0.0.2");
+    }
+
+    #[test]
+    fn test_extract_token() {
+        use actix_web::test::TestRequest;
+
+        let req = TestRequest::default()
+            .cookie(Cookie::new(SESSION_COOKIE, "session"))
+            .to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("session"));
+
+        let req = TestRequest::default()
+            .insert_header(("Authorization", "Bearer bgm_abc"))
+            .to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("bgm_abc"));
+
+        // 只有 WebSocket 接口接受查询参数
+        let req = TestRequest::with_uri("/ws?token=bgm_abc").to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("bgm_abc"));
+        let req = TestRequest::with_uri("/w%73?token=bgm_abc").to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("bgm_abc"));
+        let req =
+            TestRequest::with_uri("/api/fs/qbittorrent/a.mkv?token=bgm_abc").to_http_request();
+        assert!(extract_token(&req).is_none());
+
+        let req = TestRequest::default().to_http_request();
+        assert!(extract_token(&req).is_none());
+    }
+}
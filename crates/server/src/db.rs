@@ -1,14 +1,18 @@
 use crate::model::DownloadTask;
 use anyhow::Result;
+use chrono::{Local, NaiveDateTime};
 use model::{
-    bangumi, episode_download_tasks, sea_orm_active_enums::DownloadStatus, subscriptions,
-    torrent_download_tasks, torrents,
+    api_tokens, auth_sessions, bangumi, episode_download_tasks, episode_watch_states,
+    sea_orm_active_enums::{DownloadStatus, SubscribeStatus},
+    subscriptions, torrent_download_tasks, torrents, user_subscriptions, users,
 };
+use sea_orm::sea_query::OnConflict;
+use sea_orm::sqlx::sqlite::SqliteJournalMode;
 use sea_orm::{
-    ColumnTrait, ConnectOptions, Database, DatabaseConnection, EntityTrait, QueryFilter,
-    QuerySelect,
+    ActiveModelTrait, ColumnTrait, ConnectOptions, Database, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
-use std::{sync::Arc, time::Duration};
+use std::{path::Path, sync::Arc, time::Duration};
 #[derive(Clone)]
 pub struct Db {
     conn: Arc<DatabaseConnection>,
@@ -26,6 +30,15 @@ impl Db {
             .sqlx_logging(true)
             .sqlx_logging_level(tracing::log::LevelFilter::Debug);
 
+        if database_url.starts_with("sqlite:") {
+            create_sqlite_parent_dir(database_url)?;
+            opt.map_sqlx_sqlite_opts(|opts| {
+                opts.create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .busy_timeout(Duration::from_secs(5))
+            });
+        }
+
         let conn = Database::connect(opt).await?;
         Ok(Self {
             conn: Arc::new(conn),
@@ -178,6 +191,448 @@ impl Db {
     }
 }
 
+/// 认证相关
+impl Db {
+    pub async fn create_session(
+        &self,
+        token_hash: String,
+        user_id: i32,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        let now = Local::now().naive_utc();
+        auth_sessions::Entity::insert(auth_sessions::ActiveModel {
+            token_hash: Set(token_hash),
+            user_id: Set(user_id),
+            expires_at: Set(expires_at),
+            created_at: Set(now),
+        })
+        .exec(self.conn())
+        .await?;
+        Ok(())
+    }
+
+    /// 获取未过期的会话
+    pub async fn get_session(&self, token_hash: &str) -> Result<Option<auth_sessions::Model>> {
+        let now = Local::now().naive_utc();
+        let session = auth_sessions::Entity::find_by_id(token_hash)
+            .filter(auth_sessions::Column::ExpiresAt.gt(now))
+            .one(self.conn())
+            .await?;
+        Ok(session)
+    }
+
+    pub async fn delete_session(&self, token_hash: &str) -> Result<()> {
+        auth_sessions::Entity::delete_by_id(token_hash)
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_expired_sessions(&self) -> Result<u64> {
+        let now = Local::now().naive_utc();
+        let result = auth_sessions::Entity::delete_many()
+            .filter(auth_sessions::Column::ExpiresAt.lte(now))
+            .exec(self.conn())
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 删除用户的所有会话和 API 令牌, `keep_session` 指定的会话除外
+    pub async fn revoke_user_credentials(
+        &self,
+        user_id: i32,
+        keep_session: Option<&str>,
+    ) -> Result<()> {
+        let txn = self.conn().begin().await?;
+        let mut sessions =
+            auth_sessions::Entity::delete_many().filter(auth_sessions::Column::UserId.eq(user_id));
+        if let Some(token_hash) = keep_session {
+            sessions = sessions.filter(auth_sessions::Column::TokenHash.ne(token_hash));
+        }
+        sessions.exec(&txn).await?;
+        api_tokens::Entity::delete_many()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn create_api_token(
+        &self,
+        user_id: i32,
+        name: String,
+        token_prefix: String,
+        token_hash: String,
+    ) -> Result<api_tokens::Model> {
+        let now = Local::now().naive_utc();
+        let result = api_tokens::Entity::insert(api_tokens::ActiveModel {
+            name: Set(name.clone()),
+            token_prefix: Set(token_prefix.clone()),
+            token_hash: Set(token_hash.clone()),
+            last_used_at: Set(None),
+            created_at: Set(now),
+            user_id: Set(user_id),
+            ..Default::default()
+        })
+        .exec(self.conn())
+        .await?;
+        Ok(api_tokens::Model {
+            id: result.last_insert_id,
+            name,
+            token_prefix,
+            token_hash,
+            last_used_at: None,
+            created_at: now,
+            user_id,
+        })
+    }
+
+    pub async fn list_api_tokens(&self, user_id: i32) -> Result<Vec<api_tokens::Model>> {
+        let tokens = api_tokens::Entity::find()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .order_by_desc(api_tokens::Column::CreatedAt)
+            .all(self.conn())
+            .await?;
+        Ok(tokens)
+    }
+
+    pub async fn get_api_token(&self, token_hash: &str) -> Result<Option<api_tokens::Model>> {
+        let token = api_tokens::Entity::find()
+            .filter(api_tokens::Column::TokenHash.eq(token_hash))
+            .one(self.conn())
+            .await?;
+        Ok(token)
+    }
+
+    pub async fn update_api_token_used(&self, id: i32, time: NaiveDateTime) -> Result<()> {
+        api_tokens::Entity::update_many()
+            .filter(api_tokens::Column::Id.eq(id))
+            .set(api_tokens::ActiveModel {
+                last_used_at: Set(Some(time)),
+                ..Default::default()
+            })
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+
+    /// 删除用户自己的 API 令牌
+    pub async fn delete_api_token(&self, id: i32, user_id: i32) -> Result<()> {
+        api_tokens::Entity::delete_many()
+            .filter(api_tokens::Column::Id.eq(id))
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 用户相关
+impl Db {
+    pub async fn get_user(&self, id: i32) -> Result<Option<users::Model>> {
+        let user = users::Entity::find_by_id(id).one(self.conn()).await?;
+        Ok(user)
+    }
+
+    pub async fn get_user_by_username(&self, username: &str) -> Result<Option<users::Model>> {
+        let user = users::Entity::find()
+            .filter(users::Column::Username.eq(username))
+            .one(self.conn())
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn list_users(&self) -> Result<Vec<users::Model>> {
+        let users = users::Entity::find()
+            .order_by_asc(users::Column::Id)
+            .all(self.conn())
+            .await?;
+        Ok(users)
+    }
+
+    pub async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+        is_admin: bool,
+    ) -> Result<users::Model> {
+        let now = Local::now().naive_utc();
+        let result = users::Entity::insert(users::ActiveModel {
+            username: Set(username.clone()),
+            password_hash: Set(password_hash.clone()),
+            is_admin: Set(is_admin),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .exec(self.conn())
+        .await?;
+        Ok(users::Model {
+            id: result.last_insert_id,
+            username,
+            password_hash,
+            is_admin,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// 更新用户的密码或管理员权限, 为空的字段保持不变
+    pub async fn update_user(
+        &self,
+        id: i32,
+        password_hash: Option<String>,
+        is_admin: Option<bool>,
+    ) -> Result<()> {
+        let mut user = users::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        };
+        if let Some(password_hash) = password_hash {
+            user.password_hash = Set(password_hash);
+        }
+        if let Some(is_admin) = is_admin {
+            user.is_admin = Set(is_admin);
+        }
+        if !user.is_changed() {
+            return Ok(());
+        }
+        users::Entity::update_many()
+            .filter(users::Column::Id.eq(id))
+            .set(user)
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+
+    /// 删除用户及其会话, API 令牌, 订阅和观看记录, 返回该用户订阅过的番剧
+    pub async fn delete_user(&self, user: &users::Model) -> Result<Vec<i32>> {
+        let txn = self.conn().begin().await?;
+        let bangumi_ids = user_subscriptions::Entity::find()
+            .select_only()
+            .column(user_subscriptions::Column::BangumiId)
+            .filter(user_subscriptions::Column::UserId.eq(user.id))
+            .into_tuple::<i32>()
+            .all(&txn)
+            .await?;
+        user_subscriptions::Entity::delete_many()
+            .filter(user_subscriptions::Column::UserId.eq(user.id))
+            .exec(&txn)
+            .await?;
+        episode_watch_states::Entity::delete_many()
+            .filter(episode_watch_states::Column::UserId.eq(user.id))
+            .exec(&txn)
+            .await?;
+        api_tokens::Entity::delete_many()
+            .filter(api_tokens::Column::UserId.eq(user.id))
+            .exec(&txn)
+            .await?;
+        auth_sessions::Entity::delete_many()
+            .filter(auth_sessions::Column::UserId.eq(user.id))
+            .exec(&txn)
+            .await?;
+        users::Entity::delete_by_id(user.id).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(bangumi_ids)
+    }
+
+    /// 确保配置文件中的管理员账号存在, 并同步密码
+    ///
+    /// 首次创建用户时, 已有的订阅和 API 令牌会归属到该管理员
+    pub async fn ensure_admin_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> Result<users::Model> {
+        if let Some(user) = self.get_user_by_username(username).await? {
+            let password_hash = (!password_hash.is_empty() && user.password_hash != password_hash)
+                .then(|| password_hash.to_owned());
+            self.update_user(user.id, password_hash, Some(true)).await?;
+            return Ok(self.get_user(user.id).await?.unwrap_or(user));
+        }
+
+        let txn = self.conn().begin().await?;
+        let is_first = users::Entity::find().one(&txn).await?.is_none();
+        let now = Local::now().naive_utc();
+        let result = users::Entity::insert(users::ActiveModel {
+            username: Set(username.to_owned()),
+            password_hash: Set(password_hash.to_owned()),
+            is_admin: Set(true),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await?;
+        let user_id = result.last_insert_id;
+
+        if is_first {
+            let subscriptions = subscriptions::Entity::find()
+                .filter(subscriptions::Column::SubscribeStatus.ne(SubscribeStatus::None))
+                .all(&txn)
+                .await?;
+            let models = subscriptions
+                .into_iter()
+                .map(|sub| user_subscriptions::ActiveModel {
+                    user_id: Set(user_id),
+                    bangumi_id: Set(sub.bangumi_id),
+                    start_episode_number: Set(sub.start_episode_number),
+                    resolution_filter: Set(sub.resolution_filter),
+                    language_filter: Set(sub.language_filter),
+                    release_group_filter: Set(sub.release_group_filter),
+                    created_at: Set(sub.created_at),
+                    updated_at: Set(now),
+                })
+                .collect::<Vec<_>>();
+            if !models.is_empty() {
+                user_subscriptions::Entity::insert_many(models)
+                    .exec(&txn)
+                    .await?;
+            }
+            api_tokens::Entity::update_many()
+                .filter(api_tokens::Column::UserId.eq(0))
+                .set(api_tokens::ActiveModel {
+                    user_id: Set(user_id),
+                    ..Default::default()
+                })
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+
+        Ok(users::Model {
+            id: user_id,
+            username: username.to_owned(),
+            password_hash: password_hash.to_owned(),
+            is_admin: true,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// 获取订阅了番剧的所有用户的订阅记录
+    pub async fn list_user_subscriptions(
+        &self,
+        bangumi_id: i32,
+    ) -> Result<Vec<user_subscriptions::Model>> {
+        let subscriptions = user_subscriptions::Entity::find()
+            .filter(user_subscriptions::Column::BangumiId.eq(bangumi_id))
+            .order_by_asc(user_subscriptions::Column::UserId)
+            .all(self.conn())
+            .await?;
+        Ok(subscriptions)
+    }
+
+    pub async fn upsert_user_subscription(
+        &self,
+        user_id: i32,
+        bangumi_id: i32,
+        start_episode_number: Option<i32>,
+        resolution_filter: Option<String>,
+        language_filter: Option<String>,
+        release_group_filter: Option<String>,
+    ) -> Result<()> {
+        use user_subscriptions::Column;
+
+        let now = Local::now().naive_utc();
+        user_subscriptions::Entity::insert(user_subscriptions::ActiveModel {
+            user_id: Set(user_id),
+            bangumi_id: Set(bangumi_id),
+            start_episode_number: Set(start_episode_number),
+            resolution_filter: Set(resolution_filter),
+            language_filter: Set(language_filter),
+            release_group_filter: Set(release_group_filter),
+            created_at: Set(now),
+            updated_at: Set(now),
+        })
+        .on_conflict(
+            OnConflict::columns([Column::UserId, Column::BangumiId])
+                .update_columns([
+                    Column::StartEpisodeNumber,
+                    Column::ResolutionFilter,
+                    Column::LanguageFilter,
+                    Column::ReleaseGroupFilter,
+                    Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(self.conn())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_user_subscription(&self, user_id: i32, bangumi_id: i32) -> Result<()> {
+        user_subscriptions::Entity::delete_by_id((user_id, bangumi_id))
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+
+    /// 标记剧集为已看或未看
+    pub async fn set_episode_watched(
+        &self,
+        user_id: i32,
+        bangumi_id: i32,
+        episode_numbers: &[i32],
+        watched: bool,
+    ) -> Result<()> {
+        use episode_watch_states::Column;
+
+        if episode_numbers.is_empty() {
+            return Ok(());
+        }
+        if !watched {
+            episode_watch_states::Entity::delete_many()
+                .filter(Column::UserId.eq(user_id))
+                .filter(Column::BangumiId.eq(bangumi_id))
+                .filter(Column::EpisodeNumber.is_in(episode_numbers.iter().copied()))
+                .exec(self.conn())
+                .await?;
+            return Ok(());
+        }
+
+        let now = Local::now().naive_utc();
+        let models = episode_numbers
+            .iter()
+            .map(|&episode_number| episode_watch_states::ActiveModel {
+                user_id: Set(user_id),
+                bangumi_id: Set(bangumi_id),
+                episode_number: Set(episode_number),
+                watched_at: Set(now),
+            })
+            .collect::<Vec<_>>();
+        episode_watch_states::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::columns([Column::UserId, Column::BangumiId, Column::EpisodeNumber])
+                    .update_column(Column::WatchedAt)
+                    .to_owned(),
+            )
+            .exec(self.conn())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 创建 SQLite 数据库文件所在的目录, 例如 `sqlite://data/bangumi.db` 中的 `data`
+fn create_sqlite_parent_dir(database_url: &str) -> Result<()> {
+    let path = database_url
+        .trim_start_matches("sqlite:")
+        .trim_start_matches("//");
+    let path = path.split('?').next().unwrap_or_default();
+    if path.is_empty() || path.starts_with(':') {
+        // 内存数据库
+        return Ok(());
+    }
+    if let Some(parent) = Path::new(path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +653,94 @@ mod tests {
         println!("{:?}", downloads);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_sqlite_query_downloads_info() -> Result<()> {
+        let db = Db::new("sqlite::memory:").await?;
+        model::migrator::migrate(db.conn()).await?;
+        let downloads = db.query_downloads_info(0, 10, None).await?;
+        assert!(downloads.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_users() -> Result<()> {
+        let db = Db::new("sqlite::memory:").await?;
+        model::migrator::migrate(db.conn()).await?;
+
+        let admin = db.ensure_admin_user("admin", "hash").await?;
+        assert!(admin.is_admin);
+        // 重复调用时只同步密码
+        let admin = db.ensure_admin_user("admin", "new_hash").await?;
+        assert_eq!(admin.password_hash, "new_hash");
+        assert_eq!(db.list_users().await?.len(), 1);
+
+        let user = db
+            .create_user("user".to_owned(), "".to_owned(), false)
+            .await?;
+        db.create_api_token(
+            user.id,
+            "token".to_owned(),
+            "bgm_".to_owned(),
+            "h".to_owned(),
+        )
+        .await?;
+        assert_eq!(db.list_api_tokens(user.id).await?.len(), 1);
+        assert!(db.list_api_tokens(admin.id).await?.is_empty());
+
+        db.set_episode_watched(user.id, 1, &[1, 2], true).await?;
+        db.set_episode_watched(user.id, 1, &[2], true).await?;
+        db.set_episode_watched(user.id, 1, &[1], false).await?;
+        let watched = episode_watch_states::Entity::find().all(db.conn()).await?;
+        assert_eq!(watched.len(), 1);
+        assert_eq!(watched[0].episode_number, 2);
+
+        db.delete_user(&user).await?;
+        assert!(db.get_user(user.id).await?.is_none());
+        assert!(db.get_api_token("h").await?.is_none());
+        assert!(
+            episode_watch_states::Entity::find()
+                .all(db.conn())
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_revoke_user_credentials() -> Result<()> {
+        let db = Db::new("sqlite::memory:").await?;
+        model::migrator::migrate(db.conn()).await?;
+
+        let admin = db.ensure_admin_user("admin", "hash").await?;
+        let user = db
+            .create_user("user".to_owned(), "".to_owned(), false)
+            .await?;
+        let expires_at = Local::now().naive_utc() + chrono::Duration::days(1);
+        for (token_hash, user_id) in [("a", user.id), ("b", user.id), ("c", admin.id)] {
+            db.create_session(token_hash.to_owned(), user_id, expires_at)
+                .await?;
+        }
+        for (token_hash, user_id) in [("d", user.id), ("e", admin.id)] {
+            db.create_api_token(
+                user_id,
+                "token".to_owned(),
+                "bgm_".to_owned(),
+                token_hash.to_owned(),
+            )
+            .await?;
+        }
+
+        // 只保留当前会话, 其他用户不受影响
+        db.revoke_user_credentials(user.id, Some("a")).await?;
+        assert!(db.get_session("a").await?.is_some());
+        assert!(db.get_session("b").await?.is_none());
+        assert!(db.get_session("c").await?.is_some());
+        assert!(db.list_api_tokens(user.id).await?.is_empty());
+        assert_eq!(db.list_api_tokens(admin.id).await?.len(), 1);
+
+        db.revoke_user_credentials(user.id, None).await?;
+        assert!(db.get_session("a").await?.is_none());
+        Ok(())
+    }
 }
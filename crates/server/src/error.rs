@@ -7,6 +7,18 @@ pub enum ServerError {
     #[error("bangumi not found")]
     BangumiNotFound,
 
+    #[error("用户名或密码错误")]
+    Unauthorized,
+
+    #[error("没有权限执行该操作")]
+    Forbidden,
+
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("登录失败次数过多, 请稍后再试")]
+    TooManyRequests,
+
     #[error("actix error: {0}")]
     ActixError(#[from] actix_web::Error),
 
@@ -25,7 +37,23 @@ pub enum ServerError {
 
 impl ResponseError for ServerError {
     fn error_response(&self) -> HttpResponse {
-        let resp: Resp<()> = Resp::err_msg(self.to_string());
-        HttpResponse::Ok().json(resp)
+        match self {
+            ServerError::Unauthorized => {
+                HttpResponse::Unauthorized().json(Resp::<()>::err(401, self.to_string()))
+            }
+            ServerError::Forbidden => {
+                HttpResponse::Forbidden().json(Resp::<()>::err(403, self.to_string()))
+            }
+            ServerError::BadRequest(_) => {
+                HttpResponse::BadRequest().json(Resp::<()>::err(400, self.to_string()))
+            }
+            ServerError::TooManyRequests => {
+                HttpResponse::TooManyRequests().json(Resp::<()>::err(429, self.to_string()))
+            }
+            _ => {
+                let resp: Resp<()> = Resp::err_msg(self.to_string());
+                HttpResponse::Ok().json(resp)
+            }
+        }
     }
 }
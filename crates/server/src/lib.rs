@@ -1,13 +1,16 @@
 #![deny(clippy::unused_async)]
 pub mod api;
+pub mod auth;
 pub mod config;
 pub mod db;
 pub mod error;
 mod logger;
 pub mod model;
 mod router;
+pub mod secret;
 pub mod server;
 pub mod tracing;
+pub mod users;
 pub mod ws;
 
 pub mod built_info {
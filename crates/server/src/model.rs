@@ -1,7 +1,9 @@
 use chrono::{NaiveDate, NaiveDateTime};
+use downloader::dlrs::SelectStrategy;
 use model::sea_orm_active_enums::{
     BgmKind, DownloadStatus, Kind, ParserStatus, State, SubscribeStatus,
 };
+use scheduler::{FilterRule, TorrentScore};
 use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
 
@@ -44,7 +46,7 @@ pub struct Bangumi {
     pub name: String,
     pub description: Option<String>,
     pub bangumi_tv_id: Option<i32>,
-    pub tmdb_id: Option<u64>,
+    pub tmdb_id: Option<i64>,
     pub mikan_id: Option<i32>,
     pub poster_image_url: Option<String>,
     pub air_date: Option<NaiveDateTime>,
@@ -54,7 +56,7 @@ pub struct Bangumi {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub backdrop_image_url: Option<String>,
-    pub season_number: Option<u64>,
+    pub season_number: Option<i64>,
     pub subscribe_status: Option<SubscribeStatus>,
     pub start_episode_number: Option<i32>,
     pub resolution_filter: Option<String>,
@@ -64,6 +66,12 @@ pub struct Bangumi {
     pub enforce_torrent_release_after_broadcast: Option<bool>,
     pub preferred_downloader: Option<String>,
     pub allow_fallback: Option<bool>,
+    pub filter_rules: Option<String>,
+    pub score_profile: Option<String>,
+    pub auto_upgrade: Option<bool>,
+    pub download_strategy: Option<String>,
+    /// 当前用户订阅的时间, 未订阅时为空
+    pub subscribed_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromQueryResult)]
@@ -76,14 +84,18 @@ pub struct Episode {
     pub image_url: Option<String>,
     pub description: Option<String>,
     pub air_date: Option<NaiveDate>,
-    pub duration_seconds: Option<u64>,
+    pub duration_seconds: Option<i64>,
     pub kind: Kind,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub download_state: Option<State>,
     pub ref_torrent_info_hash: Option<String>,
+    /// 洗版中的种子
+    pub upgrade_info_hash: Option<String>,
     pub task_created_at: Option<NaiveDateTime>,
     pub task_updated_at: Option<NaiveDateTime>,
+    /// 当前用户标记为已看的时间, 未看时为空
+    pub watched_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromQueryResult)]
@@ -94,6 +106,7 @@ pub struct Torrent {
     pub size: i64,
     pub magnet: String,
     pub pub_date: NaiveDateTime,
+    pub seeders: Option<i32>,
 
     // 文件名解析信息
     pub release_group: Option<String>,
@@ -109,6 +122,15 @@ pub struct Torrent {
     pub task_created_at: Option<NaiveDateTime>,
 }
 
+/// 种子信息及其评分
+#[derive(Debug, Serialize)]
+pub struct ScoredTorrent {
+    #[serde(flatten)]
+    pub torrent: Torrent,
+    /// 评分结果, 没有完成解析的种子为空
+    pub score: Option<TorrentScore>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubscribeParams {
     pub status: SubscribeStatus,
@@ -123,6 +145,17 @@ pub struct SubscribeParams {
     pub preferred_downloader: Option<String>,
     #[serde(default)]
     pub allow_fallback: bool,
+    /// 自定义 RSS 源, 不传时保留已有的 RSS 源, 传入空列表时清除
+    pub feeds: Option<Vec<String>>,
+    /// 种子过滤规则, 不传时保留已有的规则, 传入不包含任何规则的 `all` 时清除
+    pub filter_rules: Option<FilterRule>,
+    /// 评分配置中需要覆盖全局配置的字段, 不传时保留已有的配置, 传入空对象时清除
+    pub score_profile: Option<serde_json::Map<String, serde_json::Value>>,
+    /// 已下载的剧集出现评分更高的种子时自动洗版
+    #[serde(default)]
+    pub auto_upgrade: bool,
+    /// 未指定下载器时的下载器选择策略, 为空时使用全局配置
+    pub download_strategy: Option<SelectStrategy>,
 }
 
 // 定义一个结构体来接收查询结果
@@ -172,10 +205,10 @@ pub struct TMDBSeason {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateMDBParams {
     pub bangumi_id: i32,
-    pub tmdb_id: Option<u64>,
+    pub tmdb_id: Option<i64>,
     pub mikan_id: Option<i32>,
     pub bangumi_tv_id: Option<i32>,
-    pub season_number: Option<u64>,
+    pub season_number: Option<i64>,
     pub kind: BgmKind,
 }
 
@@ -226,7 +259,7 @@ pub struct AddBangumiParams {
     pub title: String,
     pub mikan_id: i32,
     pub bgm_tv_id: Option<i32>,
-    pub tmdb_id: Option<u64>,
+    pub tmdb_id: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -1,4 +1,4 @@
-use crate::{api, server::AppState, ws::ws_handler};
+use crate::{api, auth, server::AppState, users, ws::ws_handler};
 use actix_files::Files;
 use actix_web::web;
 use std::sync::Arc;
@@ -6,6 +6,9 @@ use std::sync::Arc;
 pub const ASSETS_MOUNT_PATH: &str = "/api/assets";
 pub const QBITTORRENT_MOUNT_PATH: &str = "/api/fs/qbittorrent";
 pub const TRANSMISSION_MOUNT_PATH: &str = "/api/fs/transmission";
+pub const ARIA2_MOUNT_PATH: &str = "/api/fs/aria2";
+pub const DELUGE_MOUNT_PATH: &str = "/api/fs/deluge";
+pub const WATCH_FOLDER_MOUNT_PATH: &str = "/api/fs/watch_folder";
 
 pub fn configure_app(cfg: &mut web::ServiceConfig, state: Arc<AppState>) {
     cfg.app_data(web::Data::new(state.clone()))
@@ -19,8 +22,10 @@ pub fn configure_app(cfg: &mut web::ServiceConfig, state: Arc<AppState>) {
         .service(api::get_bangumi_by_id)
         .service(api::get_bangumi_episodes_by_id)
         .service(api::subscribe_bangumi)
+        .service(api::list_subscription_feeds)
         .service(api::get_bangumi_torrents_by_id)
         .service(api::refresh_bangumi)
+        .service(api::regenerate_sidecars)
         .service(api::online_watch)
         .service(api::delete_bangumi_download_tasks)
         .service(api::list_download_tasks)
@@ -41,7 +46,19 @@ pub fn configure_app(cfg: &mut web::ServiceConfig, state: Arc<AppState>) {
         .service(api::list_download_files)
         .service(api::list_downloaders)
         .service(api::get_bangumi_release_groups)
-        .route("/ws", web::get().to(ws_handler));
+        .service(auth::login)
+        .service(auth::logout)
+        .service(auth::status)
+        .service(auth::list_api_tokens)
+        .service(auth::create_api_token)
+        .service(auth::delete_api_token)
+        .service(auth::change_password)
+        .service(users::list_users)
+        .service(users::create_user)
+        .service(users::update_user)
+        .service(users::delete_user)
+        .service(users::mark_watched)
+        .route(auth::WS_PATH, web::get().to(ws_handler));
 
     let config = state.config.read().unwrap();
     if config.downloader.qbittorrent.enabled {
@@ -62,4 +79,32 @@ pub fn configure_app(cfg: &mut web::ServiceConfig, state: Arc<AppState>) {
             );
         }
     }
+    if config.downloader.aria2.enabled {
+        if let Some(mount_path) = &config.downloader.aria2.mount_path {
+            cfg.service(
+                Files::new(ARIA2_MOUNT_PATH, mount_path.clone())
+                    .show_files_listing()
+                    .prefer_utf8(true),
+            );
+        }
+    }
+    if config.downloader.deluge.enabled {
+        if let Some(mount_path) = &config.downloader.deluge.mount_path {
+            cfg.service(
+                Files::new(DELUGE_MOUNT_PATH, mount_path.clone())
+                    .show_files_listing()
+                    .prefer_utf8(true),
+            );
+        }
+    }
+    if config.downloader.watch_folder.enabled {
+        cfg.service(
+            Files::new(
+                WATCH_FOLDER_MOUNT_PATH,
+                config.downloader.watch_folder.generic.download_dir.clone(),
+            )
+            .show_files_listing()
+            .prefer_utf8(true),
+        );
+    }
 }
@@ -0,0 +1,239 @@
+use std::{fmt, sync::Mutex};
+
+use anyhow::{Context, Result};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// 返回给客户端时替换敏感字段的占位符, 提交配置时保持占位符不变表示不修改
+pub const REDACTED: &str = "******";
+
+/// 加密配置文件中敏感字段使用的密钥所在的环境变量
+pub const SECRET_KEY_ENV: &str = "BANGUMI_SECRET_KEY";
+
+/// 从环境变量读取, 例如 `env:TELEGRAM_TOKEN`
+const ENV_PREFIX: &str = "env:";
+/// 从文件读取, 例如 `file:/run/secrets/qb_password`
+const FILE_PREFIX: &str = "file:";
+/// 使用 [`SECRET_KEY_ENV`] 加密后的值
+const ENC_PREFIX: &str = "enc:";
+
+/// 配置中的敏感字段, 如密码, Cookie 和 API Key
+///
+/// 配置文件中保存的是原始值, 可以是明文, `env:`/`file:` 引用或 `enc:` 密文,
+/// 使用时通过 [`Secret::expose`] 解析出真正的值
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({:?})", self.redacted().0)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl Secret {
+    /// 配置文件中的原始值
+    pub fn raw(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 是否为客户端提交的占位符
+    pub fn is_redacted(&self) -> bool {
+        self.0 == REDACTED
+    }
+
+    /// `env:` 和 `file:` 只是引用, 本身不是敏感信息
+    fn is_reference(&self) -> bool {
+        self.0.starts_with(ENV_PREFIX) || self.0.starts_with(FILE_PREFIX)
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.0.starts_with(ENC_PREFIX)
+    }
+
+    /// 解析出真正的值, 密文使用环境变量 [`SECRET_KEY_ENV`] 中的密钥解密
+    pub fn expose(&self) -> Result<String> {
+        self.expose_with(SecretKey::from_env().as_ref())
+    }
+
+    fn expose_with(&self, key: Option<&SecretKey>) -> Result<String> {
+        if self.is_redacted() {
+            return Err(anyhow::anyhow!("值为占位符 {}, 请重新填写", REDACTED));
+        }
+        if let Some(name) = self.0.strip_prefix(ENV_PREFIX) {
+            return std::env::var(name).with_context(|| format!("读取环境变量 {} 失败", name));
+        }
+        if let Some(path) = self.0.strip_prefix(FILE_PREFIX) {
+            let value =
+                std::fs::read_to_string(path).with_context(|| format!("读取文件 {} 失败", path))?;
+            return Ok(value.trim_end_matches(['\r', '\n']).to_owned());
+        }
+        if let Some(ciphertext) = self.0.strip_prefix(ENC_PREFIX) {
+            let key = key
+                .ok_or_else(|| anyhow::anyhow!("值已加密, 但未设置环境变量 {}", SECRET_KEY_ENV))?;
+            return key.decrypt(ciphertext);
+        }
+        Ok(self.0.clone())
+    }
+
+    /// 返回给客户端的值, 空值和 `env:`/`file:` 引用保持原样, 其余替换为占位符
+    pub fn redacted(&self) -> Secret {
+        if self.is_empty() || self.is_reference() {
+            self.clone()
+        } else {
+            Secret(REDACTED.to_owned())
+        }
+    }
+
+    /// 加密明文, 空值, 引用和已经加密的值保持原样
+    pub fn encrypt(&self, key: &SecretKey) -> Result<Secret> {
+        if self.is_empty() || self.is_reference() || self.is_encrypted() || self.is_redacted() {
+            return Ok(self.clone());
+        }
+        Ok(Secret(format!("{}{}", ENC_PREFIX, key.encrypt(&self.0)?)))
+    }
+}
+
+/// 派生密钥使用的固定盐值, 同一口令在不同机器上派生出相同的密钥
+const KEY_SALT: &[u8] = b"bangumi-rs secret key";
+
+/// 从环境变量派生的密钥, 以口令的 SHA-256 为键缓存, 避免每次读取敏感字段都重新派生
+static ENV_KEY: Mutex<Option<([u8; 32], [u8; 32])>> = Mutex::new(None);
+
+/// 加密敏感字段的密钥, 由环境变量中的口令经 argon2id 派生
+pub struct SecretKey(LessSafeKey);
+
+impl SecretKey {
+    pub fn new(passphrase: &str) -> Self {
+        Self::from_bytes(&Self::derive(passphrase))
+    }
+
+    fn derive(passphrase: &str) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), KEY_SALT, &mut key)
+            .expect("默认参数及固定盐值的 argon2 不会失败");
+        key
+    }
+
+    fn from_bytes(key: &[u8; 32]) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, key).expect("密钥长度与 AES-256 一致");
+        Self(LessSafeKey::new(key))
+    }
+
+    /// 未设置环境变量 [`SECRET_KEY_ENV`] 时不加密
+    pub fn from_env() -> Option<Self> {
+        let passphrase = std::env::var(SECRET_KEY_ENV)
+            .ok()
+            .filter(|passphrase| !passphrase.is_empty())?;
+        let digest: [u8; 32] = Sha256::digest(passphrase.as_bytes()).into();
+        let mut cache = ENV_KEY.lock().unwrap();
+        let key = match *cache {
+            Some((cached, key)) if cached == digest => key,
+            _ => {
+                let key = Self::derive(&passphrase);
+                *cache = Some((digest, key));
+                key
+            }
+        };
+        Some(Self::from_bytes(&key))
+    }
+
+    /// 使用 AES-256-GCM 加密, 结果为 base64 编码的 nonce + 密文
+    fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let mut buf = plaintext.as_bytes().to_vec();
+        self.0
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buf)
+            .map_err(|_| anyhow::anyhow!("加密失败"))?;
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&buf);
+        Ok(STANDARD.encode(data))
+    }
+
+    fn decrypt(&self, ciphertext: &str) -> Result<String> {
+        let data = STANDARD
+            .decode(ciphertext)
+            .context("密文不是有效的 base64")?;
+        if data.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("密文长度无效"));
+        }
+        let (nonce, buf) = data.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow::anyhow!("密文长度无效"))?;
+        let mut buf = buf.to_vec();
+        let plaintext = self
+            .0
+            .open_in_place(nonce, Aad::empty(), &mut buf)
+            .map_err(|_| anyhow::anyhow!("解密失败, 请检查 {} 是否正确", SECRET_KEY_ENV))?;
+        Ok(String::from_utf8(plaintext.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret() {
+        let key = SecretKey::new("passphrase");
+        let secret = Secret::from("password");
+        assert_eq!(secret.redacted().raw(), REDACTED);
+        assert_eq!(format!("{:?}", secret), format!("Secret({:?})", REDACTED));
+
+        // 加密后可以解密, 且每次加密的结果不同
+        let encrypted = secret.encrypt(&key).unwrap();
+        assert!(encrypted.raw().starts_with(ENC_PREFIX));
+        assert_ne!(encrypted, secret.encrypt(&key).unwrap());
+        assert_eq!(encrypted.encrypt(&key).unwrap(), encrypted);
+        assert_eq!(encrypted.expose_with(Some(&key)).unwrap(), "password");
+        // 同一口令派生出相同的密钥
+        assert_eq!(
+            encrypted
+                .expose_with(Some(&SecretKey::new("passphrase")))
+                .unwrap(),
+            "password"
+        );
+        assert!(encrypted.expose_with(None).is_err());
+        assert!(
+            encrypted
+                .expose_with(Some(&SecretKey::new("wrong")))
+                .is_err()
+        );
+
+        // 引用不加密也不隐藏
+        let env = Secret::from("env:PATH");
+        assert_eq!(env.redacted(), env);
+        assert_eq!(env.encrypt(&key).unwrap(), env);
+        assert_eq!(env.expose().unwrap(), std::env::var("PATH").unwrap());
+
+        let path = std::env::temp_dir().join("bangumi_secret_test");
+        std::fs::write(&path, "from_file\n").unwrap();
+        let file = Secret::from(format!("file:{}", path.display()));
+        assert_eq!(file.expose().unwrap(), "from_file");
+        std::fs::remove_file(path).unwrap();
+
+        assert!(Secret::from("env:BANGUMI_NOT_EXISTS").expose().is_err());
+        assert!(Secret::from(REDACTED).expose().is_err());
+        assert_eq!(Secret::default().redacted(), Secret::default());
+    }
+}
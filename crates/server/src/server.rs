@@ -1,24 +1,28 @@
 use actix_cors::Cors;
-use actix_web::{App, HttpServer};
+use actix_web::{App, HttpServer, middleware};
 use core::panic;
 use dict::DictCode;
-use metadata::providers::mikan::MikanProvider;
+use metadata::TorrentProvider;
+use metadata::providers::{
+    acgrip::AcgripProvider, dmhy::DmhyProvider, mikan::MikanProvider, nyaa::NyaaProvider,
+    torznab::TorznabProvider,
+};
 use parser::Parser;
 use reqwest::Url;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use std::{net::SocketAddr, path::PathBuf, str::FromStr};
 use tokio::sync::broadcast;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_actix_web::TracingLogger;
 
 use crate::config::{Config, Writer};
 use crate::logger::{LogMessage, init_logger};
-use crate::router;
+use crate::{auth, router};
 use anyhow::Result;
 use downloader::ThirdPartyDownloader;
 use mikan::client::Client;
-use sea_orm_migration::MigratorTrait;
 #[derive(Clone)]
 pub struct AppState {
     pub scheduler: scheduler::Scheduler,
@@ -30,6 +34,7 @@ pub struct AppState {
     pub config_writer: Arc<Box<dyn Writer>>,
     pub config: Arc<RwLock<Config>>,
     pub sentry_guard: Arc<Option<sentry::ClientInitGuard>>,
+    pub login_throttle: crate::auth::LoginThrottle,
 }
 
 pub struct Server {
@@ -54,7 +59,7 @@ impl Server {
             let release = format!("{}-{}", git_verison, commit);
 
             Some(sentry::init((
-                config.sentry.dsn.as_str(),
+                config.sentry.dsn.expose()?.as_str(),
                 sentry::ClientOptions {
                     release: Some(Cow::Owned(release)),
                     environment: Some(Cow::Borrowed("development")),
@@ -81,10 +86,14 @@ impl Server {
         info!("构建时间: {}", crate::built_info::BUILT_TIME_UTC);
 
         // Database
-        let db = crate::db::Db::new(&config.server.database_url).await?;
+        let db = crate::db::Db::new(&config.server.database_url.expose()?).await?;
 
         // Execute migrations
-        model::migrator::Migrator::up(db.conn(), None).await?;
+        model::migrator::migrate(db.conn()).await?;
+
+        // 确保配置文件中的管理员账号存在, 未启用认证时所有请求都使用该账号
+        db.ensure_admin_user(&config.auth.username, &config.auth.password_hash.expose()?)
+            .await?;
 
         // HTTP Client
         let client = if config.proxy.enabled {
@@ -104,7 +113,7 @@ impl Server {
         // TMDB
         let tmdb = tmdb::client::Client::new(
             client.clone(),
-            config.tmdb.api_key.as_str(),
+            config.tmdb.api_key.expose()?.as_str(),
             config.tmdb.base_url.as_str(),
             config.tmdb.image_base_url.as_str(),
             config.tmdb.language.as_str(),
@@ -126,7 +135,7 @@ impl Server {
             notify_worker.add_notifier(Box::new(
                 notify::telegram::TelegramNotifier::new_with_client(
                     client.clone(),
-                    &config.notify.telegram.token,
+                    &config.notify.telegram.token.expose()?,
                     &config.notify.telegram.chat_id,
                 )?,
             ));
@@ -134,7 +143,62 @@ impl Server {
         notify_worker.spawn().await?;
 
         // Torrent Providers
-        let mikan_provider = MikanProvider::new(mikan.clone());
+        let mut providers: Vec<Box<dyn TorrentProvider>> =
+            vec![Box::new(MikanProvider::new(mikan.clone(), db.conn_pool()))];
+        if config.nyaa.enabled {
+            let nyaa =
+                nyaa::client::Client::new_with_client(client.clone(), &config.nyaa.endpoint)?;
+            providers.push(Box::new(NyaaProvider::new(
+                nyaa,
+                config.nyaa.category.clone(),
+                config.nyaa.filter.try_into()?,
+            )));
+        }
+        if config.dmhy.enabled {
+            let dmhy = dmhy::client::Client::new_with_client(
+                client.clone(),
+                &config.dmhy.endpoint,
+                Some(dmhy::client::RateLimitConfig {
+                    max_requests_per_second: config.dmhy.max_requests_per_second,
+                }),
+            )?;
+            providers.push(Box::new(DmhyProvider::new(
+                dmhy,
+                config.dmhy.sort_id,
+                config.dmhy.team_ids.clone(),
+                config.dmhy.release_groups.clone(),
+            )));
+        }
+        if config.acgrip.enabled {
+            let acgrip = acgrip::client::Client::new_with_client(
+                client.clone(),
+                &config.acgrip.endpoint,
+                Some(acgrip::client::RateLimitConfig {
+                    max_requests_per_second: config.acgrip.max_requests_per_second,
+                }),
+            )?;
+            providers.push(Box::new(AcgripProvider::new(
+                acgrip,
+                config.acgrip.max_items,
+            )));
+        }
+        for rss in config.providers.rss.iter().filter(|rss| rss.enabled) {
+            let torznab = torznab::client::Client::new_with_client(
+                client.clone(),
+                &rss.url,
+                rss.kind.parse()?,
+                Some(rss.api_key.expose()?),
+                rss.categories.clone(),
+                Some(torznab::client::RateLimitConfig {
+                    max_requests_per_second: rss.max_requests_per_second,
+                }),
+            )?;
+            providers.push(Box::new(TorznabProvider::new(
+                rss.name.clone(),
+                torznab,
+                rss.query_template.clone(),
+            )));
+        }
 
         // Metadata Worker
         let mut metadata_worker = metadata::worker::Worker::new_with_conn(
@@ -144,12 +208,12 @@ impl Server {
             metadata::fetcher::Fetcher::new(tmdb, bgm_tv, mikan.clone(), client.clone()),
             dict.clone(),
             config.server.assets_path.clone(),
-            Arc::new(vec![Box::new(mikan_provider)]),
+            Arc::new(providers),
         )?;
         metadata_worker.spawn()?;
 
         // Parser worker
-        let parser_impl = Self::create_parser(config, client.clone());
+        let parser_impl = Self::create_parser(config, client.clone())?;
         let mut parser_worker = parser::worker::Worker::new_with_conn(db.conn_pool());
         parser_worker.spawn(parser_impl).await?;
 
@@ -157,7 +221,7 @@ impl Server {
         let mut downloaders = vec![];
         if config.downloader.pan115.enabled {
             let mut pan115 = pan_115::client::Client::new(
-                config.downloader.pan115.cookies.as_str(),
+                config.downloader.pan115.cookies.expose()?.as_str(),
                 Some(pan_115::client::RateLimitConfig {
                     max_requests_per_second: config.downloader.pan115.max_requests_per_second,
                 }),
@@ -178,7 +242,7 @@ impl Server {
                 client.clone(),
                 Url::parse(&config.downloader.qbittorrent.url)?,
                 config.downloader.qbittorrent.username.as_str(),
-                config.downloader.qbittorrent.password.as_str(),
+                config.downloader.qbittorrent.password.expose()?.as_str(),
             );
             qbittorrent.login(false).await?;
             downloaders.push(Arc::new(Box::new(
@@ -202,7 +266,55 @@ impl Server {
                             .to_downloader_config(),
                         url: config.downloader.transmission.url.clone(),
                         username: config.downloader.transmission.username.clone(),
-                        password: config.downloader.transmission.password.clone(),
+                        password: config.downloader.transmission.password.expose()?,
+                        ..Default::default()
+                    },
+                ),
+            ) as Box<dyn ThirdPartyDownloader>));
+        }
+        if config.downloader.aria2.enabled {
+            let aria2 = aria2::Client::new(
+                client.clone(),
+                Url::parse(&config.downloader.aria2.url)?,
+                Some(config.downloader.aria2.secret.expose()?),
+            );
+            downloaders.push(Arc::new(Box::new(
+                downloader::thirdparty::aria2_impl::Aria2DownloaderImpl::new(
+                    aria2,
+                    downloader::thirdparty::aria2_impl::Config {
+                        generic: config.downloader.aria2.generic.to_downloader_config(),
+                        subscribe_notifications: config.downloader.aria2.subscribe_notifications,
+                        ..Default::default()
+                    },
+                ),
+            ) as Box<dyn ThirdPartyDownloader>));
+        }
+        if config.downloader.deluge.enabled {
+            let deluge = deluge::Client::new(
+                client.clone(),
+                Url::parse(&config.downloader.deluge.url)?,
+                config.downloader.deluge.password.expose()?.as_str(),
+            )?;
+            deluge.login().await?;
+            downloaders.push(Arc::new(Box::new(
+                downloader::thirdparty::deluge_impl::DelugeDownloaderImpl::new(
+                    deluge,
+                    downloader::thirdparty::deluge_impl::Config {
+                        generic: config.downloader.deluge.generic.to_downloader_config(),
+                        ..Default::default()
+                    },
+                ),
+            ) as Box<dyn ThirdPartyDownloader>));
+        }
+        if config.downloader.watch_folder.enabled {
+            let watch_folder = &config.downloader.watch_folder;
+            downloaders.push(Arc::new(Box::new(
+                downloader::thirdparty::watch_folder_impl::WatchFolderDownloaderImpl::new(
+                    client.clone(),
+                    downloader::thirdparty::watch_folder_impl::Config {
+                        generic: watch_folder.generic.to_downloader_config(),
+                        watch_dir: PathBuf::from(&watch_folder.watch_dir),
+                        state_dir: watch_folder.state_dir(),
                         ..Default::default()
                     },
                 ),
@@ -215,7 +327,7 @@ impl Server {
                     config.generic.to_downloader_config(),
                     config.url.clone(),
                     config.username.clone(),
-                    config.password.clone(),
+                    config.password.expose()?,
                     config.tool,
                 )
                 .await?;
@@ -230,7 +342,10 @@ impl Server {
         let dl_store = downloader::db::Db::new(db.conn_pool());
         let mut downloader_worker = downloader::actor::Actor::new(
             Box::new(dl_store),
-            downloader::config::Config::default(),
+            downloader::config::Config {
+                select_strategy: config.downloader.strategy,
+                ..Default::default()
+            },
             downloaders,
         )?;
 
@@ -244,6 +359,8 @@ impl Server {
             Arc::new(Box::new(downloader_worker)),
             notify_worker,
             client.clone(),
+            config.scoring.clone(),
+            Self::create_organizer(config)?,
         );
         scheduler.spawn().await?;
 
@@ -260,15 +377,58 @@ impl Server {
             config_writer: Arc::new(config_writer),
             config: Arc::new(RwLock::new(config.clone())),
             sentry_guard: Arc::new(sentry_guard),
+            login_throttle: Default::default(),
         }))
     }
 
-    fn create_parser(config: &Config, client: reqwest::Client) -> Arc<dyn Parser + Send + Sync> {
+    /// 创建媒体库整理器, 只整理挂载到本地的下载器中的文件
+    fn create_organizer(config: &Config) -> Result<Option<scheduler::Organizer>> {
+        if !config.organizer.enabled {
+            return Ok(None);
+        }
+        let mut mounts = HashMap::new();
+        let qbittorrent = &config.downloader.qbittorrent;
+        if let (true, Some(mount_path)) = (qbittorrent.enabled, &qbittorrent.mount_path) {
+            mounts.insert("qbittorrent".to_owned(), PathBuf::from(mount_path));
+        }
+        let transmission = &config.downloader.transmission;
+        if let (true, Some(mount_path)) = (transmission.enabled, &transmission.mount_path) {
+            mounts.insert("transmission".to_owned(), PathBuf::from(mount_path));
+        }
+        let aria2 = &config.downloader.aria2;
+        if let (true, Some(mount_path)) = (aria2.enabled, &aria2.mount_path) {
+            mounts.insert("aria2".to_owned(), PathBuf::from(mount_path));
+        }
+        let deluge = &config.downloader.deluge;
+        if let (true, Some(mount_path)) = (deluge.enabled, &deluge.mount_path) {
+            mounts.insert("deluge".to_owned(), PathBuf::from(mount_path));
+        }
+        let watch_folder = &config.downloader.watch_folder;
+        if watch_folder.enabled {
+            mounts.insert(
+                "watch_folder".to_owned(),
+                PathBuf::from(&watch_folder.generic.download_dir),
+            );
+        }
+        if mounts.is_empty() && !config.downloader.pan115.enabled {
+            warn!("没有配置 mount_path 的下载器, 媒体库整理不会生效");
+        }
+        Ok(Some(scheduler::Organizer::new(
+            config.organizer.clone(),
+            mounts,
+            &config.server.assets_path,
+        )?))
+    }
+
+    fn create_parser(
+        config: &Config,
+        client: reqwest::Client,
+    ) -> Result<Arc<dyn Parser + Send + Sync>> {
         let parser_impl: Arc<dyn Parser + Send + Sync>;
         if config.parser.siliconflow.enabled {
             parser_impl = Arc::new(parser::impls::siliconflow::Client::new(
                 parser::impls::siliconflow::Config {
-                    api_key: config.parser.siliconflow.api_key.clone(),
+                    api_key: config.parser.siliconflow.api_key.expose()?,
                     base_url: config.parser.siliconflow.base_url.clone(),
                     model: config.parser.siliconflow.model.clone(),
                     ..Default::default()
@@ -278,7 +438,7 @@ impl Server {
         } else if config.parser.deepseek.enabled {
             parser_impl = Arc::new(parser::impls::deepseek::Client::new(
                 parser::impls::deepseek::Config {
-                    api_key: config.parser.deepseek.api_key.clone(),
+                    api_key: config.parser.deepseek.api_key.expose()?,
                     base_url: config.parser.deepseek.base_url.clone(),
                     model: config.parser.deepseek.model.clone(),
                     ..Default::default()
@@ -288,7 +448,7 @@ impl Server {
         } else if config.parser.deepbricks.enabled {
             parser_impl = Arc::new(parser::impls::deepbricks::Client::new(
                 parser::impls::deepbricks::Config {
-                    api_key: config.parser.deepbricks.api_key.clone(),
+                    api_key: config.parser.deepbricks.api_key.expose()?,
                     base_url: config.parser.deepbricks.base_url.clone(),
                     model: config.parser.deepbricks.model.clone(),
                 },
@@ -299,7 +459,7 @@ impl Server {
         } else {
             panic!("No parser enabled");
         }
-        parser_impl
+        Ok(parser_impl)
     }
 
     pub async fn serve(&self) -> Result<()> {
@@ -314,6 +474,7 @@ impl Server {
                 .allow_any_header()
                 .max_age(3600);
             App::new()
+                .wrap(middleware::from_fn(auth::auth_middleware))
                 .wrap(TracingLogger::default())
                 .wrap(cors)
                 .configure(|cfg| router::configure_app(cfg, state.clone()))
@@ -0,0 +1,388 @@
+use std::sync::Arc;
+
+use actix_web::{
+    HttpRequest, get, post,
+    web::{self, Json},
+};
+use chrono::NaiveDateTime;
+use downloader::dlrs::SelectStrategy;
+use model::sea_orm_active_enums::SubscribeStatus;
+use model::user_subscriptions;
+use parser::{Language, VideoResolution};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    auth::{Identity, current_session, hash_password},
+    error::ServerError,
+    model::Resp,
+    server::AppState,
+};
+
+/// 用户的个人订阅条件
+///
+/// 过滤条件保存为逗号分隔的字符串, 与 `subscriptions` 表一致
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscriptionFilters {
+    pub start_episode_number: Option<i32>,
+    pub resolution_filter: Option<String>,
+    pub language_filter: Option<String>,
+    pub release_group_filter: Option<String>,
+}
+
+impl From<user_subscriptions::Model> for SubscriptionFilters {
+    fn from(m: user_subscriptions::Model) -> Self {
+        Self {
+            start_episode_number: m.start_episode_number,
+            resolution_filter: m.resolution_filter,
+            language_filter: m.language_filter,
+            release_group_filter: m.release_group_filter,
+        }
+    }
+}
+
+impl SubscriptionFilters {
+    pub fn resolutions(&self) -> Option<Vec<VideoResolution>> {
+        self.resolution_filter
+            .as_deref()
+            .map(|filter| split_filter(filter).map(VideoResolution::from).collect())
+    }
+
+    pub fn languages(&self) -> Option<Vec<Language>> {
+        self.language_filter
+            .as_deref()
+            .map(|filter| split_filter(filter).map(Language::from).collect())
+    }
+}
+
+fn split_filter(filter: &str) -> impl Iterator<Item = &str> {
+    filter.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// 合并多个过滤条件, 任意一个用户没有设置过滤条件时不过滤, 否则取并集
+fn merge_filter<'a>(filters: impl Iterator<Item = Option<&'a str>>) -> Option<String> {
+    let mut merged: Vec<&str> = Vec::new();
+    for filter in filters {
+        let mut values = split_filter(filter?).peekable();
+        values.peek()?;
+        for value in values {
+            if !merged.contains(&value) {
+                merged.push(value);
+            }
+        }
+    }
+    (!merged.is_empty()).then(|| merged.join(","))
+}
+
+/// 合并所有用户的订阅条件, 作为调度器实际使用的订阅
+///
+/// 开始集数取最小值, 分辨率, 语言和发布组过滤取并集, 保证每个用户需要的剧集都会被下载
+pub fn merge_subscriptions(subscriptions: &[SubscriptionFilters]) -> SubscriptionFilters {
+    let start_episode_number = subscriptions
+        .iter()
+        .map(|s| s.start_episode_number)
+        .collect::<Option<Vec<_>>>()
+        .and_then(|numbers| numbers.into_iter().min());
+    SubscriptionFilters {
+        start_episode_number,
+        resolution_filter: merge_filter(
+            subscriptions.iter().map(|s| s.resolution_filter.as_deref()),
+        ),
+        language_filter: merge_filter(subscriptions.iter().map(|s| s.language_filter.as_deref())),
+        release_group_filter: merge_filter(
+            subscriptions
+                .iter()
+                .map(|s| s.release_group_filter.as_deref()),
+        ),
+    }
+}
+
+/// 用户取消订阅后, 根据剩余用户的订阅重新计算合并后的订阅
+///
+/// 没有用户订阅时取消订阅, 下载任务等其他订阅配置保持不变
+pub async fn resync_subscription(state: &AppState, bangumi_id: i32) -> Result<(), ServerError> {
+    let subscriptions = state
+        .db
+        .list_user_subscriptions(bangumi_id)
+        .await?
+        .into_iter()
+        .map(SubscriptionFilters::from)
+        .collect::<Vec<_>>();
+    if subscriptions.is_empty() {
+        info!("番剧 {} 已没有用户订阅, 取消订阅", bangumi_id);
+        state.scheduler.unsubscribe(bangumi_id).await?;
+        return Ok(());
+    }
+
+    let Some(current) = state.db.get_subscription_by_bangumi_id(bangumi_id).await? else {
+        return Ok(());
+    };
+    // 已下载完成的番剧不需要重新订阅
+    if current.subscribe_status != SubscribeStatus::Subscribed {
+        return Ok(());
+    }
+
+    subscribe_merged(state, bangumi_id, &subscriptions).await
+}
+
+/// 使用所有用户订阅条件的合并结果订阅番剧
+///
+/// 下载器、过滤规则、评分配置等共享配置只能由管理员修改, 这里沿用已有的订阅,
+/// 番剧还没有订阅记录时使用默认配置
+pub async fn subscribe_merged(
+    state: &AppState,
+    bangumi_id: i32,
+    subscriptions: &[SubscriptionFilters],
+) -> Result<(), ServerError> {
+    let merged = merge_subscriptions(subscriptions);
+    let current = state.db.get_subscription_by_bangumi_id(bangumi_id).await?;
+    let download_strategy = current
+        .as_ref()
+        .and_then(|current| current.download_strategy.as_deref())
+        .map(str::parse::<SelectStrategy>)
+        .transpose()?;
+    // 默认配置与订阅界面一致
+    let (
+        collector_interval,
+        metadata_interval,
+        enforce_torrent_release_after_broadcast,
+        preferred_downloader,
+        allow_fallback,
+        auto_upgrade,
+    ) = match current {
+        Some(current) => (
+            current.collector_interval,
+            current.metadata_interval,
+            current.enforce_torrent_release_after_broadcast,
+            current.preferred_downloader,
+            current.allow_fallback,
+            current.auto_upgrade,
+        ),
+        None => (None, None, true, None, true, false),
+    };
+    state
+        .scheduler
+        .subscribe(
+            bangumi_id,
+            merged.start_episode_number,
+            merged.resolutions(),
+            merged.languages(),
+            merged.release_group_filter,
+            collector_interval,
+            metadata_interval,
+            enforce_torrent_release_after_broadcast,
+            preferred_downloader,
+            allow_fallback,
+            None,
+            None,
+            None,
+            auto_upgrade,
+            download_strategy,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchedParams {
+    pub episode_numbers: Vec<i32>,
+    pub watched: bool,
+}
+
+/// 标记当前用户的剧集观看状态
+#[post("/api/bangumi/{id}/watched")]
+pub async fn mark_watched(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    id: web::Path<i32>,
+    params: Json<WatchedParams>,
+) -> Result<Json<Resp<()>>, ServerError> {
+    state
+        .db
+        .set_episode_watched(
+            identity.user_id,
+            id.into_inner(),
+            &params.episode_numbers,
+            params.watched,
+        )
+        .await?;
+    Ok(Json(Resp::ok(())))
+}
+
+/// 用户信息, 不包含密码哈希
+#[derive(Debug, Serialize)]
+pub struct UserInfo {
+    pub id: i32,
+    pub username: String,
+    pub is_admin: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<model::users::Model> for UserInfo {
+    fn from(m: model::users::Model) -> Self {
+        Self {
+            id: m.id,
+            username: m.username,
+            is_admin: m.is_admin,
+            created_at: m.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserParams {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserParams {
+    /// 为空时不修改密码
+    pub password: Option<String>,
+    pub is_admin: Option<bool>,
+}
+
+#[get("/api/users")]
+pub async fn list_users(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+) -> Result<Json<Resp<Vec<UserInfo>>>, ServerError> {
+    identity.require_admin()?;
+    let users = state.db.list_users().await?;
+    Ok(Json(Resp::ok(users.into_iter().map(Into::into).collect())))
+}
+
+#[post("/api/users")]
+pub async fn create_user(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    params: Json<CreateUserParams>,
+) -> Result<Json<Resp<UserInfo>>, ServerError> {
+    identity.require_admin()?;
+    let params = params.into_inner();
+    let username = params.username.trim().to_owned();
+    if username.is_empty() {
+        return Err(ServerError::BadRequest("用户名不能为空".to_owned()));
+    }
+    if params.password.is_empty() {
+        return Err(ServerError::BadRequest("密码不能为空".to_owned()));
+    }
+    if state.db.get_user_by_username(&username).await?.is_some() {
+        return Err(ServerError::BadRequest(format!("用户 {} 已存在", username)));
+    }
+    let password_hash = hash_password(&params.password)?;
+    let user = state
+        .db
+        .create_user(username, password_hash, params.is_admin)
+        .await?;
+    info!("{} 创建了用户 {}", identity.username, user.username);
+    Ok(Json(Resp::ok(user.into())))
+}
+
+/// 修改用户, 重置密码时退出该用户的其他会话并删除其 API 令牌
+#[post("/api/users/{id}/update")]
+pub async fn update_user(
+    state: web::Data<Arc<AppState>>,
+    req: HttpRequest,
+    identity: Identity,
+    id: web::Path<i32>,
+    params: Json<UpdateUserParams>,
+) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
+    let id = id.into_inner();
+    let params = params.into_inner();
+    if id == identity.user_id && params.is_admin == Some(false) {
+        return Err(ServerError::BadRequest(
+            "不能取消自己的管理员权限".to_owned(),
+        ));
+    }
+    let password_hash = match params.password.as_deref() {
+        Some(password) if !password.is_empty() => Some(hash_password(password)?),
+        _ => None,
+    };
+    let password_changed = password_hash.is_some();
+    state
+        .db
+        .update_user(id, password_hash, params.is_admin)
+        .await?;
+    if password_changed {
+        let keep_session = current_session(&req, &identity).filter(|_| id == identity.user_id);
+        state
+            .db
+            .revoke_user_credentials(id, keep_session.as_deref())
+            .await?;
+    }
+    Ok(Json(Resp::ok(())))
+}
+
+#[post("/api/users/{id}/delete")]
+pub async fn delete_user(
+    state: web::Data<Arc<AppState>>,
+    identity: Identity,
+    id: web::Path<i32>,
+) -> Result<Json<Resp<()>>, ServerError> {
+    identity.require_admin()?;
+    let id = id.into_inner();
+    if id == identity.user_id {
+        return Err(ServerError::BadRequest("不能删除自己".to_owned()));
+    }
+    let Some(user) = state.db.get_user(id).await? else {
+        return Ok(Json(Resp::ok(())));
+    };
+    if user.username == state.config.read().unwrap().auth.username {
+        return Err(ServerError::BadRequest(
+            "不能删除配置文件中的管理员".to_owned(),
+        ));
+    }
+
+    let bangumi_ids = state.db.delete_user(&user).await?;
+    info!("{} 删除了用户 {}", identity.username, user.username);
+    // 重新计算该用户订阅过的番剧
+    for bangumi_id in bangumi_ids {
+        resync_subscription(&state, bangumi_id).await?;
+    }
+    Ok(Json(Resp::ok(())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(
+        start: Option<i32>,
+        resolution: Option<&str>,
+        language: Option<&str>,
+        release_group: Option<&str>,
+    ) -> SubscriptionFilters {
+        SubscriptionFilters {
+            start_episode_number: start,
+            resolution_filter: resolution.map(str::to_owned),
+            language_filter: language.map(str::to_owned),
+            release_group_filter: release_group.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_merge_subscriptions() {
+        let merged = merge_subscriptions(&[
+            filters(Some(3), Some("1080P"), Some("CHS"), Some("A")),
+            filters(Some(1), Some("720P,1080P"), Some("CHT"), Some("B, A")),
+        ]);
+        assert_eq!(
+            merged,
+            filters(Some(1), Some("1080P,720P"), Some("CHS,CHT"), Some("A,B"))
+        );
+
+        // 任意一个用户不过滤时, 合并后也不过滤
+        let merged = merge_subscriptions(&[
+            filters(Some(3), Some("1080P"), Some(""), None),
+            filters(None, None, Some("CHS"), Some("A")),
+        ]);
+        assert_eq!(merged, filters(None, None, None, None));
+
+        let merged = merge_subscriptions(&[filters(Some(2), Some("1080P"), None, None)]);
+        assert_eq!(merged, filters(Some(2), Some("1080P"), None, None));
+    }
+}
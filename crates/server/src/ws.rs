@@ -1,12 +1,30 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{error::ServerError, server::AppState};
 use actix_web::{HttpRequest, Responder, web};
 use actix_ws::Message;
 use anyhow::Result;
+use downloader::TaskProgress;
+use serde::Serialize;
 use tokio_stream::StreamExt;
 use tracing::info;
 
+/// 通过 WebSocket 推送给前端的消息
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum WsMessage {
+    /// 日志
+    Log(String),
+    /// 活跃任务的下载进度, key 为 info_hash
+    Progress(HashMap<String, TaskProgress>),
+}
+
+impl WsMessage {
+    fn to_text(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
 pub async fn ws_handler(
     state: actix_web::web::Data<Arc<AppState>>,
     req: HttpRequest,
@@ -18,6 +36,15 @@ pub async fn ws_handler(
     // 启动WebSocket处理任务
     actix_web::rt::spawn(async move {
         let mut rx = state.log_tx.subscribe();
+        let downloader = state.scheduler.get_downloader();
+        let mut dl_rx = downloader.subscribe().await;
+
+        // 连接建立后先推送当前进度
+        let progress = WsMessage::Progress(downloader.task_progress());
+        if session.text(progress.to_text()).await.is_err() {
+            return;
+        }
+
         loop {
             tokio::select! {
                 Some(msg) = msg_stream.next() => {
@@ -32,7 +59,12 @@ pub async fn ws_handler(
                     }
                 }
                 Ok(log_msg) = rx.recv() => {
-                    if session.text(log_msg.content).await.is_err() {
+                    if session.text(WsMessage::Log(log_msg.content).to_text()).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(downloader::Event::ProgressUpdated(progress)) = dl_rx.recv() => {
+                    if session.text(WsMessage::Progress(progress).to_text()).await.is_err() {
                         return;
                     }
                 }
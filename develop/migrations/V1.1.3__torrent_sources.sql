@@ -0,0 +1,7 @@
+create table if not exists torrent_sources (
+    info_hash varchar(40) not null comment '种子文件的info hash',
+    source enum ('Mikan', 'AcgripOrg', 'NyaaLand', 'DmhyOrg', 'User') not null comment '种子来源',
+    created_at datetime not null default current_timestamp comment '首次发现时间',
+    updated_at datetime not null default current_timestamp on update current_timestamp comment '最近发现时间',
+    primary key (info_hash, source)
+) comment '种子来源记录, 同一个种子可能被多个来源发现';
@@ -0,0 +1,5 @@
+alter table torrents
+    modify source enum ('Mikan', 'AcgripOrg', 'NyaaLand', 'DmhyOrg', 'User', 'Rss') not null default 'Mikan';
+
+alter table torrent_sources
+    modify source enum ('Mikan', 'AcgripOrg', 'NyaaLand', 'DmhyOrg', 'User', 'Rss') not null comment '种子来源';
@@ -0,0 +1,10 @@
+create table if not exists subscription_feeds (
+    id int auto_increment primary key,
+    bangumi_id int not null comment '番剧ID',
+    url varchar(512) not null comment 'RSS 订阅地址',
+    enabled bool default true not null comment '是否启用',
+    last_collected_at datetime null comment '最近一次收集时间',
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp on update current_timestamp,
+    unique key uk_bangumi_url (bangumi_id, url)
+) comment '订阅的自定义 RSS 源, 例如指定字幕组的 Mikan RSS';
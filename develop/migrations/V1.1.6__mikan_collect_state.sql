@@ -0,0 +1,8 @@
+create table if not exists mikan_collect_state (
+    mikan_id int not null primary key comment 'Mikan 番剧ID',
+    etag varchar(255) null comment '上一次响应的 ETag',
+    last_modified varchar(64) null comment '上一次响应的 Last-Modified',
+    last_pub_date datetime null comment '已收集种子的最新发布时间',
+    last_info_hashes text null comment '发布时间等于 last_pub_date 的种子 info hash, 逗号分隔',
+    updated_at datetime not null default current_timestamp on update current_timestamp
+) comment 'Mikan 番剧 RSS 增量收集状态';
@@ -0,0 +1,2 @@
+alter table subscriptions
+    add filter_rules text null comment '种子过滤规则, JSON 格式';
@@ -0,0 +1,5 @@
+alter table torrents
+    add seeders int null comment '做种数, 来源不提供时为空';
+
+alter table subscriptions
+    add score_profile text null comment '种子评分配置, JSON 格式, 覆盖全局配置';
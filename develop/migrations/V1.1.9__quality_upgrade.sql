@@ -0,0 +1,6 @@
+alter table subscriptions
+    add auto_upgrade bool default false not null comment '已下载的剧集出现评分更高的种子时自动洗版';
+
+alter table episode_download_tasks
+    add upgrade_info_hash varchar(40) null comment '洗版中的种子, 下载完成后替换 ref_torrent_info_hash',
+    add key upgrade_info_hash_idx (upgrade_info_hash);
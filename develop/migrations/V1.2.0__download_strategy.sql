@@ -0,0 +1,2 @@
+alter table subscriptions
+    add download_strategy varchar(32) null comment '未指定下载器时的下载器选择策略, 为空时使用全局配置';
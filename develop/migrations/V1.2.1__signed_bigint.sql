@@ -0,0 +1,6 @@
+alter table bangumi
+    modify tmdb_id bigint null comment 'TMDB 动漫ID',
+    modify season_number bigint null comment '季数';
+
+alter table episodes
+    modify duration_seconds bigint null comment '时长';
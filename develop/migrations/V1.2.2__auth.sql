@@ -0,0 +1,17 @@
+create table if not exists auth_sessions (
+    token_hash varchar(64) not null primary key comment '会话令牌的 SHA-256',
+    username varchar(255) not null comment '用户名',
+    expires_at datetime not null comment '过期时间',
+    created_at datetime not null default current_timestamp comment '创建时间',
+    key idx_expires_at (expires_at)
+) comment 'Web 界面的登录会话';
+
+create table if not exists api_tokens (
+    id int auto_increment primary key,
+    name varchar(255) not null comment '令牌名称',
+    token_prefix varchar(16) not null comment '令牌前缀, 用于识别令牌',
+    token_hash varchar(64) not null comment 'API 令牌的 SHA-256',
+    last_used_at datetime null comment '最近一次使用时间',
+    created_at datetime not null default current_timestamp comment '创建时间',
+    unique key uk_token_hash (token_hash)
+) comment 'API 令牌, 用于脚本和播放器访问';
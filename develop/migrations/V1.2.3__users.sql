@@ -0,0 +1,32 @@
+create table if not exists users (
+    id int auto_increment primary key,
+    username varchar(255) not null comment '用户名',
+    password_hash varchar(255) not null default '' comment '密码的 argon2 哈希',
+    is_admin boolean not null default false comment '是否为管理员',
+    created_at datetime not null default current_timestamp comment '创建时间',
+    updated_at datetime not null default current_timestamp on update current_timestamp comment '更新时间',
+    unique key uk_username (username)
+) comment '用户';
+
+create table if not exists user_subscriptions (
+    user_id int not null comment '用户ID',
+    bangumi_id int not null comment '番剧ID',
+    start_episode_number int null comment '开始订阅集数',
+    resolution_filter varchar(255) null comment '分辨率过滤',
+    language_filter varchar(255) null comment '语言过滤',
+    release_group_filter varchar(255) null comment '发布组过滤',
+    created_at datetime not null default current_timestamp comment '创建时间',
+    updated_at datetime not null default current_timestamp on update current_timestamp comment '更新时间',
+    primary key (user_id, bangumi_id),
+    key idx_bangumi_id (bangumi_id)
+) comment '用户的个人订阅, subscriptions 为所有用户订阅合并后的结果';
+
+create table if not exists episode_watch_states (
+    user_id int not null comment '用户ID',
+    bangumi_id int not null comment '番剧ID',
+    episode_number int not null comment '集数',
+    watched_at datetime not null default current_timestamp comment '标记为已看的时间',
+    primary key (user_id, bangumi_id, episode_number)
+) comment '用户的剧集观看状态, 有记录即为已看';
+
+alter table api_tokens add column user_id int not null default 0 comment '所属用户ID';
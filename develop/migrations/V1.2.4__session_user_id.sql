@@ -0,0 +1,6 @@
+-- 会话改为关联用户ID, 已有的会话需要重新登录
+delete from auth_sessions;
+
+alter table auth_sessions drop column username;
+alter table auth_sessions add column user_id int not null comment '用户ID';
+alter table auth_sessions add key idx_user_id (user_id);
@@ -0,0 +1,317 @@
+-- PostgreSQL 初始化脚本, 与 MySQL 迁移 V1.0.0 ~ V1.2.1 的最终结构一致
+-- 枚举类型名称需要与 sea_orm_active_enums 中的 enum_name 保持一致
+
+create type bgm_kind as enum ('anime', 'movie');
+create type subscribe_status as enum ('none', 'subscribed', 'downloaded');
+create type kind as enum ('EP', 'SP', 'OP', 'ED', 'MAD', 'Other');
+create type state as enum ('missing', 'ready', 'downloading', 'downloaded', 'failed', 'retrying');
+create type download_status as enum ('pending', 'downloading', 'completed', 'failed', 'retrying', 'cancelled', 'paused');
+create type resource_type as enum ('torrent', 'torrent_url', 'magnet', 'info_hash');
+create type parser_status as enum ('pending', 'completed', 'failed');
+create type source as enum ('Mikan', 'AcgripOrg', 'NyaaLand', 'DmhyOrg', 'User', 'Rss');
+
+create table if not exists bangumi (
+    id serial primary key,
+    name varchar(255) not null,
+    description text,
+    bangumi_tv_id int null,
+    tmdb_id bigint null,
+    mikan_id int null,
+
+    air_date timestamp null,
+    air_week int null,
+    rating float8 null,
+
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+
+    poster_image_url varchar(255) null,
+    backdrop_image_url varchar(255) null,
+
+    season_number bigint null,
+    ep_count int not null,
+    ep_start_number int not null default 1,
+    calendar_season varchar(50) null,
+    bgm_kind bgm_kind null,
+
+    constraint uk_mikan_id unique (mikan_id),
+    constraint uk_bangumi_tv_id unique (bangumi_tv_id)
+);
+
+create index if not exists uk_tmdb_id on bangumi (tmdb_id);
+create index if not exists idx_calendar_season on bangumi (calendar_season);
+create index if not exists idx_air_date on bangumi (air_date desc);
+
+comment on table bangumi is '用于记录番剧的信息';
+comment on column bangumi.name is '动漫名称';
+comment on column bangumi.description is '动漫简介';
+comment on column bangumi.bangumi_tv_id is 'bangumi.tv 动漫ID';
+comment on column bangumi.tmdb_id is 'TMDB 动漫ID';
+comment on column bangumi.mikan_id is 'mikan 动漫ID';
+comment on column bangumi.air_date is '播出日期';
+comment on column bangumi.air_week is '播出星期';
+comment on column bangumi.rating is '评分';
+comment on column bangumi.poster_image_url is '海报图片';
+comment on column bangumi.backdrop_image_url is '背景图片';
+comment on column bangumi.season_number is '季数';
+comment on column bangumi.ep_count is '总集数';
+comment on column bangumi.ep_start_number is '开始集数';
+comment on column bangumi.calendar_season is '放送季';
+
+create table if not exists subscriptions (
+    bangumi_id int not null primary key references bangumi (id),
+    subscribe_status subscribe_status not null,
+    start_episode_number int null,
+    resolution_filter varchar(255) null,
+    language_filter varchar(255) null,
+    release_group_filter varchar(255) null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+    collector_interval int null,
+    metadata_interval int null,
+    enforce_torrent_release_after_broadcast bool not null default true,
+    preferred_downloader varchar(255) null,
+    allow_fallback bool not null default true,
+    filter_rules text null,
+    score_profile text null,
+    auto_upgrade bool not null default false,
+    download_strategy varchar(32) null
+);
+
+create index if not exists idx_subscribe_status on subscriptions (subscribe_status);
+
+comment on table subscriptions is '订阅信息，用于记录订阅的信息';
+comment on column subscriptions.subscribe_status is '状态, subscribed: 已订阅, downloaded: 已下载';
+comment on column subscriptions.start_episode_number is '开始订阅集数';
+comment on column subscriptions.resolution_filter is '分辨率过滤';
+comment on column subscriptions.language_filter is '语言过滤';
+comment on column subscriptions.release_group_filter is '发布组过滤';
+comment on column subscriptions.collector_interval is '种子收集间隔';
+comment on column subscriptions.metadata_interval is '元数据刷新间隔';
+comment on column subscriptions.filter_rules is '种子过滤规则, JSON 格式';
+comment on column subscriptions.score_profile is '种子评分配置, JSON 格式, 覆盖全局配置';
+comment on column subscriptions.auto_upgrade is '已下载的剧集出现评分更高的种子时自动洗版';
+comment on column subscriptions.download_strategy is '未指定下载器时的下载器选择策略, 为空时使用全局配置';
+
+create table if not exists episodes (
+    id serial primary key,
+    bangumi_id int not null references bangumi (id),
+    number int not null,
+    sort_number int null,
+    name varchar(255) null,
+    image_url varchar(255) null,
+    description text null,
+    air_date date null,
+    duration_seconds bigint null,
+    kind kind not null default 'EP',
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+
+    constraint uk_bangumi_id_number unique (bangumi_id, number)
+);
+
+comment on table episodes is '用于记录番剧的每一集的信息';
+comment on column episodes.number is '集数';
+comment on column episodes.sort_number is '排序后的集数';
+comment on column episodes.name is '集名称';
+comment on column episodes.image_url is '图片';
+comment on column episodes.description is '集简介';
+comment on column episodes.air_date is '播出日期';
+comment on column episodes.duration_seconds is '时长';
+comment on column episodes.kind is '集类型';
+
+create table if not exists torrents (
+    info_hash varchar(40) not null primary key,
+    bangumi_id int not null references bangumi (id),
+    title varchar(255) not null,
+    size bigint not null,
+    magnet text not null,
+    data bytea null,
+    download_url varchar(255) null,
+    pub_date timestamp not null,
+    source source not null default 'Mikan',
+    seeders int null
+);
+
+create index if not exists torrent_bangumi_id_idx on torrents (bangumi_id);
+
+comment on table torrents is '种子信息，用于记录种子的信息';
+comment on column torrents.info_hash is '种子文件的info hash, 用于去重';
+comment on column torrents.title is '标题';
+comment on column torrents.size is '文件大小';
+comment on column torrents.magnet is '磁力链接';
+comment on column torrents.data is '种子文件';
+comment on column torrents.download_url is '种子下载地址';
+comment on column torrents.pub_date is '发布时间';
+comment on column torrents.seeders is '做种数, 来源不提供时为空';
+
+create table if not exists episode_download_tasks (
+    bangumi_id int not null references bangumi (id),
+    episode_number int not null,
+    state state not null,
+    ref_torrent_info_hash varchar(40) null references torrents (info_hash),
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+    upgrade_info_hash varchar(40) null,
+    primary key (bangumi_id, episode_number)
+);
+
+create index if not exists edt_status_idx on episode_download_tasks (state);
+create index if not exists ref_torrent_info_hash_idx on episode_download_tasks (ref_torrent_info_hash);
+create index if not exists upgrade_info_hash_idx on episode_download_tasks (upgrade_info_hash);
+
+comment on table episode_download_tasks is '用于记录番剧的每一集的下载状态';
+comment on column episode_download_tasks.episode_number is '集数';
+comment on column episode_download_tasks.state is '任务状态';
+comment on column episode_download_tasks.ref_torrent_info_hash is '下载中会填写该字段';
+comment on column episode_download_tasks.upgrade_info_hash is '洗版中的种子, 下载完成后替换 ref_torrent_info_hash';
+
+create table if not exists torrent_download_tasks (
+    info_hash varchar(40) not null primary key,
+    download_status download_status not null,
+    downloader varchar(255) not null,
+    dir varchar(255) not null,
+    context text,
+    err_msg text null,
+    retry_count int not null default 0,
+    next_retry_at timestamp not null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+    resource_type resource_type not null default 'info_hash',
+    magnet text null,
+    allow_fallback bool not null default true,
+    torrent_url text null,
+    tid varchar(255) null
+);
+
+create index if not exists tdt_status_idx on torrent_download_tasks (download_status, updated_at desc);
+
+comment on table torrent_download_tasks is '种子下载任务，用于记录种子的下载状态';
+comment on column torrent_download_tasks.info_hash is '种子文件的info hash, 用于去重';
+comment on column torrent_download_tasks.download_status is '状态, pending: 等待下载, downloading: 下载中, completed: 下载完成, failed: 下载失败, retrying: 重试中, cancelled: 已取消';
+comment on column torrent_download_tasks.downloader is '下载器名称';
+comment on column torrent_download_tasks.dir is '下载目录';
+comment on column torrent_download_tasks.context is '下载器上下文';
+comment on column torrent_download_tasks.err_msg is '错误信息';
+comment on column torrent_download_tasks.retry_count is '重试次数';
+comment on column torrent_download_tasks.next_retry_at is '重试时间';
+
+create table if not exists file_name_parse_record (
+    file_name varchar(255) not null primary key,
+    release_group varchar(255) null,
+    bangumi_name varchar(255),
+    season_number int null default 0,
+    episode_number int null default 0,
+    language varchar(255) null,
+    video_resolution varchar(255) null,
+    year int null,
+    parser_name varchar(255) not null,
+    parser_status parser_status not null,
+    err_msg text null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp
+);
+
+create index if not exists idx_parser_status on file_name_parse_record (parser_status);
+
+comment on table file_name_parse_record is '文件名解析记录，从文件名解析出番剧的信息, 用于文件规范命名';
+comment on column file_name_parse_record.file_name is '文件名称';
+comment on column file_name_parse_record.bangumi_name is '动漫名称';
+comment on column file_name_parse_record.season_number is '季数';
+comment on column file_name_parse_record.episode_number is '集数';
+comment on column file_name_parse_record.language is '语言';
+comment on column file_name_parse_record.video_resolution is '分辨率';
+comment on column file_name_parse_record.parser_name is '解析器名称';
+comment on column file_name_parse_record.parser_status is '状态, pending: 等待解析，completed: 解析完成，failed: 解析失败';
+comment on column file_name_parse_record.err_msg is '错误信息';
+
+create table if not exists dictionary (
+    code varchar(50) not null primary key,
+    group_code varchar(50) not null,
+    value varchar(100) not null,
+    sort_order int default 0,
+    description text null
+);
+
+comment on column dictionary.code is '字典代码';
+comment on column dictionary.group_code is '字典分组';
+comment on column dictionary.value is '值';
+comment on column dictionary.sort_order is '排序';
+comment on column dictionary.description is '描述';
+
+create table if not exists torrent_sources (
+    info_hash varchar(40) not null,
+    source source not null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+    primary key (info_hash, source)
+);
+
+comment on table torrent_sources is '种子来源记录, 同一个种子可能被多个来源发现';
+comment on column torrent_sources.info_hash is '种子文件的info hash';
+comment on column torrent_sources.source is '种子来源';
+comment on column torrent_sources.created_at is '首次发现时间';
+comment on column torrent_sources.updated_at is '最近发现时间';
+
+create table if not exists subscription_feeds (
+    id serial primary key,
+    bangumi_id int not null,
+    url varchar(512) not null,
+    enabled bool not null default true,
+    last_collected_at timestamp null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+
+    constraint uk_bangumi_url unique (bangumi_id, url)
+);
+
+comment on table subscription_feeds is '订阅的自定义 RSS 源, 例如指定字幕组的 Mikan RSS';
+comment on column subscription_feeds.bangumi_id is '番剧ID';
+comment on column subscription_feeds.url is 'RSS 订阅地址';
+comment on column subscription_feeds.enabled is '是否启用';
+comment on column subscription_feeds.last_collected_at is '最近一次收集时间';
+
+create table if not exists mikan_collect_state (
+    mikan_id int not null primary key,
+    etag varchar(255) null,
+    last_modified varchar(64) null,
+    last_pub_date timestamp null,
+    last_info_hashes text null,
+    updated_at timestamp not null default current_timestamp
+);
+
+comment on table mikan_collect_state is 'Mikan 番剧 RSS 增量收集状态';
+comment on column mikan_collect_state.mikan_id is 'Mikan 番剧ID';
+comment on column mikan_collect_state.etag is '上一次响应的 ETag';
+comment on column mikan_collect_state.last_modified is '上一次响应的 Last-Modified';
+comment on column mikan_collect_state.last_pub_date is '已收集种子的最新发布时间';
+comment on column mikan_collect_state.last_info_hashes is '发布时间等于 last_pub_date 的种子 info hash, 逗号分隔';
+
+-- 更新时间触发器, 对应 MySQL 的 on update current_timestamp, 仅在更新语句没有显式修改 updated_at 时生效
+create or replace function set_updated_at() returns trigger as $$
+begin
+    if new.updated_at = old.updated_at then
+        new.updated_at := current_timestamp;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger bangumi_updated_at before update on bangumi
+    for each row execute function set_updated_at();
+create trigger subscriptions_updated_at before update on subscriptions
+    for each row execute function set_updated_at();
+create trigger episodes_updated_at before update on episodes
+    for each row execute function set_updated_at();
+create trigger episode_download_tasks_updated_at before update on episode_download_tasks
+    for each row execute function set_updated_at();
+create trigger torrent_download_tasks_updated_at before update on torrent_download_tasks
+    for each row execute function set_updated_at();
+create trigger file_name_parse_record_updated_at before update on file_name_parse_record
+    for each row execute function set_updated_at();
+create trigger torrent_sources_updated_at before update on torrent_sources
+    for each row execute function set_updated_at();
+create trigger subscription_feeds_updated_at before update on subscription_feeds
+    for each row execute function set_updated_at();
+create trigger mikan_collect_state_updated_at before update on mikan_collect_state
+    for each row execute function set_updated_at();
@@ -0,0 +1,30 @@
+create table if not exists auth_sessions (
+    token_hash varchar(64) not null primary key,
+    username varchar(255) not null,
+    expires_at timestamp not null,
+    created_at timestamp not null default current_timestamp
+);
+
+create index if not exists idx_expires_at on auth_sessions (expires_at);
+
+comment on table auth_sessions is 'Web 界面的登录会话';
+comment on column auth_sessions.token_hash is '会话令牌的 SHA-256';
+comment on column auth_sessions.username is '用户名';
+comment on column auth_sessions.expires_at is '过期时间';
+
+create table if not exists api_tokens (
+    id serial primary key,
+    name varchar(255) not null,
+    token_prefix varchar(16) not null,
+    token_hash varchar(64) not null,
+    last_used_at timestamp null,
+    created_at timestamp not null default current_timestamp,
+
+    constraint uk_token_hash unique (token_hash)
+);
+
+comment on table api_tokens is 'API 令牌, 用于脚本和播放器访问';
+comment on column api_tokens.name is '令牌名称';
+comment on column api_tokens.token_prefix is '令牌前缀, 用于识别令牌';
+comment on column api_tokens.token_hash is 'API 令牌的 SHA-256';
+comment on column api_tokens.last_used_at is '最近一次使用时间';
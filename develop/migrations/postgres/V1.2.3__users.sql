@@ -0,0 +1,57 @@
+create table if not exists users (
+    id serial primary key,
+    username varchar(255) not null,
+    password_hash varchar(255) not null default '',
+    is_admin bool not null default false,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+
+    constraint uk_username unique (username)
+);
+
+comment on table users is '用户';
+comment on column users.username is '用户名';
+comment on column users.password_hash is '密码的 argon2 哈希';
+comment on column users.is_admin is '是否为管理员';
+
+create table if not exists user_subscriptions (
+    user_id int not null,
+    bangumi_id int not null references bangumi (id),
+    start_episode_number int null,
+    resolution_filter varchar(255) null,
+    language_filter varchar(255) null,
+    release_group_filter varchar(255) null,
+    created_at timestamp not null default current_timestamp,
+    updated_at timestamp not null default current_timestamp,
+
+    primary key (user_id, bangumi_id)
+);
+
+create index if not exists idx_user_subscriptions_bangumi_id on user_subscriptions (bangumi_id);
+
+comment on table user_subscriptions is '用户的个人订阅, subscriptions 为所有用户订阅合并后的结果';
+comment on column user_subscriptions.start_episode_number is '开始订阅集数';
+comment on column user_subscriptions.resolution_filter is '分辨率过滤';
+comment on column user_subscriptions.language_filter is '语言过滤';
+comment on column user_subscriptions.release_group_filter is '发布组过滤';
+
+create table if not exists episode_watch_states (
+    user_id int not null,
+    bangumi_id int not null,
+    episode_number int not null,
+    watched_at timestamp not null default current_timestamp,
+
+    primary key (user_id, bangumi_id, episode_number)
+);
+
+comment on table episode_watch_states is '用户的剧集观看状态, 有记录即为已看';
+comment on column episode_watch_states.watched_at is '标记为已看的时间';
+
+alter table api_tokens add column user_id int not null default 0;
+
+comment on column api_tokens.user_id is '所属用户ID';
+
+create trigger users_updated_at before update on users
+    for each row execute function set_updated_at();
+create trigger user_subscriptions_updated_at before update on user_subscriptions
+    for each row execute function set_updated_at();
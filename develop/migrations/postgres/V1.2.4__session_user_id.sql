@@ -0,0 +1,9 @@
+-- 会话改为关联用户ID, 已有的会话需要重新登录
+delete from auth_sessions;
+
+alter table auth_sessions drop column username;
+alter table auth_sessions add column user_id int not null;
+
+create index if not exists idx_auth_sessions_user_id on auth_sessions (user_id);
+
+comment on column auth_sessions.user_id is '用户ID';
@@ -0,0 +1,233 @@
+-- SQLite 初始化脚本, 与 MySQL 迁移 V1.0.0 ~ V1.2.1 的最终结构一致
+-- 枚举字段使用 text 存储, on update current_timestamp 使用触发器实现
+
+create table if not exists bangumi (
+    id integer primary key autoincrement,
+    name varchar(255) not null, -- 动漫名称
+    description text, -- 动漫简介
+    bangumi_tv_id integer null, -- bangumi.tv 动漫ID
+    tmdb_id bigint null, -- TMDB 动漫ID
+    mikan_id integer null, -- mikan 动漫ID
+
+    air_date datetime null, -- 播出日期
+    air_week integer null, -- 播出星期
+    rating real null, -- 评分
+
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp,
+
+    poster_image_url varchar(255) null, -- 海报图片
+    backdrop_image_url varchar(255) null, -- 背景图片
+
+    season_number bigint null, -- 季数
+    ep_count integer not null, -- 总集数
+    ep_start_number integer not null default 1, -- 开始集数
+    calendar_season varchar(50) null, -- 放送季
+    bgm_kind text null -- anime, movie
+);
+
+create unique index if not exists uk_mikan_id on bangumi (mikan_id);
+create unique index if not exists uk_bangumi_tv_id on bangumi (bangumi_tv_id);
+create index if not exists uk_tmdb_id on bangumi (tmdb_id);
+create index if not exists idx_calendar_season on bangumi (calendar_season);
+create index if not exists idx_air_date on bangumi (air_date desc);
+
+create table if not exists subscriptions (
+    bangumi_id integer not null primary key references bangumi (id),
+    subscribe_status text not null, -- none, subscribed, downloaded
+    start_episode_number integer null, -- 开始订阅集数
+    resolution_filter varchar(255) null, -- 分辨率过滤
+    language_filter varchar(255) null, -- 语言过滤
+    release_group_filter varchar(255) null, -- 发布组过滤
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp,
+    collector_interval integer null, -- 种子收集间隔
+    metadata_interval integer null, -- 元数据刷新间隔
+    enforce_torrent_release_after_broadcast boolean not null default true,
+    preferred_downloader varchar(255) null,
+    allow_fallback boolean not null default true,
+    filter_rules text null, -- 种子过滤规则, JSON 格式
+    score_profile text null, -- 种子评分配置, JSON 格式, 覆盖全局配置
+    auto_upgrade boolean not null default false, -- 已下载的剧集出现评分更高的种子时自动洗版
+    download_strategy varchar(32) null -- 未指定下载器时的下载器选择策略, 为空时使用全局配置
+);
+
+create index if not exists idx_subscribe_status on subscriptions (subscribe_status);
+
+create table if not exists episodes (
+    id integer primary key autoincrement,
+    bangumi_id integer not null references bangumi (id),
+    number integer not null, -- 集数
+    sort_number integer null, -- 排序后的集数
+    name varchar(255) null, -- 集名称
+    image_url varchar(255) null, -- 图片
+    description text null, -- 集简介
+    air_date date null, -- 播出日期
+    duration_seconds bigint null, -- 时长
+    kind text not null default 'EP', -- EP, SP, OP, ED, MAD, Other
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+
+create unique index if not exists uk_bangumi_id_number on episodes (bangumi_id, number);
+
+create table if not exists torrents (
+    info_hash varchar(40) not null primary key, -- 种子文件的info hash, 用于去重
+    bangumi_id integer not null references bangumi (id),
+    title varchar(255) not null, -- 标题
+    size bigint not null, -- 文件大小
+    magnet text not null, -- 磁力链接
+    data blob null, -- 种子文件
+    download_url varchar(255) null, -- 种子下载地址
+    pub_date datetime not null, -- 发布时间
+    source text not null default 'Mikan', -- Mikan, AcgripOrg, NyaaLand, DmhyOrg, User, Rss
+    seeders integer null -- 做种数, 来源不提供时为空
+);
+
+create index if not exists torrent_bangumi_id_idx on torrents (bangumi_id);
+
+create table if not exists episode_download_tasks (
+    bangumi_id integer not null references bangumi (id),
+    episode_number integer not null, -- 集数
+    state text not null, -- missing, ready, downloading, downloaded, failed, retrying
+    ref_torrent_info_hash varchar(40) null references torrents (info_hash), -- 下载中会填写该字段
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp,
+    upgrade_info_hash varchar(40) null, -- 洗版中的种子, 下载完成后替换 ref_torrent_info_hash
+    primary key (bangumi_id, episode_number)
+);
+
+create index if not exists edt_status_idx on episode_download_tasks (state);
+create index if not exists ref_torrent_info_hash_idx on episode_download_tasks (ref_torrent_info_hash);
+create index if not exists upgrade_info_hash_idx on episode_download_tasks (upgrade_info_hash);
+
+create table if not exists torrent_download_tasks (
+    info_hash varchar(40) not null primary key, -- 种子文件的info hash, 用于去重
+    download_status text not null, -- pending, downloading, completed, failed, retrying, cancelled, paused
+    downloader varchar(255) not null, -- 下载器名称
+    dir varchar(255) not null, -- 下载目录
+    context text, -- 下载器上下文
+    err_msg text null, -- 错误信息
+    retry_count integer not null default 0, -- 重试次数
+    next_retry_at datetime not null, -- 重试时间
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp,
+    resource_type text not null default 'info_hash', -- torrent, torrent_url, magnet, info_hash
+    magnet text null,
+    allow_fallback boolean not null default true,
+    torrent_url text null,
+    tid varchar(255) null
+);
+
+create index if not exists tdt_status_idx on torrent_download_tasks (download_status, updated_at desc);
+
+create table if not exists file_name_parse_record (
+    file_name varchar(255) not null primary key, -- 文件名称
+    release_group varchar(255) null,
+    bangumi_name varchar(255), -- 动漫名称
+    season_number integer null default 0, -- 季数
+    episode_number integer null default 0, -- 集数
+    language varchar(255) null, -- 语言
+    video_resolution varchar(255) null, -- 分辨率
+    year integer null,
+    parser_name varchar(255) not null, -- 解析器名称
+    parser_status text not null, -- pending, completed, failed
+    err_msg text null, -- 错误信息
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+
+create index if not exists idx_parser_status on file_name_parse_record (parser_status);
+
+create table if not exists dictionary (
+    code varchar(50) not null primary key, -- 字典代码
+    group_code varchar(50) not null, -- 字典分组
+    value varchar(100) not null, -- 值
+    sort_order integer default 0, -- 排序
+    description text null -- 描述
+);
+
+create table if not exists torrent_sources (
+    info_hash varchar(40) not null, -- 种子文件的info hash
+    source text not null, -- 种子来源
+    created_at datetime not null default current_timestamp, -- 首次发现时间
+    updated_at datetime not null default current_timestamp, -- 最近发现时间
+    primary key (info_hash, source)
+);
+
+create table if not exists subscription_feeds (
+    id integer primary key autoincrement,
+    bangumi_id integer not null, -- 番剧ID
+    url varchar(512) not null, -- RSS 订阅地址
+    enabled boolean not null default true, -- 是否启用
+    last_collected_at datetime null, -- 最近一次收集时间
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+
+create unique index if not exists uk_bangumi_url on subscription_feeds (bangumi_id, url);
+
+create table if not exists mikan_collect_state (
+    mikan_id integer not null primary key, -- Mikan 番剧ID
+    etag varchar(255) null, -- 上一次响应的 ETag
+    last_modified varchar(64) null, -- 上一次响应的 Last-Modified
+    last_pub_date datetime null, -- 已收集种子的最新发布时间
+    last_info_hashes text null, -- 发布时间等于 last_pub_date 的种子 info hash, 逗号分隔
+    updated_at datetime not null default current_timestamp
+);
+
+-- 更新时间触发器, 仅在更新语句没有显式修改 updated_at 时生效
+
+create trigger if not exists bangumi_updated_at after update on bangumi
+    for each row when new.updated_at = old.updated_at
+begin
+    update bangumi set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists subscriptions_updated_at after update on subscriptions
+    for each row when new.updated_at = old.updated_at
+begin
+    update subscriptions set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists episodes_updated_at after update on episodes
+    for each row when new.updated_at = old.updated_at
+begin
+    update episodes set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists episode_download_tasks_updated_at after update on episode_download_tasks
+    for each row when new.updated_at = old.updated_at
+begin
+    update episode_download_tasks set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists torrent_download_tasks_updated_at after update on torrent_download_tasks
+    for each row when new.updated_at = old.updated_at
+begin
+    update torrent_download_tasks set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists file_name_parse_record_updated_at after update on file_name_parse_record
+    for each row when new.updated_at = old.updated_at
+begin
+    update file_name_parse_record set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists torrent_sources_updated_at after update on torrent_sources
+    for each row when new.updated_at = old.updated_at
+begin
+    update torrent_sources set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists subscription_feeds_updated_at after update on subscription_feeds
+    for each row when new.updated_at = old.updated_at
+begin
+    update subscription_feeds set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists mikan_collect_state_updated_at after update on mikan_collect_state
+    for each row when new.updated_at = old.updated_at
+begin
+    update mikan_collect_state set updated_at = current_timestamp where rowid = new.rowid;
+end;
@@ -0,0 +1,19 @@
+create table if not exists auth_sessions (
+    token_hash varchar(64) not null primary key, -- 会话令牌的 SHA-256
+    username varchar(255) not null, -- 用户名
+    expires_at datetime not null, -- 过期时间
+    created_at datetime not null default current_timestamp
+);
+
+create index if not exists idx_expires_at on auth_sessions (expires_at);
+
+create table if not exists api_tokens (
+    id integer primary key autoincrement,
+    name varchar(255) not null, -- 令牌名称
+    token_prefix varchar(16) not null, -- 令牌前缀, 用于识别令牌
+    token_hash varchar(64) not null, -- API 令牌的 SHA-256
+    last_used_at datetime null, -- 最近一次使用时间
+    created_at datetime not null default current_timestamp
+);
+
+create unique index if not exists uk_token_hash on api_tokens (token_hash);
@@ -0,0 +1,48 @@
+create table if not exists users (
+    id integer primary key autoincrement,
+    username varchar(255) not null, -- 用户名
+    password_hash varchar(255) not null default '', -- 密码的 argon2 哈希
+    is_admin boolean not null default false, -- 是否为管理员
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+
+create unique index if not exists uk_username on users (username);
+
+-- 用户的个人订阅, subscriptions 为所有用户订阅合并后的结果
+create table if not exists user_subscriptions (
+    user_id int not null,
+    bangumi_id int not null,
+    start_episode_number int null, -- 开始订阅集数
+    resolution_filter varchar(255) null, -- 分辨率过滤
+    language_filter varchar(255) null, -- 语言过滤
+    release_group_filter varchar(255) null, -- 发布组过滤
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp,
+    primary key (user_id, bangumi_id)
+);
+
+create index if not exists idx_user_subscriptions_bangumi_id on user_subscriptions (bangumi_id);
+
+-- 用户的剧集观看状态, 有记录即为已看
+create table if not exists episode_watch_states (
+    user_id int not null,
+    bangumi_id int not null,
+    episode_number int not null,
+    watched_at datetime not null default current_timestamp, -- 标记为已看的时间
+    primary key (user_id, bangumi_id, episode_number)
+);
+
+alter table api_tokens add column user_id int not null default 0; -- 所属用户ID
+
+create trigger if not exists users_updated_at after update on users
+    for each row when new.updated_at = old.updated_at
+begin
+    update users set updated_at = current_timestamp where rowid = new.rowid;
+end;
+
+create trigger if not exists user_subscriptions_updated_at after update on user_subscriptions
+    for each row when new.updated_at = old.updated_at
+begin
+    update user_subscriptions set updated_at = current_timestamp where rowid = new.rowid;
+end;
@@ -0,0 +1,7 @@
+-- 会话改为关联用户ID, 已有的会话需要重新登录
+delete from auth_sessions;
+
+alter table auth_sessions drop column username;
+alter table auth_sessions add column user_id int not null default 0; -- 用户ID
+
+create index if not exists idx_auth_sessions_user_id on auth_sessions (user_id);
@@ -43,6 +43,8 @@ export default defineConfig({
         items: [
           { text: '配置概览', link: '/configuration/' },
           { text: '服务配置', link: '/configuration/server' },
+          { text: '认证配置', link: '/configuration/auth' },
+          { text: '敏感信息', link: '/configuration/secrets' },
           { text: '站点配置', link: '/configuration/sites' },
           { text: '通知配置', link: '/configuration/notification' },
           { text: '代理配置', link: '/configuration/proxy' },
@@ -0,0 +1,182 @@
+use std::{num::NonZero, sync::Arc};
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use governor::{Quota, RateLimiter};
+use reqwest::Url;
+use tracing::{info, instrument};
+
+use crate::model::AcgripRss;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub max_requests_per_second: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub title: String,
+    pub view_url: Option<String>,
+    pub pub_date: Option<NaiveDateTime>,
+    pub torrent_download_url: Url,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    endpoint: Url,
+    limiter: Arc<
+        RateLimiter<
+            governor::state::NotKeyed,
+            governor::state::InMemoryState,
+            governor::clock::DefaultClock,
+        >,
+    >,
+}
+
+impl Client {
+    pub fn new_with_client(
+        cli: reqwest::Client,
+        endpoint: &str,
+        rate_limit_config: Option<RateLimitConfig>,
+    ) -> Result<Client> {
+        let rate_limit_config = rate_limit_config.unwrap_or_default();
+        let quota = Quota::per_second(
+            NonZero::new(rate_limit_config.max_requests_per_second)
+                .ok_or_else(|| anyhow::anyhow!("max_requests_per_second 不能为 0"))?,
+        )
+        .allow_burst(NonZero::new(1).unwrap());
+        Ok(Client {
+            cli,
+            endpoint: endpoint.parse()?,
+            limiter: Arc::new(RateLimiter::direct(quota)),
+        })
+    }
+
+    pub fn from_env() -> Result<Client> {
+        let endpoint = std::env::var("ACGRIP_ENDPOINT")?;
+        Self::new_with_client(reqwest::Client::new(), &endpoint, None)
+    }
+
+    async fn acquire(&self) {
+        let _ = self.limiter.until_n_ready(NonZero::new(1).unwrap()).await;
+    }
+
+    /// 通过 RSS 搜索种子
+    #[instrument(name = "搜索acg.rip种子", skip(self))]
+    pub async fn search(&self, keyword: &str) -> Result<Vec<FeedItem>> {
+        let base_url = self.endpoint.join(".xml")?;
+        let url = Url::parse_with_params(base_url.as_str(), &[("term", keyword)])?;
+        info!("url: {}", url);
+
+        self.acquire().await;
+        let xml = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        self.parse_rss(&xml)
+    }
+
+    /// 下载种子文件
+    #[instrument(name = "下载acg.rip种子文件", skip(self))]
+    pub async fn download_torrent(&self, url: &Url) -> Result<Vec<u8>> {
+        self.acquire().await;
+        let bytes = self
+            .cli
+            .get(url.clone())
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(bytes.to_vec())
+    }
+
+    /// 将 RSS 内容转换为条目列表, 没有种子链接的条目会被忽略
+    pub fn parse_rss(&self, xml: &str) -> Result<Vec<FeedItem>> {
+        let rss = AcgripRss::from_xml(xml)?;
+
+        let mut result = Vec::new();
+        for item in rss.channel.items {
+            let Some(torrent_download_url) = item
+                .get_torrent_url()
+                .and_then(|url| self.endpoint.join(&url).ok())
+            else {
+                continue;
+            };
+            result.push(FeedItem {
+                pub_date: item.get_pub_date(),
+                view_url: item.link.clone(),
+                title: item.title.trim().to_owned(),
+                torrent_download_url,
+            });
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() -> Result<()> {
+        let xml = r#"<rss version="2.0">
+        <channel>
+        <title>ACG.RIP</title>
+        <item>
+        <title>[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [WebRip 1080p HEVC-10bit AAC]</title>
+        <pubDate>Sat, 23 Mar 2024 06:02:16 -0700</pubDate>
+        <link>https://acg.rip/t/300123</link>
+        <enclosure url="/t/300123.torrent" type="application/x-bittorrent"/>
+        </item>
+        <item>
+        <title>没有种子链接</title>
+        <pubDate>Sat, 23 Mar 2024 06:02:16 -0700</pubDate>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let client = Client::new_with_client(reqwest::Client::new(), "https://acg.rip", None)?;
+        let items = client.parse_rss(xml)?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].torrent_download_url.as_str(),
+            "https://acg.rip/t/300123.torrent"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search() -> Result<()> {
+        dotenv::dotenv()?;
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(true)
+            .init();
+        let acgrip = Client::from_env()?;
+        let result = acgrip.search("葬送的芙莉莲").await?;
+        for item in result {
+            println!("{} {}", item.title, item.torrent_download_url);
+        }
+        Ok(())
+    }
+}
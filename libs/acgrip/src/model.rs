@@ -0,0 +1,103 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use quick_xml::de::from_str;
+use serde::{Deserialize, Serialize};
+use utils::date::smart_parse_date;
+
+/// acg.rip RSS 根元素
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AcgripRss {
+    pub channel: Channel,
+}
+
+/// RSS 频道信息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Channel {
+    pub title: String,
+    #[serde(rename = "item", default)]
+    pub items: Vec<Item>,
+}
+
+/// RSS 条目，表示一个种子
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Item {
+    pub title: String,
+    /// 种子详情页
+    pub link: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+    pub enclosure: Option<Enclosure>,
+}
+
+/// 附件信息, acg.rip 只提供种子文件, 不提供磁力链接
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Enclosure {
+    #[serde(rename = "@type")]
+    pub enclosure_type: Option<String>,
+    #[serde(rename = "@url")]
+    pub url: String,
+}
+
+impl AcgripRss {
+    /// 从XML字符串解析RSS
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let rss: AcgripRss = from_str(xml)?;
+        Ok(rss)
+    }
+}
+
+impl Item {
+    /// 获取种子发布日期
+    pub fn get_pub_date(&self) -> Option<NaiveDateTime> {
+        self.pub_date
+            .as_ref()
+            .and_then(|date| smart_parse_date(date.trim()).ok())
+    }
+
+    /// 获取种子下载链接
+    pub fn get_torrent_url(&self) -> Option<String> {
+        self.enclosure
+            .as_ref()
+            .map(|enclosure| enclosure.url.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0">
+        <channel>
+        <title>ACG.RIP</title>
+        <description>ACG.RIP has super cow power</description>
+        <link>https://acg.rip/.xml?term=%E8%91%AC%E9%80%81%E7%9A%84%E8%8A%99%E8%8E%89%E8%8E%B2</link>
+        <ttl>1800</ttl>
+        <item>
+        <title>[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]</title>
+        <description>&lt;p&gt;LoliHouse&lt;/p&gt;</description>
+        <pubDate>Sat, 23 Mar 2024 06:02:16 -0700</pubDate>
+        <link>https://acg.rip/t/300123</link>
+        <guid>https://acg.rip/t/300123</guid>
+        <enclosure url="https://acg.rip/t/300123.torrent" type="application/x-bittorrent"/>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let rss = AcgripRss::from_xml(xml).unwrap();
+        assert_eq!(rss.channel.items.len(), 1);
+
+        let item = &rss.channel.items[0];
+        assert_eq!(
+            item.get_torrent_url().unwrap(),
+            "https://acg.rip/t/300123.torrent"
+        );
+        assert_eq!(item.link.as_deref(), Some("https://acg.rip/t/300123"));
+        assert_eq!(
+            item.get_pub_date().unwrap().to_string(),
+            "2024-03-23 13:02:16"
+        );
+    }
+}
@@ -0,0 +1,396 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures_util::StreamExt;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    model::{AddOptions, File, Gid, Notification, NotificationKind, Status},
+};
+
+/// aria2 JSON-RPC 客户端
+///
+/// 方法调用通过 HTTP 发送, 任务事件通过 WebSocket 订阅
+#[derive(Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    endpoint: Url,
+    secret: Option<String>,
+    id: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcNotification {
+    method: String,
+    params: Vec<NotificationParam>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationParam {
+    gid: Gid,
+}
+
+impl Client {
+    /// endpoint: RPC 地址, 如 http://127.0.0.1:6800/jsonrpc
+    pub fn new(cli: reqwest::Client, endpoint: Url, secret: Option<String>) -> Self {
+        Self {
+            cli,
+            endpoint,
+            secret: secret.filter(|s| !s.is_empty()),
+            id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub async fn add_uri(&self, uris: &[String], options: &AddOptions) -> Result<Gid> {
+        self.call("aria2.addUri", vec![json!(uris), json!(options)])
+            .await
+    }
+
+    pub async fn add_torrent(&self, torrent: &[u8], options: &AddOptions) -> Result<Gid> {
+        let encoded = STANDARD.encode(torrent);
+        self.call(
+            "aria2.addTorrent",
+            vec![json!(encoded), json!([]), json!(options)],
+        )
+        .await
+    }
+
+    /// keys 为空时返回全部字段
+    pub async fn tell_status(&self, gid: &str, keys: &[&str]) -> Result<Status> {
+        let mut params = vec![json!(gid)];
+        if !keys.is_empty() {
+            params.push(json!(keys));
+        }
+        self.call("aria2.tellStatus", params).await
+    }
+
+    pub async fn tell_active(&self, keys: &[&str]) -> Result<Vec<Status>> {
+        let mut params = vec![];
+        if !keys.is_empty() {
+            params.push(json!(keys));
+        }
+        self.call("aria2.tellActive", params).await
+    }
+
+    pub async fn get_files(&self, gid: &str) -> Result<Vec<File>> {
+        self.call("aria2.getFiles", vec![json!(gid)]).await
+    }
+
+    pub async fn pause(&self, gid: &str) -> Result<Gid> {
+        self.call("aria2.pause", vec![json!(gid)]).await
+    }
+
+    pub async fn unpause(&self, gid: &str) -> Result<Gid> {
+        self.call("aria2.unpause", vec![json!(gid)]).await
+    }
+
+    pub async fn remove(&self, gid: &str) -> Result<Gid> {
+        self.call("aria2.remove", vec![json!(gid)]).await
+    }
+
+    /// 从 aria2 的已停止任务列表中清除任务
+    pub async fn remove_download_result(&self, gid: &str) -> Result<()> {
+        let _: String = self
+            .call("aria2.removeDownloadResult", vec![json!(gid)])
+            .await?;
+        Ok(())
+    }
+
+    /// 订阅任务事件, 连接断开后 receiver 会返回 None, 需要调用方重新订阅
+    pub async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<Notification>> {
+        let (ws, _) = tokio_tungstenite::connect_async(self.ws_endpoint()?.as_str())
+            .await
+            .map_err(|e| Error::WebSocket(e.to_string()))?;
+        let (_, mut read) = ws.split();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Some(msg) = read.next().await {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        warn!("aria2 WebSocket error: {}", e);
+                        break;
+                    }
+                };
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                for notification in parse_notification(&text) {
+                    if tx.send(notification).is_err() {
+                        return;
+                    }
+                }
+            }
+            debug!("aria2 WebSocket closed");
+        });
+
+        Ok(rx)
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, mut params: Vec<Value>) -> Result<T> {
+        if let Some(secret) = &self.secret {
+            params.insert(0, json!(format!("token:{}", secret)));
+        }
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.id.fetch_add(1, Ordering::Relaxed).to_string(),
+            "method": method,
+            "params": params,
+        });
+        debug!("aria2 call: {}", method);
+
+        // aria2 出错时返回非 200 状态码, 响应体中仍包含错误信息
+        let resp: RpcResponse = self
+            .cli
+            .post(self.endpoint.clone())
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(err) = resp.error {
+            return Err(Error::Rpc {
+                code: err.code,
+                message: err.message,
+            });
+        }
+        let result = resp
+            .result
+            .ok_or_else(|| Error::BadResponse(format!("{} returned no result", method)))?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    fn ws_endpoint(&self) -> Result<Url> {
+        let mut url = self.endpoint.clone();
+        let scheme = match url.scheme() {
+            "https" | "wss" => "wss",
+            _ => "ws",
+        };
+        url.set_scheme(scheme)
+            .map_err(|_| Error::BadResponse(format!("invalid endpoint: {}", self.endpoint)))?;
+        Ok(url)
+    }
+}
+
+fn parse_notification(text: &str) -> Vec<Notification> {
+    let Ok(notification) = serde_json::from_str::<RpcNotification>(text) else {
+        return vec![];
+    };
+    let Some(kind) = NotificationKind::from_method(&notification.method) else {
+        return vec![];
+    };
+    notification
+        .params
+        .into_iter()
+        .map(|p| Notification { kind, gid: p.gid })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::SinkExt;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::Mutex,
+    };
+
+    use super::*;
+    use crate::model::TaskState;
+
+    type Handler = fn(&Value) -> Value;
+
+    /// 简单的 JSON-RPC 服务, 记录收到的请求, 使用 handler 生成响应
+    async fn mock_server(handler: Handler) -> (Url, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                tokio::spawn(serve(stream, handler, recorded));
+            }
+        });
+        let url = Url::parse(&format!("http://{}/jsonrpc", addr)).unwrap();
+        (url, requests)
+    }
+
+    async fn serve(mut stream: TcpStream, handler: Handler, recorded: Arc<Mutex<Vec<Value>>>) {
+        let mut buf = vec![];
+        loop {
+            let mut chunk = [0u8; 4096];
+            let n = match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => n,
+            };
+            buf.extend_from_slice(&chunk[..n]);
+
+            let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                continue;
+            };
+            let headers = String::from_utf8_lossy(&buf[..header_end]).to_ascii_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+            let body_start = header_end + 4;
+            if buf.len() < body_start + content_length {
+                continue;
+            }
+
+            let request: Value =
+                serde_json::from_slice(&buf[body_start..body_start + content_length]).unwrap();
+            buf.drain(..body_start + content_length);
+
+            let mut response = handler(&request);
+            response["jsonrpc"] = json!("2.0");
+            response["id"] = request["id"].clone();
+            recorded.lock().await.push(request);
+
+            let body = response.to_string();
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            if stream.write_all(resp.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn handler(request: &Value) -> Value {
+        let params = request["params"].as_array().unwrap();
+        match request["method"].as_str().unwrap() {
+            "aria2.addUri" | "aria2.addTorrent" => json!({"result": "2089b05ecca3d829"}),
+            "aria2.tellStatus" if params[1] == "2089b05ecca3d829" => json!({
+                "result": {
+                    "gid": "2089b05ecca3d829",
+                    "status": "active",
+                    "totalLength": "1024",
+                    "completedLength": "1024",
+                    "downloadSpeed": "0",
+                    "dir": "/downloads/Show",
+                    "followedBy": ["d2a8b8e3c5b1a1f0"],
+                    "files": [{
+                        "index": "1",
+                        "path": "/downloads/Show/01.mkv",
+                        "length": "1024",
+                        "completedLength": "1024",
+                        "selected": "true",
+                        "uris": []
+                    }]
+                }
+            }),
+            "aria2.tellStatus" => json!({
+                "error": {"code": 1, "message": format!("GID {} is not found", params[1])}
+            }),
+            "aria2.tellActive" => json!({"result": []}),
+            _ => json!({"result": params[1]}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls() -> Result<()> {
+        let (url, requests) = mock_server(handler).await;
+        let client = Client::new(reqwest::Client::new(), url, Some("secret".to_owned()));
+
+        let options = AddOptions {
+            dir: Some("/downloads/Show".to_owned()),
+            ..Default::default()
+        };
+        let gid = client
+            .add_uri(&["magnet:?xt=urn:btih:abc".to_owned()], &options)
+            .await?;
+        assert_eq!(gid, "2089b05ecca3d829");
+        client.add_torrent(b"d4:infodee", &options).await?;
+
+        let status = client.tell_status(&gid, &[]).await?;
+        assert_eq!(status.status, TaskState::Active);
+        assert!(status.is_seeding());
+        assert_eq!(status.followed_by, vec!["d2a8b8e3c5b1a1f0".to_owned()]);
+        assert_eq!(status.files[0].length, 1024);
+        assert!(status.files[0].selected);
+
+        let err = client
+            .tell_status("0000000000000000", &[])
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+
+        assert!(client.tell_active(&["gid"]).await?.is_empty());
+        assert_eq!(client.pause(&gid).await?, gid);
+        assert_eq!(client.unpause(&gid).await?, gid);
+        assert_eq!(client.remove(&gid).await?, gid);
+
+        let requests = requests.lock().await;
+        assert_eq!(requests[0]["method"], "aria2.addUri");
+        assert_eq!(
+            requests[0]["params"],
+            json!(["token:secret", ["magnet:?xt=urn:btih:abc"], {"dir": "/downloads/Show"}])
+        );
+        assert_eq!(requests[1]["params"][1], STANDARD.encode(b"d4:infodee"));
+        assert_eq!(requests[4]["params"], json!(["token:secret", ["gid"]]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            for method in [
+                "aria2.onDownloadStart",
+                "aria2.unknown",
+                "aria2.onDownloadComplete",
+            ] {
+                let msg = json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": [{"gid": "2089b05ecca3d829"}]
+                });
+                ws.send(Message::text(msg.to_string())).await.unwrap();
+            }
+            ws.close(None).await.unwrap();
+        });
+
+        let url = Url::parse(&format!("http://{}/jsonrpc", addr)).unwrap();
+        let client = Client::new(reqwest::Client::new(), url, None);
+        let mut rx = client.subscribe().await?;
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.kind, NotificationKind::DownloadStart);
+        let second = rx.recv().await.unwrap();
+        assert_eq!(second.kind, NotificationKind::DownloadComplete);
+        assert_eq!(second.gid, "2089b05ecca3d829");
+        assert!(rx.recv().await.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("aria2 returned error: code={code}, message={message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("API returned bad response: {0}")]
+    BadResponse(String),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    #[error("Invalid url: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("serde_json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// aria2 找不到对应的 GID, 通常是任务已被删除或 aria2 重启后丢失了已完成的任务
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::Rpc { message, .. } if message.contains("is not found"))
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
+/// aria2 任务 ID
+pub type Gid = String;
+
+/// 添加任务时的选项, aria2 的选项值都是字符串
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AddOptions {
+    /// 保存目录
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// 添加后是否暂停
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause: Option<String>,
+    /// 其他 aria2 选项, 如 seed-time
+    #[serde(flatten)]
+    pub extra: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    #[default]
+    Active,
+    Waiting,
+    Paused,
+    Error,
+    Complete,
+    Removed,
+}
+
+/// tellStatus 返回的任务状态, 指定 keys 时只返回部分字段, 其余使用默认值
+#[serde_as]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Status {
+    pub gid: Gid,
+    pub status: TaskState,
+    #[serde_as(as = "DisplayFromStr")]
+    pub total_length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub completed_length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub download_speed: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub upload_speed: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub connections: u64,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    pub dir: String,
+    pub files: Vec<File>,
+    pub info_hash: Option<String>,
+    /// 磁力链接或种子 URL 下载完元数据后, 实际的 BT 任务 GID
+    pub followed_by: Vec<Gid>,
+    pub following: Option<Gid>,
+    pub bittorrent: Option<BitTorrent>,
+}
+
+impl Status {
+    /// BT 任务下载完成后会继续做种, 状态仍为 active
+    pub fn is_seeding(&self) -> bool {
+        self.status == TaskState::Active
+            && self.total_length > 0
+            && self.completed_length == self.total_length
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BitTorrent {
+    pub info: Option<BitTorrentInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BitTorrentInfo {
+    pub name: String,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct File {
+    #[serde_as(as = "DisplayFromStr")]
+    pub index: u32,
+    pub path: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub completed_length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub selected: bool,
+}
+
+/// WebSocket 推送的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    DownloadStart,
+    DownloadPause,
+    DownloadStop,
+    DownloadComplete,
+    DownloadError,
+    BtDownloadComplete,
+}
+
+impl NotificationKind {
+    pub fn from_method(method: &str) -> Option<Self> {
+        match method {
+            "aria2.onDownloadStart" => Some(Self::DownloadStart),
+            "aria2.onDownloadPause" => Some(Self::DownloadPause),
+            "aria2.onDownloadStop" => Some(Self::DownloadStop),
+            "aria2.onDownloadComplete" => Some(Self::DownloadComplete),
+            "aria2.onDownloadError" => Some(Self::DownloadError),
+            "aria2.onBtDownloadComplete" => Some(Self::BtDownloadComplete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub gid: Gid,
+}
@@ -0,0 +1,435 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::header::{COOKIE, SET_COOKIE};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tracing::{debug, info};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    model::{AddOptions, FileTree, TorrentFile, TorrentId, UiUpdate},
+};
+
+/// Deluge Web JSON-RPC 客户端
+///
+/// 登录后使用 Cookie 保持会话, 会话过期时自动重新登录
+#[derive(Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    endpoint: Url,
+    password: String,
+    cookie: Arc<Mutex<Option<String>>>,
+    id: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Client {
+    /// url: Deluge Web 的地址, 如 http://127.0.0.1:8112
+    pub fn new<T: Into<String>>(cli: reqwest::Client, mut url: Url, password: T) -> Result<Self> {
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(Self {
+            cli,
+            endpoint: url.join("json")?,
+            password: password.into(),
+            cookie: Arc::new(Mutex::new(None)),
+            id: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// 登录并确保 Deluge Web 已连接到守护进程
+    pub async fn login(&self) -> Result<()> {
+        let (ok, cookie) = self
+            .send::<bool>("auth.login", vec![json!(self.password)], None)
+            .await?;
+        let cookie = cookie.filter(|_| ok).ok_or(Error::LoginFailed)?;
+        debug!("Deluge login success");
+
+        let session = Some(cookie.clone());
+        let (connected, _) = self
+            .send::<bool>("web.connected", vec![], session.clone())
+            .await?;
+        if !connected {
+            let (hosts, _) = self
+                .send::<Vec<Vec<Value>>>("web.get_hosts", vec![], session.clone())
+                .await?;
+            let host_id = hosts
+                .first()
+                .and_then(|host| host.first())
+                .and_then(|id| id.as_str())
+                .ok_or(Error::NoDaemon)?
+                .to_owned();
+            self.send::<Value>("web.connect", vec![json!(host_id)], session)
+                .await?;
+            info!("Deluge Web connected to daemon: {}", host_id);
+        }
+
+        *self.cookie.lock().unwrap() = Some(cookie);
+        Ok(())
+    }
+
+    pub async fn add_torrent_magnet(
+        &self,
+        uri: &str,
+        options: &AddOptions,
+    ) -> Result<Option<TorrentId>> {
+        self.call("core.add_torrent_magnet", vec![json!(uri), json!(options)])
+            .await
+    }
+
+    pub async fn add_torrent_file(
+        &self,
+        filename: &str,
+        data: &[u8],
+        options: &AddOptions,
+    ) -> Result<Option<TorrentId>> {
+        self.call(
+            "core.add_torrent_file",
+            vec![
+                json!(filename),
+                json!(STANDARD.encode(data)),
+                json!(options),
+            ],
+        )
+        .await
+    }
+
+    pub async fn add_torrent_url(
+        &self,
+        url: &str,
+        options: &AddOptions,
+    ) -> Result<Option<TorrentId>> {
+        self.call("core.add_torrent_url", vec![json!(url), json!(options)])
+            .await
+    }
+
+    /// Deluge Web 是否已连接到守护进程
+    pub async fn connected(&self) -> Result<bool> {
+        self.call("web.connected", vec![]).await
+    }
+
+    /// 获取指定种子的状态, ids 为空时返回全部种子
+    pub async fn update_ui(&self, keys: &[&str], ids: &[String]) -> Result<UiUpdate> {
+        let filter = if ids.is_empty() {
+            json!({})
+        } else {
+            json!({ "id": ids })
+        };
+        self.call("web.update_ui", vec![json!(keys), filter]).await
+    }
+
+    pub async fn get_torrent_files(&self, id: &str) -> Result<Vec<TorrentFile>> {
+        let tree: FileTree = self.call("web.get_torrent_files", vec![json!(id)]).await?;
+        Ok(tree.into_files())
+    }
+
+    pub async fn pause_torrent(&self, id: &str) -> Result<()> {
+        let _: Value = self.call("core.pause_torrent", vec![json!(id)]).await?;
+        Ok(())
+    }
+
+    pub async fn resume_torrent(&self, id: &str) -> Result<()> {
+        let _: Value = self.call("core.resume_torrent", vec![json!(id)]).await?;
+        Ok(())
+    }
+
+    pub async fn remove_torrent(&self, id: &str, remove_data: bool) -> Result<bool> {
+        self.call("core.remove_torrent", vec![json!(id), json!(remove_data)])
+            .await
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Vec<Value>) -> Result<T> {
+        if self.cookie.lock().unwrap().is_none() {
+            self.login().await?;
+        }
+        let cookie = self.cookie.lock().unwrap().clone();
+        match self.send(method, params.clone(), cookie).await {
+            Err(e) if e.is_not_authenticated() => {
+                debug!("Deluge session expired, logging in again");
+                *self.cookie.lock().unwrap() = None;
+                self.login().await?;
+                let cookie = self.cookie.lock().unwrap().clone();
+                Ok(self.send(method, params, cookie).await?.0)
+            }
+            result => Ok(result?.0),
+        }
+    }
+
+    /// 发送请求, 返回结果及响应中的 Cookie
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+        cookie: Option<String>,
+    ) -> Result<(T, Option<String>)> {
+        let body = json!({
+            "id": self.id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        debug!("Deluge call: {}", method);
+
+        let mut req = self.cli.post(self.endpoint.clone()).json(&body);
+        if let Some(cookie) = cookie {
+            req = req.header(COOKIE, cookie);
+        }
+        let resp = req.send().await?.error_for_status()?;
+        let cookie = resp
+            .headers()
+            .get(SET_COOKIE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.to_owned());
+        let resp: RpcResponse = resp.json().await?;
+
+        if let Some(err) = resp.error {
+            return Err(Error::Rpc {
+                code: err.code,
+                message: err.message,
+            });
+        }
+        let result = serde_json::from_value(resp.result.unwrap_or(Value::Null))?;
+        Ok((result, cookie))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::model::State;
+
+    const SESSION: &str = "_session_id=abc123";
+
+    #[derive(Default)]
+    struct MockState {
+        requests: Vec<Value>,
+        /// 模拟会话过期, 下一次请求返回未登录
+        expire_next: bool,
+        connected: bool,
+    }
+
+    /// 简单的 Deluge Web 服务, 检查 Cookie 并记录收到的请求
+    async fn mock_server() -> (Url, Arc<Mutex<MockState>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shared = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, shared.clone()));
+            }
+        });
+        let url = Url::parse(&format!("http://{}", addr)).unwrap();
+        (url, state)
+    }
+
+    async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+        let mut buf = vec![];
+        loop {
+            let mut chunk = [0u8; 4096];
+            let n = match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => n,
+            };
+            buf.extend_from_slice(&chunk[..n]);
+
+            let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                continue;
+            };
+            let headers = String::from_utf8_lossy(&buf[..header_end]).to_ascii_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+            let body_start = header_end + 4;
+            if buf.len() < body_start + content_length {
+                continue;
+            }
+            assert!(headers.starts_with("post /json "));
+            let authenticated = headers.contains(&SESSION.to_ascii_lowercase());
+
+            let request: Value =
+                serde_json::from_slice(&buf[body_start..body_start + content_length]).unwrap();
+            buf.drain(..body_start + content_length);
+
+            let (response, set_cookie) = handle(&request, authenticated, &state);
+            let body = json!({
+                "id": request["id"],
+                "result": response.get("result").cloned().unwrap_or(Value::Null),
+                "error": response.get("error").cloned().unwrap_or(Value::Null),
+            })
+            .to_string();
+            let cookie_header = if set_cookie {
+                format!(
+                    "Set-Cookie: {}; Expires=Tue, 01 Jan 2030 00:00:00 GMT; Path=/json\r\n",
+                    SESSION
+                )
+            } else {
+                String::new()
+            };
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\n\r\n{}",
+                cookie_header,
+                body.len(),
+                body
+            );
+            if stream.write_all(resp.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn handle(request: &Value, authenticated: bool, state: &Mutex<MockState>) -> (Value, bool) {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let method = request["method"].as_str().unwrap();
+        let params = &request["params"];
+
+        if method == "auth.login" {
+            return (json!({"result": params[0] == "deluge"}), true);
+        }
+        if !authenticated || state.expire_next {
+            state.expire_next = false;
+            return (
+                json!({"error": {"message": "Not authenticated", "code": 1}}),
+                false,
+            );
+        }
+        let response = match method {
+            "web.connected" => json!({"result": state.connected}),
+            "web.get_hosts" => json!({"result": [["e3b0c442", "127.0.0.1", 58846, "admin"]]}),
+            "web.connect" => {
+                state.connected = true;
+                json!({"result": []})
+            }
+            "core.add_torrent_magnet" | "core.add_torrent_file" | "core.add_torrent_url" => {
+                json!({"result": "0123456789abcdef0123456789abcdef01234567"})
+            }
+            "web.update_ui" => json!({"result": {
+                "connected": true,
+                "stats": {},
+                "torrents": {
+                    "0123456789abcdef0123456789abcdef01234567": {
+                        "name": "Show",
+                        "state": "Seeding",
+                        "progress": 100.0,
+                        "download_location": "/downloads/Show",
+                        "message": "OK"
+                    }
+                }
+            }}),
+            "web.get_torrent_files" => json!({"result": {
+                "type": "dir",
+                "contents": {
+                    "Show": {
+                        "type": "dir",
+                        "progress": 100.0,
+                        "contents": {
+                            "02.mkv": {"type": "file", "index": 1, "path": "Show/02.mkv", "size": 20, "priority": 0},
+                            "01.mkv": {"type": "file", "index": 0, "path": "Show/01.mkv", "size": 10, "priority": 1}
+                        }
+                    }
+                }
+            }}),
+            "core.remove_torrent" => json!({"result": true}),
+            _ => json!({"result": null}),
+        };
+        (response, false)
+    }
+
+    #[tokio::test]
+    async fn test_login_failed() {
+        let (url, _) = mock_server().await;
+        let client = Client::new(reqwest::Client::new(), url, "wrong").unwrap();
+        assert!(matches!(client.login().await, Err(Error::LoginFailed)));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls() -> Result<()> {
+        let (url, state) = mock_server().await;
+        let client = Client::new(reqwest::Client::new(), url, "deluge")?;
+
+        let options = AddOptions {
+            download_location: Some("/downloads/Show".to_owned()),
+            ..Default::default()
+        };
+        let id = client
+            .add_torrent_magnet("magnet:?xt=urn:btih:0123456789abcdef", &options)
+            .await?
+            .unwrap();
+        assert_eq!(id, "0123456789abcdef0123456789abcdef01234567");
+
+        let ui = client
+            .update_ui(&["name", "state", "progress"], std::slice::from_ref(&id))
+            .await?;
+        let torrent = &ui.torrents[&id];
+        assert_eq!(torrent.state, State::Seeding);
+        assert_eq!(torrent.location(), "/downloads/Show");
+
+        let files = client.get_torrent_files(&id).await?;
+        let paths: Vec<_> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.priority))
+            .collect();
+        assert_eq!(paths, vec![("Show/01.mkv", 1), ("Show/02.mkv", 0)]);
+
+        // 会话过期后自动重新登录
+        state.lock().unwrap().expire_next = true;
+        assert!(client.remove_torrent(&id, false).await?);
+
+        let state = state.lock().unwrap();
+        let methods: Vec<_> = state
+            .requests
+            .iter()
+            .map(|r| r["method"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                "auth.login",
+                "web.connected",
+                "web.get_hosts",
+                "web.connect",
+                "core.add_torrent_magnet",
+                "web.update_ui",
+                "web.get_torrent_files",
+                "core.remove_torrent",
+                "auth.login",
+                "web.connected",
+                "core.remove_torrent",
+            ]
+        );
+        assert_eq!(
+            state.requests[4]["params"],
+            json!(["magnet:?xt=urn:btih:0123456789abcdef", {"download_location": "/downloads/Show"}])
+        );
+        assert_eq!(
+            state.requests[5]["params"][1],
+            json!({"id": ["0123456789abcdef0123456789abcdef01234567"]})
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Deluge returned error: code={code}, message={message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("Login failed, please check the password")]
+    LoginFailed,
+
+    #[error("No deluge daemon available")]
+    NoDaemon,
+
+    #[error("API returned bad response: {0}")]
+    BadResponse(String),
+
+    #[error("Invalid url: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("serde_json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+/// Deluge Web 未登录或会话过期时返回的错误码
+pub(crate) const ERR_NOT_AUTHENTICATED: i64 = 1;
+
+impl Error {
+    pub fn is_not_authenticated(&self) -> bool {
+        matches!(self, Error::Rpc { code, .. } if *code == ERR_NOT_AUTHENTICATED)
+    }
+}
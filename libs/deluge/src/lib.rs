@@ -0,0 +1,6 @@
+pub mod client;
+pub mod error;
+pub mod model;
+
+pub use client::Client;
+pub use error::{Error, Result};
@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+/// Deluge 中的种子 ID, 即小写的 info hash
+pub type TorrentId = String;
+
+/// 添加种子时的选项
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AddOptions {
+    /// 保存目录
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_location: Option<String>,
+    /// 添加后是否暂停
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_paused: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+pub enum State {
+    Allocating,
+    Checking,
+    Downloading,
+    Seeding,
+    Paused,
+    Error,
+    Queued,
+    Moving,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/// web.update_ui 返回的种子状态, 只包含请求的字段, 其余使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TorrentStatus {
+    pub name: String,
+    pub state: State,
+    /// 0 - 100
+    pub progress: f64,
+    pub save_path: String,
+    pub download_location: String,
+    /// 错误信息, 正常时为 "OK"
+    pub message: String,
+    pub total_size: u64,
+    pub total_done: u64,
+    pub total_wanted: u64,
+    pub download_payload_rate: u64,
+    pub num_peers: u32,
+    pub eta: i64,
+}
+
+impl TorrentStatus {
+    /// Deluge 2.x 使用 download_location, 1.3 使用 save_path
+    pub fn location(&self) -> &str {
+        if self.download_location.is_empty() {
+            &self.save_path
+        } else {
+            &self.download_location
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UiUpdate {
+    pub connected: bool,
+    pub torrents: HashMap<TorrentId, TorrentStatus>,
+}
+
+/// web.get_torrent_files 返回的文件树
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FileTree {
+    Dir {
+        #[serde(default)]
+        contents: BTreeMap<String, FileTree>,
+    },
+    File(TorrentFile),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TorrentFile {
+    pub index: usize,
+    /// 相对于保存目录的路径
+    pub path: String,
+    pub size: u64,
+    #[serde(default)]
+    pub progress: f64,
+    /// 0 表示不下载
+    #[serde(default = "default_priority")]
+    pub priority: i64,
+}
+
+fn default_priority() -> i64 {
+    1
+}
+
+impl FileTree {
+    /// 展开为文件列表, 按文件在种子中的顺序排列
+    pub fn into_files(self) -> Vec<TorrentFile> {
+        let mut files = vec![];
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            match node {
+                FileTree::Dir { contents } => pending.extend(contents.into_values()),
+                FileTree::File(file) => files.push(file),
+            }
+        }
+        files.sort_by_key(|f| f.index);
+        files
+    }
+}
@@ -0,0 +1,190 @@
+use std::{num::NonZero, sync::Arc};
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use governor::{Quota, RateLimiter};
+use reqwest::Url;
+use tracing::{info, instrument};
+
+use crate::model::DmhyRss;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// 动画分类
+pub const SORT_ID_ANIME: i32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub max_requests_per_second: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TorrentItem {
+    pub title: String,
+    pub sub_group: Option<String>,
+    pub info_hash: String,
+    pub magnet_link: String,
+    pub view_url: Option<String>,
+    pub pub_date: Option<NaiveDateTime>,
+    pub file_size: u64,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    endpoint: Url,
+    limiter: Arc<
+        RateLimiter<
+            governor::state::NotKeyed,
+            governor::state::InMemoryState,
+            governor::clock::DefaultClock,
+        >,
+    >,
+}
+
+impl Client {
+    pub fn new_with_client(
+        cli: reqwest::Client,
+        endpoint: &str,
+        rate_limit_config: Option<RateLimitConfig>,
+    ) -> Result<Client> {
+        let rate_limit_config = rate_limit_config.unwrap_or_default();
+        let quota = Quota::per_second(
+            NonZero::new(rate_limit_config.max_requests_per_second)
+                .ok_or_else(|| anyhow::anyhow!("max_requests_per_second 不能为 0"))?,
+        )
+        .allow_burst(NonZero::new(1).unwrap());
+        Ok(Client {
+            cli,
+            endpoint: endpoint.parse()?,
+            limiter: Arc::new(RateLimiter::direct(quota)),
+        })
+    }
+
+    pub fn from_env() -> Result<Client> {
+        let endpoint = std::env::var("DMHY_ENDPOINT")?;
+        Self::new_with_client(reqwest::Client::new(), &endpoint, None)
+    }
+
+    async fn acquire(&self) {
+        let _ = self.limiter.until_n_ready(NonZero::new(1).unwrap()).await;
+    }
+
+    /// 通过 RSS 搜索种子
+    /// team_id: 字幕组ID, 为 None 时搜索所有字幕组
+    #[instrument(name = "搜索动漫花园种子", skip(self))]
+    pub async fn search(
+        &self,
+        keyword: &str,
+        sort_id: i32,
+        team_id: Option<i32>,
+    ) -> Result<Vec<TorrentItem>> {
+        let base_url = self.endpoint.join("topics/rss/rss.xml")?;
+        let url = Url::parse_with_params(
+            base_url.as_str(),
+            &[
+                ("keyword", keyword),
+                ("sort_id", sort_id.to_string().as_str()),
+                ("team_id", team_id.unwrap_or(0).to_string().as_str()),
+                ("order", "date-desc"),
+            ],
+        )?;
+        info!("url: {}", url);
+
+        self.acquire().await;
+        let xml = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Self::parse_rss(&xml)
+    }
+
+    /// 将 RSS 内容转换为种子列表, 没有磁力链接的条目会被忽略
+    pub fn parse_rss(xml: &str) -> Result<Vec<TorrentItem>> {
+        let rss = DmhyRss::from_xml(xml)?;
+
+        let mut result = Vec::new();
+        for item in rss.channel.items {
+            let (Some(info_hash), Some(magnet_link)) =
+                (item.get_info_hash(), item.get_magnet_link())
+            else {
+                continue;
+            };
+            result.push(TorrentItem {
+                info_hash,
+                magnet_link,
+                sub_group: item.author.clone(),
+                view_url: item.link.clone(),
+                pub_date: item.get_pub_date(),
+                file_size: item.get_file_size().unwrap_or(0),
+                title: item.title.trim().to_owned(),
+            });
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() -> Result<()> {
+        let xml = r#"<rss version="2.0">
+        <channel>
+        <title>動漫花園資源網</title>
+        <item>
+        <title><![CDATA[ [桜都字幕组] 葬送的芙莉莲 / Sousou no Frieren [28][1080p][简体内嵌] ]]></title>
+        <link>http://share.dmhy.org/topics/view/662600.html</link>
+        <pubDate>Sat, 23 Mar 2024 20:00:00 +0800</pubDate>
+        <description><![CDATA[<p>无大小信息</p>]]></description>
+        <enclosure url="magnet:?xt=urn:btih:8420088902677E8E142B35E2BC325E82C6CAFB04&amp;dn=" length="1" type="application/x-bittorrent"></enclosure>
+        <author><![CDATA[桜都字幕组]]></author>
+        </item>
+        <item>
+        <title><![CDATA[没有磁力链接]]></title>
+        <pubDate>Sat, 23 Mar 2024 20:00:00 +0800</pubDate>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let items = Client::parse_rss(xml)?;
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.info_hash, "8420088902677e8e142b35e2bc325e82c6cafb04");
+        assert_eq!(item.sub_group.as_deref(), Some("桜都字幕组"));
+        assert_eq!(item.file_size, 0);
+        assert!(item.title.starts_with("[桜都字幕组]"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search() -> Result<()> {
+        dotenv::dotenv()?;
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(true)
+            .init();
+        let dmhy = Client::from_env()?;
+        let result = dmhy.search("葬送的芙莉莲", SORT_ID_ANIME, None).await?;
+        for item in result {
+            println!("{} {} {}", item.title, item.info_hash, item.file_size);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use quick_xml::de::from_str;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utils::{date::smart_parse_date, size::parse_human_size};
+
+lazy_static::lazy_static! {
+    static ref INFO_HASH_RE: Regex = Regex::new(r"(?i)xt=urn:btih:([0-9a-z]+)").unwrap();
+    static ref SIZE_RE: Regex = Regex::new(r"(?i)(\d+(?:\.\d+)?)\s*([KMGT]i?B)\b").unwrap();
+}
+
+/// 动漫花园 RSS 根元素
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DmhyRss {
+    pub channel: Channel,
+}
+
+/// RSS 频道信息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Channel {
+    pub title: String,
+    #[serde(rename = "item", default)]
+    pub items: Vec<Item>,
+}
+
+/// RSS 条目，表示一个种子
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Item {
+    pub title: String,
+    /// 种子详情页
+    pub link: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+    pub description: Option<String>,
+    /// 发布者, 通常为字幕组名称
+    pub author: Option<String>,
+    pub enclosure: Option<Enclosure>,
+}
+
+/// 附件信息, 动漫花园的附件为磁力链接
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Enclosure {
+    #[serde(rename = "@type")]
+    pub enclosure_type: Option<String>,
+    #[serde(rename = "@url")]
+    pub url: String,
+}
+
+impl DmhyRss {
+    /// 从XML字符串解析RSS
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let rss: DmhyRss = from_str(xml)?;
+        Ok(rss)
+    }
+}
+
+impl Item {
+    /// 获取种子发布日期
+    pub fn get_pub_date(&self) -> Option<NaiveDateTime> {
+        self.pub_date
+            .as_ref()
+            .and_then(|date| smart_parse_date(date.trim()).ok())
+    }
+
+    /// 获取磁力链接
+    pub fn get_magnet_link(&self) -> Option<String> {
+        self.enclosure
+            .as_ref()
+            .map(|enclosure| enclosure.url.clone())
+            .filter(|url| url.starts_with("magnet:"))
+    }
+
+    /// 从磁力链接中提取信息哈希
+    /// 动漫花园的磁力链接使用 Base32 编码, 这里统一转换为小写十六进制
+    pub fn get_info_hash(&self) -> Option<String> {
+        let magnet = self.get_magnet_link()?;
+        let hash = INFO_HASH_RE.captures(&magnet)?.get(1)?.as_str();
+        match hash.len() {
+            40 if hash.chars().all(|c| c.is_ascii_hexdigit()) => Some(hash.to_lowercase()),
+            32 => base32_to_hex(hash),
+            _ => None,
+        }
+    }
+
+    /// 获取文件大小（字节）
+    /// RSS 中没有单独的大小字段, 只能尝试从描述或标题中提取
+    pub fn get_file_size(&self) -> Option<u64> {
+        [self.description.as_deref(), Some(self.title.as_str())]
+            .into_iter()
+            .flatten()
+            .find_map(|text| {
+                SIZE_RE
+                    .captures(text)
+                    .and_then(|caps| parse_human_size(&format!("{} {}", &caps[1], &caps[2])).ok())
+            })
+    }
+}
+
+/// 将 Base32 编码的信息哈希转换为十六进制
+fn base32_to_hex(s: &str) -> Option<String> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut bytes = Vec::with_capacity(20);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    for c in s.to_ascii_uppercase().bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u64;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if bytes.len() != 20 {
+        return None;
+    }
+    Some(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+        <rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:wfw="http://wellformedweb.org/CommentAPI/">
+        <channel>
+        <title><![CDATA[動漫花園資源網 - 動漫愛好者的自由交流平台]]></title>
+        <link>http://share.dmhy.org</link>
+        <description><![CDATA[動漫花園資訊網是一個動漫愛好者交流的平台,提供最及時,最全面的動畫,漫畫,動漫音樂,動漫下載,BT,ED,動漫遊戲,資訊,分享,交流,讨论.]]></description>
+        <language>zh-cn</language>
+        <pubDate>Sat, 23 Mar 2024 21:35:01 +0800</pubDate>
+        <item>
+        <title><![CDATA[[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]]]></title>
+        <link>http://share.dmhy.org/topics/view/662612_LoliHouse_Sousou_no_Frieren_-_28.html</link>
+        <pubDate>Sat, 23 Mar 2024 21:30:00 +0800</pubDate>
+        <description><![CDATA[<p>字幕：LoliHouse</p><p>[LoliHouse] Sousou no Frieren - 28.mkv 436.5MB</p>]]></description>
+        <enclosure url="magnet:?xt=urn:btih:QQQARCICM57I4FBLGXRLYMS6QLDMV6YE&amp;dn=&amp;tr=http%3A%2F%2F104.143.10.186%3A8000%2Fannounce" length="1" type="application/x-bittorrent"></enclosure>
+        <author><![CDATA[LoliHouse]]></author>
+        <guid isPermaLink="true">http://share.dmhy.org/topics/view/662612_LoliHouse_Sousou_no_Frieren_-_28.html</guid>
+        <category domain="http://share.dmhy.org/topics/list/sort_id/2"><![CDATA[動畫]]></category>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let rss = DmhyRss::from_xml(xml).unwrap();
+        assert_eq!(rss.channel.items.len(), 1);
+
+        let item = &rss.channel.items[0];
+        assert_eq!(item.author.as_deref(), Some("LoliHouse"));
+        assert_eq!(
+            item.get_info_hash().unwrap(),
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert!(item.get_magnet_link().unwrap().starts_with("magnet:?xt="));
+        assert_eq!(item.get_file_size(), Some(457_703_424));
+        assert_eq!(
+            item.get_pub_date().unwrap().to_string(),
+            "2024-03-23 13:30:00"
+        );
+    }
+
+    #[test]
+    fn test_base32_to_hex() {
+        assert_eq!(
+            base32_to_hex("QQQARCICM57I4FBLGXRLYMS6QLDMV6YE").unwrap(),
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert!(base32_to_hex("QQQARCICM57I4FBLGXRL").is_none());
+        assert!(base32_to_hex("1111111111111111111111111111111!").is_none());
+    }
+}
@@ -48,6 +48,24 @@ impl EpisodeItem {
     }
 }
 
+/// 条件请求的缓存校验信息, 来自上一次响应的 ETag 与 Last-Modified
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedValidator {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// 条件请求的结果
+#[derive(Debug, Clone)]
+pub enum FeedResult {
+    /// 服务端返回 304, RSS 内容没有变化
+    NotModified,
+    Modified {
+        items: Vec<EpisodeItem>,
+        validator: FeedValidator,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct BangumiInfo {
     pub bangumi_tv_id: Option<i32>,
@@ -116,8 +134,60 @@ impl Client {
             .text()
             .await?;
 
+        Self::parse_episode_items(&xml)
+    }
+
+    /// 使用条件请求获取番剧 RSS, 内容未变化时返回 [`FeedResult::NotModified`]
+    #[instrument(name = "增量爬取番剧种子信息", skip(self, validator), fields(bangumi_id = %bangumi_id))]
+    pub async fn collect_by_bangumi_id_if_modified(
+        &self,
+        bangumi_id: i32,
+        validator: &FeedValidator,
+    ) -> Result<FeedResult> {
+        let url = self
+            .endpoint
+            .join(format!("/RSS/Bangumi?bangumiId={}", bangumi_id).as_str())?;
+        info!("url: {}", url);
+
+        let mut req = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT.as_str());
+        if let Some(etag) = &validator.etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validator.last_modified {
+            req = req.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let resp = req.send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(FeedResult::NotModified);
+        }
+        let resp = resp.error_for_status()?;
+
+        let header = |name: reqwest::header::HeaderName| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_owned())
+        };
+        let validator = FeedValidator {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+        let xml = resp.text().await?;
+
+        Ok(FeedResult::Modified {
+            items: Self::parse_episode_items(&xml)?,
+            validator,
+        })
+    }
+
+    /// 将番剧 RSS 转换为剧集列表, 无效的条目会被忽略
+    pub fn parse_episode_items(xml: &str) -> Result<Vec<EpisodeItem>> {
         // 解析 XML 为 MikanRss 结构体
-        let rss = MikanRss::from_xml(&xml)?;
+        let rss = MikanRss::from_xml(xml)?;
 
         // 将 RSS 条目转换为 EpisodeItem
         let mut result = Vec::new();
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use reqwest::Url;
+use tracing::{info, instrument};
+
+use crate::model::NyaaRss;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Nyaa 搜索过滤器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    NoFilter = 0,
+    NoRemakes = 1,
+    TrustedOnly = 2,
+}
+
+impl TryFrom<u8> for Filter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::NoFilter),
+            1 => Ok(Self::NoRemakes),
+            2 => Ok(Self::TrustedOnly),
+            _ => Err(anyhow::anyhow!("invalid nyaa filter: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TorrentItem {
+    pub title: String,
+    pub info_hash: String,
+    pub magnet_link: String,
+    pub torrent_download_url: Option<Url>,
+    pub view_url: Option<String>,
+    pub pub_date: Option<NaiveDateTime>,
+    pub file_size: u64,
+    pub seeders: u32,
+    pub trusted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    endpoint: Url,
+}
+
+impl Client {
+    pub fn new_with_client(cli: reqwest::Client, endpoint: &str) -> Result<Client> {
+        Ok(Client {
+            cli,
+            endpoint: endpoint.parse()?,
+        })
+    }
+
+    pub fn from_env() -> Result<Client> {
+        let cli = reqwest::Client::new();
+        let endpoint = std::env::var("NYAA_ENDPOINT")?;
+        Ok(Client {
+            cli,
+            endpoint: endpoint.parse()?,
+        })
+    }
+
+    /// 通过 RSS 搜索种子
+    /// category: 分类, 如 1_0 (Anime), 1_2 (Anime - English-translated)
+    #[instrument(name = "搜索Nyaa种子", skip(self))]
+    pub async fn search(
+        &self,
+        keyword: &str,
+        category: &str,
+        filter: Filter,
+    ) -> Result<Vec<TorrentItem>> {
+        let url = Url::parse_with_params(
+            self.endpoint.as_str(),
+            &[
+                ("page", "rss"),
+                ("q", keyword),
+                ("c", category),
+                ("f", (filter as u8).to_string().as_str()),
+            ],
+        )?;
+        info!("url: {}", url);
+
+        let xml = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Self::parse_rss(&xml)
+    }
+
+    /// 将 RSS 内容转换为种子列表, 无法获取信息哈希的条目会被忽略
+    pub fn parse_rss(xml: &str) -> Result<Vec<TorrentItem>> {
+        let rss = NyaaRss::from_xml(xml)?;
+
+        let mut result = Vec::new();
+        for item in rss.channel.items {
+            let Some(info_hash) = item.get_info_hash() else {
+                continue;
+            };
+            result.push(TorrentItem {
+                magnet_link: format!("magnet:?xt=urn:btih:{}", info_hash),
+                info_hash,
+                torrent_download_url: Url::parse(&item.link).ok(),
+                view_url: item.get_view_url(),
+                pub_date: item.get_pub_date(),
+                file_size: item.get_file_size().unwrap_or(0),
+                seeders: item.seeders.unwrap_or(0),
+                trusted: item.is_trusted(),
+                title: item.title,
+            });
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() -> Result<()> {
+        let xml = r#"<rss xmlns:nyaa="https://nyaa.si/xmlns/nyaa" version="2.0">
+        <channel>
+        <title>Nyaa - "frieren" - Torrent File RSS</title>
+        <item>
+        <title>[SubsPlease] Sousou no Frieren - 28 (1080p) [8D1B5F8B].mkv</title>
+        <link>https://nyaa.si/download/1784245.torrent</link>
+        <guid isPermaLink="true">https://nyaa.si/view/1784245</guid>
+        <pubDate>Fri, 22 Mar 2024 16:02:16 -0000</pubDate>
+        <nyaa:seeders>1234</nyaa:seeders>
+        <nyaa:infoHash>3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b</nyaa:infoHash>
+        <nyaa:size>1.4 GiB</nyaa:size>
+        <nyaa:trusted>Yes</nyaa:trusted>
+        </item>
+        <item>
+        <title>no info hash</title>
+        <link>https://nyaa.si/download/1.torrent</link>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let items = Client::parse_rss(xml)?;
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(
+            item.magnet_link,
+            "magnet:?xt=urn:btih:3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b"
+        );
+        assert_eq!(
+            item.torrent_download_url.as_ref().map(|u| u.as_str()),
+            Some("https://nyaa.si/download/1784245.torrent")
+        );
+        assert_eq!(item.file_size, 1_503_238_553);
+        assert_eq!(item.seeders, 1234);
+        assert!(item.trusted);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search() -> Result<()> {
+        dotenv::dotenv()?;
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(true)
+            .init();
+        let nyaa = Client::from_env()?;
+        let result = nyaa
+            .search("Sousou no Frieren", "1_0", Filter::NoFilter)
+            .await?;
+        for item in result {
+            println!("{} {} {}", item.title, item.info_hash, item.file_size);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use quick_xml::de::from_str;
+use serde::{Deserialize, Serialize};
+use utils::{date::smart_parse_date, size::parse_human_size};
+
+/// Nyaa RSS 根元素
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NyaaRss {
+    pub channel: Channel,
+}
+
+/// RSS 频道信息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Channel {
+    pub title: String,
+    #[serde(rename = "item", default)]
+    pub items: Vec<Item>,
+}
+
+/// RSS 条目，表示一个种子
+///
+/// `nyaa:` 命名空间下的字段按本地名称匹配
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Item {
+    pub title: String,
+    /// 种子文件下载链接
+    pub link: String,
+    /// 种子详情页
+    pub guid: Option<Guid>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+    pub seeders: Option<u32>,
+    pub leechers: Option<u32>,
+    pub downloads: Option<u32>,
+    #[serde(rename = "infoHash")]
+    pub info_hash: Option<String>,
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<String>,
+    pub size: Option<String>,
+    pub trusted: Option<String>,
+    pub remake: Option<String>,
+}
+
+/// GUID 标识
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Guid {
+    #[serde(rename = "@isPermaLink")]
+    pub is_perma_link: Option<String>,
+    #[serde(rename = "$value")]
+    pub value: String,
+}
+
+impl NyaaRss {
+    /// 从XML字符串解析RSS
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let rss: NyaaRss = from_str(xml)?;
+        Ok(rss)
+    }
+}
+
+impl Item {
+    /// 获取种子发布日期
+    pub fn get_pub_date(&self) -> Option<NaiveDateTime> {
+        self.pub_date
+            .as_ref()
+            .and_then(|date| smart_parse_date(date).ok())
+    }
+
+    /// 获取信息哈希, 统一转换为小写
+    pub fn get_info_hash(&self) -> Option<String> {
+        self.info_hash
+            .as_ref()
+            .map(|hash| hash.trim().to_lowercase())
+            .filter(|hash| hash.len() == 40)
+    }
+
+    /// 获取文件大小（字节）
+    pub fn get_file_size(&self) -> Option<u64> {
+        self.size
+            .as_ref()
+            .and_then(|size| parse_human_size(size).ok())
+    }
+
+    /// 获取详情页链接
+    pub fn get_view_url(&self) -> Option<String> {
+        self.guid.as_ref().map(|guid| guid.value.clone())
+    }
+
+    pub fn is_trusted(&self) -> bool {
+        self.trusted.as_deref() == Some("Yes")
+    }
+
+    pub fn is_remake(&self) -> bool {
+        self.remake.as_deref() == Some("Yes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<rss xmlns:atom="http://www.w3.org/2005/Atom" xmlns:nyaa="https://nyaa.si/xmlns/nyaa" version="2.0">
+    <channel>
+        <title>Nyaa - "葬送的芙莉莲" - Torrent File RSS</title>
+        <description>RSS Feed for "葬送的芙莉莲"</description>
+        <link>https://nyaa.si/</link>
+        <atom:link href="https://nyaa.si/?page=rss" rel="self" type="application/rss+xml" />
+        <item>
+            <title>[LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [WebRip 1080p HEVC-10bit AAC][简繁内封字幕]</title>
+            <link>https://nyaa.si/download/1784245.torrent</link>
+            <guid isPermaLink="true">https://nyaa.si/view/1784245</guid>
+            <pubDate>Sat, 23 Mar 2024 13:02:16 -0000</pubDate>
+            <nyaa:seeders>312</nyaa:seeders>
+            <nyaa:leechers>3</nyaa:leechers>
+            <nyaa:downloads>5021</nyaa:downloads>
+            <nyaa:infoHash>8420088902677E8E142B35E2BC325E82C6CAFB04</nyaa:infoHash>
+            <nyaa:categoryId>1_3</nyaa:categoryId>
+            <nyaa:category>Anime - Non-English-translated</nyaa:category>
+            <nyaa:size>436.5 MiB</nyaa:size>
+            <nyaa:comments>0</nyaa:comments>
+            <nyaa:trusted>Yes</nyaa:trusted>
+            <nyaa:remake>No</nyaa:remake>
+            <description><![CDATA[<a href="https://nyaa.si/view/1784245">#1784245 | [LoliHouse] 葬送的芙莉莲</a> | 436.5 MiB | Anime - Non-English-translated | 8420088902677E8E142B35E2BC325E82C6CAFB04]]></description>
+        </item>
+        <item>
+            <title>[Nekomoe kissaten] Sousou no Frieren - 28 [1080p]</title>
+            <link>https://nyaa.si/download/1784100.torrent</link>
+            <guid isPermaLink="true">https://nyaa.si/view/1784100</guid>
+            <pubDate>Sat, 23 Mar 2024 08:40:00 -0000</pubDate>
+            <nyaa:seeders>10</nyaa:seeders>
+            <nyaa:leechers>0</nyaa:leechers>
+            <nyaa:downloads>100</nyaa:downloads>
+            <nyaa:infoHash>invalid</nyaa:infoHash>
+            <nyaa:categoryId>1_3</nyaa:categoryId>
+            <nyaa:size>1.2 GiB</nyaa:size>
+            <nyaa:trusted>No</nyaa:trusted>
+            <nyaa:remake>Yes</nyaa:remake>
+        </item>
+    </channel>
+</rss>"#;
+
+    #[test]
+    fn test_parse_rss() {
+        let rss = NyaaRss::from_xml(RSS).unwrap();
+        assert_eq!(rss.channel.items.len(), 2);
+
+        let item = &rss.channel.items[0];
+        assert_eq!(
+            item.get_info_hash().unwrap(),
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert_eq!(item.link, "https://nyaa.si/download/1784245.torrent");
+        assert_eq!(item.get_view_url().unwrap(), "https://nyaa.si/view/1784245");
+        assert_eq!(item.get_file_size(), Some(457_703_424));
+        assert_eq!(item.seeders, Some(312));
+        assert!(item.is_trusted());
+        assert!(!item.is_remake());
+        assert_eq!(
+            item.get_pub_date().unwrap().to_string(),
+            "2024-03-23 13:02:16"
+        );
+
+        let item = &rss.channel.items[1];
+        assert!(item.get_info_hash().is_none());
+        assert!(item.is_remake());
+    }
+}
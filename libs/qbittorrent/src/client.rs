@@ -16,7 +16,7 @@ use crate::{
 };
 use crate::{
     ext::TORRENT_NOT_FOUND,
-    model::{Credential, Sep, torrent::TorrentContent},
+    model::{Credential, Sep, sync::MainData, torrent::TorrentContent},
 };
 const NONE: Option<&'static ()> = Option::None;
 
@@ -96,6 +96,16 @@ impl Client {
         self.request(Method::POST, "auth/logout", NONE).await?.end()
     }
 
+    /// Get the application version, also useful to check whether the login
+    /// is still valid.
+    pub async fn app_version(&self) -> Result<String> {
+        self.get("app/version")
+            .await?
+            .text()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn get_torrent_list(&self, arg: GetTorrentListArg) -> Result<Vec<Torrent>> {
         self.get_with("torrents/info", &arg)
             .await?
@@ -104,6 +114,21 @@ impl Client {
             .map_err(Into::into)
     }
 
+    /// Get the changes since the response with `rid`, use `0` to request all
+    /// the data.
+    pub async fn sync_maindata(&self, rid: i64) -> Result<MainData> {
+        #[derive(Serialize)]
+        struct Arg {
+            rid: i64,
+        }
+
+        self.get_with("sync/maindata", &Arg { rid })
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn add_torrent(&self, arg: impl Borrow<AddTorrentArg> + Send + Sync) -> Result<()> {
         let a: &AddTorrentArg = arg.borrow();
         match &a.source {
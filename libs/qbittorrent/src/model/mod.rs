@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use tap::Pipe;
 
+pub mod sync;
 pub mod torrent;
 
 /// Username and password used to authenticate with qBittorrent.
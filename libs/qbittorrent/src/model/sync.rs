@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::torrent::Torrent;
+use crate::error::Result;
+
+/// Response of `/api/v2/sync/maindata`.
+///
+/// Unless `full_update` is set, `torrents` only contains the fields that
+/// changed since the response with the requested `rid`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MainData {
+    /// Response ID, pass it to the next request to receive only the changes
+    pub rid: i64,
+    /// Whether the response contains all the data instead of the changes
+    #[serde(default)]
+    pub full_update: bool,
+    /// Changed torrents, keyed by hash
+    #[serde(default)]
+    pub torrents: HashMap<String, Map<String, Value>>,
+    /// Hashes of the torrents removed since the last response
+    #[serde(default)]
+    pub torrents_removed: Vec<String>,
+}
+
+impl MainData {
+    /// Whether any torrent changed its state or was removed, i.e. the changes
+    /// are more than progress and speed updates.
+    pub fn has_state_changes(&self) -> bool {
+        self.full_update
+            || !self.torrents_removed.is_empty()
+            || self
+                .torrents
+                .values()
+                .any(|fields| fields.contains_key("state"))
+    }
+}
+
+/// Torrent list kept up to date by applying incremental [`MainData`]
+/// responses.
+#[derive(Debug, Clone, Default)]
+pub struct SyncState {
+    rid: i64,
+    torrents: HashMap<String, Map<String, Value>>,
+}
+
+impl SyncState {
+    /// The `rid` to use for the next `sync/maindata` request.
+    pub fn rid(&self) -> i64 {
+        self.rid
+    }
+
+    pub fn len(&self) -> usize {
+        self.torrents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.torrents.is_empty()
+    }
+
+    /// Merge a `sync/maindata` response into the current state.
+    pub fn apply(&mut self, data: MainData) {
+        if data.full_update {
+            self.torrents.clear();
+        }
+        for (hash, fields) in data.torrents {
+            self.torrents.entry(hash).or_default().extend(fields);
+        }
+        for hash in &data.torrents_removed {
+            self.torrents.remove(hash);
+        }
+        self.rid = data.rid;
+    }
+
+    /// Get the merged torrent by hash, `None` if the torrent is unknown.
+    pub fn get(&self, hash: &str) -> Result<Option<Torrent>> {
+        let Some(fields) = self.torrents.get(hash) else {
+            return Ok(None);
+        };
+        let mut torrent: Torrent = serde_json::from_value(Value::Object(fields.clone()))?;
+        torrent.hash = Some(hash.to_owned());
+        Ok(Some(torrent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::torrent::State;
+
+    fn main_data(value: Value) -> MainData {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let mut state = SyncState::default();
+        state.apply(main_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": {
+                "aaa": {"name": "a", "state": "downloading", "progress": 0.5, "dlspeed": 1024},
+                "bbb": {"name": "b", "state": "uploading", "progress": 1.0}
+            }
+        })));
+        assert_eq!(state.rid(), 1);
+        assert_eq!(state.len(), 2);
+
+        state.apply(main_data(json!({
+            "rid": 2,
+            "torrents": {"aaa": {"progress": 0.75}},
+            "torrents_removed": ["bbb"]
+        })));
+        assert_eq!(state.rid(), 2);
+        assert!(state.get("bbb").unwrap().is_none());
+
+        let torrent = state.get("aaa").unwrap().unwrap();
+        assert_eq!(torrent.hash.as_deref(), Some("aaa"));
+        assert_eq!(torrent.name.as_deref(), Some("a"));
+        assert_eq!(torrent.state, Some(State::Downloading));
+        assert_eq!(torrent.progress, Some(0.75));
+        assert_eq!(torrent.dlspeed, Some(1024));
+    }
+
+    #[test]
+    fn test_full_update_replaces_state() {
+        let mut state = SyncState::default();
+        state.apply(main_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": {"aaa": {"name": "a"}}
+        })));
+        state.apply(main_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": {"bbb": {"name": "b"}}
+        })));
+        assert!(state.get("aaa").unwrap().is_none());
+        assert!(state.get("bbb").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_get_invalid_torrent() {
+        let mut state = SyncState::default();
+        state.apply(main_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": {"aaa": {"name": "a", "progress": "half"}}
+        })));
+        assert!(state.get("aaa").is_err());
+    }
+
+    #[test]
+    fn test_has_state_changes() {
+        let progress = main_data(json!({
+            "rid": 2,
+            "torrents": {"aaa": {"progress": 0.5, "dlspeed": 1024}}
+        }));
+        assert!(!progress.has_state_changes());
+
+        let state = main_data(json!({
+            "rid": 3,
+            "torrents": {"aaa": {"state": "uploading", "progress": 1.0}}
+        }));
+        assert!(state.has_state_changes());
+
+        let removed = main_data(json!({"rid": 4, "torrents_removed": ["aaa"]}));
+        assert!(removed.has_state_changes());
+    }
+}
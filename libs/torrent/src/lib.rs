@@ -141,6 +141,14 @@ impl Torrent {
         Ok(hex_hash)
     }
 
+    /// 获取种子内所有文件的总大小（字节）
+    pub fn total_size(&self) -> i64 {
+        match &self.info.files {
+            Some(files) => files.iter().map(|file| file.length).sum(),
+            None => self.info.length.unwrap_or(0),
+        }
+    }
+
     /// 获取 Magnet URI
     pub fn magnet_link(&self) -> Result<String> {
         let info_hash = self.info_hash()?;
@@ -307,6 +315,8 @@ mod tests {
             rest: BTreeMap::new(),
         };
 
+        assert_eq!(torrent.total_size(), 1024);
+
         // 测试 info_hash 方法
         let info_hash = torrent.info_hash().unwrap();
         assert_eq!(info_hash.len(), 20); // SHA1 哈希应该是 20 字节
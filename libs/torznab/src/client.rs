@@ -0,0 +1,421 @@
+use std::{num::NonZero, str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use governor::{Quota, RateLimiter};
+use reqwest::Url;
+use tracing::{info, instrument, warn};
+
+use crate::model::TorznabRss;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// 订阅源地址中的搜索关键词占位符
+pub const QUERY_PLACEHOLDER: &str = "{query}";
+
+/// 订阅源类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeedKind {
+    /// Torznab 接口 (Jackett/Prowlarr), 使用 t=search&q= 搜索
+    #[default]
+    Torznab,
+    /// 普通 RSS, 地址中的 {query} 会被替换为搜索关键词
+    Rss,
+}
+
+impl FromStr for FeedKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "torznab" => Ok(Self::Torznab),
+            "rss" => Ok(Self::Rss),
+            _ => Err(anyhow::anyhow!("invalid feed kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub max_requests_per_second: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TorrentItem {
+    pub title: String,
+    pub info_hash: String,
+    pub magnet_link: Option<String>,
+    pub torrent_download_url: Option<String>,
+    pub view_url: Option<String>,
+    pub pub_date: Option<NaiveDateTime>,
+    pub file_size: u64,
+    pub seeders: Option<u32>,
+    /// 条目没有信息哈希时下载的种子文件
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    cli: reqwest::Client,
+    url: String,
+    kind: FeedKind,
+    api_key: Option<String>,
+    categories: Vec<String>,
+    limiter: Arc<
+        RateLimiter<
+            governor::state::NotKeyed,
+            governor::state::InMemoryState,
+            governor::clock::DefaultClock,
+        >,
+    >,
+}
+
+impl Client {
+    /// url: Torznab 接口地址, 如 http://localhost:9696/1/api;
+    /// 或普通 RSS 地址, 如 https://example.com/rss?keyword={query}
+    pub fn new_with_client(
+        cli: reqwest::Client,
+        url: &str,
+        kind: FeedKind,
+        api_key: Option<String>,
+        categories: Vec<String>,
+        rate_limit_config: Option<RateLimitConfig>,
+    ) -> Result<Client> {
+        // 校验地址格式, 占位符会被编码, 不影响校验
+        let _: Url = url.parse()?;
+        let rate_limit_config = rate_limit_config.unwrap_or_default();
+        let quota = Quota::per_second(
+            NonZero::new(rate_limit_config.max_requests_per_second)
+                .ok_or_else(|| anyhow::anyhow!("max_requests_per_second 不能为 0"))?,
+        )
+        .allow_burst(NonZero::new(1).unwrap());
+        Ok(Client {
+            cli,
+            url: url.to_owned(),
+            kind,
+            api_key: api_key.filter(|key| !key.is_empty()),
+            categories,
+            limiter: Arc::new(RateLimiter::direct(quota)),
+        })
+    }
+
+    async fn acquire(&self) {
+        let _ = self.limiter.until_n_ready(NonZero::new(1).unwrap()).await;
+    }
+
+    /// 构造搜索地址
+    pub fn search_url(&self, query: &str) -> Result<Url> {
+        match self.kind {
+            FeedKind::Torznab => {
+                let mut params = vec![("t", "search".to_owned()), ("q", query.to_owned())];
+                if let Some(api_key) = &self.api_key {
+                    params.push(("apikey", api_key.clone()));
+                }
+                if !self.categories.is_empty() {
+                    params.push(("cat", self.categories.join(",")));
+                }
+                Ok(Url::parse_with_params(&self.url, &params)?)
+            }
+            FeedKind::Rss => {
+                let url = self
+                    .url
+                    .replace(QUERY_PLACEHOLDER, &urlencoding::encode(query));
+                Ok(url.parse()?)
+            }
+        }
+    }
+
+    /// 搜索种子
+    #[instrument(name = "搜索Torznab种子", skip(self))]
+    pub async fn search(&self, query: &str) -> Result<Vec<TorrentItem>> {
+        let url = self.search_url(query)?;
+        info!("url: {}", url);
+
+        self.acquire().await;
+        let xml = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let items = Self::parse_rss(&xml)?;
+        Ok(self.resolve_info_hashes(items).await)
+    }
+
+    /// 将 RSS 内容转换为种子列表
+    ///
+    /// 无法从条目中获取信息哈希时, 如果有种子下载地址, 保留条目并将 info_hash 置空,
+    /// 由 [`Client::search`] 下载种子文件后计算, 否则忽略该条目
+    pub fn parse_rss(xml: &str) -> Result<Vec<TorrentItem>> {
+        let rss = TorznabRss::from_xml(xml)?;
+
+        let mut result = Vec::new();
+        for item in rss.channel.items {
+            let torrent_download_url = item.get_torrent_url();
+            let info_hash = match item.get_info_hash() {
+                Some(info_hash) => info_hash,
+                None if torrent_download_url.is_some() => String::new(),
+                None => {
+                    warn!(
+                        "条目没有信息哈希及种子下载地址, 忽略: {}",
+                        item.title.trim()
+                    );
+                    continue;
+                }
+            };
+            result.push(TorrentItem {
+                info_hash,
+                magnet_link: item.get_magnet_link(),
+                torrent_download_url,
+                view_url: item.comments.clone(),
+                pub_date: item.get_pub_date(),
+                file_size: item.get_file_size().unwrap_or(0),
+                seeders: item.get_seeders(),
+                data: None,
+                title: item.title.trim().to_owned(),
+            });
+        }
+        Ok(result)
+    }
+
+    /// 为没有信息哈希的条目下载种子文件并计算信息哈希, 种子文件保存在条目中,
+    /// 下载或解析失败的条目会被忽略
+    async fn resolve_info_hashes(&self, items: Vec<TorrentItem>) -> Vec<TorrentItem> {
+        let mut result = Vec::with_capacity(items.len());
+        for mut item in items {
+            if item.info_hash.is_empty() {
+                let Some(url) = item.torrent_download_url.as_deref() else {
+                    continue;
+                };
+                match self.fetch_torrent(url).await {
+                    Ok((info_hash, data)) => {
+                        item.info_hash = info_hash;
+                        item.data = Some(data);
+                    }
+                    Err(e) => {
+                        warn!("获取种子 {} 的信息哈希失败, 忽略: {}", item.title, e);
+                        continue;
+                    }
+                }
+            }
+            result.push(item);
+        }
+        result
+    }
+
+    /// 下载种子文件, 返回信息哈希及文件内容
+    async fn fetch_torrent(&self, url: &str) -> Result<(String, Vec<u8>)> {
+        self.acquire().await;
+        let data = self
+            .cli
+            .get(url)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec();
+        let info_hash = torrent::Torrent::from_bytes(&data)?.info_hash_str()?;
+        Ok((info_hash, data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    const FIXTURE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+    <rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+    <channel>
+    <title>Prowlarr</title>
+    <item>
+    <title>[SubsPlease] Sousou no Frieren - 28 (1080p) [8D1B5F8B].mkv</title>
+    <comments>https://nyaa.si/view/1784245</comments>
+    <pubDate>Fri, 22 Mar 2024 16:02:16 +0000</pubDate>
+    <enclosure url="http://localhost:9696/1/download?link=abc" length="1444674560" type="application/x-bittorrent" />
+    <torznab:attr name="seeders" value="120" />
+    <torznab:attr name="infohash" value="3B2C5E7A9F1D4C6B8A0E2F4D6C8B0A2E4F6D8C0B" />
+    </item>
+    <item>
+    <title>没有信息哈希</title>
+    <enclosure url="http://localhost:9696/1/download?link=def" length="1" type="application/x-bittorrent" />
+    </item>
+    </channel>
+    </rss>"#;
+
+    /// 没有信息哈希的种子文件
+    const TORRENT: &[u8] = b"d8:announce15:http://tracker/4:infod6:lengthi5e4:name5:a.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+
+    /// 本地 HTTP 桩服务, 返回固定的 RSS 内容, 并回传收到的请求行
+    async fn serve_fixture(body: &'static str) -> Result<(String, oneshot::Receiver<String>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            let _ = tx.send(request.lines().next().unwrap_or_default().to_owned());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        Ok((format!("http://{}", addr), rx))
+    }
+
+    /// 本地 HTTP 桩服务, 按请求路径返回内容, 内容中的 {endpoint} 会被替换为服务地址
+    async fn serve_files(files: Vec<(&'static str, Vec<u8>)>) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let files: Vec<_> = files
+            .into_iter()
+            .map(|(path, body)| {
+                let body = match String::from_utf8(body) {
+                    Ok(text) => text.replace("{endpoint}", &endpoint).into_bytes(),
+                    Err(e) => e.into_bytes(),
+                };
+                (path, body)
+            })
+            .collect();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match files.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", vec![]),
+                };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+            }
+        });
+        Ok(endpoint)
+    }
+
+    #[tokio::test]
+    async fn test_search_torznab() -> Result<()> {
+        let (endpoint, request) = serve_fixture(FIXTURE).await?;
+        let client = Client::new_with_client(
+            reqwest::Client::new(),
+            &format!("{}/1/api", endpoint),
+            FeedKind::Torznab,
+            Some("secret".to_owned()),
+            vec!["5070".to_owned(), "100001".to_owned()],
+            None,
+        )?;
+
+        let items = client.search("葬送的芙莉莲 2").await?;
+        let request = request.await?;
+        assert!(request.starts_with("GET /1/api?t=search&q="));
+        assert!(request.contains("apikey=secret"));
+        assert!(request.contains("cat=5070%2C100001"));
+
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.info_hash, "3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b");
+        assert_eq!(item.file_size, 1444674560);
+        assert_eq!(item.seeders, Some(120));
+        assert!(item.magnet_link.is_none());
+        assert_eq!(
+            item.torrent_download_url.as_deref(),
+            Some("http://localhost:9696/1/download?link=abc")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_rss() -> Result<()> {
+        let (endpoint, request) = serve_fixture(FIXTURE).await?;
+        let client = Client::new_with_client(
+            reqwest::Client::new(),
+            &format!("{}/rss?keyword={{query}}&order=date", endpoint),
+            FeedKind::Rss,
+            None,
+            vec![],
+            None,
+        )?;
+
+        let items = client.search("Frieren 28").await?;
+        assert_eq!(
+            request.await?,
+            "GET /rss?keyword=Frieren%2028&order=date HTTP/1.1"
+        );
+        assert_eq!(items.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_resolve_info_hash() -> Result<()> {
+        let rss = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0">
+        <channel>
+        <title>RSS</title>
+        <item>
+        <title>没有信息哈希</title>
+        <enclosure url="{endpoint}/download/1" length="5" type="application/x-bittorrent" />
+        </item>
+        <item>
+        <title>种子下载失败</title>
+        <enclosure url="{endpoint}/download/2" length="5" type="application/x-bittorrent" />
+        </item>
+        <item>
+        <title>没有下载地址</title>
+        </item>
+        </channel>
+        </rss>"#;
+        let endpoint = serve_files(vec![
+            ("/rss", rss.as_bytes().to_vec()),
+            ("/download/1", TORRENT.to_vec()),
+        ])
+        .await?;
+        let client = Client::new_with_client(
+            reqwest::Client::new(),
+            &format!("{}/rss", endpoint),
+            FeedKind::Rss,
+            None,
+            vec![],
+            Some(RateLimitConfig {
+                max_requests_per_second: 100,
+            }),
+        )?;
+
+        let items = client.search("").await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "没有信息哈希");
+        assert_eq!(
+            items[0].info_hash,
+            torrent::Torrent::from_bytes(TORRENT)?.info_hash_str()?
+        );
+        assert_eq!(items[0].data.as_deref(), Some(TORRENT));
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use quick_xml::de::from_str;
+use serde::{Deserialize, Serialize};
+use utils::{date::smart_parse_date, size::parse_human_size};
+
+/// RSS 根元素, 同时兼容 Torznab 与普通 RSS
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TorznabRss {
+    pub channel: Channel,
+}
+
+/// RSS 频道信息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Channel {
+    pub title: Option<String>,
+    #[serde(rename = "item", default)]
+    pub items: Vec<Item>,
+}
+
+/// RSS 条目，表示一个种子
+///
+/// `torznab:attr` 按本地名称 `attr` 匹配
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Item {
+    pub title: String,
+    pub link: Option<String>,
+    /// 种子详情页
+    pub comments: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+    pub size: Option<String>,
+    pub enclosure: Option<Enclosure>,
+    #[serde(rename = "attr", default)]
+    pub attrs: Vec<Attr>,
+    /// Mikan RSS 的扩展信息
+    pub torrent: Option<TorrentExt>,
+}
+
+/// Mikan RSS 中 `<torrent>` 元素, 发布时间只存在于该元素中
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TorrentExt {
+    #[serde(rename = "contentLength")]
+    pub content_length: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+}
+
+/// 附件信息, 可能是种子文件链接或磁力链接
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Enclosure {
+    #[serde(rename = "@url")]
+    pub url: String,
+    #[serde(rename = "@length")]
+    pub length: Option<String>,
+    #[serde(rename = "@type")]
+    pub enclosure_type: Option<String>,
+}
+
+/// Torznab 扩展属性, 如 `<torznab:attr name="infohash" value="..."/>`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Attr {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@value")]
+    pub value: String,
+}
+
+impl TorznabRss {
+    /// 从XML字符串解析RSS
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let rss: TorznabRss = from_str(xml)?;
+        Ok(rss)
+    }
+}
+
+impl Item {
+    /// 获取 Torznab 扩展属性
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.name.eq_ignore_ascii_case(name))
+            .map(|attr| attr.value.trim())
+    }
+
+    /// 获取种子发布日期
+    pub fn get_pub_date(&self) -> Option<NaiveDateTime> {
+        self.pub_date
+            .as_ref()
+            .or_else(|| self.torrent.as_ref().and_then(|t| t.pub_date.as_ref()))
+            .and_then(|date| smart_parse_date(date.trim()).ok())
+    }
+
+    /// 获取磁力链接, 依次尝试 magneturl 属性、附件链接、条目链接
+    pub fn get_magnet_link(&self) -> Option<String> {
+        self.get_attr("magneturl")
+            .map(|url| url.to_owned())
+            .into_iter()
+            .chain(self.enclosure.as_ref().map(|e| e.url.clone()))
+            .chain(self.link.clone())
+            .find(|url| url.starts_with("magnet:"))
+    }
+
+    /// 获取种子文件下载链接
+    pub fn get_torrent_url(&self) -> Option<String> {
+        self.enclosure
+            .as_ref()
+            .map(|e| e.url.clone())
+            .into_iter()
+            .chain(self.link.clone())
+            .find(|url| url.starts_with("http://") || url.starts_with("https://"))
+    }
+
+    /// 获取信息哈希, 统一转换为小写
+    ///
+    /// 优先使用 infohash 属性, 其次从磁力链接中提取,
+    /// 最后尝试种子文件名 (如 Mikan 的种子以信息哈希命名)
+    pub fn get_info_hash(&self) -> Option<String> {
+        let from_magnet = || {
+            self.get_magnet_link().and_then(|magnet| {
+                magnet
+                    .split(['?', '&'])
+                    .find_map(|param| param.strip_prefix("xt=urn:btih:"))
+                    .map(|hash| hash.to_owned())
+            })
+        };
+        let from_torrent_url = || {
+            self.get_torrent_url().and_then(|url| {
+                url.split(['?', '#'])
+                    .next()
+                    .and_then(|path| path.rsplit('/').next())
+                    .and_then(|name| name.strip_suffix(".torrent"))
+                    .map(|hash| hash.to_owned())
+            })
+        };
+        [
+            self.get_attr("infohash").map(|hash| hash.to_owned()),
+            from_magnet(),
+            from_torrent_url(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|hash| hash.trim().to_lowercase())
+        .find(|hash| hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    /// 获取文件大小（字节）, 依次尝试 size 属性、size 元素、附件长度
+    ///
+    /// 部分站点的附件长度固定为 1, 视为未知
+    pub fn get_file_size(&self) -> Option<u64> {
+        self.get_attr("size")
+            .into_iter()
+            .chain(self.size.as_deref())
+            .chain(self.enclosure.as_ref().and_then(|e| e.length.as_deref()))
+            .filter_map(|size| parse_human_size(size).ok())
+            .find(|size| *size > 1)
+    }
+
+    pub fn get_seeders(&self) -> Option<u32> {
+        self.get_attr("seeders").and_then(|s| s.parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_torznab() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+        <channel>
+        <atom:link rel="self" type="application/rss+xml" />
+        <title>Prowlarr</title>
+        <item>
+        <title>[SubsPlease] Sousou no Frieren - 28 (1080p) [8D1B5F8B].mkv</title>
+        <guid>https://nyaa.si/view/1784245</guid>
+        <comments>https://nyaa.si/view/1784245</comments>
+        <pubDate>Fri, 22 Mar 2024 16:02:16 +0000</pubDate>
+        <size>1444674560</size>
+        <link>http://localhost:9696/1/download?link=abc</link>
+        <category>5070</category>
+        <category>100001</category>
+        <enclosure url="http://localhost:9696/1/download?link=abc" length="1444674560" type="application/x-bittorrent" />
+        <torznab:attr name="category" value="5070" />
+        <torznab:attr name="seeders" value="120" />
+        <torznab:attr name="infohash" value="3B2C5E7A9F1D4C6B8A0E2F4D6C8B0A2E4F6D8C0B" />
+        <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:3B2C5E7A9F1D4C6B8A0E2F4D6C8B0A2E4F6D8C0B&amp;dn=test" />
+        </item>
+        </channel>
+        </rss>"#;
+
+        let rss = TorznabRss::from_xml(xml).unwrap();
+        assert_eq!(rss.channel.items.len(), 1);
+
+        let item = &rss.channel.items[0];
+        assert_eq!(
+            item.get_info_hash().unwrap(),
+            "3b2c5e7a9f1d4c6b8a0e2f4d6c8b0a2e4f6d8c0b"
+        );
+        assert_eq!(item.get_file_size().unwrap(), 1444674560);
+        assert_eq!(item.get_seeders(), Some(120));
+        assert!(item.get_magnet_link().unwrap().starts_with("magnet:"));
+        assert_eq!(
+            item.get_torrent_url().unwrap(),
+            "http://localhost:9696/1/download?link=abc"
+        );
+        assert_eq!(
+            item.get_pub_date().unwrap().to_string(),
+            "2024-03-22 16:02:16"
+        );
+    }
+
+    #[test]
+    fn test_parse_plain_rss() {
+        let xml = r#"<rss version="2.0">
+        <channel>
+        <title>RSS</title>
+        <item>
+        <title>[LoliHouse] 葬送的芙莉莲 - 28 [1080p]</title>
+        <link>https://example.com/t/1</link>
+        <pubDate>Sat, 23 Mar 2024 06:02:16 -0700</pubDate>
+        <enclosure url="magnet:?xt=urn:btih:8420088902677E8E142B35E2BC325E82C6CAFB04&amp;dn=" length="1" type="application/x-bittorrent"/>
+        </item>
+        </channel>
+        </rss>"#;
+
+        let rss = TorznabRss::from_xml(xml).unwrap();
+        let item = &rss.channel.items[0];
+        assert_eq!(
+            item.get_info_hash().unwrap(),
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert_eq!(item.get_torrent_url().unwrap(), "https://example.com/t/1");
+        assert_eq!(item.get_file_size(), None);
+    }
+
+    #[test]
+    fn test_parse_mikan_rss() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+        <rss version="2.0">
+        <channel>
+        <title>Mikan Project - 葬送的芙莉莲</title>
+        <link>http://mikanani.me/RSS/Bangumi?bangumiId=3141&amp;subgroupid=382</link>
+        <item>
+        <guid isPermaLink="false">[喵萌奶茶屋&amp;LoliHouse] 葬送的芙莉莲 - 28 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]</guid>
+        <link>https://mikanani.me/Home/Episode/8420088902677e8e142b35e2bc325e82c6cafb04</link>
+        <title>[喵萌奶茶屋&amp;LoliHouse] 葬送的芙莉莲 - 28 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]</title>
+        <description>[喵萌奶茶屋&amp;LoliHouse] 葬送的芙莉莲 - 28 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕][700.3 MB]</description>
+        <torrent xmlns="https://mikanani.me/0.1/">
+        <link>https://mikanani.me/Home/Episode/8420088902677e8e142b35e2bc325e82c6cafb04</link>
+        <contentLength>734318592</contentLength>
+        <pubDate>2024-03-23T20:39:00.857</pubDate>
+        </torrent>
+        <enclosure type="application/x-bittorrent" length="734318592" url="https://mikanani.me/Download/20240323/8420088902677e8e142b35e2bc325e82c6cafb04.torrent" />
+        </item>
+        </channel>
+        </rss>"#;
+
+        let rss = TorznabRss::from_xml(xml).unwrap();
+        let item = &rss.channel.items[0];
+        assert_eq!(
+            item.get_info_hash().unwrap(),
+            "8420088902677e8e142b35e2bc325e82c6cafb04"
+        );
+        assert_eq!(item.get_file_size(), Some(734318592));
+        assert_eq!(
+            item.get_pub_date().unwrap().to_string(),
+            "2024-03-23 20:39:00.857"
+        );
+        assert!(item.get_magnet_link().is_none());
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::{Result, anyhow};
+
+/// 解析人类可读的文件大小, 如 "1.4 GiB", "283.06 MB", "512KB"
+///
+/// 为了兼容各站点的写法, MB/MiB 均按照 1024 进制计算
+pub fn parse_human_size(size_str: &str) -> Result<u64> {
+    let s = size_str.trim();
+    let split_at = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split_at);
+    let num: f64 = num
+        .parse()
+        .map_err(|_| anyhow!("unable parse size: {}", size_str))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" | "BYTES" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(anyhow!("unable parse size: {}", size_str)),
+    };
+
+    Ok((num * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_human_size() {
+        assert_eq!(parse_human_size("1024").unwrap(), 1024);
+        assert_eq!(parse_human_size("1 KiB").unwrap(), 1024);
+        assert_eq!(parse_human_size("283.06 MB").unwrap(), 296_809_922);
+        assert_eq!(parse_human_size("1.5GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_human_size(" 2 gb ").unwrap(), 2_147_483_648);
+        assert!(parse_human_size("abc").is_err());
+        assert!(parse_human_size("1.2 PB").is_err());
+    }
+}
@@ -7,6 +7,7 @@ import type {
   Torrent,
   SubscribeStatus,
   SubscribeParams,
+  SubscriptionFeed,
   DownloadTask,
   QueryDownloadTask,
   PaginatedResponse,
@@ -18,10 +19,19 @@ import type {
   MikanSearchResultItem,
   AddBangumiParams,
   DownloadedFile,
-  DownloaderInfo
+  DownloaderInfo,
+  AuthStatus,
+  LoginParams,
+  ApiToken,
+  CreatedApiToken,
+  ChangePasswordParams,
+  User,
+  CreateUserParams,
+  UpdateUserParams
 } from './model'
 import { ApiError } from './model'
 import { useSnackbar } from '../composables/useSnackbar'
+import router from '../router'
 
 const { showSnackbar } = useSnackbar()
 
@@ -31,6 +41,19 @@ const api = axios.create({
   timeout: 30000
 })
 
+// 未登录或登录过期时跳转到登录页
+api.interceptors.response.use(undefined, (error: unknown) => {
+  if (
+    error instanceof AxiosError &&
+    error.response?.status === 401 &&
+    error.config?.url !== '/auth/login' &&
+    router.currentRoute.value.name !== 'login'
+  ) {
+    router.push({ name: 'login', query: { redirect: router.currentRoute.value.fullPath } })
+  }
+  return Promise.reject(error)
+})
+
 // 统一的错误处理函数
 function handleError(error: unknown, defaultMessage: string): never {
   let errorMessage = defaultMessage
@@ -119,6 +142,23 @@ export async function getBangumiEpisodes(id: number): Promise<Episode[]> {
   }
 }
 
+// 标记剧集为已看或未看
+export async function markEpisodesWatched(
+  id: number,
+  episodeNumbers: number[],
+  watched: boolean
+): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>(`/bangumi/${id}/watched`, {
+      episode_numbers: episodeNumbers,
+      watched
+    })
+    handleResponse(response, '更新观看状态失败')
+  } catch (error) {
+    handleError(error, '更新观看状态失败')
+  }
+}
+
 // 种子相关 API
 export async function getBangumiTorrents(id: number): Promise<Torrent[]> {
   try {
@@ -132,13 +172,23 @@ export async function getBangumiTorrents(id: number): Promise<Torrent[]> {
 // 刷新番剧元数据
 export async function refreshBangumi(id: number, force: boolean): Promise<void> {
   try {
-    const response = await api.get<ApiResponse<null>>(`/bangumi/${id}/refresh/${force}`)
+    const response = await api.post<ApiResponse<null>>(`/bangumi/${id}/refresh/${force}`)
     handleResponse(response, '刷新元数据失败')
   } catch (error) {
     handleError(error, '刷新元数据失败')
   }
 }
 
+// 重新生成 nfo 及海报等元数据文件
+export async function regenerateSidecars(id: number): Promise<number> {
+  try {
+    const response = await api.post<ApiResponse<number>>(`/bangumi/${id}/regenerate_sidecars`)
+    return handleResponse(response, '生成元数据文件失败')
+  } catch (error) {
+    handleError(error, '生成元数据文件失败')
+  }
+}
+
 export async function getBangumiReleaseGroups(id: number): Promise<string[]> {
   try {
     const response = await api.get<ApiResponse<string[]>>(`/bangumi/${id}/release_groups`)
@@ -147,10 +197,19 @@ export async function getBangumiReleaseGroups(id: number): Promise<string[]> {
     return handleError(error, '获取发布组列表失败')
   }
 }
+// 获取订阅的自定义 RSS 源
+export async function listSubscriptionFeeds(id: number): Promise<SubscriptionFeed[]> {
+  try {
+    const response = await api.get<ApiResponse<SubscriptionFeed[]>>(`/bangumi/${id}/feeds`)
+    return handleResponse(response, '获取 RSS 源失败')
+  } catch (error) {
+    return handleError(error, '获取 RSS 源失败')
+  }
+}
 // 删除番剧下载任务
 export async function deleteBangumiDownloadTasks(id: number): Promise<void> {
   try {
-    const response = await api.get<ApiResponse<null>>(`/bangumi/${id}/delete_download_tasks`)
+    const response = await api.post<ApiResponse<null>>(`/bangumi/${id}/delete_download_tasks`)
     handleResponse(response, '删除下载任务失败')
   } catch (error) {
     handleError(error, '删除下载任务失败')
@@ -159,7 +218,7 @@ export async function deleteBangumiDownloadTasks(id: number): Promise<void> {
 
 export async function manualSelectTorrent(bangumiId: number, episodeNumber: number, infoHash: string): Promise<void> {
   try {
-    const response = await api.get<ApiResponse<null>>(`/bangumi/${bangumiId}/${episodeNumber}/manual_select_torrent/${infoHash}`)
+    const response = await api.post<ApiResponse<null>>(`/bangumi/${bangumiId}/${episodeNumber}/manual_select_torrent/${infoHash}`)
     handleResponse(response, '手动选择种子下载失败')
   } catch (error) {
     handleError(error, '手动选择种子下载失败')
@@ -192,7 +251,7 @@ export async function fetchDownloadTasks(params: QueryDownloadTask): Promise<Dow
 // 刷新放送列表
 export async function refreshCalendar(season: string = '', force: boolean = false): Promise<void> {
   try {
-    const response = await api.get<ApiResponse<null>>(`/calendar/refresh/${force}`, { params: { season } })
+    const response = await api.post<ApiResponse<null>>(`/calendar/refresh/${force}`, null, { params: { season } })
     handleResponse(response, '刷新放送列表失败')
   } catch (error) {
     handleError(error, '刷新放送列表失败')
@@ -262,7 +321,7 @@ export async function fetchBangumiList(params: QueryBangumiParams): Promise<Pagi
 // 重试下载任务
 export async function retryDownloadTask(bangumiId: number, episodeNumber: number): Promise<void> {
   try {
-    const response = await api.get<ApiResponse<null>>(`/downloads/${bangumiId}/${episodeNumber}/retry`)
+    const response = await api.post<ApiResponse<null>>(`/downloads/${bangumiId}/${episodeNumber}/retry`)
     handleResponse(response, '重试下载任务失败')
   } catch (error) {
     handleError(error, '重试下载任务失败')
@@ -296,3 +355,104 @@ export async function listDownloaders(): Promise<DownloaderInfo[]> {
     return handleError(error, '获取下载器列表失败')
   }
 }
+
+// 认证相关 API
+export async function getAuthStatus(): Promise<AuthStatus> {
+  try {
+    const response = await api.get<ApiResponse<AuthStatus>>('/auth/status')
+    return handleResponse(response, '获取登录状态失败')
+  } catch (error) {
+    return handleError(error, '获取登录状态失败')
+  }
+}
+
+export async function login(params: LoginParams): Promise<AuthStatus> {
+  try {
+    const response = await api.post<ApiResponse<AuthStatus>>('/auth/login', params)
+    return handleResponse(response, '登录失败')
+  } catch (error) {
+    return handleError(error, '登录失败')
+  }
+}
+
+export async function logout(): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>('/auth/logout')
+    handleResponse(response, '退出登录失败')
+  } catch (error) {
+    handleError(error, '退出登录失败')
+  }
+}
+
+export async function listApiTokens(): Promise<ApiToken[]> {
+  try {
+    const response = await api.get<ApiResponse<ApiToken[]>>('/auth/tokens')
+    return handleResponse(response, '获取 API 令牌失败')
+  } catch (error) {
+    return handleError(error, '获取 API 令牌失败')
+  }
+}
+
+export async function createApiToken(name: string): Promise<CreatedApiToken> {
+  try {
+    const response = await api.post<ApiResponse<CreatedApiToken>>('/auth/tokens', { name })
+    return handleResponse(response, '创建 API 令牌失败')
+  } catch (error) {
+    return handleError(error, '创建 API 令牌失败')
+  }
+}
+
+export async function deleteApiToken(id: number): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>(`/auth/tokens/${id}/delete`)
+    handleResponse(response, '删除 API 令牌失败')
+  } catch (error) {
+    handleError(error, '删除 API 令牌失败')
+  }
+}
+
+export async function changePassword(params: ChangePasswordParams): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>('/auth/password', params)
+    handleResponse(response, '修改密码失败')
+  } catch (error) {
+    handleError(error, '修改密码失败')
+  }
+}
+
+// 用户管理 API, 仅管理员可用
+export async function listUsers(): Promise<User[]> {
+  try {
+    const response = await api.get<ApiResponse<User[]>>('/users')
+    return handleResponse(response, '获取用户列表失败')
+  } catch (error) {
+    return handleError(error, '获取用户列表失败')
+  }
+}
+
+export async function createUser(params: CreateUserParams): Promise<User> {
+  try {
+    const response = await api.post<ApiResponse<User>>('/users', params)
+    return handleResponse(response, '创建用户失败')
+  } catch (error) {
+    return handleError(error, '创建用户失败')
+  }
+}
+
+export async function updateUser(id: number, params: UpdateUserParams): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>(`/users/${id}/update`, params)
+    handleResponse(response, '更新用户失败')
+  } catch (error) {
+    handleError(error, '更新用户失败')
+  }
+}
+
+export async function deleteUser(id: number): Promise<void> {
+  try {
+    const response = await api.post<ApiResponse<null>>(`/users/${id}/delete`)
+    handleResponse(response, '删除用户失败')
+  } catch (error) {
+    handleError(error, '删除用户失败')
+  }
+}
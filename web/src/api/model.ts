@@ -51,6 +51,15 @@ export enum State {
   Retrying = 'Retrying'
 }
 
+// 下载器选择策略
+export enum SelectStrategy {
+  Priority = 'priority',
+  RoundRobin = 'round_robin',
+  LeastActive = 'least_active',
+  Weighted = 'weighted',
+  ResourceType = 'resource_type'
+}
+
 // 订阅参数
 export interface SubscribeParams {
   status: SubscribeStatus
@@ -63,6 +72,61 @@ export interface SubscribeParams {
   enforce_torrent_release_after_broadcast?: boolean | undefined
   preferred_downloader?: string | undefined
   allow_fallback?: boolean | undefined
+  auto_upgrade?: boolean | undefined
+  download_strategy?: SelectStrategy | undefined
+  feeds?: string[] | undefined
+  filter_rules?: FilterRule | undefined
+  score_profile?: Partial<ScoreProfile> | undefined
+}
+
+// 种子评分配置, 订阅只保存需要覆盖全局配置的字段
+export interface ScoreProfile {
+  resolution_weight: number
+  language_weight: number
+  release_group_weight: number
+  release_groups: string[]
+  size_weight: number
+  seeders_weight: number
+  codec_weight: number
+  codecs: ('HEVC' | 'AVC' | 'AV1')[]
+  min_size: string
+}
+
+// 种子评分结果, 各项得分均已乘以权重
+export interface TorrentScore {
+  info_hash: string
+  eligible: boolean
+  resolution: number
+  language: number
+  release_group: number
+  size: number
+  seeders: number
+  codec: number
+  total: number
+}
+
+// 种子过滤规则
+export type FilterRule =
+  | { type: 'all'; rules: FilterRule[] }
+  | { type: 'any'; rules: FilterRule[] }
+  | { type: 'not'; rule: FilterRule }
+  | { type: 'title'; pattern: string }
+  | { type: 'size'; min?: string; max?: string }
+  | { type: 'codec'; codecs: ('HEVC' | 'AVC' | 'AV1')[] }
+  | { type: 'source'; sources: ('WebRip' | 'BDRip' | 'TVRip')[] }
+  | { type: 'resolution'; resolutions: string[] }
+  | { type: 'language'; languages: string[] }
+  | { type: 'release_group'; groups: string[] }
+
+// 订阅的自定义 RSS 源
+export interface SubscriptionFeed {
+  id: number
+  bangumi_id: number
+  url: string
+  enabled: boolean
+  last_collected_at: string | null
+  created_at: string
+  updated_at: string
 }
 
 // 番剧信息
@@ -90,6 +154,12 @@ export interface Bangumi {
   enforce_torrent_release_after_broadcast: boolean | null
   preferred_downloader: string | null
   allow_fallback: boolean
+  auto_upgrade: boolean | null
+  download_strategy: SelectStrategy | null
+  filter_rules: string | null
+  score_profile: string | null
+  // 当前用户订阅的时间, 未订阅时为空
+  subscribed_at: string | null
 }
 
 // 剧集信息
@@ -109,8 +179,11 @@ export interface Episode {
   // 下载状态相关字段
   download_state: State | null
   ref_torrent_info_hash: string | null
+  upgrade_info_hash: string | null
   task_created_at: string | null
   task_updated_at: string | null
+  // 当前用户标记为已看的时间
+  watched_at: string | null
 }
 
 // 种子信息
@@ -120,6 +193,7 @@ export interface Torrent {
   size: number
   magnet: string
   pub_date: string
+  seeders: number | null
 
   // 文件解析信息
   release_group: string | null
@@ -133,6 +207,9 @@ export interface Torrent {
   download_status: DownloadStatus | null
   downloader_name: string | null
   task_created_at: string | null
+
+  // 评分信息
+  score: TorrentScore | null
 }
 
 // 下载任务查询参数
@@ -158,6 +235,21 @@ export interface DownloadTask {
   retry_count: number
 }
 
+// 下载任务进度, 通过 WebSocket 推送
+export interface TaskProgress {
+  progress: number
+  downloaded: number
+  total_size: number
+  download_speed: number
+  peers: number | null
+  eta: number | null
+}
+
+// WebSocket 消息
+export type WsMessage =
+  | { type: 'log'; data: string }
+  | { type: 'progress'; data: Record<string, TaskProgress> }
+
 // TMDB 元数据信息
 export interface TMDBMetadata {
   id: number
@@ -228,8 +320,17 @@ export interface ServiceMetrics {
   status: ServiceStatus
 }
 
+export interface ProviderMetrics {
+  name: string
+  success_count: number
+  failure_count: number
+  last_torrent_count: number
+  last_error: string | null
+}
+
 export interface MetadataMetrics {
   services: ServiceMetrics[]
+  providers: ProviderMetrics[]
   last_refresh_time: number
 }
 
@@ -272,6 +373,8 @@ export interface GenericDownloaderConfig {
   download_timeout: string
   delete_task_on_completion: boolean
   priority: number
+  max_concurrent_tasks: number
+  weight: number
 }
 
 // 115网盘下载器配置
@@ -286,6 +389,8 @@ export interface Pan115Config {
   download_timeout: string
   delete_task_on_completion: boolean
   priority: number
+  max_concurrent_tasks: number
+  weight: number
 }
 
 // qBittorrent下载器配置
@@ -301,6 +406,8 @@ export interface QbittorrentConfig {
   download_timeout: string
   delete_task_on_completion: boolean
   priority: number
+  max_concurrent_tasks: number
+  weight: number
   mount_path?: string
 }
 
@@ -317,14 +424,71 @@ export interface TransmissionConfig {
   download_timeout: string
   delete_task_on_completion: boolean
   priority: number
+  max_concurrent_tasks: number
+  weight: number
+  mount_path?: string
+}
+
+// Deluge下载器配置
+export interface DelugeConfig {
+  enabled: boolean
+  url: string
+  password: string
+  download_dir: string
+  max_retry_count: number
+  retry_min_interval: string
+  retry_max_interval: string
+  download_timeout: string
+  delete_task_on_completion: boolean
+  priority: number
+  max_concurrent_tasks: number
+  weight: number
   mount_path?: string
 }
 
+// aria2下载器配置
+export interface Aria2Config {
+  enabled: boolean
+  url: string
+  secret: string
+  subscribe_notifications: boolean
+  download_dir: string
+  max_retry_count: number
+  retry_min_interval: string
+  retry_max_interval: string
+  download_timeout: string
+  delete_task_on_completion: boolean
+  priority: number
+  max_concurrent_tasks: number
+  weight: number
+  mount_path?: string
+}
+
+// 监视目录下载器配置
+export interface WatchFolderConfig {
+  enabled: boolean
+  watch_dir: string
+  state_dir?: string
+  download_dir: string
+  max_retry_count: number
+  retry_min_interval: string
+  retry_max_interval: string
+  download_timeout: string
+  delete_task_on_completion: boolean
+  priority: number
+  max_concurrent_tasks: number
+  weight: number
+}
+
 // 下载器配置
 export interface DownloaderConfig {
+  strategy: SelectStrategy
   pan115: Pan115Config
   qbittorrent: QbittorrentConfig
   transmission: TransmissionConfig
+  aria2: Aria2Config
+  deluge: DelugeConfig
+  watch_folder: WatchFolderConfig
 }
 
 export interface SiliconflowConfig {
@@ -378,6 +542,13 @@ export interface ServerConfig {
   database_url: string
 }
 
+export interface AuthConfig {
+  enabled: boolean
+  username: string
+  password_hash: string
+  session_ttl: string
+}
+
 export interface MikanConfig {
   endpoint: string
 }
@@ -394,14 +565,27 @@ export interface BangumiTvConfig {
   image_base_url: string
 }
 
+export type OrganizeMode = 'hardlink' | 'move' | 'copy'
+
+export interface OrganizerConfig {
+  enabled: boolean
+  mode: OrganizeMode
+  library_dir: string
+  remote_library_dir: string
+  template: string
+  write_sidecars: boolean
+}
+
 export interface Config {
   log: LogConfig
   server: ServerConfig
+  auth: AuthConfig
   mikan: MikanConfig
   bangumi_tv: BangumiTvConfig
   tmdb: TMDBConfig
   parser: ParserConfig
   downloader: DownloaderConfig
+  organizer: OrganizerConfig
   notify: NotifyConfig
   proxy: ProxyConfig
   sentry: SentryConfig
@@ -447,3 +631,53 @@ export interface DownloaderInfo {
   name: string
   priority: number
 }
+
+// 认证相关
+export interface AuthStatus {
+  enabled: boolean
+  authenticated: boolean
+  username: string | null
+  is_admin: boolean
+}
+
+export interface ChangePasswordParams {
+  old_password: string
+  new_password: string
+}
+
+// 用户
+export interface User {
+  id: number
+  username: string
+  is_admin: boolean
+  created_at: string
+}
+
+export interface CreateUserParams {
+  username: string
+  password: string
+  is_admin: boolean
+}
+
+export interface UpdateUserParams {
+  password?: string
+  is_admin?: boolean
+}
+
+export interface LoginParams {
+  username: string
+  password: string
+}
+
+export interface ApiToken {
+  id: number
+  name: string
+  token_prefix: string
+  last_used_at: string | null
+  created_at: string
+}
+
+// 新创建的 API 令牌, token 只在创建时返回一次
+export interface CreatedApiToken extends ApiToken {
+  token: string
+}
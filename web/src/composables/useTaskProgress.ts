@@ -0,0 +1,53 @@
+import { ref, onMounted, onUnmounted } from 'vue'
+import type { TaskProgress, WsMessage } from '@/api/model'
+
+// 订阅 WebSocket 推送的下载进度, key 为 info_hash
+export const useTaskProgress = (websocketUrl = '/ws') => {
+  const progress = ref<Record<string, TaskProgress>>({})
+  let ws: WebSocket | null = null
+  let reconnectTimer: ReturnType<typeof setTimeout> | null = null
+  let closed = false
+
+  const connect = () => {
+    if (ws) return
+
+    ws = new WebSocket(websocketUrl)
+
+    ws.onmessage = event => {
+      try {
+        const msg = JSON.parse(event.data) as WsMessage
+        if (msg.type === 'progress') {
+          progress.value = msg.data
+        }
+      } catch {
+        // 忽略非 JSON 消息
+      }
+    }
+
+    ws.onclose = () => {
+      ws = null
+      if (!closed) {
+        reconnectTimer = setTimeout(connect, 3000)
+      }
+    }
+  }
+
+  const disconnect = () => {
+    closed = true
+    if (reconnectTimer) {
+      clearTimeout(reconnectTimer)
+      reconnectTimer = null
+    }
+    if (ws) {
+      ws.close(1000, '正常关闭')
+      ws = null
+    }
+  }
+
+  onMounted(connect)
+  onUnmounted(disconnect)
+
+  return {
+    progress
+  }
+}
@@ -13,6 +13,23 @@ const router = createRouter({
   routes
 })
 
+// 启用认证时, 未登录的用户跳转到登录页
+let authenticated = false
+router.beforeEach(async to => {
+  if (to.name === 'login' || authenticated) return true
+  try {
+    const { getAuthStatus } = await import('@/api/api')
+    const status = await getAuthStatus()
+    if (status.enabled && !status.authenticated) {
+      return { name: 'login', query: { redirect: to.fullPath } }
+    }
+    authenticated = true
+  } catch (error) {
+    console.error('获取登录状态失败:', error)
+  }
+  return true
+})
+
 // Workaround for https://github.com/vitejs/vite/issues/11804
 router.onError((err, to) => {
   if (err?.message?.includes?.('Failed to fetch dynamically imported module')) {
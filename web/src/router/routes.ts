@@ -36,4 +36,9 @@ export const routes = [
       }
     ]
   },
+  {
+    path: '/login',
+    name: 'login',
+    component: () => import('@/pages/login.vue')
+  },
 ]